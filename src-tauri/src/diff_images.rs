@@ -0,0 +1,67 @@
+//! PNGs of recent image diffs, served at `visigit://diff/<id>`.
+//!
+//! `compute_image_diff` answers with counts and regions only and leaves the
+//! rendered diff here, so the image reaches the webview as raw bytes rather
+//! than as base64 inside the JSON. Only the latest few diffs are kept, since
+//! the comparer only ever shows one.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::trash::Trash;
+
+/// How many diff PNGs are kept before the oldest is dropped
+const KEPT_DIFFS: usize = 8;
+
+/// App-managed store of rendered diffs, newest last
+#[derive(Default)]
+pub struct DiffImages {
+    images: Mutex<VecDeque<(String, Vec<u8>)>>,
+}
+
+impl DiffImages {
+    /// Keep `png` and return the id it is served under. Ids are never
+    /// reused, so the webview may cache a diff for good.
+    pub fn insert(&self, png: Vec<u8>) -> String {
+        let id = Trash::new_id();
+        let mut images = self.images.lock().unwrap_or_else(|e| e.into_inner());
+
+        if images.len() == KEPT_DIFFS {
+            images.pop_front();
+        }
+        images.push_back((id.clone(), png));
+        id
+    }
+
+    /// PNG kept under `id`, `None` if it was never made or already dropped
+    pub fn get(&self, id: &str) -> Option<Vec<u8>> {
+        self.images
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|(kept, _)| kept == id)
+            .map(|(_, png)| png.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_only_the_latest_diffs() {
+        let diffs = DiffImages::default();
+        let first = diffs.insert(b"first".to_vec());
+        let ids: Vec<String> = (0..KEPT_DIFFS)
+            .map(|n| diffs.insert(vec![n as u8]))
+            .collect();
+
+        assert_eq!(diffs.get(&first), None);
+        assert_eq!(diffs.get(&ids[0]), Some(vec![0]));
+        assert_eq!(
+            diffs.get(&ids[KEPT_DIFFS - 1]),
+            Some(vec![KEPT_DIFFS as u8 - 1])
+        );
+        assert_eq!(diffs.get("unknown"), None);
+    }
+}
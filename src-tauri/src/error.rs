@@ -0,0 +1,334 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use std::fmt;
+use std::io;
+
+use crate::git::{ConflictStage, RepoOperation};
+
+/// Error returned by every command.
+///
+/// Serializes to `{ code, message, details }` so the frontend can branch on
+/// `code` instead of string-matching messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VisiGitError {
+    /// The given repository path does not exist on disk
+    RepoNotFound { path: String },
+    /// The path exists but is not inside a git repository
+    NotARepository { path: String },
+    /// The revision exists but the file is not in it
+    PathNotInRevision { path: String, rev: String },
+    /// The file has no staged version (stage 0), because it is not tracked
+    /// or is in conflict
+    PathNotInIndex { path: String },
+    /// The conflicted file has no version at this stage, because that side
+    /// deleted it or never had it
+    PathNotInStage { path: String, stage: ConflictStage },
+    /// The file is not in conflict, so there is nothing to resolve
+    NotConflicted { path: String },
+    /// The revision could not be resolved to a commit
+    RevisionNotFound { rev: String },
+    /// Two revisions share no history
+    NoMergeBase { left: String, right: String },
+    /// A revision argument is empty, malformed or looks like an option
+    InvalidRevision { rev: String },
+    /// A pagination cursor was not one this backend handed out
+    InvalidCursor { cursor: String },
+    /// A commit author has an empty name or email, or one containing `<`,
+    /// `>` or control characters
+    InvalidIdentity { name: String, email: String },
+    /// The commit message is empty or only whitespace
+    EmptyCommitMessage,
+    /// Nothing is staged, so a commit would not change anything
+    NothingToCommit,
+    /// These paths are still in conflict and have to be resolved first
+    UnresolvedConflicts { paths: Vec<String> },
+    /// A merge, rebase or similar operation has to be finished or aborted
+    /// first, or explicitly allowed
+    OperationInProgress { operation: RepoOperation },
+    /// No backup with this id was saved for the repository, or it was
+    /// already undone
+    BackupNotFound { id: String },
+    /// No diff image is kept under this id, because it was never made or
+    /// newer diffs replaced it
+    DiffNotFound { id: String },
+    /// A repo-relative path was given as an absolute path
+    AbsolutePath { path: String },
+    /// A repo-relative path climbs out of the repository (`..` or a symlink)
+    PathEscapesRepo { path: String },
+    /// A repo-relative path is empty or contains control characters
+    InvalidPath { path: String },
+    /// The file is not in an image format the backend can decode, or its
+    /// header is unreadable
+    InvalidImage { path: String, message: String },
+    /// The file is a Git LFS pointer whose object is neither stored locally
+    /// nor could be fetched, so only the pointer could be checked out
+    LfsObjectMissing { path: String },
+    /// The `git` executable could not be started
+    GitNotInstalled,
+    /// git (or libgit2) ran but reported an error
+    GitFailed {
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+    /// git produced output we could not parse
+    UnexpectedOutput { message: String },
+    /// Filesystem error
+    Io { kind: String, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, VisiGitError>;
+
+impl VisiGitError {
+    /// Stable machine-readable identifier for the variant
+    pub fn code(&self) -> &'static str {
+        match self {
+            VisiGitError::RepoNotFound { .. } => "repo_not_found",
+            VisiGitError::NotARepository { .. } => "not_a_repository",
+            VisiGitError::PathNotInRevision { .. } => "path_not_in_revision",
+            VisiGitError::PathNotInIndex { .. } => "path_not_in_index",
+            VisiGitError::PathNotInStage { .. } => "path_not_in_stage",
+            VisiGitError::NotConflicted { .. } => "not_conflicted",
+            VisiGitError::RevisionNotFound { .. } => "revision_not_found",
+            VisiGitError::NoMergeBase { .. } => "no_merge_base",
+            VisiGitError::InvalidRevision { .. } => "invalid_revision",
+            VisiGitError::InvalidCursor { .. } => "invalid_cursor",
+            VisiGitError::InvalidIdentity { .. } => "invalid_identity",
+            VisiGitError::EmptyCommitMessage => "empty_commit_message",
+            VisiGitError::NothingToCommit => "nothing_to_commit",
+            VisiGitError::UnresolvedConflicts { .. } => "unresolved_conflicts",
+            VisiGitError::OperationInProgress { .. } => "operation_in_progress",
+            VisiGitError::BackupNotFound { .. } => "backup_not_found",
+            VisiGitError::DiffNotFound { .. } => "diff_not_found",
+            VisiGitError::AbsolutePath { .. } => "absolute_path",
+            VisiGitError::PathEscapesRepo { .. } => "path_escapes_repo",
+            VisiGitError::InvalidPath { .. } => "invalid_path",
+            VisiGitError::InvalidImage { .. } => "invalid_image",
+            VisiGitError::LfsObjectMissing { .. } => "lfs_object_missing",
+            VisiGitError::GitNotInstalled => "git_not_installed",
+            VisiGitError::GitFailed { .. } => "git_failed",
+            VisiGitError::UnexpectedOutput { .. } => "unexpected_output",
+            VisiGitError::Io { .. } => "io",
+        }
+    }
+
+    /// Variant fields, or `null` for variants without any
+    pub fn details(&self) -> Value {
+        match self {
+            VisiGitError::RepoNotFound { path }
+            | VisiGitError::NotARepository { path }
+            | VisiGitError::PathNotInIndex { path }
+            | VisiGitError::NotConflicted { path }
+            | VisiGitError::AbsolutePath { path }
+            | VisiGitError::PathEscapesRepo { path }
+            | VisiGitError::InvalidPath { path }
+            | VisiGitError::LfsObjectMissing { path } => {
+                json!({ "path": path })
+            }
+            VisiGitError::PathNotInRevision { path, rev } => json!({ "path": path, "rev": rev }),
+            VisiGitError::InvalidImage { path, .. } => json!({ "path": path }),
+            VisiGitError::PathNotInStage { path, stage } => json!({ "path": path, "stage": stage }),
+            VisiGitError::NoMergeBase { left, right } => json!({ "left": left, "right": right }),
+            VisiGitError::RevisionNotFound { rev } | VisiGitError::InvalidRevision { rev } => {
+                json!({ "rev": rev })
+            }
+            VisiGitError::InvalidCursor { cursor } => json!({ "cursor": cursor }),
+            VisiGitError::InvalidIdentity { name, email } => {
+                json!({ "name": name, "email": email })
+            }
+            VisiGitError::UnresolvedConflicts { paths } => json!({ "paths": paths }),
+            VisiGitError::OperationInProgress { operation } => json!({ "operation": operation }),
+            VisiGitError::BackupNotFound { id } | VisiGitError::DiffNotFound { id } => {
+                json!({ "id": id })
+            }
+            VisiGitError::EmptyCommitMessage
+            | VisiGitError::NothingToCommit
+            | VisiGitError::GitNotInstalled => Value::Null,
+            VisiGitError::GitFailed { exit_code, stderr } => {
+                json!({ "exit_code": exit_code, "stderr": stderr })
+            }
+            VisiGitError::UnexpectedOutput { .. } => Value::Null,
+            VisiGitError::Io { kind, .. } => json!({ "kind": kind }),
+        }
+    }
+
+    /// Build a `GitFailed` from a finished git process
+    pub fn git_failed(output: &std::process::Output) -> Self {
+        VisiGitError::GitFailed {
+            exit_code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for VisiGitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VisiGitError::RepoNotFound { path } => {
+                write!(f, "Repository path does not exist: {}", path)
+            }
+            VisiGitError::NotARepository { path } => {
+                write!(f, "Not a git repository: {}", path)
+            }
+            VisiGitError::PathNotInRevision { path, rev } => {
+                write!(f, "File does not exist at {}: {}", rev, path)
+            }
+            VisiGitError::PathNotInIndex { path } => {
+                write!(f, "File has no staged version in the index: {}", path)
+            }
+            VisiGitError::PathNotInStage { path, stage } => {
+                write!(f, "File has no {} version in the conflict: {}", stage, path)
+            }
+            VisiGitError::NotConflicted { path } => write!(f, "File is not in conflict: {}", path),
+            VisiGitError::RevisionNotFound { rev } => write!(f, "Unknown revision: {}", rev),
+            VisiGitError::NoMergeBase { left, right } => {
+                write!(f, "{} and {} have no common history", left, right)
+            }
+            VisiGitError::InvalidRevision { rev } => write!(f, "Invalid revision: {:?}", rev),
+            VisiGitError::InvalidCursor { cursor } => {
+                write!(f, "Invalid pagination cursor: {:?}", cursor)
+            }
+            VisiGitError::InvalidIdentity { name, email } => {
+                write!(f, "Invalid commit author: {:?} <{:?}>", name, email)
+            }
+            VisiGitError::EmptyCommitMessage => write!(f, "Commit message is empty"),
+            VisiGitError::NothingToCommit => write!(f, "Nothing is staged to commit"),
+            VisiGitError::UnresolvedConflicts { paths } => {
+                write!(f, "Resolve the conflicts in {} first", paths.join(", "))
+            }
+            VisiGitError::OperationInProgress { operation } => {
+                write!(f, "Finish or abort the {} in progress first", operation)
+            }
+            VisiGitError::BackupNotFound { id } => write!(f, "No backup to undo: {}", id),
+            VisiGitError::DiffNotFound { id } => write!(f, "No diff image kept: {}", id),
+            VisiGitError::AbsolutePath { path } => {
+                write!(f, "Expected a path relative to the repository: {}", path)
+            }
+            VisiGitError::PathEscapesRepo { path } => {
+                write!(f, "Path is outside the repository: {}", path)
+            }
+            VisiGitError::InvalidPath { path } => write!(f, "Invalid path: {:?}", path),
+            VisiGitError::InvalidImage { path, message } => {
+                write!(f, "Not a readable image: {} ({})", path, message)
+            }
+            VisiGitError::LfsObjectMissing { path } => {
+                write!(f, "Git LFS object has not been fetched: {}", path)
+            }
+            VisiGitError::GitNotInstalled => write!(f, "git is not installed or not on PATH"),
+            VisiGitError::GitFailed { exit_code, stderr } => match exit_code {
+                Some(code) => write!(f, "git failed with exit code {}: {}", code, stderr),
+                None => write!(f, "git failed: {}", stderr),
+            },
+            VisiGitError::UnexpectedOutput { message } => {
+                write!(f, "Unexpected git output: {}", message)
+            }
+            VisiGitError::Io { message, .. } => write!(f, "I/O error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for VisiGitError {}
+
+impl From<io::Error> for VisiGitError {
+    fn from(e: io::Error) -> Self {
+        VisiGitError::Io {
+            kind: format!("{:?}", e.kind()),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl Serialize for VisiGitError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("VisiGitError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_code_message_and_details() {
+        let error = VisiGitError::PathNotInRevision {
+            path: "logo.png".to_string(),
+            rev: "HEAD".to_string(),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "path_not_in_revision",
+                "message": "File does not exist at HEAD: logo.png",
+                "details": { "path": "logo.png", "rev": "HEAD" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_serializes_git_failed_with_exit_code() {
+        let error = VisiGitError::GitFailed {
+            exit_code: Some(128),
+            stderr: "fatal: bad object".to_string(),
+        };
+
+        let value = serde_json::to_value(&error).unwrap();
+
+        assert_eq!(value["code"], "git_failed");
+        assert_eq!(value["details"]["exit_code"], 128);
+        assert_eq!(value["details"]["stderr"], "fatal: bad object");
+    }
+
+    #[test]
+    fn test_variants_without_fields_have_null_details() {
+        let value = serde_json::to_value(VisiGitError::GitNotInstalled).unwrap();
+
+        assert_eq!(value["code"], "git_not_installed");
+        assert_eq!(value["details"], Value::Null);
+    }
+
+    #[test]
+    fn test_operation_in_progress_names_the_operation() {
+        let error = VisiGitError::OperationInProgress {
+            operation: RepoOperation::CherryPick,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "operation_in_progress",
+                "message": "Finish or abort the cherry-pick in progress first",
+                "details": { "operation": "cherry_pick" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_path_not_in_stage_names_the_stage() {
+        let error = VisiGitError::PathNotInStage {
+            path: "logo.png".to_string(),
+            stage: ConflictStage::Theirs,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "path_not_in_stage",
+                "message": "File has no theirs version in the conflict: logo.png",
+                "details": { "path": "logo.png", "stage": "theirs" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_io_error_keeps_kind() {
+        let error = VisiGitError::from(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+
+        assert_eq!(error.code(), "io");
+        assert_eq!(error.details(), json!({ "kind": "PermissionDenied" }));
+    }
+}
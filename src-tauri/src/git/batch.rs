@@ -0,0 +1,313 @@
+//! A long-lived `git cat-file --batch` process for reading objects.
+//!
+//! Spawning git for every blob adds up when scrubbing through hundreds of
+//! versions of a file, so the CLI backend keeps one process per repository
+//! and writes object names to its stdin. Each answer is a header line
+//! `<id> <type> <size>` followed by the content and a newline, or
+//! `<name> missing` when the name does not resolve.
+//!
+//! The process starts on first use, is restarted after any I/O error or
+//! garbled answer, and is killed on `shutdown` or drop.
+//!
+//! git reads the index only once per process, so `:<stage>:<path>` names
+//! would go stale after staging. Callers resolve those with `rev-parse`
+//! and read the object by id instead. Refs and new objects are looked up
+//! fresh for every name.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+
+use crate::error::{Result, VisiGitError};
+
+/// An object read through the batch process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchObject {
+    /// Full object id
+    pub id: String,
+    /// `blob`, `tree`, `commit` or `tag`
+    pub kind: String,
+    pub content: Vec<u8>,
+}
+
+/// Batch reader for one repository, shared by every read on its backend
+pub struct CatFileBatch {
+    root: PathBuf,
+    process: Mutex<Option<BatchProcess>>,
+}
+
+impl CatFileBatch {
+    /// Reader for the repository at `root`. No process is started until
+    /// the first read.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            process: Mutex::new(None),
+        }
+    }
+
+    /// Read the object `name` resolves to, `None` if it does not resolve.
+    ///
+    /// `name` must not contain a newline; callers pass validated revisions
+    /// and paths. A failed process is replaced and the read tried once more.
+    pub fn read(&self, name: &str) -> Result<Option<BatchObject>> {
+        if name.contains('\n') {
+            return Err(VisiGitError::InvalidPath {
+                path: name.to_string(),
+            });
+        }
+
+        let mut process = self.process.lock().unwrap_or_else(|e| e.into_inner());
+        let mut retried = false;
+        loop {
+            let running = match process.as_mut() {
+                Some(running) => running,
+                None => process.insert(BatchProcess::spawn(&self.root)?),
+            };
+
+            match running.read(name) {
+                Ok(object) => return Ok(object),
+                Err(e) => {
+                    // Killing it leaves no half-read answer behind
+                    *process = None;
+                    if retried {
+                        return Err(e.into());
+                    }
+                    retried = true;
+                }
+            }
+        }
+    }
+
+    /// Stop the process. The next read starts a new one.
+    pub fn shutdown(&self) {
+        self.process
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+    }
+
+    /// Whether a process is currently running
+    #[cfg(test)]
+    fn is_running(&self) -> bool {
+        self.process
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// Kill the process as if it had crashed, keeping it in place
+    #[cfg(test)]
+    fn kill(&self) {
+        if let Some(process) = self
+            .process
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+        {
+            process.child.kill().unwrap();
+            process.child.wait().unwrap();
+        }
+    }
+}
+
+/// A running `git cat-file --batch`
+struct BatchProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl BatchProcess {
+    fn spawn(root: &Path) -> Result<Self> {
+        let mut child = Command::new("git")
+            .args(["cat-file", "--batch"])
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => VisiGitError::GitNotInstalled,
+                _ => VisiGitError::from(e),
+            })?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    fn read(&mut self, name: &str) -> io::Result<Option<BatchObject>> {
+        writeln!(self.stdin, "{}", name)?;
+        self.stdin.flush()?;
+
+        let mut header = String::new();
+        if self.stdout.read_line(&mut header)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "git cat-file exited",
+            ));
+        }
+        let header = header.trim_end_matches('\n');
+
+        // `<name> missing`, or `<name> ambiguous` for a short id
+        if header.ends_with(" missing") || header.ends_with(" ambiguous") {
+            return Ok(None);
+        }
+
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected cat-file header {:?}", header),
+            )
+        };
+        let mut fields = header.split(' ');
+        let (Some(id), Some(kind), Some(size), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid());
+        };
+        let size: usize = size.parse().map_err(|_| invalid())?;
+
+        let mut content = vec![0; size];
+        self.stdout.read_exact(&mut content)?;
+        let mut newline = [0];
+        self.stdout.read_exact(&mut newline)?;
+        if newline != *b"\n" {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "cat-file content is not followed by a newline",
+            ));
+        }
+
+        Ok(Some(BatchObject {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            content,
+        }))
+    }
+}
+
+impl Drop for BatchProcess {
+    fn drop(&mut self) {
+        // The process may be blocked writing an answer nobody reads, so
+        // closing stdin is not enough
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn git(root: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    fn repo_with_file(content: &[u8]) -> TempDir {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        git(root, &["init", "-q"]);
+        git(root, &["config", "user.email", "test@example.com"]);
+        git(root, &["config", "user.name", "Test"]);
+        fs::write(root.join("logo png.png"), content).unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "Add logo"]);
+        temp
+    }
+
+    #[test]
+    fn test_reads_blob_by_rev_and_path() {
+        // A trailing newline inside the content must not end the read early
+        let content = b"\x89PNG\r\n\x1a\n\0binary\n";
+        let temp = repo_with_file(content);
+        let batch = CatFileBatch::new(temp.path());
+
+        let object = batch.read("HEAD:logo png.png").unwrap().unwrap();
+
+        assert_eq!(object.kind, "blob");
+        assert_eq!(object.content, content);
+        assert_eq!(
+            object.id,
+            git(temp.path(), &["rev-parse", "HEAD:logo png.png"])
+        );
+    }
+
+    #[test]
+    fn test_missing_object_keeps_the_process() {
+        let temp = repo_with_file(b"logo");
+        let batch = CatFileBatch::new(temp.path());
+
+        assert_eq!(batch.read("HEAD:nope.png").unwrap(), None);
+        assert_eq!(batch.read("no-such-branch:logo png.png").unwrap(), None);
+        assert!(batch.is_running());
+        assert_eq!(batch.read("HEAD").unwrap().unwrap().kind, "commit");
+    }
+
+    #[test]
+    fn test_sees_new_commits() {
+        let temp = repo_with_file(b"one");
+        let batch = CatFileBatch::new(temp.path());
+        batch.read("HEAD:logo png.png").unwrap();
+
+        fs::write(temp.path().join("logo png.png"), b"two").unwrap();
+        git(temp.path(), &["commit", "-q", "-am", "Change logo"]);
+
+        assert_eq!(
+            batch.read("HEAD:logo png.png").unwrap().unwrap().content,
+            b"two"
+        );
+    }
+
+    #[test]
+    fn test_restarts_after_the_process_dies() {
+        let temp = repo_with_file(b"logo");
+        let batch = CatFileBatch::new(temp.path());
+        batch.read("HEAD").unwrap();
+
+        batch.kill();
+
+        assert_eq!(
+            batch.read("HEAD:logo png.png").unwrap().unwrap().content,
+            b"logo"
+        );
+    }
+
+    #[test]
+    fn test_shutdown_stops_the_process() {
+        let temp = repo_with_file(b"logo");
+        let batch = CatFileBatch::new(temp.path());
+        batch.read("HEAD").unwrap();
+
+        batch.shutdown();
+
+        assert!(!batch.is_running());
+        assert!(batch.read("HEAD").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_rejects_names_with_newlines() {
+        let temp = repo_with_file(b"logo");
+        let batch = CatFileBatch::new(temp.path());
+
+        assert!(matches!(
+            batch.read("HEAD\nHEAD"),
+            Err(VisiGitError::InvalidPath { .. })
+        ));
+        assert!(!batch.is_running());
+    }
+}
@@ -0,0 +1,793 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use super::libgit2::sha1_blob_id;
+use super::{
+    is_image_file, kept_mode, parse_commits, parse_porcelain_v2, parse_raw_diff, parse_raw_log,
+    parse_refs, validate, worktree_blob_id, BackendKind, Blob, CatFileBatch, CommitInfo,
+    ConflictStage, DiffEntry, FileHistoryEntry, GitBackend, HistoryFilter, Identity, RefInfo,
+    RepoInfo, RepoOperation, StatusEntry, COMMIT_FORMAT, DECORATE, IMAGE_EXTENSIONS, REF_FORMAT,
+};
+use crate::error::{Result, VisiGitError};
+
+/// Git backend that shells out to the `git` executable on PATH.
+///
+/// Kept as a fallback for repositories the in-process backend cannot open.
+pub struct CliBackend {
+    root: PathBuf,
+    /// Reads blobs without spawning git for each one
+    batch: CatFileBatch,
+    /// Whether object ids are SHA-256 rather than SHA-1
+    sha256: bool,
+}
+
+impl CliBackend {
+    pub fn open(repo_path: &Path) -> Result<Self> {
+        let root = validate::repo_root(repo_path)?;
+        let mut backend = Self {
+            batch: CatFileBatch::new(&root),
+            root,
+            sha256: false,
+        };
+
+        // Make sure git is runnable and the path is a repository before
+        // committing to this backend. Like `Repository::open`, only the
+        // top of the working tree counts: from a subfolder, worktree paths
+        // would resolve against the wrong directory.
+        let output = backend.git(&["rev-parse", "--show-object-format", "--show-prefix"])?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (format, prefix) = stdout.split_once('\n').unwrap_or_default();
+        if !output.status.success() || !prefix.trim().is_empty() {
+            return Err(VisiGitError::NotARepository {
+                path: repo_path.display().to_string(),
+            });
+        }
+        backend.sha256 = format == "sha256";
+
+        Ok(backend)
+    }
+
+    /// Find the repository containing `path`, like `git rev-parse
+    /// --show-toplevel` run from that folder
+    pub fn discover(path: &Path) -> Result<RepoInfo> {
+        let start = validate::repo_root(path)?;
+
+        let output = git_in(
+            &start,
+            &[
+                "rev-parse",
+                "--path-format=absolute",
+                "--git-dir",
+                "--git-common-dir",
+                "--is-bare-repository",
+                "--show-superproject-working-tree",
+            ],
+        )?;
+        if !output.status.success() {
+            return Err(VisiGitError::NotARepository {
+                path: path.display().to_string(),
+            });
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let lines: Vec<&str> = stdout.lines().collect();
+        let [git_dir, common_dir, is_bare, superproject @ ..] = lines.as_slice() else {
+            return Err(VisiGitError::UnexpectedOutput {
+                message: format!("rev-parse printed {:?}", stdout),
+            });
+        };
+        let git_dir = canonical_string(git_dir)?;
+        let common_dir = canonical_string(common_dir)?;
+        let is_bare = *is_bare == "true";
+
+        let toplevel = if is_bare {
+            None
+        } else {
+            let output = git_in(&start, &["rev-parse", "--show-toplevel"])?;
+            if !output.status.success() {
+                return Err(VisiGitError::git_failed(&output));
+            }
+            Some(canonical_string(
+                String::from_utf8_lossy(&output.stdout).trim(),
+            )?)
+        };
+
+        // Fails with a detached HEAD; works on an unborn branch
+        let output = git_in(&start, &["symbolic-ref", "--quiet", "--short", "HEAD"])?;
+        let branch = output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string());
+
+        Ok(RepoInfo {
+            toplevel,
+            is_worktree: git_dir != common_dir,
+            git_dir,
+            common_dir,
+            is_bare,
+            is_submodule: !superproject.is_empty(),
+            branch,
+        })
+    }
+
+    /// Run git with the given arguments inside the repository
+    fn git(&self, args: &[&str]) -> Result<Output> {
+        git_in(&self.root, args)
+    }
+
+    /// Run git with `input` on its stdin
+    fn git_with_input(&self, args: &[&str], input: &[u8]) -> Result<Output> {
+        let mut child = Command::new("git")
+            .args(args)
+            .current_dir(&self.root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(spawn_error)?;
+
+        // Dropping stdin closes it, so git sees the end of the input
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(input)?;
+        Ok(child.wait_with_output()?)
+    }
+
+    /// Whether `rev` resolves to a commit
+    fn has_commit(&self, rev: &str) -> Result<bool> {
+        let output = self.git(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            "--end-of-options",
+            &format!("{}^{{commit}}", rev),
+        ])?;
+        Ok(output.status.success())
+    }
+
+    /// Full id of the commit `rev` points at
+    fn resolve_commit(&self, rev: &str) -> Result<String> {
+        let rev = validate::revision(rev)?;
+        let output = self.git(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            "--end-of-options",
+            &format!("{}^{{commit}}", rev),
+        ])?;
+
+        if !output.status.success() {
+            return Err(VisiGitError::RevisionNotFound {
+                rev: rev.to_string(),
+            });
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Content of the blob staged for `file_path` at index stage `stage`,
+    /// failing with `missing()` if there is none
+    fn read_staged(
+        &self,
+        file_path: &str,
+        stage: u8,
+        missing: impl Fn() -> VisiGitError,
+    ) -> Result<Blob> {
+        let output = self.git(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            "--end-of-options",
+            &format!(":{}:{}", stage, file_path),
+        ])?;
+        if !output.status.success() {
+            return Err(missing());
+        }
+
+        // The batch process would keep reading the index it started with,
+        // so only the object is read through it
+        let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        match self.batch.read(&id)? {
+            Some(object) if object.kind == "blob" => Ok(Blob {
+                id: object.id,
+                content: object.content,
+            }),
+            // A submodule is staged as a commit, not a blob
+            _ => Err(missing()),
+        }
+    }
+
+    /// Parent of the oldest commit on `to`'s first-parent history that is
+    /// not reachable from `from`; `to` itself when there is none, and
+    /// `None` when the walk ends at a root commit
+    fn first_parent_base(&self, from: &str, to: &str) -> Result<Option<String>> {
+        let output = self.git(&["rev-list", "--first-parent", "--parents", to, "--not", from])?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let Some(oldest) = stdout.lines().last() else {
+            return Ok(Some(to.to_string()));
+        };
+
+        // <commit> <first parent> <other parents>...
+        Ok(oldest.split(' ').nth(1).map(str::to_string))
+    }
+
+    /// Index entries for `path` as `(stage, mode, id)`
+    fn index_entries(&self, path: &str) -> Result<Vec<(u32, u32, String)>> {
+        let output = self.git(&[
+            "--literal-pathspecs",
+            "ls-files",
+            "--stage",
+            "-z",
+            "--",
+            path,
+        ])?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        // Records are "<mode> <id> <stage>\t<path>"
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .split_terminator('\0')
+            .filter_map(|record| {
+                let (info, entry_path) = record.split_once('\t')?;
+                let mut fields = info.split(' ');
+                let mode = u32::from_str_radix(fields.next()?, 8).ok()?;
+                let id = fields.next()?.to_string();
+                let stage = fields.next()?.parse().ok()?;
+                (entry_path == path).then_some((stage, mode, id))
+            })
+            .collect())
+    }
+}
+
+impl GitBackend for CliBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Cli
+    }
+
+    fn status(&self) -> Result<Vec<StatusEntry>> {
+        // NUL-delimited porcelain v2 keeps paths unquoted and reports
+        // renames with their original path as a separate field
+        let output = self.git(&["status", "--porcelain=v2", "-z", "--untracked-files=all"])?;
+
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        parse_porcelain_v2(&output.stdout)
+    }
+
+    fn log(&self, filter: &HistoryFilter, skip: usize, limit: usize) -> Result<Vec<CommitInfo>> {
+        let start = self.resolve_commit(filter.start.as_deref().unwrap_or("HEAD"))?;
+        let pathspecs = history_pathspecs(filter)?;
+
+        // -F -i make --author and --grep case-insensitive substrings.
+        // --since-as-filter, unlike --since, does not stop the walk at the
+        // first older commit.
+        let mut args = vec![
+            "log".to_string(),
+            "-z".to_string(),
+            format!("--format={}", COMMIT_FORMAT),
+            DECORATE.to_string(),
+            "--full-history".to_string(),
+            "--fixed-strings".to_string(),
+            "--regexp-ignore-case".to_string(),
+            format!("--skip={}", skip),
+            format!("--max-count={}", limit),
+        ];
+        if let Some(author) = filter.author.as_deref().filter(|a| !a.is_empty()) {
+            args.push(format!("--author={}", author));
+        }
+        if let Some(grep) = filter.grep.as_deref().filter(|g| !g.is_empty()) {
+            args.push(format!("--grep={}", grep));
+        }
+        if let Some(since) = filter.since {
+            args.push(format!("--since-as-filter=@{} +0000", since));
+        }
+        if let Some(until) = filter.until {
+            args.push(format!("--until=@{} +0000", until));
+        }
+        if filter.all_branches {
+            args.push("--branches".to_string());
+            args.push("--remotes".to_string());
+        }
+        args.push(start);
+        args.push("--".to_string());
+        args.extend(pathspecs);
+
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = self.git(&args)?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        parse_commits(&output.stdout)
+    }
+
+    fn read_blob(&self, rev: &str, file_path: &str) -> Result<Blob> {
+        let rev = validate::revision(rev)?;
+        let file_path = validate::repo_path(file_path)?;
+
+        // The batch process resolves {rev}:{file_path} itself; names are
+        // read from stdin, so the rev can never be taken for an option
+        let object = self.batch.read(&format!("{}:{}", rev, file_path))?;
+
+        match object {
+            Some(object) if object.kind == "blob" => Ok(Blob {
+                id: object.id,
+                content: object.content,
+            }),
+            // Tell a bad revision apart from a path missing in a good one
+            None if !self.has_commit(rev)? => Err(VisiGitError::RevisionNotFound {
+                rev: rev.to_string(),
+            }),
+            // Missing, or a tree or submodule rather than a file
+            _ => Err(VisiGitError::PathNotInRevision {
+                path: file_path,
+                rev: rev.to_string(),
+            }),
+        }
+    }
+
+    fn read_blob_by_id(&self, id: &str) -> Result<Blob> {
+        let id = validate::object_id(id)?;
+
+        match self.batch.read(id)? {
+            Some(object) if object.kind == "blob" => Ok(Blob {
+                id: object.id,
+                content: object.content,
+            }),
+            _ => Err(VisiGitError::RevisionNotFound {
+                rev: id.to_string(),
+            }),
+        }
+    }
+
+    fn read_index_blob(&self, file_path: &str) -> Result<Blob> {
+        let file_path = validate::repo_path(file_path)?;
+        self.read_staged(&file_path, 0, || VisiGitError::PathNotInIndex {
+            path: file_path.clone(),
+        })
+    }
+
+    fn read_stage_blob(&self, file_path: &str, stage: ConflictStage) -> Result<Blob> {
+        let file_path = validate::repo_path(file_path)?;
+        self.read_staged(&file_path, stage.number(), || {
+            VisiGitError::PathNotInStage {
+                path: file_path.clone(),
+                stage,
+            }
+        })
+    }
+
+    fn merge_base(&self, left: &str, right: &str) -> Result<String> {
+        let left_id = self.resolve_commit(left)?;
+        let right_id = self.resolve_commit(right)?;
+
+        let output = self.git(&["merge-base", &left_id, &right_id])?;
+
+        // Exit code 1 with no output means the histories are unrelated
+        if output.status.code() == Some(1) && output.stdout.is_empty() {
+            return Err(VisiGitError::NoMergeBase {
+                left: left.to_string(),
+                right: right.to_string(),
+            });
+        }
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn diff_worktree(&self, rev: &str) -> Result<Vec<DiffEntry>> {
+        // Resolving first also rejects ranges like `a..b`, which would make
+        // git compare two commits instead of the working tree
+        let commit = self.resolve_commit(rev)?;
+
+        let output = self.git(&["diff", "--raw", "-z", "-M", "--no-abbrev", &commit, "--"])?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+        let mut entries = parse_raw_diff(&output.stdout)?;
+
+        // git diff leaves untracked files out
+        let output = self.git(&["ls-files", "--others", "--exclude-standard", "-z"])?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+        entries.extend(
+            output
+                .stdout
+                .split(|b| *b == 0)
+                .filter(|path| !path.is_empty())
+                .map(|path| DiffEntry {
+                    path: String::from_utf8_lossy(path).into_owned(),
+                    orig_path: None,
+                    status: '?',
+                    old_id: None,
+                    new_id: None,
+                }),
+        );
+
+        // git reports an all-zero id for working tree files that differ
+        // from the index
+        for entry in &mut entries {
+            if entry.new_id.is_none() && entry.status != 'D' {
+                entry.new_id = worktree_blob_id(self, &self.root, &entry.path)?;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn diff_commits(&self, from: &str, to: &str, first_parent: bool) -> Result<Vec<DiffEntry>> {
+        let from = self.resolve_commit(from)?;
+        let to = self.resolve_commit(to)?;
+
+        let base = if first_parent {
+            self.first_parent_base(&from, &to)?
+        } else {
+            Some(from)
+        };
+
+        // --raw is --name-status with the modes and blob ids added
+        let output = match base {
+            Some(base) => {
+                self.git(&["diff", "--raw", "-z", "-M", "--no-abbrev", &base, &to, "--"])?
+            }
+            None => self.git(&[
+                "diff-tree",
+                "-r",
+                "--root",
+                "--no-commit-id",
+                "--raw",
+                "-z",
+                "-M",
+                "--no-abbrev",
+                &to,
+            ])?,
+        };
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        parse_raw_diff(&output.stdout)
+    }
+
+    fn file_history(
+        &self,
+        file_path: &str,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<FileHistoryEntry>> {
+        let file_path = validate::repo_path(file_path)?;
+
+        // An unborn branch has no HEAD to walk from
+        if !self.has_commit("HEAD")? {
+            return Err(VisiGitError::RevisionNotFound {
+                rev: "HEAD".to_string(),
+            });
+        }
+
+        // --skip counts commits that --follow later drops, so skip here
+        // instead. --first-parent also diffs merges against their first
+        // parent, so every listed commit has a record for the path, and
+        // literal pathspecs keep names like `logo[1].png` from being globs.
+        let output = self.git(&[
+            "--literal-pathspecs",
+            "log",
+            "--follow",
+            "--first-parent",
+            "-z",
+            "--raw",
+            "-M",
+            "--no-abbrev",
+            "--format=%H",
+            &format!("--max-count={}", skip + limit),
+            "HEAD",
+            "--",
+            &file_path,
+        ])?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        let changes: Vec<(String, DiffEntry)> = parse_raw_log(&output.stdout)?
+            .into_iter()
+            .skip(skip)
+            .filter_map(|(hash, changes)| Some((hash, changes.into_iter().next()?)))
+            .collect();
+        if changes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Commit details in a second pass: --no-abbrev above would also
+        // expand %h
+        let mut args = vec![
+            "log".to_string(),
+            "--no-walk=unsorted".to_string(),
+            "-z".to_string(),
+            format!("--format={}", COMMIT_FORMAT),
+            DECORATE.to_string(),
+        ];
+        args.extend(changes.iter().map(|(hash, _)| hash.clone()));
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let output = self.git(&args)?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        Ok(parse_commits(&output.stdout)?
+            .into_iter()
+            .zip(&changes)
+            .map(|(commit, (_, change))| FileHistoryEntry::new(commit, change))
+            .collect())
+    }
+
+    fn list_refs(&self) -> Result<Vec<RefInfo>> {
+        let output = self.git(&[
+            "for-each-ref",
+            &format!("--format={}", REF_FORMAT),
+            "refs/heads",
+            "refs/remotes",
+            "refs/tags",
+        ])?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        parse_refs(&output.stdout)
+    }
+
+    fn operation(&self) -> Result<Option<RepoOperation>> {
+        let output = self.git(&["rev-parse", "--absolute-git-dir"])?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        let git_dir = String::from_utf8_lossy(&output.stdout);
+        Ok(RepoOperation::in_git_dir(Path::new(git_dir.trim())))
+    }
+
+    fn stage(&self, paths: &[String]) -> Result<()> {
+        let input = nul_separated(paths)?;
+        if input.is_empty() {
+            return Ok(());
+        }
+
+        // update-index adds what exists and removes what does not, so
+        // deletions are staged without a pathspec that must match
+        let output = self.git_with_input(
+            &["update-index", "--add", "--remove", "-z", "--stdin"],
+            &input,
+        )?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        Ok(())
+    }
+
+    fn unstage(&self, paths: &[String]) -> Result<()> {
+        let input = nul_separated(paths)?;
+        if input.is_empty() {
+            return Ok(());
+        }
+
+        // On an unborn branch git reset removes the paths from the index
+        let output = self.git_with_input(
+            &[
+                "--literal-pathspecs",
+                "reset",
+                "--quiet",
+                "--pathspec-from-file=-",
+                "--pathspec-file-nul",
+            ],
+            &input,
+        )?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        Ok(())
+    }
+
+    fn commit(&self, message: &str, author: Option<&Identity>, amend: bool) -> Result<String> {
+        // The message goes through stdin so it is never read as an option;
+        // whitespace cleanup is what git does for -m and -F by default
+        let mut args = vec![
+            "commit".to_string(),
+            "--quiet".to_string(),
+            "--cleanup=whitespace".to_string(),
+            "--file=-".to_string(),
+        ];
+        if amend {
+            args.push("--amend".to_string());
+        }
+        if let Some(author) = author {
+            args.push(format!("--author={} <{}>", author.name, author.email));
+        }
+
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = self.git_with_input(&args, message.as_bytes())?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        self.resolve_commit("HEAD")
+    }
+
+    fn workdir(&self) -> Result<PathBuf> {
+        Ok(self.root.clone())
+    }
+
+    fn lfs_dir(&self) -> Result<PathBuf> {
+        let output = self.git(&["rev-parse", "--path-format=absolute", "--git-common-dir"])?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        let common_dir = String::from_utf8_lossy(&output.stdout);
+        Ok(PathBuf::from(common_dir.trim()).join("lfs"))
+    }
+
+    fn hash_blob(&self, content: &[u8]) -> Result<String> {
+        // Hashing in-process saves a git run per working tree file
+        if !self.sha256 {
+            return sha1_blob_id(content);
+        }
+
+        let output = self.git_with_input(&["hash-object", "--no-filters", "--stdin"], content)?;
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn smudge(&self, blob_id: &str, file_path: &str) -> Result<Option<Vec<u8>>> {
+        let id = validate::object_id(blob_id)?;
+        let path = validate::repo_path(file_path)?;
+
+        // git-lfs fails the filter when it cannot download the object
+        let output = self.git(&["cat-file", "--filters", &format!("--path={}", path), id])?;
+        Ok(output.status.success().then_some(output.stdout))
+    }
+
+    fn set_index_entry(&self, file_path: &str, blob_id: Option<&str>) -> Result<()> {
+        let path = validate::repo_path(file_path)?;
+
+        let output = match blob_id {
+            // A mode 0 record first removes every stage of the path
+            Some(id) => {
+                let id = validate::object_id(id)?;
+                let record = format!(
+                    "0 {}\t{}\x00{:o} {}\t{}\x00",
+                    "0".repeat(id.len()),
+                    path,
+                    kept_mode(&self.index_entries(&path)?, id),
+                    id,
+                    path
+                );
+                self.git_with_input(&["update-index", "-z", "--index-info"], record.as_bytes())?
+            }
+            None => self.git_with_input(
+                &["update-index", "--force-remove", "-z", "--stdin"],
+                &nul_separated(&[path])?,
+            )?,
+        };
+        if !output.status.success() {
+            return Err(VisiGitError::git_failed(&output));
+        }
+
+        Ok(())
+    }
+
+    fn shutdown(&self) {
+        self.batch.shutdown();
+    }
+}
+
+/// Validated paths as NUL-terminated records for `-z` stdin input
+fn nul_separated(paths: &[String]) -> Result<Vec<u8>> {
+    let mut input = Vec::new();
+    for path in paths {
+        input.extend_from_slice(validate::repo_path(path)?.as_bytes());
+        input.push(0);
+    }
+    Ok(input)
+}
+
+/// Pathspecs for a history filter. User paths are matched literally; the
+/// image filter becomes globs on the extensions, spelled with character
+/// classes so only the extension ignores case.
+fn history_pathspecs(filter: &HistoryFilter) -> Result<Vec<String>> {
+    let paths = filter
+        .paths
+        .iter()
+        .map(|path| validate::repo_path(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    if !filter.images_only {
+        return Ok(paths
+            .iter()
+            .map(|path| format!(":(literal){}", path))
+            .collect());
+    }
+
+    let extensions: Vec<String> = IMAGE_EXTENSIONS.iter().map(|e| any_case_glob(e)).collect();
+    if paths.is_empty() {
+        return Ok(extensions
+            .iter()
+            .map(|ext| format!(":(glob)**/*.{}", ext))
+            .collect());
+    }
+
+    let mut pathspecs = Vec::new();
+    for path in &paths {
+        // The path itself, if it names an image file...
+        if is_image_file(path) {
+            pathspecs.push(format!(":(literal){}", path));
+        }
+        // ...or images anywhere under it, if it is a folder
+        let folder = escape_glob(path);
+        pathspecs.extend(
+            extensions
+                .iter()
+                .map(|ext| format!(":(glob){}/**/*.{}", folder, ext)),
+        );
+    }
+    Ok(pathspecs)
+}
+
+/// `png` as `[pP][nN][gG]`
+fn any_case_glob(text: &str) -> String {
+    text.chars()
+        .map(|c| format!("[{}{}]", c.to_ascii_lowercase(), c.to_ascii_uppercase()))
+        .collect()
+}
+
+/// Escape glob wildcards so `path` only matches itself
+fn escape_glob(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Run git with the given arguments in `dir`
+fn git_in(dir: &Path, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(spawn_error)
+}
+
+/// Error for git failing to start
+fn spawn_error(e: io::Error) -> VisiGitError {
+    match e.kind() {
+        io::ErrorKind::NotFound => VisiGitError::GitNotInstalled,
+        _ => VisiGitError::from(e),
+    }
+}
+
+fn canonical_string(path: &str) -> Result<String> {
+    Ok(Path::new(path)
+        .canonicalize()?
+        .to_string_lossy()
+        .into_owned())
+}
@@ -0,0 +1,175 @@
+//! Parser for `git diff --raw -z --no-abbrev`.
+//!
+//! Each record is a `:`-prefixed header followed by one NUL-terminated path,
+//! or two for renames and copies (source, then destination). Object ids of
+//! all zeros mean "no blob" on that side, or for the working tree, "not
+//! hashed by git".
+
+use crate::error::{Result, VisiGitError};
+
+/// One changed path between two trees, or a tree and the working tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+    /// Path relative to the repository root, on the new side
+    pub path: String,
+    /// Path on the old side of a rename or copy
+    pub orig_path: Option<String>,
+    /// Status letter: `A`, `M`, `D`, `R`, `C`, `T`, `U`, or `?` for an
+    /// untracked file
+    pub status: char,
+    /// Blob id on the old side, `None` when the path did not exist
+    pub old_id: Option<String>,
+    /// Blob id on the new side, `None` when the path does not exist or
+    /// git did not hash the working tree file
+    pub new_id: Option<String>,
+}
+
+/// Parse the raw stdout of `git diff --raw -z --no-abbrev`
+pub fn parse_raw_diff(output: &[u8]) -> Result<Vec<DiffEntry>> {
+    let mut fields = nul_fields(output);
+    let mut entries = Vec::new();
+
+    while let Some(header) = fields.next() {
+        entries.push(parse_record(&header, &mut fields)?);
+    }
+
+    Ok(entries)
+}
+
+/// Non-empty NUL-separated fields of `output`, decoded lossily
+pub(crate) fn nul_fields(output: &[u8]) -> impl Iterator<Item = String> + '_ {
+    output
+        .split(|b| *b == 0)
+        .filter(|field| !field.is_empty())
+        .map(|field| String::from_utf8_lossy(field).into_owned())
+}
+
+/// Parse one record from its `:`-prefixed header, taking its path or paths
+/// from `fields`
+pub(crate) fn parse_record(
+    header: &str,
+    fields: &mut impl Iterator<Item = String>,
+) -> Result<DiffEntry> {
+    let malformed = || VisiGitError::UnexpectedOutput {
+        message: format!("Malformed git diff record: {:?}", header),
+    };
+
+    // :<old mode> <new mode> <old id> <new id> <status>[<score>]
+    let parts: Vec<&str> = header
+        .strip_prefix(':')
+        .ok_or_else(malformed)?
+        .split(' ')
+        .collect();
+    let [_, _, old_id, new_id, status] = parts.as_slice() else {
+        return Err(malformed());
+    };
+    let status = status.chars().next().ok_or_else(malformed)?;
+
+    let first = fields.next().ok_or_else(malformed)?;
+    let (path, orig_path) = match status {
+        'R' | 'C' => (fields.next().ok_or_else(malformed)?, Some(first)),
+        _ => (first, None),
+    };
+
+    Ok(DiffEntry {
+        path,
+        orig_path,
+        status,
+        old_id: object_id(old_id),
+        new_id: object_id(new_id),
+    })
+}
+
+/// `None` for git's all-zero placeholder id
+fn object_id(id: &str) -> Option<String> {
+    (!id.bytes().all(|b| b == b'0')).then(|| id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA_A: &str = "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391";
+    const SHA_B: &str = "5716ca5987cbf97d6bb54920bea6adde242d87e6";
+    const ZERO: &str = "0000000000000000000000000000000000000000";
+
+    #[test]
+    fn test_parse_empty_output() {
+        assert_eq!(parse_raw_diff(b"").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_parse_add_modify_delete() {
+        let output = format!(
+            ":000000 100644 {zero} {b} A\0new logo.png\0\
+             :100644 100644 {a} {zero} M\0icons/édit.png\0\
+             :100644 000000 {a} {zero} D\0old.png\0",
+            zero = ZERO,
+            a = SHA_A,
+            b = SHA_B
+        );
+
+        let entries = parse_raw_diff(output.as_bytes()).unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                DiffEntry {
+                    path: "new logo.png".to_string(),
+                    orig_path: None,
+                    status: 'A',
+                    old_id: None,
+                    new_id: Some(SHA_B.to_string()),
+                },
+                DiffEntry {
+                    path: "icons/édit.png".to_string(),
+                    orig_path: None,
+                    status: 'M',
+                    old_id: Some(SHA_A.to_string()),
+                    new_id: None,
+                },
+                DiffEntry {
+                    path: "old.png".to_string(),
+                    orig_path: None,
+                    status: 'D',
+                    old_id: Some(SHA_A.to_string()),
+                    new_id: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rename_reads_source_then_destination() {
+        let output = format!(
+            ":100644 100644 {a} {a} R100\0img/logo.svg\0brand/logo.svg\0",
+            a = SHA_A
+        );
+
+        let entries = parse_raw_diff(output.as_bytes()).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].status, 'R');
+        assert_eq!(entries[0].path, "brand/logo.svg");
+        assert_eq!(entries[0].orig_path.as_deref(), Some("img/logo.svg"));
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_records() {
+        for output in [
+            "M\0logo.png\0".to_string(),
+            format!(":100644 100644 {} M\0logo.png\0", SHA_A),
+            format!(":100644 100644 {a} {a} M\0", a = SHA_A),
+            format!(":100644 100644 {a} {a} R090\0only-one.png\0", a = SHA_A),
+        ] {
+            assert!(
+                matches!(
+                    parse_raw_diff(output.as_bytes()),
+                    Err(VisiGitError::UnexpectedOutput { .. })
+                ),
+                "for {:?}",
+                output
+            );
+        }
+    }
+}
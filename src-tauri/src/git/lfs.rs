@@ -0,0 +1,141 @@
+//! Parser for Git LFS pointer files.
+//!
+//! A file tracked by LFS is committed as a small text pointer naming the
+//! real object by its SHA-256. `git lfs fetch` stores objects under
+//! `<common dir>/lfs/objects/<oid[0..2]>/<oid[2..4]>/<oid>`.
+//!
+//! Spec: <https://github.com/git-lfs/git-lfs/blob/main/docs/spec.md>
+
+use std::path::{Path, PathBuf};
+
+/// First line of every pointer
+const VERSION_LINE: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// Pointers are always smaller than this, so larger blobs are not parsed
+const MAX_POINTER_SIZE: usize = 1024;
+
+/// The object an LFS pointer stands for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LfsPointer {
+    /// SHA-256 of the object, lowercase hex
+    pub oid: String,
+    /// Size of the object in bytes
+    pub size: u64,
+}
+
+impl LfsPointer {
+    /// Read `content` as a pointer, `None` if it is anything else
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        if content.len() >= MAX_POINTER_SIZE {
+            return None;
+        }
+        let text = std::str::from_utf8(content).ok()?;
+        let mut lines = text.lines();
+        if lines.next()? != VERSION_LINE {
+            return None;
+        }
+
+        let mut oid = None;
+        let mut size = None;
+        for line in lines {
+            let (key, value) = line.split_once(' ')?;
+            match key {
+                "oid" => oid = Some(value.strip_prefix("sha256:")?),
+                "size" => size = Some(value.parse().ok()?),
+                // Extension keys and the like do not change the object
+                _ => {}
+            }
+        }
+
+        let oid = oid.filter(|oid| {
+            oid.len() == 64
+                && oid
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+        })?;
+
+        Some(Self {
+            oid: oid.to_string(),
+            size: size?,
+        })
+    }
+
+    /// Where `git lfs fetch` stores the object, under the `lfs` folder of
+    /// the repository's common dir
+    pub fn object_path(&self, lfs_dir: &Path) -> PathBuf {
+        lfs_dir
+            .join("objects")
+            .join(&self.oid[0..2])
+            .join(&self.oid[2..4])
+            .join(&self.oid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OID: &str = "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    fn pointer(oid: &str, size: &str) -> String {
+        format!("{}\noid sha256:{}\nsize {}\n", VERSION_LINE, oid, size)
+    }
+
+    #[test]
+    fn test_parse_pointer() {
+        assert_eq!(
+            LfsPointer::parse(pointer(OID, "12345").as_bytes()),
+            Some(LfsPointer {
+                oid: OID.to_string(),
+                size: 12345,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_ignores_extension_keys() {
+        let content = format!(
+            "{}\next-0-foo sha256:{}\noid sha256:{}\nsize 3\n",
+            VERSION_LINE, OID, OID
+        );
+
+        assert_eq!(
+            LfsPointer::parse(content.as_bytes()).map(|pointer| pointer.size),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_other_content() {
+        for content in [
+            b"\x89PNG\r\n\x1a\n".to_vec(),
+            b"version https://example.com/spec/v1\n".to_vec(),
+            pointer(&OID[..63], "3").into_bytes(),
+            pointer(&OID.to_uppercase(), "3").into_bytes(),
+            pointer(OID, "-3").into_bytes(),
+            format!("{}\nsize 3\n", VERSION_LINE).into_bytes(),
+            // Too large to be a pointer, even though it starts like one
+            (pointer(OID, "3") + &"x".repeat(MAX_POINTER_SIZE)).into_bytes(),
+        ] {
+            assert_eq!(
+                LfsPointer::parse(&content),
+                None,
+                "for {:?}",
+                String::from_utf8_lossy(&content)
+            );
+        }
+    }
+
+    #[test]
+    fn test_object_path_fans_out_by_oid() {
+        let pointer = LfsPointer {
+            oid: OID.to_string(),
+            size: 1,
+        };
+
+        assert_eq!(
+            pointer.object_path(Path::new("/repo/.git/lfs")),
+            Path::new("/repo/.git/lfs/objects/4d/7a").join(OID)
+        );
+    }
+}
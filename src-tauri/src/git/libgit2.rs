@@ -0,0 +1,1110 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use git2::{
+    AttrCheckFlags, AttrValue, Commit, Delta, DiffDelta, DiffFindOptions, DiffOptions, ErrorCode,
+    IndexEntry, IndexTime, ObjectType, Oid, Reference, ReferenceType, Repository, RepositoryState,
+    Sort, Status, StatusOptions, Tree,
+};
+
+use super::{
+    is_image_file, is_listed_ref, kept_mode, split_message, validate, worktree_blob_id,
+    BackendKind, Blob, CliBackend, CoAuthor, CommitInfo, ConflictBlobs, ConflictStage, DiffEntry,
+    FileHistoryEntry, GitBackend, HistoryFilter, Identity, RefInfo, RefKind, RepoInfo,
+    RepoOperation, StatusEntry,
+};
+use crate::error::{Result, VisiGitError};
+
+/// In-process git backend built on libgit2. Does not need git on PATH.
+pub struct Libgit2Backend {
+    // git2::Repository is Send but not Sync, so serialize access to it
+    repo: Mutex<Repository>,
+}
+
+impl Libgit2Backend {
+    pub fn open(repo_path: &Path) -> Result<Self> {
+        let root = validate::repo_root(repo_path)?;
+
+        let repo = Repository::open(&root).map_err(|e| match e.code() {
+            ErrorCode::NotFound => VisiGitError::NotARepository {
+                path: repo_path.display().to_string(),
+            },
+            _ => VisiGitError::from(e),
+        })?;
+
+        Ok(Self {
+            repo: Mutex::new(repo),
+        })
+    }
+
+    /// Find the repository containing `path` by walking up its parents
+    pub fn discover(path: &Path) -> Result<RepoInfo> {
+        let start = validate::repo_root(path)?;
+
+        let repo = Repository::discover(&start).map_err(|e| match e.code() {
+            ErrorCode::NotFound => VisiGitError::NotARepository {
+                path: path.display().to_string(),
+            },
+            _ => VisiGitError::from(e),
+        })?;
+
+        let toplevel = repo.workdir().map(canonical_string).transpose()?;
+        let is_submodule = match repo.workdir() {
+            Some(workdir) => is_submodule_checkout(workdir)?,
+            None => false,
+        };
+
+        Ok(RepoInfo {
+            toplevel,
+            git_dir: canonical_string(repo.path())?,
+            common_dir: canonical_string(repo.commondir())?,
+            is_bare: repo.is_bare(),
+            is_worktree: repo.is_worktree(),
+            is_submodule,
+            branch: current_branch(&repo)?,
+        })
+    }
+
+    fn repo(&self) -> std::sync::MutexGuard<'_, Repository> {
+        // A poisoned lock only means another command panicked mid-read;
+        // the repository handle itself is still usable
+        self.repo.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Content of the blob staged for `file_path` at index stage `stage`,
+    /// failing with `missing()` if there is none
+    fn read_staged(
+        &self,
+        file_path: &str,
+        stage: u8,
+        missing: impl Fn() -> VisiGitError,
+    ) -> Result<Blob> {
+        let repo = self.repo();
+
+        // Pick up changes other processes made to the index file
+        let mut index = repo.index()?;
+        index.read(false)?;
+
+        let entry = index
+            .get_path(Path::new(file_path), i32::from(stage))
+            .ok_or_else(&missing)?;
+        // A submodule is staged as a commit, not a blob
+        let blob = repo.find_blob(entry.id).map_err(|_| missing())?;
+
+        Ok(Blob {
+            id: blob.id().to_string(),
+            content: blob.content().to_vec(),
+        })
+    }
+}
+
+impl GitBackend for Libgit2Backend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Libgit2
+    }
+
+    fn status(&self) -> Result<Vec<StatusEntry>> {
+        let repo = self.repo();
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false)
+            .renames_head_to_index(true);
+
+        let statuses = repo.statuses(Some(&mut opts))?;
+
+        let mut conflicts = conflict_stages(&repo)?;
+        let mut entries = Vec::new();
+
+        for entry in statuses.iter() {
+            let flags = entry.status();
+
+            // entry.path() reports the old path of a rename, so read both
+            // sides from the HEAD-to-index delta instead
+            let (path, orig_path) = match entry.head_to_index() {
+                Some(delta) if flags.contains(Status::INDEX_RENAMED) => (
+                    delta.new_file().path(),
+                    delta.old_file().path().map(path_to_string),
+                ),
+                _ => (entry.path().map(Path::new), None),
+            };
+            let Some(path) = path else {
+                continue;
+            };
+
+            let path = path_to_string(path);
+            let ((index, worktree), conflict) = match conflicts.remove(&path) {
+                Some((codes, blobs)) if flags.is_conflicted() => (codes, Some(blobs)),
+                _ => (status_codes(flags), None),
+            };
+
+            entries.push(StatusEntry {
+                path,
+                orig_path,
+                index,
+                worktree,
+                conflict,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    fn log(&self, filter: &HistoryFilter, skip: usize, limit: usize) -> Result<Vec<CommitInfo>> {
+        let repo = self.repo();
+        let start = resolve_commit(&repo, filter.start.as_deref().unwrap_or("HEAD"))?;
+        let paths = filter
+            .paths
+            .iter()
+            .map(|path| validate::repo_path(path))
+            .collect::<Result<Vec<_>>>()?;
+        // ASCII-only case folding, like git's -F -i
+        let needle = |text: &Option<String>| {
+            text.as_deref()
+                .filter(|text| !text.is_empty())
+                .map(str::to_ascii_lowercase)
+        };
+        let author = needle(&filter.author);
+        let grep = needle(&filter.grep);
+
+        let refs = ref_names(&repo)?;
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME)?;
+        revwalk.push(start)?;
+        if filter.all_branches {
+            revwalk.push_glob("refs/heads")?;
+            revwalk.push_glob("refs/remotes")?;
+        }
+
+        let mut commits = Vec::new();
+        let mut skipped = 0;
+
+        for oid in revwalk {
+            if commits.len() == limit {
+                break;
+            }
+
+            let commit = repo.find_commit(oid?)?;
+            let time = commit.time().seconds();
+            if filter.since.is_some_and(|since| time < since)
+                || filter.until.is_some_and(|until| time > until)
+            {
+                continue;
+            }
+            if let Some(author) = &author {
+                let signature = commit.author();
+                let ident = format!(
+                    "{} <{}>",
+                    String::from_utf8_lossy(signature.name_bytes()),
+                    String::from_utf8_lossy(signature.email_bytes())
+                );
+                if !ident.to_ascii_lowercase().contains(author.as_str()) {
+                    continue;
+                }
+            }
+            if let Some(grep) = &grep {
+                let message = String::from_utf8_lossy(commit.message_bytes());
+                if !message.to_ascii_lowercase().contains(grep.as_str()) {
+                    continue;
+                }
+            }
+            if (!paths.is_empty() || filter.images_only)
+                && !touches(&repo, &commit, &paths, filter.images_only)?
+            {
+                continue;
+            }
+
+            if skipped < skip {
+                skipped += 1;
+                continue;
+            }
+            commits.push(commit_info(&commit, &refs));
+        }
+
+        Ok(commits)
+    }
+
+    fn read_blob(&self, rev: &str, file_path: &str) -> Result<Blob> {
+        let rev = validate::revision(rev)?;
+        let file_path = validate::repo_path(file_path)?;
+        let repo = self.repo();
+
+        // Resolve the revision and the path separately so each failure
+        // maps to its own error
+        let tree = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|_| VisiGitError::RevisionNotFound {
+                rev: rev.to_string(),
+            })?;
+        let entry =
+            tree.get_path(Path::new(&file_path))
+                .map_err(|_| VisiGitError::PathNotInRevision {
+                    path: file_path.to_string(),
+                    rev: rev.to_string(),
+                })?;
+        let blob =
+            entry
+                .to_object(&repo)?
+                .into_blob()
+                .map_err(|_| VisiGitError::PathNotInRevision {
+                    path: file_path.to_string(),
+                    rev: rev.to_string(),
+                })?;
+
+        Ok(Blob {
+            id: blob.id().to_string(),
+            content: blob.content().to_vec(),
+        })
+    }
+
+    fn read_blob_by_id(&self, id: &str) -> Result<Blob> {
+        let id = validate::object_id(id)?;
+        let repo = self.repo();
+
+        let blob = Oid::from_str(id)
+            .and_then(|oid| repo.find_blob(oid))
+            .map_err(|_| VisiGitError::RevisionNotFound {
+                rev: id.to_string(),
+            })?;
+
+        Ok(Blob {
+            id: blob.id().to_string(),
+            content: blob.content().to_vec(),
+        })
+    }
+
+    fn read_index_blob(&self, file_path: &str) -> Result<Blob> {
+        let file_path = validate::repo_path(file_path)?;
+        self.read_staged(&file_path, 0, || VisiGitError::PathNotInIndex {
+            path: file_path.clone(),
+        })
+    }
+
+    fn read_stage_blob(&self, file_path: &str, stage: ConflictStage) -> Result<Blob> {
+        let file_path = validate::repo_path(file_path)?;
+        self.read_staged(&file_path, stage.number(), || {
+            VisiGitError::PathNotInStage {
+                path: file_path.clone(),
+                stage,
+            }
+        })
+    }
+
+    fn merge_base(&self, left: &str, right: &str) -> Result<String> {
+        let repo = self.repo();
+        let left_id = resolve_commit(&repo, left)?;
+        let right_id = resolve_commit(&repo, right)?;
+
+        match repo.merge_base(left_id, right_id) {
+            Ok(base) => Ok(base.to_string()),
+            Err(e) if e.code() == ErrorCode::NotFound => Err(VisiGitError::NoMergeBase {
+                left: left.to_string(),
+                right: right.to_string(),
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn diff_worktree(&self, rev: &str) -> Result<Vec<DiffEntry>> {
+        let repo = self.repo();
+        let tree = repo.find_commit(resolve_commit(&repo, rev)?)?.tree()?;
+        let root = repo.workdir().ok_or_else(no_work_tree)?;
+
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+
+        // Same comparison as `git diff <rev>`: the tree against the working
+        // tree, using the index to see through renames staged with `git mv`
+        let mut diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut opts))?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+        let mut entries = Vec::new();
+
+        for delta in diff.deltas() {
+            let Some(mut entry) = diff_entry(&delta) else {
+                continue;
+            };
+            // libgit2 only hashes working tree files when it has to
+            if entry.new_id.is_none() && entry.status != 'D' {
+                entry.new_id = worktree_blob_id(self, root, &entry.path)?;
+            }
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    fn diff_commits(&self, from: &str, to: &str, first_parent: bool) -> Result<Vec<DiffEntry>> {
+        let repo = self.repo();
+        let from = resolve_commit(&repo, from)?;
+        let to = resolve_commit(&repo, to)?;
+
+        let base = if first_parent {
+            first_parent_base(&repo, from, to)?
+        } else {
+            Some(from)
+        };
+        let old_tree = base
+            .map(|id| repo.find_commit(id).and_then(|commit| commit.tree()))
+            .transpose()?;
+        let new_tree = repo.find_commit(to)?.tree()?;
+
+        let mut diff = repo.diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+        Ok(diff
+            .deltas()
+            .filter_map(|delta| diff_entry(&delta))
+            .collect())
+    }
+
+    fn file_history(
+        &self,
+        file_path: &str,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<FileHistoryEntry>> {
+        let mut path = validate::repo_path(file_path)?;
+        let repo = self.repo();
+        require_head(&repo)?;
+        let refs = ref_names(&repo)?;
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.simplify_first_parent()?;
+        revwalk.push_head()?;
+
+        let mut entries = Vec::new();
+        let mut skipped = 0;
+
+        for oid in revwalk {
+            if entries.len() == limit {
+                break;
+            }
+
+            let commit = repo.find_commit(oid?)?;
+            let Some(change) = follow_change(&repo, &commit, &path)? else {
+                continue;
+            };
+            // Older commits know the file by its name before the rename
+            if let Some(orig_path) = &change.orig_path {
+                path = orig_path.clone();
+            }
+
+            if skipped < skip {
+                skipped += 1;
+                continue;
+            }
+            entries.push(FileHistoryEntry::new(commit_info(&commit, &refs), &change));
+        }
+
+        Ok(entries)
+    }
+
+    fn list_refs(&self) -> Result<Vec<RefInfo>> {
+        let repo = self.repo();
+        let head = repo
+            .head()
+            .ok()
+            .and_then(|head| head.name().map(str::to_string));
+        let mut refs = Vec::new();
+
+        for reference in repo.references()? {
+            let reference = reference?;
+            if reference.kind() != Some(ReferenceType::Direct) {
+                continue;
+            }
+            let Some(name) = reference.name() else {
+                continue;
+            };
+            let Some((kind, short_name)) = RefKind::of(name) else {
+                continue;
+            };
+            let Some(commit) = ref_commit(&repo, &reference)? else {
+                continue;
+            };
+
+            let (upstream, ahead, behind) = match kind {
+                RefKind::Branch => upstream_of(&repo, name, commit.id())?,
+                _ => (None, None, None),
+            };
+            let time = commit.committer().when();
+
+            refs.push(RefInfo {
+                name: name.to_string(),
+                short_name: short_name.to_string(),
+                kind,
+                target: commit.id().to_string(),
+                upstream,
+                ahead,
+                behind,
+                date: format_iso_date(time.seconds(), time.offset_minutes()),
+                timestamp: time.seconds(),
+                is_head: head.as_deref() == Some(name),
+            });
+        }
+
+        refs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(refs)
+    }
+
+    fn operation(&self) -> Result<Option<RepoOperation>> {
+        Ok(match self.repo().state() {
+            RepositoryState::Merge => Some(RepoOperation::Merge),
+            RepositoryState::Revert | RepositoryState::RevertSequence => {
+                Some(RepoOperation::Revert)
+            }
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                Some(RepoOperation::CherryPick)
+            }
+            RepositoryState::Rebase
+            | RepositoryState::RebaseInteractive
+            | RepositoryState::RebaseMerge
+            | RepositoryState::ApplyMailboxOrRebase => Some(RepoOperation::Rebase),
+            RepositoryState::ApplyMailbox => Some(RepoOperation::ApplyMailbox),
+            RepositoryState::Clean | RepositoryState::Bisect => None,
+        })
+    }
+
+    fn stage(&self, paths: &[String]) -> Result<()> {
+        let paths = paths
+            .iter()
+            .map(|path| validate::repo_path(path))
+            .collect::<Result<Vec<_>>>()?;
+        let repo = self.repo();
+        let root = repo.workdir().ok_or_else(no_work_tree)?;
+
+        // libgit2 only runs its built-in filters, so a path with a filter
+        // driver, like git-lfs's, is left to git to run the clean filter
+        let mut filtered = Vec::new();
+        let mut index = repo.index()?;
+        index.read(false)?;
+        for path in paths {
+            if has_filter_driver(&repo, &path)? {
+                filtered.push(path);
+            } else if root.join(&path).symlink_metadata().is_ok() {
+                // Both calls drop any conflict stages for the path
+                index.add_path(Path::new(&path))?;
+            } else {
+                index.remove_path(Path::new(&path))?;
+            }
+        }
+        index.write()?;
+
+        if !filtered.is_empty() {
+            CliBackend::open(root)?.stage(&filtered)?;
+        }
+
+        Ok(())
+    }
+
+    fn unstage(&self, paths: &[String]) -> Result<()> {
+        let paths = paths
+            .iter()
+            .map(|path| validate::repo_path(path))
+            .collect::<Result<Vec<_>>>()?;
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let repo = self.repo();
+
+        // Without a target, reset_default removes the paths from the index
+        let head = head_commit(&repo)?;
+        repo.reset_default(head.as_ref().map(Commit::as_object), &paths)?;
+
+        Ok(())
+    }
+
+    fn commit(&self, message: &str, author: Option<&Identity>, amend: bool) -> Result<String> {
+        // libgit2 runs no hooks, does not sign commits and does not take
+        // the author of a cherry-picked commit, so git commits instead
+        let root = self.workdir()?;
+        CliBackend::open(&root)?.commit(message, author, amend)
+    }
+
+    fn workdir(&self) -> Result<PathBuf> {
+        let repo = self.repo();
+        let workdir = repo.workdir().ok_or_else(no_work_tree)?;
+        Ok(workdir.canonicalize()?)
+    }
+
+    fn lfs_dir(&self) -> Result<PathBuf> {
+        Ok(self.repo().commondir().join("lfs"))
+    }
+
+    // libgit2 cannot open a SHA-256 repository, so ids are always SHA-1
+    fn hash_blob(&self, content: &[u8]) -> Result<String> {
+        sha1_blob_id(content)
+    }
+
+    fn smudge(&self, blob_id: &str, file_path: &str) -> Result<Option<Vec<u8>>> {
+        // libgit2 only has its built-in filters; git-lfs is an external
+        // filter process that needs git itself
+        let root = self.workdir()?;
+        CliBackend::open(&root)?.smudge(blob_id, file_path)
+    }
+
+    fn set_index_entry(&self, file_path: &str, blob_id: Option<&str>) -> Result<()> {
+        let path = validate::repo_path(file_path)?;
+        let repo = self.repo();
+        let mut index = repo.index()?;
+        index.read(false)?;
+
+        match blob_id {
+            Some(id) => {
+                let blob = repo.find_blob(Oid::from_str(validate::object_id(id)?)?)?;
+                let entries: Vec<_> = (0..=3)
+                    .filter_map(|stage| {
+                        let entry = index.get_path(Path::new(&path), stage)?;
+                        Some((stage as u32, entry.mode, entry.id.to_string()))
+                    })
+                    .collect();
+                match index.conflict_remove(Path::new(&path)) {
+                    Err(e) if e.code() != ErrorCode::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+                // Zeroed stat data makes status hash the working tree
+                // file instead of trusting the entry
+                index.add(&IndexEntry {
+                    ctime: IndexTime::new(0, 0),
+                    mtime: IndexTime::new(0, 0),
+                    dev: 0,
+                    ino: 0,
+                    mode: kept_mode(&entries, id),
+                    uid: 0,
+                    gid: 0,
+                    file_size: u32::try_from(blob.size()).unwrap_or(u32::MAX),
+                    id: blob.id(),
+                    flags: 0,
+                    flags_extended: 0,
+                    path: path.into_bytes(),
+                })?;
+            }
+            None => index.remove_path(Path::new(&path))?,
+        }
+        index.write()?;
+
+        Ok(())
+    }
+
+    // Everything runs in-process
+    fn shutdown(&self) {}
+}
+
+/// Whether `.gitattributes` gives `path` a `filter` driver
+fn has_filter_driver(repo: &Repository, path: &str) -> Result<bool> {
+    let value = repo.get_attr(Path::new(path), "filter", AttrCheckFlags::FILE_THEN_INDEX)?;
+    Ok(matches!(
+        AttrValue::from_string(value),
+        AttrValue::String(_)
+    ))
+}
+
+/// Convert a delta to git's raw diff form. `new_id` is left empty when
+/// libgit2 did not hash the new side.
+fn diff_entry(delta: &DiffDelta) -> Option<DiffEntry> {
+    let status = match delta.status() {
+        Delta::Added => 'A',
+        Delta::Deleted => 'D',
+        Delta::Modified => 'M',
+        Delta::Renamed => 'R',
+        Delta::Copied => 'C',
+        Delta::Typechange => 'T',
+        Delta::Untracked => '?',
+        Delta::Conflicted => 'U',
+        _ => return None,
+    };
+    let path = path_to_string(delta.new_file().path().or(delta.old_file().path())?);
+
+    let orig_path = match status {
+        'R' | 'C' => delta.old_file().path().map(path_to_string),
+        _ => None,
+    };
+    let old_id = match status {
+        'A' | '?' => None,
+        _ => Some(delta.old_file().id().to_string()),
+    };
+    let new_file = delta.new_file();
+    let new_id = (status != 'D' && new_file.is_valid_id() && !new_file.id().is_zero())
+        .then(|| new_file.id().to_string());
+
+    Some(DiffEntry {
+        path,
+        orig_path,
+        status,
+        old_id,
+        new_id,
+    })
+}
+
+/// Parent of the oldest commit on `to`'s first-parent history that is not
+/// reachable from `from`; `to` itself when there is none, and `None` when
+/// the walk ends at a root commit
+fn first_parent_base(repo: &Repository, from: Oid, to: Oid) -> Result<Option<Oid>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+    revwalk.simplify_first_parent()?;
+    revwalk.push(to)?;
+    revwalk.hide(from)?;
+
+    let Some(oldest) = revwalk.last().transpose()? else {
+        return Ok(Some(to));
+    };
+
+    Ok(repo.find_commit(oldest)?.parent_ids().next())
+}
+
+/// How `commit` changed `path` compared with its first parent, or `None`
+/// if it left the file alone. A file that appears at `path` is checked for
+/// a rename, like `git log --follow` does.
+fn follow_change(repo: &Repository, commit: &Commit, path: &str) -> Result<Option<DiffEntry>> {
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+
+    let new_id = blob_id_at(&tree, path);
+    let old_id = parent_tree.as_ref().and_then(|tree| blob_id_at(tree, path));
+    let status = match (&old_id, &new_id) {
+        (None, None) => return Ok(None),
+        (Some(old), Some(new)) if old == new => return Ok(None),
+        (None, Some(_)) => 'A',
+        (Some(_), None) => 'D',
+        (Some(_), Some(_)) => 'M',
+    };
+
+    if let (Some(parent_tree), 'A') = (&parent_tree, status) {
+        let mut diff = repo.diff_tree_to_tree(Some(parent_tree), Some(&tree), None)?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+        let renamed = diff
+            .deltas()
+            .filter_map(|delta| diff_entry(&delta))
+            .find(|entry| entry.status == 'R' && entry.path == path);
+        if renamed.is_some() {
+            return Ok(renamed);
+        }
+    }
+
+    Ok(Some(DiffEntry {
+        path: path.to_string(),
+        orig_path: None,
+        status,
+        old_id,
+        new_id,
+    }))
+}
+
+/// Whether `commit` changes a file at or under one of `paths` (anywhere,
+/// if empty) compared with at least one parent, like `git log
+/// --full-history -- <paths>`. With `images_only`, only image files count.
+fn touches(
+    repo: &Repository,
+    commit: &Commit,
+    paths: &[String],
+    images_only: bool,
+) -> Result<bool> {
+    let tree = commit.tree()?;
+    let mut opts = DiffOptions::new();
+    opts.disable_pathspec_match(true);
+    for path in paths {
+        opts.pathspec(path);
+    }
+
+    // A root commit is compared with the empty tree
+    let parent_trees = match commit.parent_count() {
+        0 => vec![None],
+        _ => commit
+            .parents()
+            .map(|parent| parent.tree().map(Some))
+            .collect::<std::result::Result<Vec<_>, _>>()?,
+    };
+
+    for parent_tree in parent_trees {
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts))?;
+        let changed = diff.deltas().any(|delta| {
+            !images_only
+                || delta
+                    .new_file()
+                    .path()
+                    .or(delta.old_file().path())
+                    .is_some_and(|path| is_image_file(&path_to_string(path)))
+        });
+        if changed {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Id of the blob at `path` in `tree`, `None` if there is no file there
+fn blob_id_at(tree: &Tree, path: &str) -> Option<String> {
+    tree.get_path(Path::new(path))
+        .ok()
+        .filter(|entry| entry.kind() == Some(ObjectType::Blob))
+        .map(|entry| entry.id().to_string())
+}
+
+/// Fail with `RevisionNotFound` on an unborn branch, which has no HEAD to
+/// walk from
+fn require_head(repo: &Repository) -> Result<()> {
+    match repo.head() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(match e.code() {
+            ErrorCode::UnbornBranch | ErrorCode::NotFound => VisiGitError::RevisionNotFound {
+                rev: "HEAD".to_string(),
+            },
+            _ => VisiGitError::from(e),
+        }),
+    }
+}
+
+/// Error for a bare repository, which has no working tree to read or
+/// write
+fn no_work_tree() -> VisiGitError {
+    VisiGitError::GitFailed {
+        exit_code: None,
+        stderr: "this operation must be run in a work tree".to_string(),
+    }
+}
+
+/// Commit HEAD points at, `None` on an unborn branch
+fn head_commit(repo: &Repository) -> Result<Option<Commit<'_>>> {
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_commit()?)),
+        Err(e) if matches!(e.code(), ErrorCode::UnbornBranch | ErrorCode::NotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn commit_info(commit: &Commit, refs: &HashMap<Oid, Vec<String>>) -> CommitInfo {
+    let oid = commit.id();
+    let short_hash = commit
+        .as_object()
+        .short_id()
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string))
+        .unwrap_or_else(|| oid.to_string()[..7].to_string());
+    let author = commit.author();
+    let committer = commit.committer();
+    let time = author.when();
+    let raw_message = String::from_utf8_lossy(commit.message_bytes());
+    let (message, body) = split_message(&raw_message);
+    let co_authors = git2::message_trailers_strs(&raw_message)
+        .map(|trailers| {
+            trailers
+                .iter()
+                .filter(|(key, _)| key.eq_ignore_ascii_case("Co-authored-by"))
+                .map(|(_, value)| CoAuthor::parse(value))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    CommitInfo {
+        hash: oid.to_string(),
+        short_hash,
+        message,
+        body,
+        parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+        author: String::from_utf8_lossy(author.name_bytes()).into_owned(),
+        author_email: String::from_utf8_lossy(author.email_bytes()).into_owned(),
+        date: format_iso_date(time.seconds(), time.offset_minutes()),
+        timestamp: time.seconds(),
+        timezone_offset: time.offset_minutes(),
+        committer: String::from_utf8_lossy(committer.name_bytes()).into_owned(),
+        committer_email: String::from_utf8_lossy(committer.email_bytes()).into_owned(),
+        co_authors,
+        refs: refs.get(&oid).cloned().unwrap_or_default(),
+    }
+}
+
+/// Names of the refs pointing at each commit, in the form `CommitInfo::refs`
+/// lists them. Refs that do not lead to a commit are left out.
+fn ref_names(repo: &Repository) -> Result<HashMap<Oid, Vec<String>>> {
+    let mut refs: HashMap<Oid, Vec<String>> = HashMap::new();
+
+    for reference in repo.references()? {
+        let reference = reference?;
+        let Some(name) = reference.name().filter(|name| is_listed_ref(name)) else {
+            continue;
+        };
+        if let Ok(commit) = reference.peel_to_commit() {
+            refs.entry(commit.id()).or_default().push(name.to_string());
+        }
+    }
+    if let Ok(commit) = repo.head().and_then(|head| head.peel_to_commit()) {
+        refs.entry(commit.id())
+            .or_default()
+            .push("HEAD".to_string());
+    }
+
+    for names in refs.values_mut() {
+        names.sort();
+    }
+    Ok(refs)
+}
+
+/// Commit a direct ref points at, looking through one annotated tag like
+/// `%(*objectname)` does; `None` if that does not lead to a commit
+fn ref_commit<'r>(repo: &'r Repository, reference: &Reference) -> Result<Option<Commit<'r>>> {
+    let Some(oid) = reference.target() else {
+        return Ok(None);
+    };
+    let object = repo.find_object(oid, None)?;
+    let object = match object.as_tag() {
+        Some(tag) => tag.target()?,
+        None => object,
+    };
+
+    Ok(object.into_commit().ok())
+}
+
+/// Configured upstream of the branch `name`, with the commits the branch
+/// is ahead and behind by. The counts are `None` when the upstream ref is
+/// missing.
+fn upstream_of(
+    repo: &Repository,
+    name: &str,
+    oid: Oid,
+) -> Result<(Option<String>, Option<usize>, Option<usize>)> {
+    let upstream = match repo.branch_upstream_name(name) {
+        Ok(buf) => buf.as_str().map(str::to_string),
+        Err(e) if e.code() == ErrorCode::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let Some(upstream) = upstream else {
+        return Ok((None, None, None));
+    };
+
+    let Ok(upstream_oid) = repo.refname_to_id(&upstream) else {
+        return Ok((Some(upstream), None, None));
+    };
+    let upstream_oid = repo.find_object(upstream_oid, None)?.peel_to_commit()?.id();
+    let (ahead, behind) = repo.graph_ahead_behind(oid, upstream_oid)?;
+
+    Ok((Some(upstream), Some(ahead), Some(behind)))
+}
+
+/// Full id of the commit `rev` points at
+fn resolve_commit(repo: &Repository, rev: &str) -> Result<Oid> {
+    let rev = validate::revision(rev)?;
+
+    repo.revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map(|commit| commit.id())
+        .map_err(|_| VisiGitError::RevisionNotFound {
+            rev: rev.to_string(),
+        })
+}
+
+/// SHA-1 object id `content` would get as a git blob, like `git
+/// hash-object` in a SHA-1 repository
+pub(crate) fn sha1_blob_id(content: &[u8]) -> Result<String> {
+    Ok(Oid::hash_object(ObjectType::Blob, content)?.to_string())
+}
+
+impl From<git2::Error> for VisiGitError {
+    fn from(e: git2::Error) -> Self {
+        VisiGitError::GitFailed {
+            exit_code: None,
+            stderr: e.message().to_string(),
+        }
+    }
+}
+
+/// Unmerged XY codes of a conflicted path, with its stage blob ids
+type Conflict = ((char, char), ConflictBlobs);
+
+/// Conflict for every unmerged path. The XY codes are derived from which
+/// index stages (base, ours, theirs) are present, following git's own
+/// table.
+fn conflict_stages(repo: &Repository) -> Result<HashMap<String, Conflict>> {
+    let index = repo.index()?;
+    let mut conflicts = HashMap::new();
+
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let stages = (
+            conflict.ancestor.is_some(),
+            conflict.our.is_some(),
+            conflict.their.is_some(),
+        );
+        let blob_id = |entry: &Option<IndexEntry>| entry.as_ref().map(|entry| entry.id.to_string());
+        let blobs = ConflictBlobs {
+            base: blob_id(&conflict.ancestor),
+            ours: blob_id(&conflict.our),
+            theirs: blob_id(&conflict.their),
+        };
+        let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) else {
+            continue;
+        };
+
+        let xy = match stages {
+            (true, false, false) => ('D', 'D'),
+            (false, true, false) => ('A', 'U'),
+            (true, true, false) => ('U', 'D'),
+            (false, false, true) => ('U', 'A'),
+            (true, false, true) => ('D', 'U'),
+            (false, true, true) => ('A', 'A'),
+            _ => ('U', 'U'),
+        };
+
+        conflicts.insert(
+            String::from_utf8_lossy(&entry.path).into_owned(),
+            (xy, blobs),
+        );
+    }
+
+    Ok(conflicts)
+}
+
+/// Translate libgit2 status flags into porcelain v2 XY codes
+fn status_codes(flags: Status) -> (char, char) {
+    if flags.is_conflicted() {
+        return ('U', 'U');
+    }
+    if flags.is_wt_new() {
+        return ('?', '?');
+    }
+    if flags.is_ignored() {
+        return ('!', '!');
+    }
+
+    let index = if flags.is_index_new() {
+        'A'
+    } else if flags.is_index_deleted() {
+        'D'
+    } else if flags.is_index_renamed() {
+        'R'
+    } else if flags.is_index_typechange() {
+        'T'
+    } else if flags.is_index_modified() {
+        'M'
+    } else {
+        '.'
+    };
+
+    let worktree = if flags.is_wt_deleted() {
+        'D'
+    } else if flags.is_wt_renamed() {
+        'R'
+    } else if flags.is_wt_typechange() {
+        'T'
+    } else if flags.is_wt_modified() {
+        'M'
+    } else {
+        '.'
+    };
+
+    (index, worktree)
+}
+
+/// Whether the working tree at `workdir` is a registered submodule of the
+/// repository above it
+fn is_submodule_checkout(workdir: &Path) -> Result<bool> {
+    let workdir = workdir.canonicalize()?;
+    let Some(parent) = workdir.parent() else {
+        return Ok(false);
+    };
+    let Ok(superproject) = Repository::discover(parent) else {
+        return Ok(false);
+    };
+    let Some(super_workdir) = superproject.workdir() else {
+        return Ok(false);
+    };
+    let Ok(relative) = workdir.strip_prefix(super_workdir.canonicalize()?) else {
+        return Ok(false);
+    };
+
+    let submodules = superproject.submodules()?;
+    let registered = submodules
+        .iter()
+        .any(|submodule| submodule.path() == relative);
+    Ok(registered)
+}
+
+/// Short name of the branch HEAD points at, including an unborn one,
+/// or `None` when HEAD is detached
+fn current_branch(repo: &Repository) -> Result<Option<String>> {
+    let head = repo.find_reference("HEAD")?;
+
+    Ok(head
+        .symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/heads/"))
+        .map(str::to_string))
+}
+
+fn canonical_string(path: &Path) -> Result<String> {
+    Ok(path_to_string(&path.canonicalize()?))
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Format a timestamp the way `git log --format=%ai` does,
+/// e.g. `2024-03-09 14:05:00 +0100`
+fn format_iso_date(seconds: i64, offset_minutes: i32) -> String {
+    let local = seconds + i64::from(offset_minutes) * 60;
+    let days = local.div_euclid(86_400);
+    let secs_of_day = local.rem_euclid(86_400);
+
+    // Civil-from-days conversion (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let sign = if offset_minutes < 0 { '-' } else { '+' };
+    let offset = offset_minutes.abs();
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60,
+        sign,
+        offset / 60,
+        offset % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha1_blob_id_matches_git_hash_object() {
+        // `printf 'hello' | git hash-object --stdin`
+        assert_eq!(
+            sha1_blob_id(b"hello").unwrap(),
+            "b6fc4c620b67d95f953a5c1c1230aaab5db5a1b0"
+        );
+    }
+
+    #[test]
+    fn test_format_iso_date_matches_git_format() {
+        assert_eq!(format_iso_date(0, 0), "1970-01-01 00:00:00 +0000");
+        assert_eq!(
+            format_iso_date(1_710_000_000, 60),
+            "2024-03-09 17:00:00 +0100"
+        );
+        assert_eq!(
+            format_iso_date(1_710_000_000, -300),
+            "2024-03-09 11:00:00 -0500"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Parsers for `git log -z` output.
+//!
+//! `--format=COMMIT_FORMAT` gives one NUL-terminated header per commit.
+//! For `--raw` walks the format is just `%H`, since `--no-abbrev` (needed
+//! for full blob ids) also makes `%h` print the full hash. Each hash is
+//! followed by the raw diff records for the paths the commit touched; git
+//! starts every record with a newline, which tells it apart from the next
+//! hash.
+
+use super::diff::{nul_fields, parse_record};
+use super::{is_listed_ref, split_message, CoAuthor, CommitInfo, DiffEntry};
+use crate::error::{Result, VisiGitError};
+
+/// `--format` for one commit header, fields separated by the ASCII record
+/// separator: hash, short hash, parents, author name, email and ISO date,
+/// author Unix time, committer name and email, decorations, co-author
+/// trailers (split by the unit separator) and the raw message. The message
+/// goes last since it is the only field that can hold anything.
+pub const COMMIT_FORMAT: &str =
+    "%H%x1e%h%x1e%P%x1e%an%x1e%ae%x1e%ai%x1e%at%x1e%cn%x1e%ce%x1e%D%x1e\
+%(trailers:key=Co-authored-by,valueonly,unfold,separator=%x1f)%x1e%B";
+
+/// Pass alongside `COMMIT_FORMAT` so `%D` lists full ref names
+pub const DECORATE: &str = "--decorate=full";
+
+/// Parse the stdout of `git log -z --decorate=full --format=COMMIT_FORMAT`
+pub fn parse_commits(output: &[u8]) -> Result<Vec<CommitInfo>> {
+    nul_fields(output)
+        .map(|header| parse_commit_header(&header))
+        .collect()
+}
+
+/// Parse the stdout of `git log -z --raw --no-abbrev --format=%H` into
+/// each commit id paired with the changes listed under it
+pub fn parse_raw_log(output: &[u8]) -> Result<Vec<(String, Vec<DiffEntry>)>> {
+    let mut fields = nul_fields(output).peekable();
+    let mut commits = Vec::new();
+
+    while let Some(hash) = fields.next() {
+        let hash = hash.trim_start_matches('\n').to_string();
+        if hash.is_empty() || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(VisiGitError::UnexpectedOutput {
+                message: format!("Expected a commit id in git log output: {:?}", hash),
+            });
+        }
+
+        let mut changes = Vec::new();
+        while let Some(record) = fields.next_if(|field| field.starts_with("\n:")) {
+            changes.push(parse_record(&record[1..], &mut fields)?);
+        }
+
+        commits.push((hash, changes));
+    }
+
+    Ok(commits)
+}
+
+fn parse_commit_header(header: &str) -> Result<CommitInfo> {
+    let malformed = || VisiGitError::UnexpectedOutput {
+        message: format!("Malformed git log header: {:?}", header),
+    };
+
+    let parts: Vec<&str> = header.trim_start_matches('\n').splitn(12, '\x1e').collect();
+    let [hash, short_hash, parents, author, author_email, date, timestamp, committer, committer_email, decorations, co_authors, message] =
+        parts.as_slice()
+    else {
+        return Err(malformed());
+    };
+
+    let timestamp = timestamp.parse().map_err(|_| malformed())?;
+    let timezone_offset = parse_offset(date).ok_or_else(malformed)?;
+    let (message, body) = split_message(message);
+
+    Ok(CommitInfo {
+        hash: hash.to_string(),
+        short_hash: short_hash.to_string(),
+        message,
+        body,
+        parents: parents.split_whitespace().map(str::to_string).collect(),
+        author: author.to_string(),
+        author_email: author_email.to_string(),
+        date: date.to_string(),
+        timestamp,
+        timezone_offset,
+        committer: committer.to_string(),
+        committer_email: committer_email.to_string(),
+        co_authors: co_authors
+            .split('\x1f')
+            .filter(|value| !value.trim().is_empty())
+            .map(CoAuthor::parse)
+            .collect(),
+        refs: parse_decorations(decorations),
+    })
+}
+
+/// UTC offset in minutes from the `+hhmm` at the end of an ISO date
+fn parse_offset(date: &str) -> Option<i32> {
+    let zone = date.rsplit(' ').next()?;
+    let (sign, digits) = match zone.split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// Ref names from `%D` with `--decorate=full`, e.g.
+/// `HEAD -> refs/heads/main, tag: refs/tags/v1`
+fn parse_decorations(decorations: &str) -> Vec<String> {
+    let mut refs: Vec<String> = decorations
+        .split(", ")
+        .flat_map(|item| item.split(" -> "))
+        .map(|name| name.strip_prefix("tag: ").unwrap_or(name).trim())
+        .filter(|name| is_listed_ref(name))
+        .map(str::to_string)
+        .collect();
+    refs.sort();
+    refs.dedup();
+    refs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA_A: &str = "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391";
+    const SHA_B: &str = "5716ca5987cbf97d6bb54920bea6adde242d87e6";
+    const ZERO: &str = "0000000000000000000000000000000000000000";
+
+    fn header(message: &str, decorations: &str, co_authors: &str) -> String {
+        format!(
+            "{a}\x1e{short}\x1e{b}\x1eTest | User\x1etest@example.com\x1e\
+             2024-01-01 17:30:00 +0530\x1e1704110400\x1eCommitter\x1ecommitter@example.com\x1e\
+             {decorations}\x1e{co_authors}\x1e{message}\0",
+            a = SHA_A,
+            short = &SHA_A[..7],
+            b = SHA_B,
+        )
+    }
+
+    #[test]
+    fn test_parse_commits_reads_all_fields() {
+        let output = header(
+            "Move logo | tidy up\n\nKeep the old one around.\n\nCo-authored-by: Ann <ann@example.com>\n",
+            "HEAD -> refs/heads/main, tag: refs/tags/v1, refs/remotes/origin/main",
+            "Ann <ann@example.com>\x1fBo Bee <bo@example.com>",
+        );
+
+        let commits = parse_commits(output.as_bytes()).unwrap();
+
+        assert_eq!(
+            commits,
+            vec![CommitInfo {
+                hash: SHA_A.to_string(),
+                short_hash: SHA_A[..7].to_string(),
+                message: "Move logo | tidy up".to_string(),
+                body: "Keep the old one around.\n\nCo-authored-by: Ann <ann@example.com>"
+                    .to_string(),
+                parents: vec![SHA_B.to_string()],
+                author: "Test | User".to_string(),
+                author_email: "test@example.com".to_string(),
+                date: "2024-01-01 17:30:00 +0530".to_string(),
+                timestamp: 1_704_110_400,
+                timezone_offset: 330,
+                committer: "Committer".to_string(),
+                committer_email: "committer@example.com".to_string(),
+                co_authors: vec![
+                    CoAuthor {
+                        name: "Ann".to_string(),
+                        email: "ann@example.com".to_string(),
+                    },
+                    CoAuthor {
+                        name: "Bo Bee".to_string(),
+                        email: "bo@example.com".to_string(),
+                    },
+                ],
+                refs: vec![
+                    "HEAD".to_string(),
+                    "refs/heads/main".to_string(),
+                    "refs/remotes/origin/main".to_string(),
+                    "refs/tags/v1".to_string(),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_commits_handles_tricky_messages() {
+        let output = [
+            header("", "", ""),
+            header(
+                "First line\nsecond line\n\nBody with \x1e and \x1f\n",
+                "",
+                "",
+            ),
+            header("\n\nÜnïcödé 🎨\n\n\n  indented body\n\n\n", "", ""),
+        ]
+        .concat();
+
+        let commits = parse_commits(output.as_bytes()).unwrap();
+
+        let messages: Vec<(&str, &str)> = commits
+            .iter()
+            .map(|c| (c.message.as_str(), c.body.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("", ""),
+                ("First line second line", "Body with \x1e and \x1f"),
+                ("Ünïcödé 🎨", "  indented body"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_commits_reads_root_commit_and_detached_head() {
+        let output =
+            header("Initial", "HEAD", "").replace(&format!("\x1e{}\x1e", SHA_B), "\x1e\x1e");
+
+        let commits = parse_commits(output.as_bytes()).unwrap();
+
+        assert!(commits[0].parents.is_empty());
+        assert_eq!(commits[0].refs, vec!["HEAD".to_string()]);
+        assert!(commits[0].co_authors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_raw_log_pairs_commits_with_changes() {
+        let output = format!(
+            "{a}\0\n:100644 100644 {b} {b} R100\0img/logo.svg\0brand/logo.svg\0\
+             {b}\0\n:000000 100644 {zero} {b} A\0img/logo.svg\0",
+            a = SHA_A,
+            b = SHA_B,
+            zero = ZERO
+        );
+
+        let commits = parse_raw_log(output.as_bytes()).unwrap();
+
+        assert_eq!(commits.len(), 2);
+        let (hash, changes) = &commits[0];
+        assert_eq!(hash, SHA_A);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "brand/logo.svg");
+        assert_eq!(changes[0].orig_path.as_deref(), Some("img/logo.svg"));
+
+        let (hash, changes) = &commits[1];
+        assert_eq!(hash, SHA_B);
+        assert_eq!(changes[0].status, 'A');
+        assert_eq!(changes[0].old_id, None);
+    }
+
+    #[test]
+    fn test_parse_raw_log_accepts_commit_without_changes() {
+        let output = format!("{}\0{}\0", SHA_A, SHA_B);
+
+        let commits = parse_raw_log(output.as_bytes()).unwrap();
+
+        assert_eq!(commits.len(), 2);
+        assert!(commits.iter().all(|(_, changes)| changes.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_output() {
+        assert!(matches!(
+            parse_commits(b"not a header\0"),
+            Err(VisiGitError::UnexpectedOutput { .. })
+        ));
+        assert!(matches!(
+            parse_commits(
+                header("Bad date", "", "")
+                    .replace("+0530", "IST")
+                    .as_bytes()
+            ),
+            Err(VisiGitError::UnexpectedOutput { .. })
+        ));
+        assert!(matches!(
+            parse_raw_log(b"not a hash\0"),
+            Err(VisiGitError::UnexpectedOutput { .. })
+        ));
+    }
+}
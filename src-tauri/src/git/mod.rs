@@ -0,0 +1,6020 @@
+mod batch;
+mod cli;
+mod diff;
+mod lfs;
+mod libgit2;
+mod log;
+mod refs;
+mod status;
+pub(crate) mod validate;
+
+pub use batch::CatFileBatch;
+pub use cli::CliBackend;
+pub use diff::{parse_raw_diff, DiffEntry};
+pub use lfs::LfsPointer;
+pub use libgit2::Libgit2Backend;
+pub use log::{parse_commits, parse_raw_log, COMMIT_FORMAT, DECORATE};
+pub use refs::{parse_refs, REF_FORMAT};
+pub use status::{parse_porcelain_v2, ConflictBlobs, StatusEntry};
+
+use crate::diff_images::DiffImages;
+use crate::error::{Result, VisiGitError};
+use crate::imaging::{self, DiffMethod, Dimensions, ImageInfo, Region, RegionOptions, Similarity};
+use crate::mime::detect_mime;
+use crate::trash::{Backup, Trash};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Which side of a conflict did what to the path, using git's unmerged
+/// status letters (`A` added, `D` deleted, `U` modified)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictSide {
+    Added,
+    Deleted,
+    Modified,
+}
+
+impl ConflictSide {
+    fn from_code(code: char) -> Self {
+        match code {
+            'A' => ConflictSide::Added,
+            'D' => ConflictSide::Deleted,
+            _ => ConflictSide::Modified,
+        }
+    }
+}
+
+/// How a file changed, serialized as `{ "kind": "renamed", "from": "..." }`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    Renamed {
+        from: String,
+    },
+    Copied {
+        from: String,
+    },
+    TypeChanged,
+    Unmerged {
+        ours: ConflictSide,
+        theirs: ConflictSide,
+    },
+    Untracked,
+    Ignored,
+}
+
+impl ChangeKind {
+    /// Collapse a status entry's index and worktree columns into one kind
+    pub fn from_status(entry: &StatusEntry) -> Self {
+        if entry.is_unmerged() {
+            return ChangeKind::Unmerged {
+                ours: ConflictSide::from_code(entry.index),
+                theirs: ConflictSide::from_code(entry.worktree),
+            };
+        }
+        if entry.is_untracked() {
+            return ChangeKind::Untracked;
+        }
+        if entry.is_ignored() {
+            return ChangeKind::Ignored;
+        }
+
+        let codes = [entry.index, entry.worktree];
+        match (entry.index, &entry.orig_path) {
+            ('R', Some(from)) => ChangeKind::Renamed { from: from.clone() },
+            ('C', Some(from)) => ChangeKind::Copied { from: from.clone() },
+            _ if codes.contains(&'D') => ChangeKind::Deleted,
+            ('A', _) => ChangeKind::Added,
+            _ if codes.contains(&'T') => ChangeKind::TypeChanged,
+            _ => ChangeKind::Modified,
+        }
+    }
+
+    /// Kind of change for a diff entry. Conflicts take their sides from
+    /// the matching status entry when there is one.
+    pub fn from_diff(entry: &DiffEntry, status: Option<&StatusEntry>) -> Self {
+        match (entry.status, &entry.orig_path) {
+            ('A', _) => ChangeKind::Added,
+            ('D', _) => ChangeKind::Deleted,
+            ('R', Some(from)) => ChangeKind::Renamed { from: from.clone() },
+            ('C', Some(from)) => ChangeKind::Copied { from: from.clone() },
+            ('T', _) => ChangeKind::TypeChanged,
+            ('?', _) => ChangeKind::Untracked,
+            ('U', _) => match status {
+                Some(status) if status.is_unmerged() => ChangeKind::from_status(status),
+                _ => ChangeKind::Unmerged {
+                    ours: ConflictSide::Modified,
+                    theirs: ConflictSide::Modified,
+                },
+            },
+            _ => ChangeKind::Modified,
+        }
+    }
+
+    /// Kind of change for one porcelain v2 status column, `None` when the
+    /// column is `.` (unchanged)
+    fn from_column(code: char, orig_path: Option<&String>) -> Option<Self> {
+        let kind = match (code, orig_path) {
+            ('.', _) => return None,
+            ('A', _) => ChangeKind::Added,
+            ('D', _) => ChangeKind::Deleted,
+            ('R', Some(from)) => ChangeKind::Renamed { from: from.clone() },
+            ('C', Some(from)) => ChangeKind::Copied { from: from.clone() },
+            ('T', _) => ChangeKind::TypeChanged,
+            _ => ChangeKind::Modified,
+        };
+        Some(kind)
+    }
+
+    /// Path of this file at HEAD, if it existed there under another name
+    pub fn original_path(&self) -> Option<&str> {
+        match self {
+            ChangeKind::Renamed { from } | ChangeKind::Copied { from } => Some(from),
+            _ => None,
+        }
+    }
+}
+
+/// Represents a changed file in the git repository
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChangedFile {
+    pub path: String,
+    pub filename: String,
+    pub status: ChangeKind,
+    /// The index differs from HEAD (there is something to commit)
+    pub staged: bool,
+    /// The working tree differs from the index (edits not yet staged)
+    pub unstaged: bool,
+    /// Change from HEAD to the index, `None` when nothing is staged
+    pub index_status: Option<ChangeKind>,
+    /// Change from the index to the working tree, `None` when there are no
+    /// unstaged edits. Conflicts and untracked files only appear here.
+    pub worktree_status: Option<ChangeKind>,
+    /// Blob ids of the base, ours and theirs versions of a conflicted file
+    pub conflict: Option<ConflictBlobs>,
+    /// Size of the image at HEAD and in the working tree, only filled in
+    /// when asked for
+    pub dimensions: Option<ImageDimensions>,
+    /// How alike the image looks before and after the change, only filled
+    /// in when asked for and when both sides are readable images
+    pub similarity: Option<Similarity>,
+}
+
+impl ChangedFile {
+    pub fn from_status(entry: &StatusEntry) -> Self {
+        let status = ChangeKind::from_status(entry);
+        let (index_status, worktree_status) = match status {
+            // Conflicts have to be resolved in the working tree first
+            ChangeKind::Unmerged { .. } | ChangeKind::Untracked => (None, Some(status.clone())),
+            ChangeKind::Ignored => (None, None),
+            _ => (
+                ChangeKind::from_column(entry.index, entry.orig_path.as_ref()),
+                ChangeKind::from_column(entry.worktree, entry.orig_path.as_ref()),
+            ),
+        };
+
+        Self {
+            path: entry.path.clone(),
+            filename: file_name(&entry.path),
+            status,
+            staged: index_status.is_some(),
+            unstaged: worktree_status.is_some(),
+            index_status,
+            worktree_status,
+            conflict: entry.conflict.clone(),
+            dimensions: None,
+            similarity: None,
+        }
+    }
+
+    /// Build from a diff entry. `staged`/`unstaged` and the index and
+    /// worktree statuses describe the path's current `git status`, and are
+    /// all empty when it is clean there.
+    pub fn from_diff(entry: &DiffEntry, status: Option<&StatusEntry>) -> Self {
+        let current = status.map(ChangedFile::from_status);
+
+        Self {
+            path: entry.path.clone(),
+            filename: file_name(&entry.path),
+            status: ChangeKind::from_diff(entry, status),
+            staged: current.as_ref().is_some_and(|file| file.staged),
+            unstaged: current.as_ref().is_some_and(|file| file.unstaged),
+            index_status: current.as_ref().and_then(|file| file.index_status.clone()),
+            worktree_status: current
+                .as_ref()
+                .and_then(|file| file.worktree_status.clone()),
+            conflict: current.and_then(|file| file.conflict),
+            dimensions: None,
+            similarity: None,
+        }
+    }
+}
+
+/// Width and height of a changed image before and after the change
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImageDimensions {
+    /// At HEAD, `None` if the file is new or not a readable image there
+    pub before: Option<Dimensions>,
+    /// In the working tree, `None` if it was deleted or is not a readable
+    /// image
+    pub after: Option<Dimensions>,
+}
+
+/// One version of a file, serialized as
+/// `{ "kind": "revision", "rev": "HEAD", "path": "logo.png" }`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FileVersion {
+    /// As committed at `rev`
+    Revision { rev: String, path: String },
+    /// As staged in the index
+    Index { path: String },
+    /// One side of a conflicted file
+    Stage { stage: ConflictStage, path: String },
+    /// The file on disk
+    Worktree { path: String },
+    /// The blob with the full object id `id`, such as a side of a
+    /// `DiffedFile`; `path` picks its LFS filter
+    Blob { id: String, path: String },
+}
+
+impl FileVersion {
+    fn path(&self) -> &str {
+        match self {
+            FileVersion::Revision { path, .. }
+            | FileVersion::Index { path }
+            | FileVersion::Stage { path, .. }
+            | FileVersion::Worktree { path }
+            | FileVersion::Blob { path, .. } => path,
+        }
+    }
+}
+
+/// Pixel comparison of two versions of an image
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageDiff {
+    /// Size of the compared area, the part both versions cover
+    pub width: u32,
+    pub height: u32,
+    pub changed_pixels: u64,
+    /// Pixels that only differ by anti-aliasing, for the perceptual method
+    pub antialiased_pixels: u64,
+    pub total_pixels: u64,
+    /// Id of the PNG of the changed pixels in magenta and the anti-aliased
+    /// ones in yellow, over a dimmed grayscale copy of the current version.
+    /// Served at `visigit://diff/<id>` from `DiffImages`.
+    pub id: String,
+    /// Clusters of changed pixels, top to bottom and then left to right
+    pub regions: Vec<Region>,
+}
+
+/// Extract the file name from a repo-relative path
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// A changed file with the blob id on each side of the comparison
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiffedFile {
+    #[serde(flatten)]
+    pub file: ChangedFile,
+    /// Blob on the old side, `None` if the file did not exist there
+    pub old_blob_id: Option<String>,
+    /// Blob on the new side (for the working tree, the id its content
+    /// would get), `None` if the file does not exist there
+    pub new_blob_id: Option<String>,
+}
+
+/// Represents information about a git commit
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommitInfo {
+    pub hash: String,
+    pub short_hash: String,
+    /// Subject: the first paragraph of the message, on one line
+    pub message: String,
+    /// Rest of the message after the subject, empty if there is none
+    pub body: String,
+    /// Parent commit ids, first parent first; empty for a root commit
+    pub parents: Vec<String>,
+    pub author: String,
+    pub author_email: String,
+    /// Author date, e.g. `2024-03-09 14:05:00 +0100`
+    pub date: String,
+    /// Author date as Unix time
+    pub timestamp: i64,
+    /// Author's UTC offset in minutes
+    pub timezone_offset: i32,
+    pub committer: String,
+    pub committer_email: String,
+    /// People credited in `Co-authored-by:` trailers
+    pub co_authors: Vec<CoAuthor>,
+    /// Full names of the branches, remote branches and tags pointing at the
+    /// commit, plus `HEAD` if it does, sorted
+    pub refs: Vec<String>,
+}
+
+/// Someone credited in a `Co-authored-by:` trailer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CoAuthor {
+    pub name: String,
+    /// Empty if the trailer has no `<email>`
+    pub email: String,
+}
+
+impl CoAuthor {
+    /// Parse a trailer value like `Name <email>`
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        match value.rsplit_once('<') {
+            Some((name, email)) if email.ends_with('>') => Self {
+                name: name.trim().to_string(),
+                email: email.trim_end_matches('>').trim().to_string(),
+            },
+            _ => Self {
+                name: value.to_string(),
+                email: String::new(),
+            },
+        }
+    }
+}
+
+/// Name and email to record as a commit's author
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+impl Identity {
+    /// Reject names and emails git could not write into a commit header
+    pub fn validate(&self) -> Result<&Self> {
+        let bad = |part: &str| {
+            part.trim().is_empty() || part.chars().any(|c| c == '<' || c == '>' || c.is_control())
+        };
+
+        if bad(&self.name) || bad(&self.email) {
+            return Err(VisiGitError::InvalidIdentity {
+                name: self.name.clone(),
+                email: self.email.clone(),
+            });
+        }
+
+        Ok(self)
+    }
+}
+
+/// Split a raw commit message into its subject and body. Like git's `%s`,
+/// the subject is the first paragraph with its lines joined by spaces;
+/// blank lines around the body are dropped.
+pub(crate) fn split_message(message: &str) -> (String, String) {
+    let mut lines = message
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .peekable();
+
+    let mut subject = Vec::new();
+    while let Some(line) = lines.next_if(|line| !line.trim().is_empty()) {
+        subject.push(line.trim_end());
+    }
+
+    let body: Vec<&str> = lines
+        .skip_while(|line| line.trim().is_empty())
+        .map(str::trim_end)
+        .collect();
+
+    (subject.join(" "), body.join("\n").trim_end().to_string())
+}
+
+/// Keep the refs a commit should list: `HEAD`, branches, remote branches
+/// and tags
+pub(crate) fn is_listed_ref(name: &str) -> bool {
+    name == "HEAD"
+        || ["refs/heads/", "refs/remotes/", "refs/tags/"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// One version of a file in its history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileHistoryEntry {
+    pub commit: CommitInfo,
+    /// Path of the file in this commit, which changes across renames
+    pub path: String,
+    /// Blob at this commit, `None` if the commit deleted the file
+    pub blob_id: Option<String>,
+    /// How this commit changed the file, relative to its first parent
+    pub status: ChangeKind,
+}
+
+impl FileHistoryEntry {
+    pub fn new(commit: CommitInfo, entry: &DiffEntry) -> Self {
+        Self {
+            commit,
+            path: entry.path.clone(),
+            blob_id: entry.new_id.clone(),
+            status: ChangeKind::from_diff(entry, None),
+        }
+    }
+}
+
+/// A page of results, and the cursor to pass back for the next one
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Build a page from up to `limit + 1` items fetched after skipping
+    /// `skip`; the extra item only tells whether there is another page
+    fn from_overfetch(mut items: Vec<T>, skip: usize, limit: usize) -> Self {
+        let next_cursor = (items.len() > limit).then(|| (skip + limit).to_string());
+        items.truncate(limit);
+        Self { items, next_cursor }
+    }
+}
+
+/// Page size when a query does not give one
+pub const DEFAULT_HISTORY_LIMIT: u32 = 50;
+
+/// Which commits a history walk returns. Every filter is optional and
+/// they all have to match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HistoryFilter {
+    /// Revision to walk back from, HEAD when absent
+    pub start: Option<String>,
+    /// Also walk every local and remote-tracking branch
+    pub all_branches: bool,
+    /// Case-insensitive substring of the author's `Name <email>`
+    pub author: Option<String>,
+    /// Only commits committed at or after this Unix time
+    pub since: Option<i64>,
+    /// Only commits committed at or before this Unix time
+    pub until: Option<i64>,
+    /// Case-insensitive substring of the full commit message
+    pub grep: Option<String>,
+    /// Only commits that change a file at or under one of these paths
+    pub paths: Vec<String>,
+    /// Only commits that change an image file
+    pub images_only: bool,
+}
+
+/// A history filter plus the page to return, sent by the frontend as one
+/// flat object
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HistoryQuery {
+    #[serde(flatten)]
+    pub filter: HistoryFilter,
+    /// `next_cursor` of the previous page, `None` for the first
+    pub cursor: Option<String>,
+    pub limit: u32,
+}
+
+impl Default for HistoryQuery {
+    fn default() -> Self {
+        Self {
+            filter: HistoryFilter::default(),
+            cursor: None,
+            limit: DEFAULT_HISTORY_LIMIT,
+        }
+    }
+}
+
+/// Number of items to skip for `cursor`, which is opaque to the frontend
+fn parse_cursor(cursor: Option<&str>) -> Result<usize> {
+    match cursor {
+        None => Ok(0),
+        Some(cursor) => cursor.parse().map_err(|_| VisiGitError::InvalidCursor {
+            cursor: cursor.to_string(),
+        }),
+    }
+}
+
+/// Where a repository lives and what kind it is, as found by walking up
+/// from any folder inside it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RepoInfo {
+    /// Root of the working tree, `None` for bare repositories
+    pub toplevel: Option<String>,
+    /// This checkout's git directory (`.git/worktrees/<name>` for a
+    /// linked worktree, `.git/modules/<name>` for a submodule)
+    pub git_dir: String,
+    /// Directory shared by all worktrees of the repository
+    pub common_dir: String,
+    pub is_bare: bool,
+    /// A linked worktree created by `git worktree add`
+    pub is_worktree: bool,
+    /// Checked out as a submodule of another repository
+    pub is_submodule: bool,
+    /// Current branch, `None` when HEAD is detached
+    pub branch: Option<String>,
+}
+
+/// A multi-step operation the repository is in the middle of, as `git
+/// status` reports it. Bisecting is left out because it does not get in
+/// the way of committing.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RepoOperation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+    /// `git am` applying patches from a mailbox
+    ApplyMailbox,
+}
+
+impl RepoOperation {
+    /// Operation whose state files are in `git_dir`, checked in the same
+    /// order as libgit2's `git_repository_state`
+    pub(crate) fn in_git_dir(git_dir: &Path) -> Option<Self> {
+        let rebase_apply = git_dir.join("rebase-apply");
+
+        if git_dir.join("rebase-merge").is_dir() {
+            Some(RepoOperation::Rebase)
+        } else if rebase_apply.is_dir() {
+            if rebase_apply.join("applying").exists() {
+                Some(RepoOperation::ApplyMailbox)
+            } else {
+                Some(RepoOperation::Rebase)
+            }
+        } else if git_dir.join("MERGE_HEAD").exists() {
+            Some(RepoOperation::Merge)
+        } else if git_dir.join("REVERT_HEAD").exists() {
+            Some(RepoOperation::Revert)
+        } else if git_dir.join("CHERRY_PICK_HEAD").exists() {
+            Some(RepoOperation::CherryPick)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for RepoOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepoOperation::Merge => write!(f, "merge"),
+            RepoOperation::Rebase => write!(f, "rebase"),
+            RepoOperation::CherryPick => write!(f, "cherry-pick"),
+            RepoOperation::Revert => write!(f, "revert"),
+            RepoOperation::ApplyMailbox => write!(f, "git am session"),
+        }
+    }
+}
+
+/// Where `restore_file` writes the restored version
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreTarget {
+    /// The file on disk, like `git restore --worktree`
+    Worktree,
+    /// The staged version, like `git restore --staged`
+    Index,
+}
+
+/// One side of a conflicted path in the index
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStage {
+    /// Stage 1, the merge base
+    Base,
+    /// Stage 2, the branch being merged into
+    Ours,
+    /// Stage 3, the branch being merged in
+    Theirs,
+}
+
+impl ConflictStage {
+    /// Index stage number, as in `git show :<number>:<path>`
+    pub fn number(self) -> u8 {
+        match self {
+            ConflictStage::Base => 1,
+            ConflictStage::Ours => 2,
+            ConflictStage::Theirs => 3,
+        }
+    }
+}
+
+impl fmt::Display for ConflictStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictStage::Base => write!(f, "base"),
+            ConflictStage::Ours => write!(f, "ours"),
+            ConflictStage::Theirs => write!(f, "theirs"),
+        }
+    }
+}
+
+/// Which version `resolve_conflict` keeps for a conflicted path
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// The version in the merge base
+    Base,
+    /// Our side, like `git checkout --ours`
+    Ours,
+    /// Their side, like `git checkout --theirs`
+    Theirs,
+    /// Whatever is in the working tree now, like `git add`
+    WorktreeFile,
+}
+
+/// What a ref listed by `list_refs` is
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RefKind {
+    Branch,
+    RemoteBranch,
+    Tag,
+}
+
+impl RefKind {
+    /// Kind and short name of a full ref name, `None` for refs outside
+    /// `refs/heads/`, `refs/remotes/` and `refs/tags/`
+    pub fn of(name: &str) -> Option<(RefKind, &str)> {
+        [
+            ("refs/heads/", RefKind::Branch),
+            ("refs/remotes/", RefKind::RemoteBranch),
+            ("refs/tags/", RefKind::Tag),
+        ]
+        .into_iter()
+        .find_map(|(prefix, kind)| Some((kind, name.strip_prefix(prefix)?)))
+    }
+}
+
+/// A branch, remote-tracking branch or tag, and the commit it points at
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RefInfo {
+    /// Full name, e.g. `refs/heads/main`
+    pub name: String,
+    /// Name without its `refs/...` prefix, e.g. `main`, `origin/main` or
+    /// `v1.0`
+    pub short_name: String,
+    pub kind: RefKind,
+    /// Commit the ref points at, through an annotated tag if there is one
+    pub target: String,
+    /// Full name of a branch's configured upstream
+    pub upstream: Option<String>,
+    /// Commits on the branch that are not on its upstream, `None` without
+    /// an upstream or when the upstream ref is gone
+    pub ahead: Option<usize>,
+    /// Commits on the upstream that are not on the branch
+    pub behind: Option<usize>,
+    /// Committer date of the target, e.g. `2024-03-09 14:05:00 +0100`
+    pub date: String,
+    /// Committer date of the target as Unix time
+    pub timestamp: i64,
+    /// HEAD is this branch
+    pub is_head: bool,
+}
+
+/// A file's content as stored in git
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blob {
+    /// Object id of the blob
+    pub id: String,
+    pub content: Vec<u8>,
+}
+
+/// Whether a file's content came out of Git LFS
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LfsStatus {
+    /// The blob is an LFS pointer
+    pub is_lfs: bool,
+    /// The pointer's object is neither stored locally nor could be fetched
+    /// through git's smudge filter, so the content is the pointer text
+    pub lfs_missing: bool,
+}
+
+/// Describes a blob without shipping its content
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlobMetadata {
+    /// Size in bytes, of the LFS object for a pointer
+    pub size: u64,
+    /// Id of the blob git stores, the pointer for LFS files
+    pub blob_id: String,
+    /// MIME type sniffed from the content, falling back to the extension
+    pub mime: String,
+    #[serde(flatten)]
+    pub lfs: LfsStatus,
+}
+
+impl BlobMetadata {
+    pub fn new(blob: &Blob, lfs: LfsStatus, file_path: &str) -> Self {
+        Self {
+            size: blob.content.len() as u64,
+            blob_id: blob.id.clone(),
+            mime: detect_mime(&blob.content, file_path).to_string(),
+            lfs,
+        }
+    }
+}
+
+/// Image file extensions we care about
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "ico"];
+
+/// Check if a file path has an image extension
+pub(crate) fn is_image_file(path: &str) -> bool {
+    let path_lower = path.to_lowercase();
+    IMAGE_EXTENSIONS
+        .iter()
+        .any(|ext| path_lower.ends_with(&format!(".{}", ext)))
+}
+
+/// Which implementation a `GitBackend` uses to talk to the repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// In-process libgit2, no external processes
+    Libgit2,
+    /// The `git` executable on PATH
+    Cli,
+}
+
+impl BackendKind {
+    /// Backends in order of preference
+    pub const ALL: [BackendKind; 2] = [BackendKind::Libgit2, BackendKind::Cli];
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendKind::Libgit2 => write!(f, "libgit2"),
+            BackendKind::Cli => write!(f, "cli"),
+        }
+    }
+}
+
+/// Access to a single repository.
+///
+/// Each backend is bound to the repository it was opened on.
+pub trait GitBackend: Send + Sync {
+    /// Which implementation this is
+    fn kind(&self) -> BackendKind;
+
+    /// Every changed path in the index and working tree, images or not
+    fn status(&self) -> Result<Vec<StatusEntry>>;
+
+    /// Commits matching `filter`, newest first, leaving out the first
+    /// `skip` and returning at most `limit`.
+    ///
+    /// A commit matches the path filters when it differs from at least
+    /// one of its parents there (`git log --full-history`), so merges that
+    /// bring in a change are listed too.
+    fn log(&self, filter: &HistoryFilter, skip: usize, limit: usize) -> Result<Vec<CommitInfo>>;
+
+    /// Raw content and object id of `file_path` at revision `rev`
+    fn read_blob(&self, rev: &str, file_path: &str) -> Result<Blob>;
+
+    /// Raw content and object id of `file_path` as staged in the index
+    /// (stage 0, like `git show :0:<path>`)
+    fn read_index_blob(&self, file_path: &str) -> Result<Blob>;
+
+    /// Raw content and object id of one side of a conflicted `file_path`
+    /// (stage 1, 2 or 3, like `git show :<stage>:<path>`)
+    fn read_stage_blob(&self, file_path: &str, stage: ConflictStage) -> Result<Blob>;
+
+    /// Raw content of the blob with the full object id `id`, like the ids
+    /// `diff_commits` reports. Missing objects and ids of anything other
+    /// than a blob fail with `RevisionNotFound`.
+    fn read_blob_by_id(&self, id: &str) -> Result<Blob>;
+
+    /// Best common ancestor of two revisions, as a full commit id
+    fn merge_base(&self, left: &str, right: &str) -> Result<String>;
+
+    /// Every path that differs between the commit `rev` and the working
+    /// tree, untracked files included, with renames detected. `new_id`
+    /// is always filled in for files present in the working tree.
+    fn diff_worktree(&self, rev: &str) -> Result<Vec<DiffEntry>>;
+
+    /// Every path that differs between the commits `from` and `to`, with
+    /// renames detected.
+    ///
+    /// With `first_parent`, `from` is replaced by the commit where `to`'s
+    /// first-parent history meets it, so a merged side branch counts as
+    /// the merge's change rather than its own commits. If that history
+    /// never meets `from`, the comparison starts from an empty tree.
+    fn diff_commits(&self, from: &str, to: &str, first_parent: bool) -> Result<Vec<DiffEntry>>;
+
+    /// Commits on HEAD's first-parent history that changed `file_path`,
+    /// newest first, following it back through renames like `git log
+    /// --follow`. The first `skip` are left out and at most `limit`
+    /// returned.
+    fn file_history(
+        &self,
+        file_path: &str,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<FileHistoryEntry>>;
+
+    /// Local branches, remote-tracking branches and tags, sorted by full
+    /// name. Symbolic refs such as `refs/remotes/origin/HEAD` and tags that
+    /// do not lead to a commit are left out.
+    fn list_refs(&self) -> Result<Vec<RefInfo>>;
+
+    /// Merge, rebase or other operation in progress, if any
+    fn operation(&self) -> Result<Option<RepoOperation>>;
+
+    /// Stage the working tree state of each path, like `git add --all`: a
+    /// file missing from the working tree is removed from the index, and
+    /// a conflicted file is marked resolved
+    fn stage(&self, paths: &[String]) -> Result<()>;
+
+    /// Put each path in the index back to its HEAD version, like `git
+    /// reset -- <paths>`. Paths HEAD does not have are removed from the
+    /// index.
+    fn unstage(&self, paths: &[String]) -> Result<()>;
+
+    /// Commit the index on top of HEAD and return the new commit id.
+    ///
+    /// `author` replaces the configured identity as the author (the
+    /// committer is always the configured one). With `amend`, HEAD is
+    /// replaced instead and keeps its author unless one is given. During
+    /// a merge the commit gets `MERGE_HEAD` as a second parent and
+    /// concludes it, as `git commit` does.
+    fn commit(&self, message: &str, author: Option<&Identity>, amend: bool) -> Result<String>;
+
+    /// Canonical root of the working tree
+    fn workdir(&self) -> Result<PathBuf>;
+
+    /// Folder Git LFS keeps its objects in, `lfs` under the common git dir
+    fn lfs_dir(&self) -> Result<PathBuf>;
+
+    /// Id `content` would get as a blob in the repository's object format
+    /// (SHA-1 or SHA-256), like `git hash-object --no-filters --stdin`
+    fn hash_blob(&self, content: &[u8]) -> Result<String>;
+
+    /// Content of the blob `blob_id` after git's smudge filters for
+    /// `file_path`, like `git cat-file --filters`, which makes git-lfs fetch
+    /// an object that is missing locally. `None` if filters cannot be run
+    /// or failed.
+    fn smudge(&self, blob_id: &str, file_path: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Point the stage 0 index entry for `file_path` at the existing blob
+    /// `blob_id`, or remove the path from the index with `None`. The
+    /// entry keeps its mode, as `kept_mode` picks it from the entries it
+    /// replaces. Conflict stages for the path are dropped either way.
+    fn set_index_entry(&self, file_path: &str, blob_id: Option<&str>) -> Result<()>;
+
+    /// Stop any helper processes the backend keeps running. The backend
+    /// stays usable and starts them again when needed.
+    fn shutdown(&self);
+}
+
+/// Id the working tree file at `path` under `root` would get as a blob in
+/// `backend`'s repository, `None` if it does not exist
+pub(crate) fn worktree_blob_id(
+    backend: &dyn GitBackend,
+    root: &Path,
+    path: &str,
+) -> Result<Option<String>> {
+    match std::fs::read(root.join(path)) {
+        Ok(content) => Ok(Some(backend.hash_blob(&content)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Mode for a stage 0 entry pointing at `blob_id`, from the index entries
+/// it replaces as `(stage, mode, id)`: the mode of one with the same blob,
+/// otherwise of the first of stage 0, ours, theirs and base, so an
+/// executable or a symlink stays one. A regular file without entries.
+pub(crate) fn kept_mode(entries: &[(u32, u32, String)], blob_id: &str) -> u32 {
+    let by_stage = |stage| entries.iter().find(|entry| entry.0 == stage);
+    entries
+        .iter()
+        .find(|entry| entry.2 == blob_id)
+        .or_else(|| [0, 2, 3, 1].into_iter().find_map(by_stage))
+        .map_or(0o100644, |entry| entry.1)
+}
+
+/// Replace the content of an LFS pointer blob with its object, as a
+/// checkout would. The object is read from the LFS folder when it has been
+/// fetched, and otherwise through the backend's smudge filters. Other
+/// blobs come back unchanged.
+pub fn smudge_blob(
+    backend: &dyn GitBackend,
+    mut blob: Blob,
+    file_path: &str,
+) -> Result<(Blob, LfsStatus)> {
+    let Some(pointer) = LfsPointer::parse(&blob.content) else {
+        return Ok((blob, LfsStatus::default()));
+    };
+    let mut lfs = LfsStatus {
+        is_lfs: true,
+        lfs_missing: false,
+    };
+
+    match fs::read(pointer.object_path(&backend.lfs_dir()?)) {
+        // A partly downloaded object is as good as a missing one
+        Ok(content) if content.len() as u64 == pointer.size => {
+            blob.content = content;
+            return Ok((blob, lfs));
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    match backend.smudge(&blob.id, file_path)? {
+        // Without git-lfs the filter hands back the pointer itself
+        Some(content) if LfsPointer::parse(&content).is_none() => blob.content = content,
+        _ => lfs.lfs_missing = true,
+    }
+
+    Ok((blob, lfs))
+}
+
+/// Open `repo_path` with a specific backend
+pub fn open_backend(repo_path: &Path, kind: BackendKind) -> Result<Arc<dyn GitBackend>> {
+    Ok(match kind {
+        BackendKind::Libgit2 => Arc::new(Libgit2Backend::open(repo_path)?),
+        BackendKind::Cli => Arc::new(CliBackend::open(repo_path)?),
+    })
+}
+
+/// Open `repo_path` with the first backend that can handle it.
+///
+/// Prefers the in-process backend and falls back to the git CLI. If none
+/// can open it, the preferred backend's error is returned.
+pub fn open_preferred_backend(repo_path: &Path) -> Result<Arc<dyn GitBackend>> {
+    let mut first_error = None;
+
+    for kind in BackendKind::ALL {
+        match open_backend(repo_path, kind) {
+            Ok(backend) => return Ok(backend),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    Err(first_error.expect("BackendKind::ALL is not empty"))
+}
+
+/// Find the repository containing `path` with a specific backend
+pub fn discover_with(path: &Path, kind: BackendKind) -> Result<RepoInfo> {
+    match kind {
+        BackendKind::Libgit2 => Libgit2Backend::discover(path),
+        BackendKind::Cli => CliBackend::discover(path),
+    }
+}
+
+/// Backends chosen for each opened repository, keyed by canonical path.
+///
+/// Held in Tauri managed state so a backend is picked once when the repo
+/// opens and reused by every later command. Every spelling of a path to
+/// the repository (relative, with a trailing slash, through a symlink or
+/// its `.git` folder) shares the backend of its working tree root.
+#[derive(Default)]
+pub struct BackendCache {
+    backends: Mutex<HashMap<PathBuf, Arc<dyn GitBackend>>>,
+    /// Opaque ids handed to the webview for `visigit://` URLs, mapped to
+    /// the repository root they stand for
+    repo_ids: Mutex<HashMap<String, String>>,
+}
+
+impl BackendCache {
+    /// Get the backend for `repo_path`, opening one on first use
+    pub fn get(&self, repo_path: &str) -> Result<Arc<dyn GitBackend>> {
+        let path = validate::repo_root(Path::new(repo_path))?;
+        let mut backends = self.backends.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(backend) = backends.get(&path) {
+            return Ok(Arc::clone(backend));
+        }
+
+        let backend = open_preferred_backend(&path)?;
+        // Opened through another path to the same working tree before
+        let root = backend_root(backend.as_ref(), &path);
+        let backend = Arc::clone(backends.entry(root).or_insert(backend));
+        backends.insert(path, Arc::clone(&backend));
+        Ok(backend)
+    }
+
+    /// Id that stands for `repo_path` in `visigit://` URLs, the same for
+    /// every path to the repository.
+    ///
+    /// Opens the repository first so only real repositories get an id.
+    pub fn repo_id(&self, repo_path: &str) -> Result<String> {
+        let path = validate::repo_root(Path::new(repo_path))?;
+        let backend = self.get(repo_path)?;
+        let root = backend_root(backend.as_ref(), &path)
+            .to_string_lossy()
+            .into_owned();
+
+        let mut hasher = DefaultHasher::new();
+        root.hash(&mut hasher);
+        let id = format!("{:016x}", hasher.finish());
+
+        self.repo_ids
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.clone(), root);
+        Ok(id)
+    }
+
+    /// Shut down every opened backend and forget them, for when the app
+    /// closes. Repo ids stay valid and reopen their backend on next use.
+    pub fn shutdown(&self) {
+        let backends =
+            std::mem::take(&mut *self.backends.lock().unwrap_or_else(|e| e.into_inner()));
+        for backend in backends.values() {
+            backend.shutdown();
+        }
+    }
+
+    /// Canonical repository root registered under `repo_id`, if any
+    pub fn repo_path(&self, repo_id: &str) -> Option<String> {
+        self.repo_ids
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(repo_id)
+            .cloned()
+    }
+}
+
+/// Canonical working tree root of `backend`, or `path` it was opened with
+/// for a bare repository
+fn backend_root(backend: &dyn GitBackend, path: &Path) -> PathBuf {
+    backend.workdir().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Core logic: Validates if the given path is inside a git repository
+pub fn validate_git_repo_impl(path: &str) -> Result<bool> {
+    match discover_repo_impl(path) {
+        Ok(_) => Ok(true),
+        Err(VisiGitError::NotARepository { .. }) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Core logic: Finds the repository containing `path`, walking up like
+/// `git rev-parse --show-toplevel`.
+///
+/// Tries each backend in order of preference; if none succeeds, the
+/// preferred backend's error is returned.
+pub fn discover_repo_impl(path: &str) -> Result<RepoInfo> {
+    let mut first_error = None;
+
+    for kind in BackendKind::ALL {
+        match discover_with(Path::new(path), kind) {
+            Ok(info) => return Ok(info),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    Err(first_error.expect("BackendKind::ALL is not empty"))
+}
+
+/// Core logic: Gets the list of changed image files in the repository
+pub fn get_changed_files_impl(backend: &dyn GitBackend) -> Result<Vec<ChangedFile>> {
+    let files = backend
+        .status()?
+        .iter()
+        // Skip non-image files
+        .filter(|entry| is_image_file(&entry.path))
+        .map(ChangedFile::from_status)
+        .collect();
+
+    Ok(files)
+}
+
+/// Core logic: Stages the given paths and returns the updated list of
+/// changed images
+pub fn stage_files_impl(
+    backend: &dyn GitBackend,
+    paths: &[String],
+    allow_in_progress: bool,
+) -> Result<Vec<ChangedFile>> {
+    check_operation(backend, allow_in_progress)?;
+    backend.stage(paths)?;
+    get_changed_files_impl(backend)
+}
+
+/// Core logic: Unstages the given paths and returns the updated list of
+/// changed images
+pub fn unstage_files_impl(
+    backend: &dyn GitBackend,
+    paths: &[String],
+    allow_in_progress: bool,
+) -> Result<Vec<ChangedFile>> {
+    check_operation(backend, allow_in_progress)?;
+    backend.unstage(paths)?;
+    get_changed_files_impl(backend)
+}
+
+/// Core logic: Commits the index (or amends HEAD) and returns the new
+/// commit.
+///
+/// Refuses to commit with an empty message, with unresolved conflicts, or
+/// with nothing staged unless amending or concluding a merge.
+pub fn create_commit_impl(
+    backend: &dyn GitBackend,
+    message: &str,
+    author: Option<&Identity>,
+    amend: bool,
+    allow_in_progress: bool,
+) -> Result<CommitInfo> {
+    if message.trim().is_empty() {
+        return Err(VisiGitError::EmptyCommitMessage);
+    }
+    if let Some(author) = author {
+        author.validate()?;
+    }
+
+    let operation = check_operation(backend, allow_in_progress)?;
+    let merging = operation == Some(RepoOperation::Merge);
+    // git cannot amend in the middle of a merge either
+    if amend && merging {
+        return Err(VisiGitError::OperationInProgress {
+            operation: RepoOperation::Merge,
+        });
+    }
+
+    let files: Vec<ChangedFile> = backend
+        .status()?
+        .iter()
+        .map(ChangedFile::from_status)
+        .collect();
+    let conflicts: Vec<String> = files
+        .iter()
+        .filter(|file| matches!(file.status, ChangeKind::Unmerged { .. }))
+        .map(|file| file.path.clone())
+        .collect();
+    if !conflicts.is_empty() {
+        return Err(VisiGitError::UnresolvedConflicts { paths: conflicts });
+    }
+    if !amend && !merging && !files.iter().any(|file| file.staged) {
+        return Err(VisiGitError::NothingToCommit);
+    }
+
+    let id = backend.commit(message, author, amend)?;
+
+    let filter = HistoryFilter {
+        start: Some(id.clone()),
+        ..HistoryFilter::default()
+    };
+    backend
+        .log(&filter, 0, 1)?
+        .pop()
+        .ok_or_else(|| VisiGitError::UnexpectedOutput {
+            message: format!("New commit {} is missing from the log", id),
+        })
+}
+
+/// Core logic: Restores a file in the working tree or the index to its
+/// version at `source_rev`, like `git restore --source=<rev>`.
+///
+/// The version being replaced is saved to `trash` first. Pass the id of
+/// the returned backup to `undo_restore_impl` to put it back.
+pub fn restore_file_impl(
+    backend: &dyn GitBackend,
+    trash: &Trash,
+    file_path: &str,
+    source_rev: &str,
+    target: RestoreTarget,
+) -> Result<Backup> {
+    let path = validate::repo_path(file_path)?;
+    let blob = backend.read_blob(source_rev, &path)?;
+    let root = backend.workdir()?;
+
+    let mut backup = Backup {
+        id: Trash::new_id(),
+        repo: root.to_string_lossy().into_owned(),
+        path: path.clone(),
+        target,
+        source_rev: source_rev.to_string(),
+        previous_id: None,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default(),
+    };
+
+    match target {
+        RestoreTarget::Worktree => {
+            let destination = validate::worktree_destination(&root, &path)?;
+            let previous = match fs::read(&destination) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            };
+            backup.previous_id = previous
+                .as_deref()
+                .map(|content| backend.hash_blob(content))
+                .transpose()?;
+            let blob = checkout_blob(backend, blob, &path)?;
+
+            trash.save(&backup, previous.as_deref())?;
+            write_worktree_file(&destination, &blob.content)?;
+        }
+        RestoreTarget::Index => {
+            backup.previous_id = match backend.read_index_blob(&path) {
+                Ok(previous) => Some(previous.id),
+                Err(VisiGitError::PathNotInIndex { .. }) => None,
+                Err(e) => return Err(e),
+            };
+
+            trash.save(&backup, None)?;
+            backend.set_index_entry(&path, Some(&blob.id))?;
+        }
+    }
+
+    Ok(backup)
+}
+
+/// Core logic: Puts back what the restore with `backup_id` replaced and
+/// returns the updated list of changed images. Each backup can be undone
+/// once.
+pub fn undo_restore_impl(
+    backend: &dyn GitBackend,
+    trash: &Trash,
+    backup_id: &str,
+) -> Result<Vec<ChangedFile>> {
+    let root = backend.workdir()?;
+    let backup = trash.load(&root.to_string_lossy(), backup_id)?;
+
+    match backup.target {
+        RestoreTarget::Worktree => {
+            let destination = validate::worktree_destination(&root, &backup.path)?;
+            match backup.previous_id {
+                Some(_) => write_worktree_file(&destination, &trash.content(&backup)?)?,
+                // The restore created the file
+                None => remove_worktree_file(&destination)?,
+            }
+        }
+        RestoreTarget::Index => {
+            backend.set_index_entry(&backup.path, backup.previous_id.as_deref())?;
+        }
+    }
+
+    trash.remove(&backup)?;
+    get_changed_files_impl(backend)
+}
+
+/// Core logic: Resolves a conflicted file by writing the chosen version to
+/// the working tree and staging it, then returns the updated list of
+/// changed images.
+///
+/// Choosing a side that has no version of the file (it deleted the file or
+/// never had it) resolves the conflict by deleting it.
+pub fn resolve_conflict_impl(
+    backend: &dyn GitBackend,
+    file_path: &str,
+    resolution: ConflictResolution,
+) -> Result<Vec<ChangedFile>> {
+    let path = validate::repo_path(file_path)?;
+    let conflict = backend
+        .status()?
+        .into_iter()
+        .find(|entry| entry.path == path)
+        .and_then(|entry| entry.conflict)
+        .ok_or_else(|| VisiGitError::NotConflicted { path: path.clone() })?;
+
+    let (stage, blob_id) = match resolution {
+        ConflictResolution::Base => (ConflictStage::Base, conflict.base),
+        ConflictResolution::Ours => (ConflictStage::Ours, conflict.ours),
+        ConflictResolution::Theirs => (ConflictStage::Theirs, conflict.theirs),
+        ConflictResolution::WorktreeFile => {
+            backend.stage(std::slice::from_ref(&path))?;
+            return get_changed_files_impl(backend);
+        }
+    };
+
+    let root = backend.workdir()?;
+    let destination = validate::worktree_destination(&root, &path)?;
+    match blob_id {
+        Some(_) => {
+            let blob = checkout_blob(backend, backend.read_stage_blob(&path, stage)?, &path)?;
+            write_worktree_file(&destination, &blob.content)?;
+            backend.set_index_entry(&path, Some(&blob.id))?;
+        }
+        None => {
+            remove_worktree_file(&destination)?;
+            backend.set_index_entry(&path, None)?;
+        }
+    }
+
+    get_changed_files_impl(backend)
+}
+
+/// Content to write to the working tree for `blob`: an LFS file is checked
+/// out with its object, never as the pointer, so a missing object fails
+/// with `LfsObjectMissing` before anything is written
+fn checkout_blob(backend: &dyn GitBackend, blob: Blob, file_path: &str) -> Result<Blob> {
+    let (blob, lfs) = smudge_blob(backend, blob, file_path)?;
+    if lfs.lfs_missing {
+        return Err(VisiGitError::LfsObjectMissing {
+            path: file_path.to_string(),
+        });
+    }
+    Ok(blob)
+}
+
+/// Write `content` to a working tree file, creating missing folders
+fn write_worktree_file(destination: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::write(destination, content)?)
+}
+
+/// Delete a working tree file if it exists
+fn remove_worktree_file(destination: &Path) -> Result<()> {
+    match fs::remove_file(destination) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Fail with `OperationInProgress` unless nothing is in progress or the
+/// caller allows it
+fn check_operation(
+    backend: &dyn GitBackend,
+    allow_in_progress: bool,
+) -> Result<Option<RepoOperation>> {
+    let operation = backend.operation()?;
+    match operation {
+        Some(operation) if !allow_in_progress => {
+            Err(VisiGitError::OperationInProgress { operation })
+        }
+        _ => Ok(operation),
+    }
+}
+
+/// Core logic: Gets the changed image files between `rev` and the working
+/// tree, with the blob id on each side.
+///
+/// `rev...` compares against the merge base of `rev` and HEAD instead, so
+/// only the current branch's changes appear.
+pub fn get_changed_files_against_impl(
+    backend: &dyn GitBackend,
+    rev: &str,
+) -> Result<Vec<DiffedFile>> {
+    let base = match rev.strip_suffix("...") {
+        Some(upstream) => backend.merge_base(upstream, "HEAD")?,
+        None => rev.to_string(),
+    };
+
+    let status: HashMap<String, StatusEntry> = backend
+        .status()?
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+
+    let files = backend
+        .diff_worktree(&base)?
+        .into_iter()
+        // Skip non-image files
+        .filter(|entry| is_image_file(&entry.path))
+        .map(|entry| DiffedFile {
+            file: ChangedFile::from_diff(&entry, status.get(&entry.path)),
+            old_blob_id: entry.old_id,
+            new_blob_id: entry.new_id,
+        })
+        .collect();
+
+    Ok(files)
+}
+
+/// Core logic: Gets the changed image files between two commits, with the
+/// blob id on each side. `staged`/`unstaged` are always false.
+pub fn get_changes_between_impl(
+    backend: &dyn GitBackend,
+    from: &str,
+    to: &str,
+    first_parent: bool,
+) -> Result<Vec<DiffedFile>> {
+    let files = backend
+        .diff_commits(from, to, first_parent)?
+        .into_iter()
+        // Skip non-image files
+        .filter(|entry| is_image_file(&entry.path))
+        .map(|entry| DiffedFile {
+            file: ChangedFile::from_diff(&entry, None),
+            old_blob_id: entry.old_id,
+            new_blob_id: entry.new_id,
+        })
+        .collect();
+
+    Ok(files)
+}
+
+/// Core logic: Gets the base64-encoded content of a file at HEAD
+pub fn get_file_at_head_impl(backend: &dyn GitBackend, file_path: &str) -> Result<String> {
+    let (blob, _) = smudge_blob(backend, backend.read_blob("HEAD", file_path)?, file_path)?;
+
+    // Encode the binary content as base64
+    Ok(STANDARD.encode(blob.content))
+}
+
+/// Core logic: Gets the base64-encoded content of a file as staged in the
+/// index
+pub fn get_file_at_index_impl(backend: &dyn GitBackend, file_path: &str) -> Result<String> {
+    let (blob, _) = smudge_blob(backend, backend.read_index_blob(file_path)?, file_path)?;
+
+    // Encode the binary content as base64
+    Ok(STANDARD.encode(blob.content))
+}
+
+/// Core logic: Gets the raw bytes of a file as staged in the index, for
+/// binary IPC
+pub fn get_file_bytes_at_index_impl(backend: &dyn GitBackend, file_path: &str) -> Result<Vec<u8>> {
+    let (blob, _) = smudge_blob(backend, backend.read_index_blob(file_path)?, file_path)?;
+    Ok(blob.content)
+}
+
+/// Core logic: Gets the raw bytes of one side of a conflicted file, for
+/// binary IPC
+pub fn get_file_bytes_at_stage_impl(
+    backend: &dyn GitBackend,
+    file_path: &str,
+    stage: ConflictStage,
+) -> Result<Vec<u8>> {
+    let blob = backend.read_stage_blob(file_path, stage)?;
+    let (blob, _) = smudge_blob(backend, blob, file_path)?;
+    Ok(blob.content)
+}
+
+/// Core logic: Gets the list of commits reachable from `rev` (HEAD by
+/// default), which may be a commit, branch or tag
+pub fn get_commits_impl(
+    backend: &dyn GitBackend,
+    limit: u32,
+    rev: Option<&str>,
+) -> Result<Vec<CommitInfo>> {
+    let filter = HistoryFilter {
+        start: rev.map(str::to_string),
+        ..HistoryFilter::default()
+    };
+    backend.log(&filter, 0, limit as usize)
+}
+
+/// Core logic: Lists branches, remote-tracking branches and tags
+pub fn list_refs_impl(backend: &dyn GitBackend) -> Result<Vec<RefInfo>> {
+    backend.list_refs()
+}
+
+/// Core logic: Gets one page of filtered commit history
+pub fn get_history_impl(
+    backend: &dyn GitBackend,
+    query: &HistoryQuery,
+) -> Result<Page<CommitInfo>> {
+    let skip = parse_cursor(query.cursor.as_deref())?;
+    let limit = query.limit as usize;
+
+    let commits = backend.log(&query.filter, skip, limit + 1)?;
+    Ok(Page::from_overfetch(commits, skip, limit))
+}
+
+/// Core logic: Gets one page of a file's history, following renames
+pub fn get_file_history_impl(
+    backend: &dyn GitBackend,
+    file_path: &str,
+    limit: u32,
+    cursor: Option<&str>,
+) -> Result<Page<FileHistoryEntry>> {
+    let skip = parse_cursor(cursor)?;
+    let limit = limit as usize;
+
+    let entries = backend.file_history(file_path, skip, limit + 1)?;
+    Ok(Page::from_overfetch(entries, skip, limit))
+}
+
+/// Core logic: Gets the base64-encoded content of a file at a specific commit
+pub fn get_file_at_commit_impl(
+    backend: &dyn GitBackend,
+    file_path: &str,
+    commit_hash: &str,
+) -> Result<String> {
+    let (blob, _) = smudge_blob(
+        backend,
+        backend.read_blob(commit_hash, file_path)?,
+        file_path,
+    )?;
+
+    // Encode the binary content as base64
+    Ok(STANDARD.encode(blob.content))
+}
+
+/// Core logic: Gets the raw bytes of a file at a revision, for binary IPC
+pub fn get_file_bytes_impl(
+    backend: &dyn GitBackend,
+    file_path: &str,
+    rev: &str,
+) -> Result<Vec<u8>> {
+    let (blob, _) = smudge_blob(backend, backend.read_blob(rev, file_path)?, file_path)?;
+    Ok(blob.content)
+}
+
+/// Core logic: Gets the size, blob id, MIME type and LFS status of a file at
+/// a revision
+pub fn get_blob_metadata_impl(
+    backend: &dyn GitBackend,
+    file_path: &str,
+    rev: &str,
+) -> Result<BlobMetadata> {
+    let (blob, lfs) = smudge_blob(backend, backend.read_blob(rev, file_path)?, file_path)?;
+    Ok(BlobMetadata::new(&blob, lfs, file_path))
+}
+
+/// Core logic: Decodes a file at a revision, or the working tree file when
+/// `rev` is `None`, and reports its format, size, color and frames
+pub fn inspect_image_impl(
+    backend: &dyn GitBackend,
+    file_path: &str,
+    rev: Option<&str>,
+) -> Result<ImageInfo> {
+    let content = match rev {
+        Some(rev) => {
+            let (blob, _) = smudge_blob(backend, backend.read_blob(rev, file_path)?, file_path)?;
+            blob.content
+        }
+        None => fs::read(validate::worktree_file(&backend.workdir()?, file_path)?)?,
+    };
+
+    imaging::inspect(&content).map_err(|e| VisiGitError::InvalidImage {
+        path: file_path.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Core logic: Fills in `dimensions` for each file, from the image headers
+/// at HEAD and in the working tree. Renamed and copied files are measured
+/// at their old path at HEAD.
+pub fn add_dimensions_impl(backend: &dyn GitBackend, files: &mut [ChangedFile]) -> Result<()> {
+    let root = backend.workdir()?;
+
+    for file in files {
+        let before = match &file.status {
+            ChangeKind::Added | ChangeKind::Untracked => None,
+            ChangeKind::Renamed { from } | ChangeKind::Copied { from } => {
+                head_dimensions(backend, from)?
+            }
+            _ => head_dimensions(backend, &file.path)?,
+        };
+        let after = match &file.status {
+            ChangeKind::Deleted => None,
+            _ => worktree_dimensions(&root, &file.path)?,
+        };
+
+        file.dimensions = Some(ImageDimensions { before, after });
+    }
+
+    Ok(())
+}
+
+/// Core logic: Compares two versions of an image pixel by pixel with
+/// `method`, and clusters the changed pixels into regions. The rendered
+/// diff is kept in `diffs` under the returned `id`.
+pub fn compute_image_diff_impl(
+    backend: &dyn GitBackend,
+    diffs: &DiffImages,
+    previous: &FileVersion,
+    current: &FileVersion,
+    method: DiffMethod,
+    regions: RegionOptions,
+) -> Result<ImageDiff> {
+    let previous = decode_version(backend, previous)?;
+    let current = decode_version(backend, current)?;
+    let diff = imaging::pixel_diff(&previous, &current, method);
+    let regions = imaging::changed_regions(&previous, &current, &diff, method, regions);
+
+    // Encoding into memory only fails if the encoder itself is broken
+    let png = imaging::encode_png(&diff.image).map_err(std::io::Error::other)?;
+    Ok(ImageDiff {
+        width: diff.image.width(),
+        height: diff.image.height(),
+        changed_pixels: diff.changed_pixels,
+        antialiased_pixels: diff.antialiased_pixels,
+        total_pixels: diff.total_pixels,
+        id: diffs.insert(png),
+        regions,
+    })
+}
+
+/// Core logic: Fills in `similarity` for each file, comparing HEAD with
+/// the working tree. Renamed and copied files are compared with their old
+/// path at HEAD; added, untracked, deleted and conflicted files, and files
+/// missing on either side, have nothing to compare.
+pub fn add_similarity_impl(backend: &dyn GitBackend, files: &mut [ChangedFile]) -> Result<()> {
+    for file in files {
+        let previous = match &file.status {
+            ChangeKind::Added
+            | ChangeKind::Untracked
+            | ChangeKind::Deleted
+            | ChangeKind::Unmerged { .. } => continue,
+            ChangeKind::Renamed { from } | ChangeKind::Copied { from } => from,
+            _ => &file.path,
+        };
+        let previous = FileVersion::Revision {
+            rev: "HEAD".to_string(),
+            path: previous.clone(),
+        };
+        let current = FileVersion::Worktree {
+            path: file.path.clone(),
+        };
+
+        file.similarity = compare_versions(backend, &previous, &current)?;
+    }
+
+    Ok(())
+}
+
+/// Core logic: Fills in `similarity` for each diffed file, comparing its
+/// old blob with its new one, or with the working tree file when
+/// `worktree` (the new blob id of a working tree file is only a hash of
+/// its content).
+pub fn add_diff_similarity_impl(
+    backend: &dyn GitBackend,
+    files: &mut [DiffedFile],
+    worktree: bool,
+) -> Result<()> {
+    for diffed in files {
+        let (Some(old_id), Some(new_id)) = (&diffed.old_blob_id, &diffed.new_blob_id) else {
+            continue;
+        };
+        let file = &diffed.file;
+        let previous = FileVersion::Blob {
+            id: old_id.clone(),
+            path: file
+                .status
+                .original_path()
+                .unwrap_or(&file.path)
+                .to_string(),
+        };
+        let current = if worktree {
+            FileVersion::Worktree {
+                path: file.path.clone(),
+            }
+        } else {
+            FileVersion::Blob {
+                id: new_id.clone(),
+                path: file.path.clone(),
+            }
+        };
+
+        diffed.file.similarity = compare_versions(backend, &previous, &current)?;
+    }
+
+    Ok(())
+}
+
+/// Similarity of two versions of an image, `None` if either is missing or
+/// not a readable image
+fn compare_versions(
+    backend: &dyn GitBackend,
+    previous: &FileVersion,
+    current: &FileVersion,
+) -> Result<Option<Similarity>> {
+    match (
+        decode_version(backend, previous),
+        decode_version(backend, current),
+    ) {
+        (Ok(previous), Ok(current)) => Ok(Some(imaging::similarity(&previous, &current))),
+        (Err(e), _) | (_, Err(e)) if no_image(&e) => Ok(None),
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    }
+}
+
+/// Whether reading a version failed because it has no image to compare:
+/// the file is not in the revision (or the branch is unborn), not in the
+/// working tree, or not decodable
+fn no_image(error: &VisiGitError) -> bool {
+    match error {
+        VisiGitError::PathNotInRevision { .. }
+        | VisiGitError::RevisionNotFound { .. }
+        | VisiGitError::InvalidImage { .. } => true,
+        VisiGitError::Io { kind, .. } => kind == "NotFound",
+        _ => false,
+    }
+}
+
+/// Content of one version of a file, with LFS pointers replaced by their
+/// object
+fn read_version(backend: &dyn GitBackend, version: &FileVersion) -> Result<Vec<u8>> {
+    let blob = match version {
+        FileVersion::Revision { rev, path } => backend.read_blob(rev, path)?,
+        FileVersion::Index { path } => backend.read_index_blob(path)?,
+        FileVersion::Stage { stage, path } => backend.read_stage_blob(path, *stage)?,
+        FileVersion::Blob { id, .. } => backend.read_blob_by_id(id)?,
+        FileVersion::Worktree { path } => {
+            let file = validate::worktree_file(&backend.workdir()?, path)?;
+            return Ok(fs::read(file)?);
+        }
+    };
+    let (blob, _) = smudge_blob(backend, blob, version.path())?;
+    Ok(blob.content)
+}
+
+fn decode_version(backend: &dyn GitBackend, version: &FileVersion) -> Result<image::RgbaImage> {
+    imaging::decode_rgba(&read_version(backend, version)?).map_err(|e| VisiGitError::InvalidImage {
+        path: version.path().to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Dimensions of `file_path` at HEAD, `None` if it is not there
+fn head_dimensions(backend: &dyn GitBackend, file_path: &str) -> Result<Option<Dimensions>> {
+    match backend.read_blob("HEAD", file_path) {
+        Ok(blob) => {
+            let (blob, _) = smudge_blob(backend, blob, file_path)?;
+            Ok(imaging::dimensions(&blob.content))
+        }
+        // Also an unborn branch, which has no HEAD yet
+        Err(VisiGitError::PathNotInRevision { .. } | VisiGitError::RevisionNotFound { .. }) => {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Dimensions of the working tree file, `None` if it does not exist
+fn worktree_dimensions(root: &Path, file_path: &str) -> Result<Option<Dimensions>> {
+    let path = match validate::worktree_file(root, file_path) {
+        Ok(path) => path,
+        Err(VisiGitError::Io { kind, .. }) if kind == "NotFound" => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    Ok(imaging::dimensions(&fs::read(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::libgit2::sha1_blob_id;
+    use super::*;
+    use crate::test_support::{commit_lfs_pointer, store_lfs_object};
+    use std::fs;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    /// Helper to create a temporary git repository for testing
+    fn create_test_git_repo() -> TempDir {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path();
+
+        // Initialize git repo
+        Command::new("git")
+            .args(["init"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to init git repo");
+
+        // Configure git user for commits
+        Command::new("git")
+            .args(["config", "user.email", "test@test.com"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to configure git email");
+
+        Command::new("git")
+            .args(["config", "user.name", "Test User"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to configure git user");
+
+        temp_dir
+    }
+
+    /// Helper to open the repository with every backend, so each test
+    /// checks that both implementations agree
+    fn all_backends(path: &Path) -> Vec<Arc<dyn GitBackend>> {
+        BackendKind::ALL
+            .iter()
+            .map(|kind| open_backend(path, *kind).expect("Failed to open backend"))
+            .collect()
+    }
+
+    // ============================================
+    // Tests for backend selection
+    // ============================================
+
+    #[test]
+    fn test_open_preferred_backend_uses_libgit2() {
+        let temp_repo = create_test_git_repo();
+
+        let backend = open_preferred_backend(temp_repo.path()).expect("Failed to open backend");
+
+        assert_eq!(backend.kind(), BackendKind::Libgit2);
+    }
+
+    #[test]
+    fn test_open_preferred_backend_returns_error_for_nonexistent_path() {
+        let result = open_preferred_backend(Path::new("/nonexistent/path/that/does/not/exist"));
+
+        assert!(
+            matches!(result, Err(VisiGitError::RepoNotFound { .. })),
+            "Expected RepoNotFound for nonexistent path"
+        );
+    }
+
+    #[test]
+    fn test_open_backend_returns_not_a_repository_for_plain_directory() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        for kind in BackendKind::ALL {
+            let result = open_backend(temp_dir.path(), kind);
+
+            assert!(
+                matches!(result, Err(VisiGitError::NotARepository { .. })),
+                "[{}] Expected NotARepository",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_open_backend_rejects_a_subfolder_of_a_repo() {
+        let temp_repo = create_test_git_repo();
+        let subfolder = temp_repo.path().join("assets");
+        fs::create_dir(&subfolder).unwrap();
+
+        for kind in BackendKind::ALL {
+            let result = open_backend(&subfolder, kind);
+
+            assert!(
+                matches!(result, Err(VisiGitError::NotARepository { .. })),
+                "[{}] Expected NotARepository for a subfolder",
+                kind
+            );
+        }
+        assert!(matches!(
+            BackendCache::default().get(subfolder.to_str().unwrap()),
+            Err(VisiGitError::NotARepository { .. })
+        ));
+    }
+
+    #[test]
+    fn test_backend_cache_reuses_backend_for_same_repo() {
+        let temp_repo = create_test_git_repo();
+        let path_str = temp_repo.path().to_str().unwrap();
+        let cache = BackendCache::default();
+
+        let first = cache.get(path_str).expect("Failed to open backend");
+        let second = cache.get(path_str).expect("Failed to open backend");
+
+        assert!(Arc::ptr_eq(&first, &second), "Expected the cached backend");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_backend_cache_shares_backend_and_id_between_paths_to_a_repo() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        let temp_link = TempDir::new().expect("Failed to create temp dir");
+        let link = temp_link.path().join("repo");
+        std::os::unix::fs::symlink(root, &link).unwrap();
+        let cache = BackendCache::default();
+        let first = cache.get(root.to_str().unwrap()).unwrap();
+        let id = cache.repo_id(root.to_str().unwrap()).unwrap();
+
+        for path in [
+            format!("{}/", root.display()),
+            root.join(".git").display().to_string(),
+            link.display().to_string(),
+        ] {
+            let backend = cache.get(&path).expect("Failed to open backend");
+
+            assert!(
+                Arc::ptr_eq(&first, &backend),
+                "Expected the same backend for {}",
+                path
+            );
+            assert_eq!(cache.repo_id(&path).unwrap(), id, "for {}", path);
+        }
+    }
+
+    #[test]
+    fn test_backend_cache_resolves_repo_ids() {
+        let temp_repo = create_test_git_repo();
+        let path_str = temp_repo.path().to_str().unwrap();
+        let canonical = temp_repo.path().canonicalize().unwrap();
+        let cache = BackendCache::default();
+
+        let id = cache.repo_id(path_str).expect("Failed to register repo");
+
+        assert_eq!(cache.repo_id(path_str).unwrap(), id, "Expected a stable id");
+        assert_eq!(cache.repo_path(&id).as_deref(), canonical.to_str());
+        assert_eq!(cache.repo_path("unknown"), None);
+    }
+
+    #[test]
+    fn test_backend_cache_shutdown_forgets_backends() {
+        let temp_repo = create_test_git_repo();
+        let path_str = temp_repo.path().to_str().unwrap();
+        let cache = BackendCache::default();
+        let id = cache.repo_id(path_str).unwrap();
+        let first = cache.get(path_str).unwrap();
+
+        cache.shutdown();
+
+        let second = cache.get(path_str).expect("Failed to reopen backend");
+        assert!(!Arc::ptr_eq(&first, &second), "Expected a new backend");
+        assert!(
+            cache.repo_path(&id).is_some(),
+            "Expected the id to stay valid"
+        );
+    }
+
+    #[test]
+    fn test_backend_cache_repo_id_requires_a_repository() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let cache = BackendCache::default();
+
+        let result = cache.repo_id(temp_dir.path().to_str().unwrap());
+
+        assert!(
+            matches!(result, Err(VisiGitError::NotARepository { .. })),
+            "Expected NotARepository, got {:?}",
+            result
+        );
+    }
+
+    // ============================================
+    // Tests for validate_git_repo_impl
+    // ============================================
+
+    #[test]
+    fn test_validate_git_repo_returns_true_for_valid_repo() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path().to_str().unwrap();
+
+        let result = validate_git_repo_impl(path);
+
+        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
+        assert!(result.unwrap(), "Expected true for valid git repo");
+    }
+
+    #[test]
+    fn test_validate_git_repo_returns_false_for_non_git_directory() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().to_str().unwrap();
+
+        let result = validate_git_repo_impl(path);
+
+        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
+        assert!(!result.unwrap(), "Expected false for non-git directory");
+    }
+
+    #[test]
+    fn test_validate_git_repo_returns_error_for_nonexistent_path() {
+        let result = validate_git_repo_impl("/nonexistent/path/that/does/not/exist");
+
+        assert_eq!(
+            result,
+            Err(VisiGitError::RepoNotFound {
+                path: "/nonexistent/path/that/does/not/exist".to_string()
+            })
+        );
+    }
+
+    // ============================================
+    // Tests for discover_repo_impl
+    // ============================================
+
+    /// Helper to run git in `dir`, panicking if it fails
+    fn run_git(dir: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("Failed to run git");
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    /// Helper to commit `logo.png` in `dir` on a branch named `designs`
+    fn commit_logo(dir: &Path) {
+        fs::write(dir.join("logo.png"), b"logo").expect("Failed to write file");
+        run_git(dir, &["add", "."]);
+        run_git(dir, &["commit", "-m", "Add logo"]);
+        run_git(dir, &["branch", "-M", "designs"]);
+    }
+
+    fn canonical(path: &Path) -> String {
+        path.canonicalize().unwrap().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_discover_repo_walks_up_from_subfolder() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_logo(root);
+        fs::create_dir_all(root.join("assets/icons")).unwrap();
+
+        for kind in BackendKind::ALL {
+            let info = discover_with(&root.join("assets/icons"), kind).expect("Failed to discover");
+
+            assert_eq!(
+                info,
+                RepoInfo {
+                    toplevel: Some(canonical(root)),
+                    git_dir: canonical(&root.join(".git")),
+                    common_dir: canonical(&root.join(".git")),
+                    is_bare: false,
+                    is_worktree: false,
+                    is_submodule: false,
+                    branch: Some("designs".to_string()),
+                },
+                "[{}]",
+                kind
+            );
+        }
+        assert_eq!(
+            validate_git_repo_impl(root.join("assets").to_str().unwrap()),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_discover_repo_reports_detached_head() {
+        let temp_repo = create_test_git_repo();
+        commit_logo(temp_repo.path());
+        run_git(temp_repo.path(), &["checkout", "--detach"]);
+
+        for kind in BackendKind::ALL {
+            let info = discover_with(temp_repo.path(), kind).expect("Failed to discover");
+
+            assert_eq!(info.branch, None, "[{}] Expected no branch", kind);
+        }
+    }
+
+    #[test]
+    fn test_discover_repo_finds_linked_worktree() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_logo(root);
+        let worktrees = TempDir::new().expect("Failed to create temp dir");
+        let worktree = worktrees.path().join("review");
+        run_git(
+            root,
+            &[
+                "worktree",
+                "add",
+                "-b",
+                "review",
+                worktree.to_str().unwrap(),
+            ],
+        );
+
+        for kind in BackendKind::ALL {
+            let info = discover_with(&worktree, kind).expect("Failed to discover");
+
+            assert_eq!(info.toplevel, Some(canonical(&worktree)), "[{}]", kind);
+            assert_eq!(
+                info.git_dir,
+                canonical(&root.join(".git/worktrees/review")),
+                "[{}]",
+                kind
+            );
+            assert_eq!(info.common_dir, canonical(&root.join(".git")), "[{}]", kind);
+            assert!(info.is_worktree, "[{}] Expected a worktree", kind);
+            assert!(!info.is_submodule, "[{}]", kind);
+            assert_eq!(info.branch.as_deref(), Some("review"), "[{}]", kind);
+        }
+
+        // Commands accept the resolved root
+        for backend in all_backends(&worktree) {
+            assert_eq!(
+                get_file_bytes_impl(backend.as_ref(), "logo.png", "HEAD"),
+                Ok(b"logo".to_vec()),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_discover_repo_finds_submodule() {
+        let library = create_test_git_repo();
+        commit_logo(library.path());
+        let app = create_test_git_repo();
+        commit_logo(app.path());
+        run_git(
+            app.path(),
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                library.path().to_str().unwrap(),
+                "vendor/library",
+            ],
+        );
+        run_git(app.path(), &["commit", "-m", "Add library"]);
+        let submodule = app.path().join("vendor/library");
+
+        for kind in BackendKind::ALL {
+            let info = discover_with(&submodule, kind).expect("Failed to discover");
+
+            assert_eq!(info.toplevel, Some(canonical(&submodule)), "[{}]", kind);
+            assert_eq!(
+                info.git_dir,
+                canonical(&app.path().join(".git/modules/vendor/library")),
+                "[{}]",
+                kind
+            );
+            assert!(info.is_submodule, "[{}] Expected a submodule", kind);
+            assert!(!info.is_worktree, "[{}]", kind);
+
+            // The superproject itself is not a submodule
+            let info = discover_with(app.path(), kind).expect("Failed to discover");
+            assert!(!info.is_submodule, "[{}]", kind);
+        }
+
+        for backend in all_backends(&submodule) {
+            assert!(
+                get_file_at_head_impl(backend.as_ref(), "logo.png").is_ok(),
+                "[{}] Expected to read from the submodule",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_discover_repo_reports_bare_repository() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        run_git(temp_dir.path(), &["init", "--bare", "--quiet"]);
+
+        for kind in BackendKind::ALL {
+            let info = discover_with(temp_dir.path(), kind).expect("Failed to discover");
+
+            assert!(info.is_bare, "[{}] Expected a bare repo", kind);
+            assert_eq!(info.toplevel, None, "[{}]", kind);
+            assert_eq!(info.git_dir, canonical(temp_dir.path()), "[{}]", kind);
+        }
+    }
+
+    #[test]
+    fn test_discover_repo_returns_not_a_repository() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().to_str().unwrap();
+
+        for kind in BackendKind::ALL {
+            let result = discover_with(temp_dir.path(), kind);
+
+            assert_eq!(
+                result,
+                Err(VisiGitError::NotARepository {
+                    path: path.to_string()
+                }),
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for get_changed_files_impl
+    // ============================================
+
+    #[test]
+    fn test_get_changed_files_returns_empty_for_clean_repo() {
+        let temp_repo = create_test_git_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let result = get_changed_files_impl(backend.as_ref());
+
+            assert!(
+                result.is_ok(),
+                "[{}] Expected Ok result, got {:?}",
+                backend.kind(),
+                result
+            );
+            assert!(
+                result.unwrap().is_empty(),
+                "[{}] Expected empty list for clean repo",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_changed_files_detects_modified_png() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        // Create and commit an image file
+        let image_path = path.join("test.png");
+        fs::write(&image_path, b"fake png content").expect("Failed to write image");
+
+        Command::new("git")
+            .args(["add", "test.png"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add file");
+
+        Command::new("git")
+            .args(["commit", "-m", "Initial commit"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        // Modify the image
+        fs::write(&image_path, b"modified png content").expect("Failed to modify image");
+
+        for backend in all_backends(path) {
+            let result = get_changed_files_impl(backend.as_ref());
+
+            assert!(
+                result.is_ok(),
+                "[{}] Expected Ok result, got {:?}",
+                backend.kind(),
+                result
+            );
+            let files = result.unwrap();
+            assert_eq!(
+                files.len(),
+                1,
+                "[{}] Expected 1 changed file",
+                backend.kind()
+            );
+            assert_eq!(files[0].path, "test.png");
+            assert_eq!(files[0].status, ChangeKind::Modified);
+            assert!(!files[0].staged, "[{}] Edit is not staged", backend.kind());
+            assert!(files[0].unstaged, "[{}] Edit is unstaged", backend.kind());
+        }
+    }
+
+    #[test]
+    fn test_get_changed_files_detects_new_image() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        // Create initial commit (needed for git status to work properly)
+        fs::write(path.join("README.md"), "readme").expect("Failed to write readme");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add");
+        Command::new("git")
+            .args(["commit", "-m", "Initial"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        // Create a new untracked image
+        fs::write(path.join("new.jpg"), b"new jpg content").expect("Failed to write new image");
+
+        for backend in all_backends(path) {
+            let result = get_changed_files_impl(backend.as_ref());
+
+            assert!(
+                result.is_ok(),
+                "[{}] Expected Ok result, got {:?}",
+                backend.kind(),
+                result
+            );
+            let files = result.unwrap();
+            assert_eq!(files.len(), 1, "[{}] Expected 1 new file", backend.kind());
+            assert_eq!(files[0].path, "new.jpg");
+            assert_eq!(files[0].status, ChangeKind::Untracked);
+        }
+    }
+
+    #[test]
+    fn test_get_changed_files_ignores_non_image_files() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        // Create initial commit
+        fs::write(path.join("README.md"), "readme").expect("Failed to write readme");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add");
+        Command::new("git")
+            .args(["commit", "-m", "Initial"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        // Add a non-image file
+        fs::write(path.join("code.rs"), "fn main() {}").expect("Failed to write code file");
+
+        for backend in all_backends(path) {
+            let result = get_changed_files_impl(backend.as_ref());
+
+            assert!(
+                result.is_ok(),
+                "[{}] Expected Ok result, got {:?}",
+                backend.kind(),
+                result
+            );
+            let files = result.unwrap();
+            assert!(
+                files.is_empty(),
+                "[{}] Expected no files (code.rs should be ignored)",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_changed_files_returns_clean_unicode_paths() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        // Paths that plain --porcelain would quote and escape
+        fs::create_dir(path.join("assets")).expect("Failed to create dir");
+        fs::write(path.join("assets/héro image.png"), b"hero").expect("Failed to write image");
+        fs::write(path.join("assets/say \"cheese\".jpg"), b"cheese")
+            .expect("Failed to write image");
+
+        for backend in all_backends(path) {
+            let mut files = get_changed_files_impl(backend.as_ref()).expect("Failed to get status");
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+
+            let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+            assert_eq!(
+                paths,
+                vec!["assets/héro image.png", "assets/say \"cheese\".jpg"],
+                "[{}] Paths should be unquoted",
+                backend.kind()
+            );
+            assert_eq!(files[0].filename, "héro image.png");
+        }
+    }
+
+    #[test]
+    fn test_get_changed_files_reports_original_path_for_rename() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        fs::create_dir(path.join("img")).expect("Failed to create dir");
+        fs::write(path.join("img/logo.svg"), b"<svg>logo</svg>").expect("Failed to write image");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add");
+        Command::new("git")
+            .args(["commit", "-m", "Initial"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        // Stage a move so git records it as a rename
+        fs::create_dir(path.join("brand")).expect("Failed to create dir");
+        Command::new("git")
+            .args(["mv", "img/logo.svg", "brand/logo.svg"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to move file");
+
+        for backend in all_backends(path) {
+            let files = get_changed_files_impl(backend.as_ref()).expect("Failed to get status");
+
+            assert_eq!(
+                files.len(),
+                1,
+                "[{}] Expected a single rename entry",
+                backend.kind()
+            );
+            assert_eq!(files[0].path, "brand/logo.svg");
+            assert_eq!(
+                files[0].status,
+                ChangeKind::Renamed {
+                    from: "img/logo.svg".to_string()
+                }
+            );
+            assert!(files[0].staged);
+
+            // The original path is what exists at HEAD
+            let original = files[0].status.original_path().unwrap();
+            assert!(get_file_at_head_impl(backend.as_ref(), original).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_get_changed_files_separates_staged_and_unstaged() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        fs::write(path.join("staged.png"), b"v1").expect("Failed to write image");
+        fs::write(path.join("both.png"), b"v1").expect("Failed to write image");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add");
+        Command::new("git")
+            .args(["commit", "-m", "Initial"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        // Stage one edit, then keep editing the other after staging it
+        fs::write(path.join("staged.png"), b"v2").expect("Failed to write image");
+        fs::write(path.join("both.png"), b"v2").expect("Failed to write image");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add");
+        fs::write(path.join("both.png"), b"v3").expect("Failed to write image");
+
+        for backend in all_backends(path) {
+            let mut files = get_changed_files_impl(backend.as_ref()).expect("Failed to get status");
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+
+            assert_eq!(files.len(), 2, "[{}] Expected 2 files", backend.kind());
+            assert_eq!(files[0].path, "both.png");
+            assert!(files[0].staged && files[0].unstaged, "[{}]", backend.kind());
+            assert_eq!(files[0].index_status, Some(ChangeKind::Modified));
+            assert_eq!(files[0].worktree_status, Some(ChangeKind::Modified));
+            assert_eq!(files[1].path, "staged.png");
+            assert!(
+                files[1].staged && !files[1].unstaged,
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_changed_files_lists_conflicted_images() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        fs::write(path.join("hero.png"), b"base").expect("Failed to write image");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add");
+        Command::new("git")
+            .args(["commit", "-m", "Base"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+        Command::new("git")
+            .args(["branch", "other"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to branch");
+
+        // Change the image differently on both branches
+        fs::write(path.join("hero.png"), b"ours").expect("Failed to write image");
+        Command::new("git")
+            .args(["commit", "-am", "Ours"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+        Command::new("git")
+            .args(["checkout", "other"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to checkout");
+        fs::write(path.join("hero.png"), b"theirs").expect("Failed to write image");
+        Command::new("git")
+            .args(["commit", "-am", "Theirs"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+        Command::new("git")
+            .args(["checkout", "-"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to checkout");
+        Command::new("git")
+            .args(["merge", "other"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to run merge");
+
+        for backend in all_backends(path) {
+            let files = get_changed_files_impl(backend.as_ref()).expect("Failed to get status");
+
+            assert_eq!(
+                files.len(),
+                1,
+                "[{}] Conflict should be listed",
+                backend.kind()
+            );
+            assert_eq!(
+                files[0].status,
+                ChangeKind::Unmerged {
+                    ours: ConflictSide::Modified,
+                    theirs: ConflictSide::Modified,
+                },
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(files[0].index_status, None);
+            assert_eq!(files[0].worktree_status, Some(files[0].status.clone()));
+        }
+    }
+
+    #[test]
+    fn test_get_changed_files_reports_index_and_worktree_changes() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        fs::write(root.join("moved.png"), b"moved image content").unwrap();
+        fs::write(root.join("gone.png"), b"gone").unwrap();
+        commit_file(root, "edited.png", b"v1", "Initial");
+
+        // Staged: an added image that was edited again, a rename and a
+        // deletion. Unstaged only: an edit and an untracked image.
+        fs::write(root.join("added.png"), b"v1").unwrap();
+        run_git(root, &["add", "added.png"]);
+        fs::write(root.join("added.png"), b"v2").unwrap();
+        run_git(root, &["mv", "moved.png", "renamed.png"]);
+        run_git(root, &["rm", "-q", "gone.png"]);
+        fs::write(root.join("edited.png"), b"v2").unwrap();
+        fs::write(root.join("untracked.png"), b"new").unwrap();
+
+        for backend in all_backends(root) {
+            let files = get_changed_files_impl(backend.as_ref()).expect("Failed to get status");
+            let states = |path: &str| {
+                let file = files
+                    .iter()
+                    .find(|f| f.path == path)
+                    .unwrap_or_else(|| panic!("[{}] {} not listed", backend.kind(), path));
+                (file.index_status.clone(), file.worktree_status.clone())
+            };
+
+            assert_eq!(
+                states("added.png"),
+                (Some(ChangeKind::Added), Some(ChangeKind::Modified)),
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(
+                states("renamed.png"),
+                (
+                    Some(ChangeKind::Renamed {
+                        from: "moved.png".to_string()
+                    }),
+                    None
+                ),
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(states("gone.png"), (Some(ChangeKind::Deleted), None));
+            assert_eq!(states("edited.png"), (None, Some(ChangeKind::Modified)));
+            assert_eq!(states("untracked.png"), (None, Some(ChangeKind::Untracked)));
+        }
+    }
+
+    // ============================================
+    // Tests for get_changed_files_against_impl
+    // ============================================
+
+    /// Helper to read `git rev-parse` output in `dir`
+    fn rev_parse(dir: &Path, rev: &str) -> String {
+        let output = Command::new("git")
+            .args(["rev-parse", rev])
+            .current_dir(dir)
+            .output()
+            .expect("Failed to run rev-parse");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// Helper to find a file in a comparison result
+    fn find<'a>(files: &'a [DiffedFile], path: &str) -> &'a DiffedFile {
+        files
+            .iter()
+            .find(|f| f.file.path == path)
+            .unwrap_or_else(|| panic!("{} not in {:?}", path, files))
+    }
+
+    #[test]
+    fn test_changed_files_against_includes_committed_and_worktree_changes() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        fs::write(root.join("logo.png"), b"logo v1").unwrap();
+        fs::write(root.join("gone.png"), b"gone").unwrap();
+        run_git(root, &["add", "."]);
+        run_git(root, &["commit", "-m", "First"]);
+        let first = rev_parse(root, "HEAD");
+
+        // Committed since `first`
+        fs::write(root.join("logo.png"), b"logo v2").unwrap();
+        run_git(root, &["commit", "-am", "Second"]);
+        // Only in the working tree
+        fs::remove_file(root.join("gone.png")).unwrap();
+        fs::write(root.join("fresh.png"), b"fresh").unwrap();
+        fs::write(root.join("notes.txt"), b"not an image").unwrap();
+
+        for backend in all_backends(root) {
+            let files = get_changed_files_against_impl(backend.as_ref(), &first)
+                .expect("Failed to compare");
+            let kind = backend.kind();
+
+            assert_eq!(files.len(), 3, "[{}] Got {:?}", kind, files);
+
+            let logo = find(&files, "logo.png");
+            assert_eq!(logo.file.status, ChangeKind::Modified, "[{}]", kind);
+            assert!(!logo.file.staged && !logo.file.unstaged, "[{}]", kind);
+            assert_eq!(
+                logo.old_blob_id,
+                Some(rev_parse(root, &format!("{}:logo.png", first))),
+                "[{}]",
+                kind
+            );
+            assert_eq!(
+                logo.new_blob_id,
+                Some(sha1_blob_id(b"logo v2").unwrap()),
+                "[{}]",
+                kind
+            );
+
+            let gone = find(&files, "gone.png");
+            assert_eq!(gone.file.status, ChangeKind::Deleted, "[{}]", kind);
+            assert!(gone.file.unstaged, "[{}]", kind);
+            assert_eq!(gone.new_blob_id, None, "[{}]", kind);
+
+            let fresh = find(&files, "fresh.png");
+            assert_eq!(fresh.file.status, ChangeKind::Untracked, "[{}]", kind);
+            assert_eq!(fresh.old_blob_id, None, "[{}]", kind);
+            assert_eq!(
+                fresh.new_blob_id,
+                Some(sha1_blob_id(b"fresh").unwrap()),
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_sha256_repository_gets_sha256_blob_ids() {
+        let temp_repo = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_repo.path();
+        run_git(root, &["init", "-q", "--object-format=sha256"]);
+        run_git(root, &["config", "user.email", "test@test.com"]);
+        run_git(root, &["config", "user.name", "Test User"]);
+        commit_file(root, "logo.png", b"logo v1", "First");
+        fs::write(root.join("logo.png"), b"logo v2").unwrap();
+        let expected = {
+            let mut child = Command::new("git")
+                .args(["hash-object", "--stdin"])
+                .current_dir(root)
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .spawn()
+                .unwrap();
+            std::io::Write::write_all(&mut child.stdin.take().unwrap(), b"logo v2").unwrap();
+            let output = child.wait_with_output().unwrap();
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        assert_eq!(expected.len(), 64);
+
+        // Only git itself opens SHA-256 repositories
+        assert!(open_backend(root, BackendKind::Libgit2).is_err());
+        let backend = open_preferred_backend(root).unwrap();
+        let files = get_changed_files_against_impl(backend.as_ref(), "HEAD").unwrap();
+
+        assert_eq!(files[0].new_blob_id, Some(expected));
+        assert_eq!(files[0].old_blob_id, Some(rev_parse(root, "HEAD:logo.png")));
+    }
+
+    #[test]
+    fn test_changed_files_against_merge_base_shows_only_branch_changes() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_logo(root);
+        run_git(root, &["checkout", "-b", "feature"]);
+        fs::write(root.join("feature.png"), b"feature").unwrap();
+        run_git(root, &["add", "."]);
+        run_git(root, &["commit", "-m", "Feature work"]);
+        // `designs` moves on after the fork
+        run_git(root, &["checkout", "designs"]);
+        fs::write(root.join("upstream.png"), b"upstream").unwrap();
+        run_git(root, &["add", "."]);
+        run_git(root, &["commit", "-m", "Upstream work"]);
+        run_git(root, &["checkout", "feature"]);
+
+        for backend in all_backends(root) {
+            let kind = backend.kind();
+
+            let direct = get_changed_files_against_impl(backend.as_ref(), "designs").unwrap();
+            assert_eq!(
+                find(&direct, "upstream.png").file.status,
+                ChangeKind::Deleted,
+                "[{}]",
+                kind
+            );
+
+            let fork = get_changed_files_against_impl(backend.as_ref(), "designs...").unwrap();
+            let paths: Vec<&str> = fork.iter().map(|f| f.file.path.as_str()).collect();
+            assert_eq!(paths, vec!["feature.png"], "[{}]", kind);
+            assert_eq!(fork[0].file.status, ChangeKind::Added, "[{}]", kind);
+        }
+    }
+
+    #[test]
+    fn test_changed_files_against_detects_staged_rename() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_logo(root);
+        fs::create_dir(root.join("brand")).unwrap();
+        run_git(root, &["mv", "logo.png", "brand/logo.png"]);
+
+        for backend in all_backends(root) {
+            let files = get_changed_files_against_impl(backend.as_ref(), "HEAD").unwrap();
+            let kind = backend.kind();
+
+            assert_eq!(files.len(), 1, "[{}] Got {:?}", kind, files);
+            assert_eq!(
+                files[0].file.status,
+                ChangeKind::Renamed {
+                    from: "logo.png".to_string()
+                },
+                "[{}]",
+                kind
+            );
+            assert!(files[0].file.staged, "[{}]", kind);
+            assert_eq!(files[0].old_blob_id, files[0].new_blob_id, "[{}]", kind);
+        }
+    }
+
+    #[test]
+    fn test_changed_files_against_rejects_unknown_and_unrelated_revisions() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_logo(root);
+        run_git(root, &["checkout", "--orphan", "unrelated"]);
+        run_git(root, &["commit", "-m", "Unrelated"]);
+
+        for backend in all_backends(root) {
+            let kind = backend.kind();
+
+            for rev in ["missing", "designs..unrelated"] {
+                assert_eq!(
+                    get_changed_files_against_impl(backend.as_ref(), rev),
+                    Err(VisiGitError::RevisionNotFound {
+                        rev: rev.to_string()
+                    }),
+                    "[{}] for {}",
+                    kind,
+                    rev
+                );
+            }
+
+            assert_eq!(
+                get_changed_files_against_impl(backend.as_ref(), "designs..."),
+                Err(VisiGitError::NoMergeBase {
+                    left: "designs".to_string(),
+                    right: "HEAD".to_string()
+                }),
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for get_changes_between_impl
+    // ============================================
+
+    /// Helper to write `content` to `path` in `dir` and commit everything
+    fn commit_file(dir: &Path, path: &str, content: &[u8], message: &str) {
+        fs::write(dir.join(path), content).expect("Failed to write file");
+        run_git(dir, &["add", "-A"]);
+        run_git(dir, &["commit", "-m", message]);
+    }
+
+    #[test]
+    fn test_changes_between_lists_typed_changes_with_blob_ids() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        fs::write(root.join("logo.png"), b"logo v1").unwrap();
+        fs::write(root.join("gone.png"), b"gone").unwrap();
+        commit_file(root, "icon.png", b"an icon that keeps its content", "First");
+        let first = rev_parse(root, "HEAD");
+
+        fs::write(root.join("logo.png"), b"logo v2").unwrap();
+        fs::remove_file(root.join("gone.png")).unwrap();
+        fs::create_dir(root.join("brand")).unwrap();
+        fs::rename(root.join("icon.png"), root.join("brand/icon.png")).unwrap();
+        fs::write(root.join("notes.txt"), b"not an image").unwrap();
+        commit_file(root, "fresh.png", b"fresh", "Second");
+        // Left uncommitted, so it must not show up
+        fs::write(root.join("logo.png"), b"logo v3").unwrap();
+
+        for backend in all_backends(root) {
+            let files = get_changes_between_impl(backend.as_ref(), &first, "HEAD", false)
+                .expect("Failed to compare");
+            let kind = backend.kind();
+
+            assert_eq!(files.len(), 4, "[{}] Got {:?}", kind, files);
+            assert!(
+                files.iter().all(|f| !f.file.staged && !f.file.unstaged),
+                "[{}]",
+                kind
+            );
+
+            let logo = find(&files, "logo.png");
+            assert_eq!(logo.file.status, ChangeKind::Modified, "[{}]", kind);
+            assert_eq!(
+                logo.old_blob_id,
+                Some(sha1_blob_id(b"logo v1").unwrap()),
+                "[{}]",
+                kind
+            );
+            assert_eq!(
+                logo.new_blob_id,
+                Some(sha1_blob_id(b"logo v2").unwrap()),
+                "[{}]",
+                kind
+            );
+
+            let gone = find(&files, "gone.png");
+            assert_eq!(gone.file.status, ChangeKind::Deleted, "[{}]", kind);
+            assert_eq!(gone.new_blob_id, None, "[{}]", kind);
+
+            let icon = find(&files, "brand/icon.png");
+            assert_eq!(
+                icon.file.status,
+                ChangeKind::Renamed {
+                    from: "icon.png".to_string()
+                },
+                "[{}]",
+                kind
+            );
+            assert_eq!(icon.old_blob_id, icon.new_blob_id, "[{}]", kind);
+
+            let fresh = find(&files, "fresh.png");
+            assert_eq!(fresh.file.status, ChangeKind::Added, "[{}]", kind);
+            assert_eq!(fresh.old_blob_id, None, "[{}]", kind);
+        }
+    }
+
+    #[test]
+    fn test_changes_between_first_parent_measures_from_the_mainline() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_logo(root);
+        run_git(root, &["checkout", "-b", "feature"]);
+        commit_file(root, "side.png", b"side v1", "Start side");
+        let side_start = rev_parse(root, "HEAD");
+        commit_file(root, "side.png", b"side v2", "Finish side");
+        run_git(root, &["checkout", "designs"]);
+        commit_file(root, "main.png", b"main", "Mainline work");
+        run_git(
+            root,
+            &["merge", "--no-ff", "-m", "Merge feature", "feature"],
+        );
+
+        for backend in all_backends(root) {
+            let kind = backend.kind();
+            let statuses = |first_parent| {
+                let mut files: Vec<(String, ChangeKind)> =
+                    get_changes_between_impl(backend.as_ref(), &side_start, "HEAD", first_parent)
+                        .expect("Failed to compare")
+                        .into_iter()
+                        .map(|f| (f.file.path, f.file.status))
+                        .collect();
+                files.sort_by(|a, b| a.0.cmp(&b.0));
+                files
+            };
+
+            // Straight tree comparison from the middle of the side branch
+            assert_eq!(
+                statuses(false),
+                vec![
+                    ("main.png".to_string(), ChangeKind::Added),
+                    ("side.png".to_string(), ChangeKind::Modified),
+                ],
+                "[{}]",
+                kind
+            );
+            // The mainline met it at the fork, so the merge brings the
+            // whole side branch in
+            assert_eq!(
+                statuses(true),
+                vec![
+                    ("main.png".to_string(), ChangeKind::Added),
+                    ("side.png".to_string(), ChangeKind::Added),
+                ],
+                "[{}]",
+                kind
+            );
+
+            let merged = get_changes_between_impl(backend.as_ref(), "feature", "HEAD", true)
+                .expect("Failed to compare");
+            let paths: Vec<&str> = merged.iter().map(|f| f.file.path.as_str()).collect();
+            assert_eq!(paths, vec!["main.png", "side.png"], "[{}]", kind);
+
+            let behind = get_changes_between_impl(backend.as_ref(), "HEAD", "designs~1", true)
+                .expect("Failed to compare");
+            assert_eq!(behind, vec![], "[{}]", kind);
+        }
+    }
+
+    #[test]
+    fn test_changes_between_unrelated_first_parent_starts_from_empty_tree() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_logo(root);
+        run_git(root, &["checkout", "--orphan", "unrelated"]);
+        run_git(root, &["rm", "-q", "-f", "logo.png"]);
+        commit_file(root, "other.png", b"other", "Unrelated");
+
+        for backend in all_backends(root) {
+            let kind = backend.kind();
+            let statuses = |first_parent| {
+                get_changes_between_impl(backend.as_ref(), "designs", "unrelated", first_parent)
+                    .expect("Failed to compare")
+                    .into_iter()
+                    .map(|f| (f.file.path, f.file.status))
+                    .collect::<Vec<_>>()
+            };
+
+            assert_eq!(
+                statuses(false),
+                vec![
+                    ("logo.png".to_string(), ChangeKind::Deleted),
+                    ("other.png".to_string(), ChangeKind::Added),
+                ],
+                "[{}]",
+                kind
+            );
+            assert_eq!(
+                statuses(true),
+                vec![("other.png".to_string(), ChangeKind::Added)],
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_changes_between_rejects_bad_revisions() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_logo(root);
+
+        for backend in all_backends(root) {
+            let kind = backend.kind();
+
+            assert_eq!(
+                get_changes_between_impl(backend.as_ref(), "missing", "HEAD", false),
+                Err(VisiGitError::RevisionNotFound {
+                    rev: "missing".to_string()
+                }),
+                "[{}]",
+                kind
+            );
+            assert_eq!(
+                get_changes_between_impl(backend.as_ref(), "HEAD", "--output=x", true),
+                Err(VisiGitError::InvalidRevision {
+                    rev: "--output=x".to_string()
+                }),
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for ChangeKind
+    // ============================================
+
+    fn entry(xy: &str, orig_path: Option<&str>) -> StatusEntry {
+        let mut codes = xy.chars();
+        StatusEntry {
+            path: "a.png".to_string(),
+            orig_path: orig_path.map(str::to_string),
+            index: codes.next().unwrap(),
+            worktree: codes.next().unwrap(),
+            conflict: None,
+        }
+    }
+
+    #[test]
+    fn test_change_kind_from_status_codes() {
+        assert_eq!(
+            ChangeKind::from_status(&entry(".M", None)),
+            ChangeKind::Modified
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry("MM", None)),
+            ChangeKind::Modified
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry("A.", None)),
+            ChangeKind::Added
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry("AD", None)),
+            ChangeKind::Deleted
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry(".D", None)),
+            ChangeKind::Deleted
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry(".T", None)),
+            ChangeKind::TypeChanged
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry("??", None)),
+            ChangeKind::Untracked
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry("!!", None)),
+            ChangeKind::Ignored
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry("C.", Some("b.png"))),
+            ChangeKind::Copied {
+                from: "b.png".to_string()
+            }
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry("UD", None)),
+            ChangeKind::Unmerged {
+                ours: ConflictSide::Modified,
+                theirs: ConflictSide::Deleted,
+            }
+        );
+        assert_eq!(
+            ChangeKind::from_status(&entry("AA", None)),
+            ChangeKind::Unmerged {
+                ours: ConflictSide::Added,
+                theirs: ConflictSide::Added,
+            }
+        );
+    }
+
+    #[test]
+    fn test_change_kind_serializes_with_kind_tag() {
+        let renamed = ChangeKind::Renamed {
+            from: "img/logo.svg".to_string(),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&renamed).unwrap(),
+            serde_json::json!({ "kind": "renamed", "from": "img/logo.svg" })
+        );
+        assert_eq!(
+            serde_json::to_value(ChangeKind::TypeChanged).unwrap(),
+            serde_json::json!({ "kind": "type_changed" })
+        );
+    }
+
+    // ============================================
+    // Tests for get_file_at_head_impl
+    // ============================================
+
+    #[test]
+    fn test_get_file_at_head_returns_base64_content() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        // Create and commit an image file
+        let content = b"test image content";
+        fs::write(path.join("test.png"), content).expect("Failed to write image");
+
+        Command::new("git")
+            .args(["add", "test.png"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add file");
+
+        Command::new("git")
+            .args(["commit", "-m", "Add image"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        for backend in all_backends(path) {
+            let result = get_file_at_head_impl(backend.as_ref(), "test.png");
+
+            assert!(
+                result.is_ok(),
+                "[{}] Expected Ok result, got {:?}",
+                backend.kind(),
+                result
+            );
+
+            // Verify the base64 decodes to original content
+            let base64_content = result.unwrap();
+            let decoded = STANDARD
+                .decode(&base64_content)
+                .expect("Failed to decode base64");
+            assert_eq!(
+                decoded,
+                content,
+                "[{}] Decoded content should match original",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_file_at_head_returns_error_for_new_file() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        // Create initial commit
+        fs::write(path.join("README.md"), "readme").expect("Failed to write readme");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add");
+        Command::new("git")
+            .args(["commit", "-m", "Initial"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        // Create a new file that hasn't been committed
+        fs::write(path.join("new.png"), b"new content").expect("Failed to write new file");
+
+        for backend in all_backends(path) {
+            let result = get_file_at_head_impl(backend.as_ref(), "new.png");
+
+            // Should return error since file doesn't exist at HEAD
+            assert_eq!(
+                result,
+                Err(VisiGitError::PathNotInRevision {
+                    path: "new.png".to_string(),
+                    rev: "HEAD".to_string(),
+                }),
+                "[{}] Expected error for file not in HEAD",
+                backend.kind()
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for get_file_at_index_impl
+    // ============================================
+
+    #[test]
+    fn test_get_file_at_index_returns_staged_version() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "logo.png", b"committed", "Initial");
+        fs::write(root.join("logo.png"), b"staged").unwrap();
+        run_git(root, &["add", "logo.png"]);
+        fs::write(root.join("logo.png"), b"still editing").unwrap();
+        fs::create_dir(root.join("icons")).unwrap();
+        fs::write(root.join("icons/new.png"), b"new and staged").unwrap();
+        run_git(root, &["add", "icons/new.png"]);
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+
+            assert_eq!(
+                get_file_at_index_impl(backend, "logo.png"),
+                Ok(STANDARD.encode(b"staged")),
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(
+                get_file_bytes_at_index_impl(backend, "./icons/new.png"),
+                Ok(b"new and staged".to_vec()),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_reads_follow_staging_and_commits_on_an_open_backend() {
+        // The same backend is reused across changes, as the app does
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            commit_file(root, "logo.png", b"first", "Initial");
+        }) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            assert_eq!(
+                get_file_bytes_impl(backend, "logo.png", "HEAD"),
+                Ok(b"first".to_vec())
+            );
+            assert_eq!(
+                get_file_bytes_at_index_impl(backend, "logo.png"),
+                Ok(b"first".to_vec())
+            );
+
+            fs::write(root.join("logo.png"), b"staged").unwrap();
+            run_git(root, &["add", "logo.png"]);
+            assert_eq!(
+                get_file_bytes_at_index_impl(backend, "logo.png"),
+                Ok(b"staged".to_vec()),
+                "[{}]",
+                backend.kind()
+            );
+
+            run_git(root, &["commit", "-q", "-m", "Update"]);
+            assert_eq!(
+                get_file_bytes_impl(backend, "logo.png", "HEAD"),
+                Ok(b"staged".to_vec()),
+                "[{}]",
+                backend.kind()
+            );
+            backend.shutdown();
+        }
+    }
+
+    #[test]
+    fn test_get_file_at_index_rejects_unstaged_and_invalid_paths() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "logo.png", b"committed", "Initial");
+        fs::write(root.join("untracked.png"), b"new").unwrap();
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+
+            assert_eq!(
+                get_file_at_index_impl(backend, "untracked.png"),
+                Err(VisiGitError::PathNotInIndex {
+                    path: "untracked.png".to_string()
+                }),
+                "[{}]",
+                backend.kind()
+            );
+            assert!(matches!(
+                get_file_at_index_impl(backend, "../outside.png"),
+                Err(VisiGitError::PathEscapesRepo { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_get_file_at_index_has_no_version_for_conflicts() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "hero.png", b"base", "Base");
+        run_git(root, &["branch", "other"]);
+        commit_file(root, "hero.png", b"ours", "Ours");
+        run_git(root, &["checkout", "-q", "other"]);
+        commit_file(root, "hero.png", b"theirs", "Theirs");
+        run_git(root, &["checkout", "-q", "-"]);
+        // Fails with the conflict
+        let _ = Command::new("git")
+            .args(["merge", "other"])
+            .current_dir(root)
+            .output();
+
+        for backend in all_backends(root) {
+            assert_eq!(
+                get_file_at_index_impl(backend.as_ref(), "hero.png"),
+                Err(VisiGitError::PathNotInIndex {
+                    path: "hero.png".to_string()
+                }),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for stage_files_impl, unstage_files_impl and create_commit_impl
+    // ============================================
+
+    /// Helper to create a fresh repository for each backend, since these
+    /// tests change the repository they run on
+    fn each_backend_with_repo(setup: impl Fn(&Path)) -> Vec<(TempDir, Arc<dyn GitBackend>)> {
+        BackendKind::ALL
+            .iter()
+            .map(|kind| {
+                let temp_repo = create_test_git_repo();
+                setup(temp_repo.path());
+                let backend =
+                    open_backend(temp_repo.path(), *kind).expect("Failed to open backend");
+                (temp_repo, backend)
+            })
+            .collect()
+    }
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    /// Helper to leave `hero.png` conflicted in the middle of a merge
+    fn start_conflicted_merge(root: &Path) {
+        commit_file(root, "hero.png", b"base", "Base");
+        run_git(root, &["branch", "other"]);
+        commit_file(root, "hero.png", b"ours", "Ours");
+        run_git(root, &["checkout", "-q", "other"]);
+        commit_file(root, "hero.png", b"theirs", "Theirs");
+        run_git(root, &["checkout", "-q", "-"]);
+        // Fails with the conflict
+        let _ = Command::new("git")
+            .args(["merge", "other"])
+            .current_dir(root)
+            .output();
+    }
+
+    #[test]
+    fn test_stage_and_unstage_files_round_trip() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            fs::write(root.join("old.png"), b"old").unwrap();
+            commit_file(root, "logo.png", b"v1", "Initial");
+        }) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            fs::write(root.join("logo.png"), b"v2").unwrap();
+            fs::write(root.join("new image.png"), b"new").unwrap();
+            fs::remove_file(root.join("old.png")).unwrap();
+            let changed = paths(&["logo.png", "new image.png", "old.png"]);
+
+            let mut files = stage_files_impl(backend, &changed, false).expect("Failed to stage");
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+            let staged: Vec<_> = files
+                .iter()
+                .map(|file| (file.path.as_str(), file.index_status.clone(), file.unstaged))
+                .collect();
+            assert_eq!(
+                staged,
+                vec![
+                    ("logo.png", Some(ChangeKind::Modified), false),
+                    ("new image.png", Some(ChangeKind::Added), false),
+                    ("old.png", Some(ChangeKind::Deleted), false),
+                ],
+                "[{}]",
+                backend.kind()
+            );
+
+            let mut files =
+                unstage_files_impl(backend, &changed, false).expect("Failed to unstage");
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+            let unstaged: Vec<_> = files
+                .iter()
+                .map(|file| {
+                    (
+                        file.path.as_str(),
+                        file.staged,
+                        file.worktree_status.clone(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                unstaged,
+                vec![
+                    ("logo.png", false, Some(ChangeKind::Modified)),
+                    ("new image.png", false, Some(ChangeKind::Untracked)),
+                    ("old.png", false, Some(ChangeKind::Deleted)),
+                ],
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_unstage_on_unborn_branch_removes_from_index() {
+        for (temp_repo, backend) in each_backend_with_repo(|_| {}) {
+            fs::write(temp_repo.path().join("logo.png"), b"new").unwrap();
+            let backend = backend.as_ref();
+
+            stage_files_impl(backend, &paths(&["logo.png"]), false).expect("Failed to stage");
+            let files = unstage_files_impl(backend, &paths(&["logo.png"]), false)
+                .expect("Failed to unstage");
+
+            assert_eq!(files.len(), 1, "[{}]", backend.kind());
+            assert_eq!(
+                files[0].status,
+                ChangeKind::Untracked,
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_stage_files_runs_the_clean_filter() {
+        let pointer = format!(
+            "version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize 12\n",
+            "ef".repeat(32)
+        );
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            fs::write(root.join(".gitattributes"), "*.png filter=lfs\n").unwrap();
+            // Stands in for git-lfs storing the object and printing its pointer
+            let clean = format!("cat >/dev/null; printf '{}'", pointer.replace('\n', "\\n"));
+            run_git(root, &["config", "filter.lfs.clean", &clean]);
+        }) {
+            let backend = backend.as_ref();
+            fs::write(temp_repo.path().join("hero.png"), b"\x89PNG\r\n\x1a\nhero").unwrap();
+
+            stage_files_impl(backend, &paths(&["hero.png"]), false).expect("Failed to stage");
+
+            assert_eq!(
+                backend.read_index_blob("hero.png").unwrap().content,
+                pointer.as_bytes(),
+                "[{}] Expected the clean filter's output in the index",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_stage_files_rejects_paths_outside_the_repo() {
+        for (_temp_repo, backend) in each_backend_with_repo(|_| {}) {
+            assert!(
+                matches!(
+                    stage_files_impl(backend.as_ref(), &paths(&["../outside.png"]), false),
+                    Err(VisiGitError::PathEscapesRepo { .. })
+                ),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_commit_returns_new_commit() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            commit_file(root, "logo.png", b"v1", "Initial");
+        }) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            let parent = rev_parse(root, "HEAD");
+            fs::write(root.join("logo.png"), b"v2").unwrap();
+            stage_files_impl(backend, &paths(&["logo.png"]), false).expect("Failed to stage");
+            let author = Identity {
+                name: "Dana Designer".to_string(),
+                email: "dana@example.com".to_string(),
+            };
+
+            let commit = create_commit_impl(
+                backend,
+                "\nBrighten logo  \n\n\nMore contrast.\n\n",
+                Some(&author),
+                false,
+                false,
+            )
+            .expect("Failed to commit");
+
+            assert_eq!(commit.hash, rev_parse(root, "HEAD"), "[{}]", backend.kind());
+            assert_eq!(commit.parents, vec![parent]);
+            assert_eq!(commit.message, "Brighten logo");
+            assert_eq!(commit.body, "More contrast.");
+            assert_eq!(commit.author, "Dana Designer");
+            assert_eq!(commit.author_email, "dana@example.com");
+            assert_eq!(commit.committer, "Test User");
+            assert!(get_changed_files_impl(backend).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_create_commit_amends_head() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            commit_file(root, "logo.png", b"v1", "Initial");
+            commit_file(root, "logo.png", b"v2", "Tweak logo");
+        }) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            let parent = rev_parse(root, "HEAD~1");
+
+            // Amending only the message needs nothing staged
+            let commit = create_commit_impl(backend, "Recolor logo", None, true, false)
+                .expect("Failed to amend");
+
+            assert_eq!(commit.hash, rev_parse(root, "HEAD"), "[{}]", backend.kind());
+            assert_eq!(commit.parents, vec![parent]);
+            assert_eq!(commit.message, "Recolor logo");
+            assert_eq!(commit.author, "Test User");
+            assert_eq!(
+                get_file_at_head_impl(backend, "logo.png"),
+                Ok(STANDARD.encode(b"v2"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_commit_rejects_bad_requests() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            commit_file(root, "logo.png", b"v1", "Initial");
+        }) {
+            let backend = backend.as_ref();
+            fs::write(temp_repo.path().join("logo.png"), b"unstaged").unwrap();
+
+            assert_eq!(
+                create_commit_impl(backend, " \n\t", None, false, false),
+                Err(VisiGitError::EmptyCommitMessage),
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(
+                create_commit_impl(backend, "Update logo", None, false, false),
+                Err(VisiGitError::NothingToCommit),
+                "[{}]",
+                backend.kind()
+            );
+
+            stage_files_impl(backend, &paths(&["logo.png"]), false).expect("Failed to stage");
+            let author = Identity {
+                name: "Mallory".to_string(),
+                email: "m@example.com>\ncommitter x".to_string(),
+            };
+            assert_eq!(
+                create_commit_impl(backend, "Update logo", Some(&author), false, false),
+                Err(VisiGitError::InvalidIdentity {
+                    name: author.name.clone(),
+                    email: author.email.clone(),
+                }),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_commit_runs_hooks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            commit_file(root, "logo.png", b"v1", "Initial");
+            let hook = root.join(".git/hooks/pre-commit");
+            fs::write(&hook, "#!/bin/sh\necho 'logo too large' >&2\nexit 1\n").unwrap();
+            fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+        }) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            let head = rev_parse(root, "HEAD");
+            fs::write(root.join("logo.png"), b"v2").unwrap();
+            stage_files_impl(backend, &paths(&["logo.png"]), false).expect("Failed to stage");
+
+            let result = create_commit_impl(backend, "Update logo", None, false, false);
+
+            assert!(
+                matches!(&result, Err(VisiGitError::GitFailed { stderr, .. }) if stderr.contains("logo too large")),
+                "[{}] Expected the hook to refuse the commit, got {:?}",
+                backend.kind(),
+                result
+            );
+            assert_eq!(rev_parse(root, "HEAD"), head);
+        }
+    }
+
+    #[test]
+    fn test_create_commit_keeps_the_cherry_picked_author() {
+        for (_temp_repo, backend) in each_backend_with_repo(|root| {
+            commit_file(root, "hero.png", b"base", "Base");
+            run_git(root, &["branch", "other"]);
+            commit_file(root, "hero.png", b"ours", "Ours");
+            run_git(root, &["checkout", "-q", "other"]);
+            commit_file(root, "hero.png", b"theirs", "Theirs");
+            run_git(
+                root,
+                &[
+                    "commit",
+                    "-q",
+                    "--amend",
+                    "--no-edit",
+                    "--author=Ada <ada@example.com>",
+                ],
+            );
+            run_git(root, &["checkout", "-q", "-"]);
+            // Fails with the conflict
+            let _ = Command::new("git")
+                .args(["cherry-pick", "other"])
+                .current_dir(root)
+                .output();
+        }) {
+            let backend = backend.as_ref();
+            assert_eq!(backend.operation(), Ok(Some(RepoOperation::CherryPick)));
+            resolve_conflict_impl(backend, "hero.png", ConflictResolution::Theirs).unwrap();
+
+            let commit =
+                create_commit_impl(backend, "Theirs", None, false, true).expect("Failed to commit");
+
+            assert_eq!(commit.author, "Ada", "[{}]", backend.kind());
+            assert_eq!(commit.author_email, "ada@example.com");
+            assert_eq!(commit.committer, "Test User");
+            assert_eq!(backend.operation(), Ok(None));
+        }
+    }
+
+    #[test]
+    fn test_commands_refuse_to_run_during_merge_unless_allowed() {
+        for (temp_repo, backend) in each_backend_with_repo(start_conflicted_merge) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            let hero = paths(&["hero.png"]);
+            let in_progress = Err(VisiGitError::OperationInProgress {
+                operation: RepoOperation::Merge,
+            });
+
+            assert_eq!(backend.operation(), Ok(Some(RepoOperation::Merge)));
+            assert_eq!(
+                stage_files_impl(backend, &hero, false).map(|_| ()),
+                in_progress.clone(),
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(
+                create_commit_impl(backend, "Merge other", None, false, false).map(|_| ()),
+                in_progress.clone()
+            );
+            assert_eq!(
+                create_commit_impl(backend, "Merge other", None, true, true).map(|_| ()),
+                in_progress
+            );
+            assert_eq!(
+                create_commit_impl(backend, "Merge other", None, false, true).map(|_| ()),
+                Err(VisiGitError::UnresolvedConflicts {
+                    paths: paths(&["hero.png"])
+                }),
+                "[{}]",
+                backend.kind()
+            );
+
+            // Taking our side leaves nothing staged, which a merge may commit
+            fs::write(root.join("hero.png"), b"ours").unwrap();
+            stage_files_impl(backend, &hero, true).expect("Failed to stage");
+            let commit = create_commit_impl(backend, "Merge other", None, false, true)
+                .expect("Failed to commit");
+
+            assert_eq!(
+                commit.parents,
+                vec![rev_parse(root, "HEAD~1"), rev_parse(root, "other")],
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(backend.operation(), Ok(None), "[{}]", backend.kind());
+        }
+    }
+
+    // ============================================
+    // Tests for restore_file_impl and undo_restore_impl
+    // ============================================
+
+    /// Helper to commit three versions of `logo.png`, v1 to v3
+    fn commit_logo_versions(root: &Path) {
+        for version in ["v1", "v2", "v3"] {
+            commit_file(root, "logo.png", version.as_bytes(), version);
+        }
+    }
+
+    #[test]
+    fn test_restore_file_to_worktree_and_undo() {
+        for (temp_repo, backend) in each_backend_with_repo(commit_logo_versions) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            let temp_trash = TempDir::new().unwrap();
+            let trash = Trash::new(temp_trash.path().to_path_buf());
+            fs::write(root.join("logo.png"), b"work in progress").unwrap();
+
+            let backup = restore_file_impl(
+                backend,
+                &trash,
+                "logo.png",
+                "HEAD~2",
+                RestoreTarget::Worktree,
+            )
+            .expect("Failed to restore");
+
+            assert_eq!(fs::read(root.join("logo.png")).unwrap(), b"v1");
+            assert_eq!(backup.path, "logo.png", "[{}]", backend.kind());
+            assert_eq!(backup.source_rev, "HEAD~2");
+            assert_eq!(
+                backup.previous_id,
+                Some(sha1_blob_id(b"work in progress").unwrap())
+            );
+            // Staging is left alone
+            assert_eq!(
+                get_file_bytes_at_index_impl(backend, "logo.png"),
+                Ok(b"v3".to_vec())
+            );
+
+            undo_restore_impl(backend, &trash, &backup.id).expect("Failed to undo");
+
+            assert_eq!(
+                fs::read(root.join("logo.png")).unwrap(),
+                b"work in progress",
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(
+                undo_restore_impl(backend, &trash, &backup.id).map(|_| ()),
+                Err(VisiGitError::BackupNotFound { id: backup.id })
+            );
+        }
+    }
+
+    #[test]
+    fn test_restore_file_to_index_and_undo() {
+        for (temp_repo, backend) in each_backend_with_repo(commit_logo_versions) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            let temp_trash = TempDir::new().unwrap();
+            let trash = Trash::new(temp_trash.path().to_path_buf());
+            fs::write(root.join("logo.png"), b"staged").unwrap();
+            run_git(root, &["add", "logo.png"]);
+
+            let backup =
+                restore_file_impl(backend, &trash, "logo.png", "HEAD~1", RestoreTarget::Index)
+                    .expect("Failed to restore");
+
+            assert_eq!(
+                get_file_bytes_at_index_impl(backend, "logo.png"),
+                Ok(b"v2".to_vec()),
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(fs::read(root.join("logo.png")).unwrap(), b"staged");
+            let files = get_changed_files_impl(backend).unwrap();
+            assert_eq!(files[0].index_status, Some(ChangeKind::Modified));
+            assert_eq!(files[0].worktree_status, Some(ChangeKind::Modified));
+
+            let files = undo_restore_impl(backend, &trash, &backup.id).expect("Failed to undo");
+
+            assert_eq!(
+                get_file_bytes_at_index_impl(backend, "logo.png"),
+                Ok(b"staged".to_vec()),
+                "[{}]",
+                backend.kind()
+            );
+            assert!(files[0].staged && !files[0].unstaged);
+        }
+    }
+
+    #[test]
+    fn test_restore_file_to_index_keeps_an_executable_mode() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            // Keeps the executable bit in the index whatever the file system
+            run_git(root, &["config", "core.fileMode", "false"]);
+            commit_file(root, "logo.png", b"v1", "v1");
+            run_git(root, &["update-index", "--chmod=+x", "logo.png"]);
+            run_git(root, &["commit", "-q", "-m", "Make executable"]);
+            commit_file(root, "logo.png", b"v2", "v2");
+        }) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            let temp_trash = TempDir::new().unwrap();
+            let trash = Trash::new(temp_trash.path().to_path_buf());
+
+            restore_file_impl(backend, &trash, "logo.png", "HEAD~2", RestoreTarget::Index).unwrap();
+
+            assert_eq!(
+                index_mode(root, "logo.png"),
+                "100755",
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    /// Helper to read the mode of the stage 0 index entry for `path`
+    fn index_mode(root: &Path, path: &str) -> String {
+        let output = Command::new("git")
+            .args(["ls-files", "--stage", path])
+            .current_dir(root)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()[..6].to_string()
+    }
+
+    #[test]
+    fn test_restore_deleted_file_and_undo_removes_it_again() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            fs::create_dir(root.join("icons")).unwrap();
+            commit_file(root, "icons/old.png", b"old", "Add icon");
+            fs::remove_dir_all(root.join("icons")).unwrap();
+            run_git(root, &["commit", "-qam", "Remove icon"]);
+        }) {
+            let root = temp_repo.path();
+            let backend = backend.as_ref();
+            let temp_trash = TempDir::new().unwrap();
+            let trash = Trash::new(temp_trash.path().to_path_buf());
+
+            let worktree = restore_file_impl(
+                backend,
+                &trash,
+                "icons/old.png",
+                "HEAD~1",
+                RestoreTarget::Worktree,
+            )
+            .expect("Failed to restore");
+            let index = restore_file_impl(
+                backend,
+                &trash,
+                "icons/old.png",
+                "HEAD~1",
+                RestoreTarget::Index,
+            )
+            .expect("Failed to restore");
+
+            assert_eq!(fs::read(root.join("icons/old.png")).unwrap(), b"old");
+            assert_eq!(worktree.previous_id, None, "[{}]", backend.kind());
+            assert_eq!(index.previous_id, None, "[{}]", backend.kind());
+            let files = get_changed_files_impl(backend).unwrap();
+            assert_eq!(files[0].index_status, Some(ChangeKind::Added));
+
+            undo_restore_impl(backend, &trash, &index.id).expect("Failed to undo");
+            let files = undo_restore_impl(backend, &trash, &worktree.id).expect("Failed to undo");
+
+            assert!(files.is_empty(), "[{}] {:?}", backend.kind(), files);
+            assert!(!root.join("icons/old.png").exists());
+        }
+    }
+
+    #[test]
+    fn test_restore_file_rejects_bad_arguments() {
+        for (_temp_repo, backend) in each_backend_with_repo(commit_logo_versions) {
+            let backend = backend.as_ref();
+            let temp_trash = TempDir::new().unwrap();
+            let trash = Trash::new(temp_trash.path().to_path_buf());
+            let restore = |path: &str, rev: &str| {
+                restore_file_impl(backend, &trash, path, rev, RestoreTarget::Worktree).map(|_| ())
+            };
+
+            assert_eq!(
+                restore("hero.png", "HEAD"),
+                Err(VisiGitError::PathNotInRevision {
+                    path: "hero.png".to_string(),
+                    rev: "HEAD".to_string(),
+                }),
+                "[{}]",
+                backend.kind()
+            );
+            assert!(matches!(
+                restore("../logo.png", "HEAD"),
+                Err(VisiGitError::PathEscapesRepo { .. })
+            ));
+            assert!(matches!(
+                restore("logo.png", "--output=x"),
+                Err(VisiGitError::InvalidRevision { .. })
+            ));
+            assert_eq!(
+                fs::read_dir(temp_trash.path()).unwrap().count(),
+                0,
+                "[{}] Nothing should be backed up",
+                backend.kind()
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for conflict stages and resolve_conflict_impl
+    // ============================================
+
+    #[test]
+    fn test_conflicted_files_list_stage_blobs_that_can_be_read() {
+        for (_temp_repo, backend) in each_backend_with_repo(start_conflicted_merge) {
+            let backend = backend.as_ref();
+
+            let files = get_changed_files_impl(backend).unwrap();
+
+            assert_eq!(
+                files[0].conflict,
+                Some(ConflictBlobs {
+                    base: Some(sha1_blob_id(b"base").unwrap()),
+                    ours: Some(sha1_blob_id(b"ours").unwrap()),
+                    theirs: Some(sha1_blob_id(b"theirs").unwrap()),
+                }),
+                "[{}]",
+                backend.kind()
+            );
+            for (stage, content) in [
+                (ConflictStage::Base, b"base".as_slice()),
+                (ConflictStage::Ours, b"ours"),
+                (ConflictStage::Theirs, b"theirs"),
+            ] {
+                assert_eq!(
+                    get_file_bytes_at_stage_impl(backend, "hero.png", stage),
+                    Ok(content.to_vec()),
+                    "[{}] {}",
+                    backend.kind(),
+                    stage
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_conflict_writes_and_stages_the_chosen_side() {
+        for (resolution, content) in [
+            (ConflictResolution::Base, b"base".as_slice()),
+            (ConflictResolution::Ours, b"ours"),
+            (ConflictResolution::Theirs, b"theirs"),
+        ] {
+            for (temp_repo, backend) in each_backend_with_repo(start_conflicted_merge) {
+                let backend = backend.as_ref();
+
+                let files = resolve_conflict_impl(backend, "hero.png", resolution).unwrap();
+
+                assert!(
+                    files.iter().all(|file| file.conflict.is_none()),
+                    "[{}] {:?} should resolve the conflict",
+                    backend.kind(),
+                    resolution
+                );
+                assert_eq!(
+                    fs::read(temp_repo.path().join("hero.png")).unwrap(),
+                    content
+                );
+                assert_eq!(
+                    get_file_bytes_at_index_impl(backend, "hero.png"),
+                    Ok(content.to_vec()),
+                    "[{}] {:?}",
+                    backend.kind(),
+                    resolution
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_conflict_keeps_an_executable_mode() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            run_git(root, &["config", "core.fileMode", "false"]);
+            commit_file(root, "hero.png", b"start", "Start");
+            run_git(root, &["update-index", "--chmod=+x", "hero.png"]);
+            run_git(root, &["commit", "-q", "-m", "Make executable"]);
+            start_conflicted_merge(root);
+        }) {
+            let backend = backend.as_ref();
+
+            resolve_conflict_impl(backend, "hero.png", ConflictResolution::Theirs).unwrap();
+
+            assert_eq!(
+                index_mode(temp_repo.path(), "hero.png"),
+                "100755",
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_conflict_stages_the_worktree_file() {
+        for (temp_repo, backend) in each_backend_with_repo(start_conflicted_merge) {
+            let backend = backend.as_ref();
+            fs::write(temp_repo.path().join("hero.png"), b"painted over").unwrap();
+
+            let files =
+                resolve_conflict_impl(backend, "hero.png", ConflictResolution::WorktreeFile)
+                    .unwrap();
+
+            assert_eq!(
+                files[0].status,
+                ChangeKind::Modified,
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(
+                get_file_bytes_at_index_impl(backend, "hero.png"),
+                Ok(b"painted over".to_vec()),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_conflict_with_a_deleted_side_deletes_the_file() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            commit_file(root, "hero.png", b"base", "Base");
+            run_git(root, &["branch", "other"]);
+            commit_file(root, "hero.png", b"ours", "Ours");
+            run_git(root, &["checkout", "-q", "other"]);
+            run_git(root, &["rm", "-q", "hero.png"]);
+            run_git(root, &["commit", "-q", "-m", "Delete"]);
+            run_git(root, &["checkout", "-q", "-"]);
+            // Fails with a modify/delete conflict
+            let _ = Command::new("git")
+                .args(["merge", "other"])
+                .current_dir(root)
+                .output();
+        }) {
+            let backend = backend.as_ref();
+            assert_eq!(
+                get_file_bytes_at_stage_impl(backend, "hero.png", ConflictStage::Theirs),
+                Err(VisiGitError::PathNotInStage {
+                    path: "hero.png".to_string(),
+                    stage: ConflictStage::Theirs,
+                }),
+                "[{}]",
+                backend.kind()
+            );
+
+            let files =
+                resolve_conflict_impl(backend, "hero.png", ConflictResolution::Theirs).unwrap();
+
+            assert_eq!(files[0].status, ChangeKind::Deleted, "[{}]", backend.kind());
+            assert!(files[0].staged);
+            assert!(!temp_repo.path().join("hero.png").exists());
+        }
+    }
+
+    #[test]
+    fn test_resolve_conflict_needs_a_conflicted_file() {
+        for (_temp_repo, backend) in each_backend_with_repo(commit_logo_versions) {
+            let backend = backend.as_ref();
+
+            assert_eq!(
+                resolve_conflict_impl(backend, "logo.png", ConflictResolution::Ours),
+                Err(VisiGitError::NotConflicted {
+                    path: "logo.png".to_string()
+                }),
+                "[{}]",
+                backend.kind()
+            );
+            assert!(matches!(
+                resolve_conflict_impl(backend, "../logo.png", ConflictResolution::Ours),
+                Err(VisiGitError::PathEscapesRepo { .. })
+            ));
+        }
+    }
+
+    // ============================================
+    // Tests for is_image_file helper
+    // ============================================
+
+    #[test]
+    fn test_is_image_file_recognizes_common_formats() {
+        assert!(is_image_file("photo.png"));
+        assert!(is_image_file("photo.PNG"));
+        assert!(is_image_file("photo.jpg"));
+        assert!(is_image_file("photo.jpeg"));
+        assert!(is_image_file("icon.svg"));
+        assert!(is_image_file("animation.gif"));
+        assert!(is_image_file("modern.webp"));
+    }
+
+    #[test]
+    fn test_is_image_file_rejects_non_images() {
+        assert!(!is_image_file("code.rs"));
+        assert!(!is_image_file("style.css"));
+        assert!(!is_image_file("script.js"));
+        assert!(!is_image_file("README.md"));
+        assert!(!is_image_file("data.json"));
+    }
+
+    // ============================================
+    // Tests for get_commits_impl
+    // ============================================
+
+    #[test]
+    fn test_get_commits_returns_commit_history() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        // Create first commit
+        fs::write(path.join("file1.txt"), "content 1").expect("Failed to write file1");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add files");
+        Command::new("git")
+            .args(["commit", "-m", "First commit"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to create first commit");
+
+        // Create second commit
+        fs::write(path.join("file2.txt"), "content 2").expect("Failed to write file2");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add files");
+        Command::new("git")
+            .args(["commit", "-m", "Second commit"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to create second commit");
+
+        let mut results = Vec::new();
+
+        for backend in all_backends(path) {
+            let result = get_commits_impl(backend.as_ref(), 10, None);
+
+            assert!(
+                result.is_ok(),
+                "[{}] Expected Ok result, got {:?}",
+                backend.kind(),
+                result
+            );
+            let commits = result.unwrap();
+            assert_eq!(commits.len(), 2, "[{}] Expected 2 commits", backend.kind());
+
+            // Most recent commit should be first
+            assert_eq!(commits[0].message, "Second commit");
+            assert_eq!(commits[1].message, "First commit");
+
+            // Verify commit info structure
+            assert!(!commits[0].hash.is_empty(), "Hash should not be empty");
+            assert!(
+                !commits[0].short_hash.is_empty(),
+                "Short hash should not be empty"
+            );
+            assert!(!commits[0].author.is_empty(), "Author should not be empty");
+            assert!(!commits[0].date.is_empty(), "Date should not be empty");
+
+            results.push(commits);
+        }
+
+        // Both backends should describe the history identically
+        assert_eq!(
+            results[0], results[1],
+            "Backends disagree on commit history"
+        );
+    }
+
+    #[test]
+    fn test_get_commits_respects_limit() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        for i in 0..3 {
+            fs::write(path.join("file.txt"), format!("content {}", i))
+                .expect("Failed to write file");
+            Command::new("git")
+                .args(["add", "."])
+                .current_dir(path)
+                .output()
+                .expect("Failed to add files");
+            Command::new("git")
+                .args(["commit", "-m", &format!("Commit {}", i)])
+                .current_dir(path)
+                .output()
+                .expect("Failed to commit");
+        }
+
+        for backend in all_backends(path) {
+            let commits =
+                get_commits_impl(backend.as_ref(), 2, None).expect("Failed to get commits");
+
+            assert_eq!(
+                commits.len(),
+                2,
+                "[{}] Expected limit to be applied",
+                backend.kind()
+            );
+            assert_eq!(commits[0].message, "Commit 2");
+        }
+    }
+
+    #[test]
+    fn test_get_commits_returns_revision_not_found_for_empty_repo() {
+        let temp_repo = create_test_git_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let result = get_commits_impl(backend.as_ref(), 10, None);
+
+            assert_eq!(
+                result,
+                Err(VisiGitError::RevisionNotFound {
+                    rev: "HEAD".to_string()
+                }),
+                "[{}] Expected RevisionNotFound for unborn HEAD",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_commits_describes_message_parents_and_people() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "a.txt", b"a", "Base");
+        let base = rev_parse(root, "HEAD");
+        run_git(root, &["checkout", "-q", "-b", "side"]);
+        commit_file(root, "b.txt", b"b", "Side");
+        let side = rev_parse(root, "HEAD");
+        run_git(root, &["checkout", "-q", "-"]);
+        commit_file(root, "c.txt", b"c", "Main");
+        let main = rev_parse(root, "HEAD");
+
+        // Kept verbatim: leading blank lines, a two-line subject, pipes,
+        // separators git output could be split on, and trailers
+        let message = "\n\nMerge side | keep\nboth sides 🎨\n\n\
+                       Body with \x1e and \x1f inside.\n\n\
+                       Co-authored-by: Ann Example <ann@example.com>\n\
+                       co-authored-by: Bo  Bee <bo@example.com>\n";
+        fs::write(root.join("MSG"), message).unwrap();
+        let output = Command::new("git")
+            .args([
+                "merge",
+                "-q",
+                "--no-ff",
+                "--cleanup=verbatim",
+                "-F",
+                "MSG",
+                "side",
+            ])
+            .current_dir(root)
+            .env("GIT_AUTHOR_NAME", "Pat | Doe")
+            .env("GIT_AUTHOR_EMAIL", "pat@example.com")
+            .env("GIT_AUTHOR_DATE", "@1700000000 +0530")
+            .env("GIT_COMMITTER_NAME", "Robo Committer")
+            .env("GIT_COMMITTER_EMAIL", "robo@example.com")
+            .output()
+            .expect("Failed to merge");
+        assert!(output.status.success(), "{:?}", output);
+
+        let mut results = Vec::new();
+
+        for backend in all_backends(root) {
+            let commits =
+                get_commits_impl(backend.as_ref(), 10, None).expect("Failed to get commits");
+            let merge = &commits[0];
+
+            assert_eq!(merge.message, "Merge side | keep both sides 🎨");
+            assert_eq!(
+                merge.body,
+                "Body with \x1e and \x1f inside.\n\n\
+                 Co-authored-by: Ann Example <ann@example.com>\n\
+                 co-authored-by: Bo  Bee <bo@example.com>"
+            );
+            assert_eq!(merge.parents, vec![main.clone(), side.clone()]);
+            assert_eq!(merge.author, "Pat | Doe");
+            assert_eq!(merge.author_email, "pat@example.com");
+            assert_eq!(merge.date, "2023-11-15 03:43:20 +0530");
+            assert_eq!(merge.timestamp, 1_700_000_000);
+            assert_eq!(merge.timezone_offset, 330);
+            assert_eq!(merge.committer, "Robo Committer");
+            assert_eq!(merge.committer_email, "robo@example.com");
+            assert_eq!(
+                merge.co_authors,
+                vec![
+                    CoAuthor {
+                        name: "Ann Example".to_string(),
+                        email: "ann@example.com".to_string(),
+                    },
+                    CoAuthor {
+                        name: "Bo  Bee".to_string(),
+                        email: "bo@example.com".to_string(),
+                    },
+                ],
+                "[{}]",
+                backend.kind()
+            );
+
+            let root_commit = commits.iter().find(|c| c.hash == base).unwrap();
+            assert!(root_commit.parents.is_empty());
+            assert!(root_commit.body.is_empty());
+
+            results.push(merge.clone());
+        }
+
+        assert_eq!(
+            results[0], results[1],
+            "Backends disagree on commit details"
+        );
+    }
+
+    #[test]
+    fn test_get_commits_lists_refs_pointing_at_each_commit() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        run_git(root, &["checkout", "-q", "-b", "trunk"]);
+        commit_file(root, "a.txt", b"a", "First");
+        run_git(root, &["tag", "v1"]);
+        run_git(root, &["tag", "-a", "-m", "Annotated", "v1-notes"]);
+        run_git(root, &["update-ref", "refs/remotes/origin/main", "HEAD"]);
+        commit_file(root, "b.txt", b"b", "Second");
+        run_git(root, &["branch", "keep"]);
+        // A ref outside branches, remotes and tags is not listed
+        run_git(root, &["update-ref", "refs/notes/commits", "HEAD"]);
+
+        let mut results = Vec::new();
+
+        for backend in all_backends(root) {
+            let commits =
+                get_commits_impl(backend.as_ref(), 10, None).expect("Failed to get commits");
+
+            assert_eq!(
+                commits[0].refs,
+                vec![
+                    "HEAD".to_string(),
+                    "refs/heads/keep".to_string(),
+                    "refs/heads/trunk".to_string(),
+                ],
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(
+                commits[1].refs,
+                vec![
+                    "refs/remotes/origin/main".to_string(),
+                    "refs/tags/v1".to_string(),
+                    "refs/tags/v1-notes".to_string(),
+                ],
+                "[{}]",
+                backend.kind()
+            );
+
+            results.push(commits);
+        }
+
+        // A detached HEAD is listed on its own
+        run_git(root, &["checkout", "-q", "--detach", "HEAD~1"]);
+        for backend in all_backends(root) {
+            let commits =
+                get_commits_impl(backend.as_ref(), 10, None).expect("Failed to get commits");
+
+            assert_eq!(commits[0].refs[0], "HEAD", "[{}]", backend.kind());
+        }
+
+        assert_eq!(results[0], results[1], "Backends disagree on refs");
+    }
+
+    // ============================================
+    // Tests for list_refs_impl and ref names as revisions
+    // ============================================
+
+    /// Helper to build a repository with every kind of ref:
+    ///
+    /// ```text
+    /// T0+200  C  origin/trunk (upstream of trunk, diverged)
+    /// T0+100  B  trunk (HEAD), tag v2 (annotated)
+    /// T0      A  feature/new-icons (upstream gone), tag v1
+    /// ```
+    ///
+    /// plus a symbolic `origin/HEAD` and a tag on a blob, which are not
+    /// listed. Returns the ids of A, B and C.
+    fn create_refs_repo() -> (TempDir, [String; 3]) {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        run_git(root, &["checkout", "-q", "-b", "trunk"]);
+        fs::write(root.join("logo.png"), b"logo v1").unwrap();
+        git_at(root, &["add", "-A"], "Alice", T0);
+        git_at(root, &["commit", "-q", "-m", "A"], "Alice", T0);
+        let a = rev_parse(root, "HEAD");
+
+        run_git(root, &["checkout", "-q", "-b", "upstream"]);
+        fs::write(root.join("logo.png"), b"logo upstream").unwrap();
+        git_at(root, &["commit", "-q", "-am", "C"], "Bob", T0 + 200);
+        let c = rev_parse(root, "HEAD");
+        run_git(root, &["checkout", "-q", "trunk"]);
+        run_git(root, &["branch", "-q", "-D", "upstream"]);
+
+        fs::write(root.join("logo.png"), b"logo v2").unwrap();
+        git_at(root, &["commit", "-q", "-am", "B"], "Alice", T0 + 100);
+        let b = rev_parse(root, "HEAD");
+
+        run_git(
+            root,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://example.invalid/repo.git",
+            ],
+        );
+        run_git(root, &["update-ref", "refs/remotes/origin/trunk", &c]);
+        run_git(
+            root,
+            &[
+                "symbolic-ref",
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/trunk",
+            ],
+        );
+        run_git(root, &["config", "branch.trunk.remote", "origin"]);
+        run_git(root, &["config", "branch.trunk.merge", "refs/heads/trunk"]);
+
+        run_git(root, &["branch", "feature/new-icons", &a]);
+        run_git(
+            root,
+            &["config", "branch.feature/new-icons.remote", "origin"],
+        );
+        run_git(
+            root,
+            &[
+                "config",
+                "branch.feature/new-icons.merge",
+                "refs/heads/gone",
+            ],
+        );
+
+        run_git(root, &["tag", "v1", &a]);
+        git_at(
+            root,
+            &["tag", "-a", "-m", "Second", "v2", &b],
+            "Alice",
+            T0 + 300,
+        );
+        run_git(root, &["tag", "blob-tag", "HEAD:logo.png"]);
+
+        (temp_repo, [a, b, c])
+    }
+
+    #[test]
+    fn test_list_refs_describes_branches_remotes_and_tags() {
+        let (temp_repo, [a, b, c]) = create_refs_repo();
+        // Committer dates of A, B and C
+        let date = |target: &String| match target {
+            t if *t == a => ("2023-11-14 22:13:20 +0000", T0),
+            t if *t == b => ("2023-11-14 22:15:00 +0000", T0 + 100),
+            _ => ("2023-11-14 22:16:40 +0000", T0 + 200),
+        };
+        let info = |name: &str, kind, target: &String| RefInfo {
+            name: name.to_string(),
+            short_name: RefKind::of(name).unwrap().1.to_string(),
+            kind,
+            target: target.clone(),
+            upstream: None,
+            ahead: None,
+            behind: None,
+            date: date(target).0.to_string(),
+            timestamp: date(target).1,
+            is_head: false,
+        };
+
+        let expected = vec![
+            RefInfo {
+                upstream: Some("refs/remotes/origin/gone".to_string()),
+                ..info("refs/heads/feature/new-icons", RefKind::Branch, &a)
+            },
+            RefInfo {
+                upstream: Some("refs/remotes/origin/trunk".to_string()),
+                ahead: Some(1),
+                behind: Some(1),
+                is_head: true,
+                ..info("refs/heads/trunk", RefKind::Branch, &b)
+            },
+            info("refs/remotes/origin/trunk", RefKind::RemoteBranch, &c),
+            info("refs/tags/v1", RefKind::Tag, &a),
+            info("refs/tags/v2", RefKind::Tag, &b),
+        ];
+
+        for backend in all_backends(temp_repo.path()) {
+            let refs = list_refs_impl(backend.as_ref()).expect("Failed to list refs");
+
+            assert_eq!(refs, expected, "[{}]", backend.kind());
+        }
+    }
+
+    #[test]
+    fn test_list_refs_is_empty_for_new_repo() {
+        let temp_repo = create_test_git_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            assert_eq!(list_refs_impl(backend.as_ref()), Ok(Vec::new()));
+        }
+    }
+
+    #[test]
+    fn test_ref_names_work_as_revisions() {
+        let (temp_repo, [a, b, _]) = create_refs_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let backend = backend.as_ref();
+
+            let commits = get_commits_impl(backend, 10, Some("feature/new-icons")).unwrap();
+            assert_eq!(commits.len(), 1, "[{}]", backend.kind());
+            assert_eq!(commits[0].hash, a);
+            let commits = get_commits_impl(backend, 10, Some("refs/tags/v2")).unwrap();
+            assert_eq!(commits[0].hash, b);
+
+            assert_eq!(
+                get_file_bytes_impl(backend, "logo.png", "feature/new-icons").unwrap(),
+                b"logo v1"
+            );
+            assert_eq!(
+                get_file_bytes_impl(backend, "logo.png", "origin/trunk").unwrap(),
+                b"logo upstream"
+            );
+            assert_eq!(
+                get_file_at_commit_impl(backend, "logo.png", "v2").unwrap(),
+                STANDARD.encode(b"logo v2")
+            );
+            assert_eq!(
+                get_commits_impl(backend, 10, Some("feature/missing")),
+                Err(VisiGitError::RevisionNotFound {
+                    rev: "feature/missing".to_string()
+                }),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for get_history_impl
+    // ============================================
+
+    const T0: i64 = 1_700_000_000;
+
+    /// Helper to run git in `dir` as `author`, at Unix time `time`
+    fn git_at(dir: &Path, args: &[&str], author: &str, time: i64) {
+        let date = format!("@{} +0000", time);
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_NAME", author)
+            .env(
+                "GIT_AUTHOR_EMAIL",
+                format!("{}@example.com", author.to_lowercase()),
+            )
+            .env("GIT_AUTHOR_DATE", &date)
+            .env("GIT_COMMITTER_DATE", &date)
+            .output()
+            .expect("Failed to run git");
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    /// Helper to build this history, one commit per line:
+    ///
+    /// ```text
+    /// T0+500  Carol  Merge feature         (designs)
+    /// T0+400  Bob    Add guide             docs/guide.md (feature)
+    /// T0+300  Alice  Recolor LOGO          assets/logo.png
+    /// T0+200  Bob    Add star icon         assets/icons/star.svg (side, unmerged)
+    /// T0+100  Bob    Update readme | docs  README.md
+    /// T0      Alice  Add logo              assets/logo.png, README.md
+    /// ```
+    fn create_history_repo() -> TempDir {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        let commit = |path: &str, content: &[u8], message: &str, author: &str, time: i64| {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), content).unwrap();
+            run_git(root, &["add", "-A"]);
+            git_at(root, &["commit", "-m", message], author, time);
+        };
+
+        fs::write(root.join("README.md"), b"readme").unwrap();
+        commit("assets/logo.png", b"logo v1", "Add logo", "Alice", T0);
+        run_git(root, &["branch", "-M", "designs"]);
+        commit(
+            "README.md",
+            b"readme v2",
+            "Update readme | docs",
+            "Bob",
+            T0 + 100,
+        );
+        run_git(root, &["checkout", "-b", "side"]);
+        commit(
+            "assets/icons/star.svg",
+            b"<svg/>",
+            "Add star icon",
+            "Bob",
+            T0 + 200,
+        );
+        run_git(root, &["checkout", "designs"]);
+        commit(
+            "assets/logo.png",
+            b"logo v2",
+            "Recolor LOGO\n\nNew palette from the brand team",
+            "Alice",
+            T0 + 300,
+        );
+        run_git(root, &["checkout", "-b", "feature"]);
+        commit("docs/guide.md", b"guide", "Add guide", "Bob", T0 + 400);
+        run_git(root, &["checkout", "designs"]);
+        git_at(
+            root,
+            &["merge", "--no-ff", "-m", "Merge feature", "feature"],
+            "Carol",
+            T0 + 500,
+        );
+
+        temp_repo
+    }
+
+    /// Helper to run a query and list the subjects on the returned page
+    fn history_subjects(backend: &dyn GitBackend, query: &HistoryQuery) -> Vec<String> {
+        get_history_impl(backend, query)
+            .expect("Failed to read history")
+            .items
+            .into_iter()
+            .map(|commit| commit.message)
+            .collect()
+    }
+
+    fn filtered(filter: HistoryFilter) -> HistoryQuery {
+        HistoryQuery {
+            filter,
+            ..HistoryQuery::default()
+        }
+    }
+
+    #[test]
+    fn test_get_history_defaults_to_current_branch() {
+        let temp_repo = create_history_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let page = get_history_impl(backend.as_ref(), &HistoryQuery::default()).unwrap();
+            let subjects: Vec<&str> = page.items.iter().map(|c| c.message.as_str()).collect();
+
+            assert_eq!(
+                subjects,
+                vec![
+                    "Merge feature",
+                    "Add guide",
+                    "Recolor LOGO",
+                    "Update readme | docs",
+                    "Add logo"
+                ],
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(page.items[3].author, "Bob", "[{}]", backend.kind());
+            assert_eq!(page.next_cursor, None, "[{}]", backend.kind());
+        }
+    }
+
+    #[test]
+    fn test_get_history_pages_with_cursor() {
+        let temp_repo = create_history_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let kind = backend.kind();
+            let mut query = HistoryQuery {
+                limit: 2,
+                ..HistoryQuery::default()
+            };
+            let mut pages = Vec::new();
+
+            loop {
+                let page = get_history_impl(backend.as_ref(), &query).unwrap();
+                pages.push(
+                    page.items
+                        .into_iter()
+                        .map(|c| c.message)
+                        .collect::<Vec<_>>(),
+                );
+                match page.next_cursor {
+                    Some(cursor) => query.cursor = Some(cursor),
+                    None => break,
+                }
+            }
+
+            assert_eq!(
+                pages,
+                vec![
+                    vec!["Merge feature", "Add guide"],
+                    vec!["Recolor LOGO", "Update readme | docs"],
+                    vec!["Add logo"],
+                ],
+                "[{}]",
+                kind
+            );
+
+            query.cursor = Some("-1".to_string());
+            assert_eq!(
+                get_history_impl(backend.as_ref(), &query),
+                Err(VisiGitError::InvalidCursor {
+                    cursor: "-1".to_string()
+                }),
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_history_walks_from_start_or_all_branches() {
+        let temp_repo = create_history_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let kind = backend.kind();
+
+            let side = filtered(HistoryFilter {
+                start: Some("side".to_string()),
+                ..HistoryFilter::default()
+            });
+            assert_eq!(
+                history_subjects(backend.as_ref(), &side),
+                vec!["Add star icon", "Update readme | docs", "Add logo"],
+                "[{}]",
+                kind
+            );
+
+            let all = filtered(HistoryFilter {
+                all_branches: true,
+                ..HistoryFilter::default()
+            });
+            assert_eq!(
+                history_subjects(backend.as_ref(), &all),
+                vec![
+                    "Merge feature",
+                    "Add guide",
+                    "Recolor LOGO",
+                    "Add star icon",
+                    "Update readme | docs",
+                    "Add logo"
+                ],
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_history_filters_by_author_message_and_date() {
+        let temp_repo = create_history_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let kind = backend.kind();
+            let subjects =
+                |filter: HistoryFilter| history_subjects(backend.as_ref(), &filtered(filter));
+
+            assert_eq!(
+                subjects(HistoryFilter {
+                    author: Some("ALICE".to_string()),
+                    ..HistoryFilter::default()
+                }),
+                vec!["Recolor LOGO", "Add logo"],
+                "[{}] author name",
+                kind
+            );
+            assert_eq!(
+                subjects(HistoryFilter {
+                    author: Some("carol@example".to_string()),
+                    ..HistoryFilter::default()
+                }),
+                vec!["Merge feature"],
+                "[{}] author email",
+                kind
+            );
+            assert_eq!(
+                subjects(HistoryFilter {
+                    grep: Some("logo".to_string()),
+                    ..HistoryFilter::default()
+                }),
+                vec!["Recolor LOGO", "Add logo"],
+                "[{}] grep",
+                kind
+            );
+            assert_eq!(
+                subjects(HistoryFilter {
+                    grep: Some("palette".to_string()),
+                    ..HistoryFilter::default()
+                }),
+                vec!["Recolor LOGO"],
+                "[{}] grep in body",
+                kind
+            );
+            assert_eq!(
+                subjects(HistoryFilter {
+                    grep: Some("[docs".to_string()),
+                    ..HistoryFilter::default()
+                }),
+                Vec::<String>::new(),
+                "[{}] grep is not a regex",
+                kind
+            );
+            assert_eq!(
+                subjects(HistoryFilter {
+                    author: Some("bob".to_string()),
+                    grep: Some("| docs".to_string()),
+                    ..HistoryFilter::default()
+                }),
+                vec!["Update readme | docs"],
+                "[{}] author and grep",
+                kind
+            );
+            assert_eq!(
+                subjects(HistoryFilter {
+                    since: Some(T0 + 100),
+                    until: Some(T0 + 300),
+                    ..HistoryFilter::default()
+                }),
+                vec!["Recolor LOGO", "Update readme | docs"],
+                "[{}] date range",
+                kind
+            );
+            // Newer commits are skipped, not the end of the walk
+            assert_eq!(
+                subjects(HistoryFilter {
+                    until: Some(T0 + 150),
+                    ..HistoryFilter::default()
+                }),
+                vec!["Update readme | docs", "Add logo"],
+                "[{}] until",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_history_filters_by_paths_and_images() {
+        let temp_repo = create_history_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let kind = backend.kind();
+            let subjects = |paths: &[&str], images_only: bool, all_branches: bool| {
+                history_subjects(
+                    backend.as_ref(),
+                    &filtered(HistoryFilter {
+                        paths: paths.iter().map(|p| p.to_string()).collect(),
+                        images_only,
+                        all_branches,
+                        ..HistoryFilter::default()
+                    }),
+                )
+            };
+
+            assert_eq!(
+                subjects(&["assets"], false, false),
+                vec!["Recolor LOGO", "Add logo"],
+                "[{}] folder",
+                kind
+            );
+            assert_eq!(
+                subjects(&["README.md"], false, false),
+                vec!["Update readme | docs", "Add logo"],
+                "[{}] file",
+                kind
+            );
+            // The merge differs from its first parent under docs/
+            assert_eq!(
+                subjects(&["docs"], false, false),
+                vec!["Merge feature", "Add guide"],
+                "[{}] merge",
+                kind
+            );
+            assert_eq!(
+                subjects(&["assets", "README.md"], false, true),
+                vec![
+                    "Recolor LOGO",
+                    "Add star icon",
+                    "Update readme | docs",
+                    "Add logo"
+                ],
+                "[{}] several paths",
+                kind
+            );
+            assert_eq!(
+                subjects(&[], true, true),
+                vec!["Recolor LOGO", "Add star icon", "Add logo"],
+                "[{}] images",
+                kind
+            );
+            assert_eq!(
+                subjects(&["assets/icons"], true, true),
+                vec!["Add star icon"],
+                "[{}] images in folder",
+                kind
+            );
+            assert_eq!(
+                subjects(&["assets/logo.png"], true, false),
+                vec!["Recolor LOGO", "Add logo"],
+                "[{}] image path",
+                kind
+            );
+            assert_eq!(
+                subjects(&["README.md"], true, false),
+                Vec::<String>::new(),
+                "[{}] non-image path",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_history_rejects_bad_arguments() {
+        let temp_repo = create_history_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let kind = backend.kind();
+
+            let missing = filtered(HistoryFilter {
+                start: Some("missing".to_string()),
+                ..HistoryFilter::default()
+            });
+            assert_eq!(
+                get_history_impl(backend.as_ref(), &missing),
+                Err(VisiGitError::RevisionNotFound {
+                    rev: "missing".to_string()
+                }),
+                "[{}]",
+                kind
+            );
+
+            let escaping = filtered(HistoryFilter {
+                paths: vec!["../outside".to_string()],
+                ..HistoryFilter::default()
+            });
+            assert_eq!(
+                get_history_impl(backend.as_ref(), &escaping),
+                Err(VisiGitError::PathEscapesRepo {
+                    path: "../outside".to_string()
+                }),
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_history_query_deserializes_from_flat_json() {
+        let query: HistoryQuery = serde_json::from_value(serde_json::json!({
+            "images_only": true,
+            "paths": ["assets"],
+            "cursor": "50",
+        }))
+        .unwrap();
+
+        assert_eq!(query.limit, DEFAULT_HISTORY_LIMIT);
+        assert_eq!(query.cursor.as_deref(), Some("50"));
+        assert!(query.filter.images_only);
+        assert_eq!(query.filter.paths, vec!["assets"]);
+        assert_eq!(query.filter.start, None);
+    }
+
+    // ============================================
+    // Tests for get_file_history_impl
+    // ============================================
+
+    /// Helper to build a history where `img/logo.svg` is added, edited,
+    /// moved to `brand/logo.svg` and edited again, with an unrelated
+    /// commit in between
+    fn create_logo_history() -> TempDir {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        fs::create_dir(root.join("img")).unwrap();
+        commit_file(root, "img/logo.svg", b"<svg>v1</svg>", "Add logo");
+        commit_file(root, "img/logo.svg", b"<svg>v2</svg>", "Tweak logo");
+        commit_file(root, "notes.txt", b"unrelated", "Write notes");
+        fs::create_dir(root.join("brand")).unwrap();
+        run_git(root, &["mv", "img/logo.svg", "brand/logo.svg"]);
+        run_git(root, &["commit", "-m", "Move logo"]);
+        commit_file(root, "brand/logo.svg", b"<svg>v3</svg>", "Recolor logo");
+        temp_repo
+    }
+
+    #[test]
+    fn test_get_file_history_follows_renames() {
+        let temp_repo = create_logo_history();
+        let v2 = Some(sha1_blob_id(b"<svg>v2</svg>").unwrap());
+
+        for backend in all_backends(temp_repo.path()) {
+            let page = get_file_history_impl(backend.as_ref(), "brand/logo.svg", 10, None)
+                .expect("Failed to read history");
+            let kind = backend.kind();
+
+            let versions: Vec<(&str, &str, &ChangeKind)> = page
+                .items
+                .iter()
+                .map(|e| (e.commit.message.as_str(), e.path.as_str(), &e.status))
+                .collect();
+            assert_eq!(
+                versions,
+                vec![
+                    ("Recolor logo", "brand/logo.svg", &ChangeKind::Modified),
+                    (
+                        "Move logo",
+                        "brand/logo.svg",
+                        &ChangeKind::Renamed {
+                            from: "img/logo.svg".to_string()
+                        }
+                    ),
+                    ("Tweak logo", "img/logo.svg", &ChangeKind::Modified),
+                    ("Add logo", "img/logo.svg", &ChangeKind::Added),
+                ],
+                "[{}]",
+                kind
+            );
+            assert_eq!(page.next_cursor, None, "[{}]", kind);
+
+            assert_eq!(
+                page.items[0].blob_id,
+                Some(sha1_blob_id(b"<svg>v3</svg>").unwrap()),
+                "[{}]",
+                kind
+            );
+            assert_eq!(page.items[1].blob_id, v2, "[{}]", kind);
+            assert_eq!(page.items[2].blob_id, v2, "[{}]", kind);
+            assert_eq!(
+                page.items[0].commit.hash,
+                rev_parse(temp_repo.path(), "HEAD"),
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_file_history_pages_with_cursor() {
+        let temp_repo = create_logo_history();
+
+        for backend in all_backends(temp_repo.path()) {
+            let kind = backend.kind();
+            let all = get_file_history_impl(backend.as_ref(), "brand/logo.svg", 10, None).unwrap();
+
+            let first = get_file_history_impl(backend.as_ref(), "brand/logo.svg", 3, None).unwrap();
+            assert_eq!(first.items, all.items[..3], "[{}]", kind);
+            let cursor = first.next_cursor.expect("Expected another page");
+
+            let second =
+                get_file_history_impl(backend.as_ref(), "brand/logo.svg", 3, Some(&cursor))
+                    .unwrap();
+            assert_eq!(second.items, all.items[3..], "[{}]", kind);
+            assert_eq!(second.next_cursor, None, "[{}]", kind);
+
+            // A page that ends exactly at the last version has no next page
+            let exact = get_file_history_impl(backend.as_ref(), "brand/logo.svg", 4, None).unwrap();
+            assert_eq!(exact.items.len(), 4, "[{}]", kind);
+            assert_eq!(exact.next_cursor, None, "[{}]", kind);
+
+            assert_eq!(
+                get_file_history_impl(backend.as_ref(), "brand/logo.svg", 3, Some("page-2")),
+                Err(VisiGitError::InvalidCursor {
+                    cursor: "page-2".to_string()
+                }),
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_file_history_lists_merged_versions_at_the_merge() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_logo(root);
+        run_git(root, &["checkout", "-b", "feature"]);
+        commit_file(root, "logo.png", b"logo from feature", "Feature logo");
+        run_git(root, &["checkout", "designs"]);
+        commit_file(root, "other.png", b"other", "Other work");
+        run_git(
+            root,
+            &["merge", "--no-ff", "-m", "Merge feature", "feature"],
+        );
+        run_git(root, &["rm", "-q", "logo.png"]);
+        run_git(root, &["commit", "-m", "Drop logo"]);
+
+        for backend in all_backends(root) {
+            let page = get_file_history_impl(backend.as_ref(), "logo.png", 10, None).unwrap();
+            let kind = backend.kind();
+
+            let versions: Vec<(&str, &ChangeKind)> = page
+                .items
+                .iter()
+                .map(|e| (e.commit.message.as_str(), &e.status))
+                .collect();
+            assert_eq!(
+                versions,
+                vec![
+                    ("Drop logo", &ChangeKind::Deleted),
+                    ("Merge feature", &ChangeKind::Modified),
+                    ("Add logo", &ChangeKind::Added),
+                ],
+                "[{}]",
+                kind
+            );
+            assert_eq!(page.items[0].blob_id, None, "[{}]", kind);
+            assert_eq!(
+                page.items[1].blob_id,
+                Some(sha1_blob_id(b"logo from feature").unwrap()),
+                "[{}]",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_file_history_of_unknown_path_is_empty() {
+        let temp_repo = create_test_git_repo();
+        commit_logo(temp_repo.path());
+
+        for backend in all_backends(temp_repo.path()) {
+            let page = get_file_history_impl(backend.as_ref(), "missing.png", 10, None).unwrap();
+
+            assert_eq!(page.items, vec![], "[{}]", backend.kind());
+            assert_eq!(page.next_cursor, None, "[{}]", backend.kind());
+        }
+    }
+
+    #[test]
+    fn test_get_file_history_returns_revision_not_found_for_empty_repo() {
+        let temp_repo = create_test_git_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            assert_eq!(
+                get_file_history_impl(backend.as_ref(), "logo.png", 10, None),
+                Err(VisiGitError::RevisionNotFound {
+                    rev: "HEAD".to_string()
+                }),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for get_file_bytes_impl / get_blob_metadata_impl
+    // ============================================
+
+    /// Deterministic binary content covering every byte value, including
+    /// NUL, CR and LF, which text-mode handling would corrupt
+    fn large_binary_content(len: usize) -> Vec<u8> {
+        let mut state: u32 = 0x9E37_79B9;
+        (0..len)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                if i % 7 == 0 {
+                    (i % 256) as u8
+                } else {
+                    state as u8
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_get_file_bytes_round_trips_large_binary_exactly() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        let content = large_binary_content(8 * 1024 * 1024);
+        fs::write(path.join("export.psd"), &content).expect("Failed to write file");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add files");
+        Command::new("git")
+            .args(["commit", "-m", "Add export"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        for backend in all_backends(path) {
+            let bytes = get_file_bytes_impl(backend.as_ref(), "export.psd", "HEAD")
+                .expect("Failed to read bytes");
+            assert_eq!(
+                bytes.len(),
+                content.len(),
+                "[{}] Size mismatch",
+                backend.kind()
+            );
+            assert!(
+                bytes == content,
+                "[{}] Bytes should round trip exactly",
+                backend.kind()
+            );
+
+            // The base64 variant must agree with the binary one
+            let base64_content =
+                get_file_at_head_impl(backend.as_ref(), "export.psd").expect("Failed to read");
+            let decoded = STANDARD
+                .decode(base64_content)
+                .expect("Failed to decode base64");
+            assert!(
+                decoded == content,
+                "[{}] Base64 should round trip",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_blob_metadata_reports_size_id_and_mime() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        let content = b"\x89PNG\r\n\x1a\nnot really a png";
+        fs::write(path.join("logo.png"), content).expect("Failed to write file");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add files");
+        Command::new("git")
+            .args(["commit", "-m", "Add logo"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD:logo.png"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to get blob id");
+        let blob_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        for backend in all_backends(path) {
+            let metadata = get_blob_metadata_impl(backend.as_ref(), "logo.png", "HEAD")
+                .expect("Failed to read metadata");
+
+            assert_eq!(
+                metadata,
+                BlobMetadata {
+                    size: content.len() as u64,
+                    blob_id: blob_id.clone(),
+                    mime: "image/png".to_string(),
+                    lfs: LfsStatus::default(),
+                },
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for image inspection
+    // ============================================
+
+    /// Helper to encode a blank PNG of the given size
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut content = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(width, height)
+            .write_to(&mut content, image::ImageFormat::Png)
+            .unwrap();
+        content.into_inner()
+    }
+
+    fn size(width: u32, height: u32) -> Option<Dimensions> {
+        Some(Dimensions { width, height })
+    }
+
+    #[test]
+    fn test_inspect_image_at_rev_and_in_worktree() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "logo.png", &png(4, 3), "Add logo");
+        commit_file(root, "broken.png", b"not really a png", "Add broken");
+        fs::write(root.join("logo.png"), png(8, 6)).unwrap();
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+
+            let committed = inspect_image_impl(backend, "logo.png", Some("HEAD")).unwrap();
+            assert_eq!((committed.format.as_str(), committed.width), ("png", 4));
+            assert_eq!(committed.frame_count, 1);
+            let working = inspect_image_impl(backend, "logo.png", None).unwrap();
+            assert_eq!((working.width, working.height), (8, 6));
+
+            assert!(matches!(
+                inspect_image_impl(backend, "broken.png", Some("HEAD")),
+                Err(VisiGitError::InvalidImage { ref path, .. }) if path == "broken.png"
+            ));
+            assert!(matches!(
+                inspect_image_impl(backend, "gone.png", Some("HEAD")),
+                Err(VisiGitError::PathNotInRevision { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_compute_image_diff_between_versions() {
+        // Black images with some white pixels in the top row
+        let spotted = |width: u32, spots: u32| {
+            let mut image = image::RgbImage::new(width, 3);
+            for x in 0..spots {
+                image.put_pixel(x, 0, image::Rgb([255, 255, 255]));
+            }
+            let mut content = std::io::Cursor::new(Vec::new());
+            image
+                .write_to(&mut content, image::ImageFormat::Png)
+                .unwrap();
+            content.into_inner()
+        };
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "logo.png", &spotted(4, 0), "Add logo");
+        commit_file(root, "notes.txt", b"not an image", "Add notes");
+        fs::write(root.join("logo.png"), spotted(4, 1)).unwrap();
+        run_git(root, &["add", "logo.png"]);
+        fs::write(root.join("logo.png"), spotted(6, 3)).unwrap();
+
+        let head = FileVersion::Revision {
+            rev: "HEAD".to_string(),
+            path: "logo.png".to_string(),
+        };
+        let index = FileVersion::Index {
+            path: "logo.png".to_string(),
+        };
+        let worktree = FileVersion::Worktree {
+            path: "logo.png".to_string(),
+        };
+
+        let average = DiffMethod::Average { threshold: 10.0 };
+        let options = RegionOptions {
+            min_pixels: 1,
+            merge_distance: 0,
+        };
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+            let diffs = DiffImages::default();
+
+            let diff = compute_image_diff_impl(backend, &diffs, &head, &worktree, average, options)
+                .unwrap();
+            assert_eq!((diff.width, diff.height), (4, 3));
+            assert_eq!((diff.changed_pixels, diff.total_pixels), (3, 12));
+            assert_eq!(
+                diff.regions,
+                [Region {
+                    x: 0,
+                    y: 0,
+                    width: 3,
+                    height: 1,
+                    pixels: 3,
+                    mean_delta: 255.0,
+                }]
+            );
+            let png = diffs
+                .get(&diff.id)
+                .expect("Expected the diff PNG to be kept");
+            assert_eq!(imaging::dimensions(&png), size(4, 3));
+
+            let perceptual = DiffMethod::Perceptual { threshold: 0.1 };
+            let diff =
+                compute_image_diff_impl(backend, &diffs, &head, &worktree, perceptual, options)
+                    .unwrap();
+            assert_eq!((diff.changed_pixels, diff.antialiased_pixels), (3, 0));
+
+            let diff =
+                compute_image_diff_impl(backend, &diffs, &head, &index, average, options).unwrap();
+            assert_eq!(diff.changed_pixels, 1);
+            let diff =
+                compute_image_diff_impl(backend, &diffs, &index, &worktree, average, options)
+                    .unwrap();
+            assert_eq!(diff.changed_pixels, 2);
+
+            let notes = FileVersion::Revision {
+                rev: "HEAD".to_string(),
+                path: "notes.txt".to_string(),
+            };
+            assert!(matches!(
+                compute_image_diff_impl(backend, &diffs, &head, &notes, average, options),
+                Err(VisiGitError::InvalidImage { ref path, .. }) if path == "notes.txt"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_add_dimensions_measures_head_and_worktree() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "resized.png", &png(2, 2), "Add resized");
+        commit_file(root, "deleted.png", &png(3, 3), "Add deleted");
+        commit_file(root, "old.png", &png(5, 5), "Add old");
+        fs::write(root.join("resized.png"), png(4, 4)).unwrap();
+        fs::remove_file(root.join("deleted.png")).unwrap();
+        run_git(root, &["mv", "old.png", "new.png"]);
+        fs::write(root.join("untracked.png"), png(6, 1)).unwrap();
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+            let mut files = get_changed_files_impl(backend).unwrap();
+
+            add_dimensions_impl(backend, &mut files).unwrap();
+
+            let dimensions: HashMap<_, _> = files
+                .iter()
+                .map(|file| (file.path.as_str(), file.dimensions.unwrap()))
+                .collect();
+            let expected = HashMap::from([
+                (
+                    "resized.png",
+                    ImageDimensions {
+                        before: size(2, 2),
+                        after: size(4, 4),
+                    },
+                ),
+                (
+                    "deleted.png",
+                    ImageDimensions {
+                        before: size(3, 3),
+                        after: None,
+                    },
+                ),
+                (
+                    "new.png",
+                    ImageDimensions {
+                        before: size(5, 5),
+                        after: size(5, 5),
+                    },
+                ),
+                (
+                    "untracked.png",
+                    ImageDimensions {
+                        before: None,
+                        after: size(6, 1),
+                    },
+                ),
+            ]);
+            assert_eq!(dimensions, expected, "[{}]", backend.kind());
+        }
+    }
+
+    #[test]
+    fn test_add_dimensions_on_an_unborn_branch() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        fs::write(root.join("first.png"), png(1, 2)).unwrap();
+        run_git(root, &["add", "first.png"]);
+
+        for backend in all_backends(root) {
+            let mut files = get_changed_files_impl(backend.as_ref()).unwrap();
+
+            add_dimensions_impl(backend.as_ref(), &mut files).unwrap();
+
+            assert_eq!(
+                files[0].dimensions,
+                Some(ImageDimensions {
+                    before: None,
+                    after: size(1, 2),
+                }),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_blob_by_id_reads_only_blobs() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "logo.png", b"logo content", "Add logo");
+        let commit = rev_parse(root, "HEAD");
+        let id = sha1_blob_id(b"logo content").unwrap();
+
+        for backend in all_backends(root) {
+            let blob = backend.read_blob_by_id(&id).unwrap();
+            assert_eq!(blob.content, b"logo content", "[{}]", backend.kind());
+            assert_eq!(blob.id, id, "[{}]", backend.kind());
+
+            for missing in [commit.as_str(), &"0".repeat(40)] {
+                assert!(
+                    matches!(
+                        backend.read_blob_by_id(missing),
+                        Err(VisiGitError::RevisionNotFound { .. })
+                    ),
+                    "[{}] {}",
+                    backend.kind(),
+                    missing
+                );
+            }
+        }
+    }
+
+    /// PNG of an 8x8 gradient
+    fn gradient_png(invert: bool) -> Vec<u8> {
+        let image = image::RgbaImage::from_fn(8, 8, |x, y| {
+            let v = (x * 32 + y * 4) as u8;
+            let v = if invert { 255 - v } else { v };
+            image::Rgba([v, v, v, 255])
+        });
+        imaging::encode_png(&image).unwrap()
+    }
+
+    #[test]
+    fn test_add_similarity_compares_head_with_worktree() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "resaved.png", &png(8, 8), "Add resaved");
+        commit_file(root, "edited.png", &gradient_png(false), "Add edited");
+        commit_file(root, "broken.png", &png(8, 8), "Add broken");
+        // The same pixels, encoded differently
+        fs::write(
+            root.join("resaved.png"),
+            imaging::encode_png(&image::RgbaImage::from_pixel(
+                8,
+                8,
+                image::Rgba([0, 0, 0, 255]),
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        fs::write(root.join("edited.png"), gradient_png(true)).unwrap();
+        fs::write(root.join("broken.png"), b"not a png").unwrap();
+        fs::write(root.join("untracked.png"), png(8, 8)).unwrap();
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+            let mut files = get_changed_files_impl(backend).unwrap();
+
+            add_similarity_impl(backend, &mut files).unwrap();
+
+            let similarity: HashMap<_, _> = files
+                .iter()
+                .map(|file| (file.path.as_str(), file.similarity.as_ref()))
+                .collect();
+            let kind = backend.kind();
+            assert_eq!(similarity.len(), 4, "[{}]", kind);
+            let resaved = similarity["resaved.png"].unwrap();
+            assert_eq!(resaved.ssim, 1.0, "[{}]", kind);
+            assert!(resaved.trivial, "[{}]", kind);
+            let edited = similarity["edited.png"].unwrap();
+            assert!(
+                edited.ssim < 0.5 && !edited.trivial,
+                "[{}] {:?}",
+                kind,
+                edited
+            );
+            assert_eq!(similarity["broken.png"], None, "[{}]", kind);
+            assert_eq!(similarity["untracked.png"], None, "[{}]", kind);
+        }
+    }
+
+    #[test]
+    fn test_add_similarity_skips_conflicted_files() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "logo.png", &png(8, 8), "Add logo");
+        run_git(root, &["branch", "other"]);
+        commit_file(root, "clash.png", &png(8, 8), "Add clash here");
+        run_git(root, &["checkout", "-q", "other"]);
+        commit_file(root, "clash.png", &gradient_png(false), "Add clash there");
+        run_git(root, &["checkout", "-q", "-"]);
+        // Fails with an add/add conflict on clash.png
+        let _ = Command::new("git")
+            .args(["merge", "other"])
+            .current_dir(root)
+            .output();
+        fs::write(root.join("logo.png"), gradient_png(true)).unwrap();
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+            let mut files = get_changed_files_impl(backend).unwrap();
+
+            add_similarity_impl(backend, &mut files).expect("Conflicts should be skipped");
+
+            let similarity: HashMap<_, _> = files
+                .iter()
+                .map(|file| (file.path.as_str(), file.similarity.is_some()))
+                .collect();
+            assert_eq!(
+                similarity,
+                HashMap::from([("clash.png", false), ("logo.png", true)]),
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_add_similarity_skips_files_missing_from_the_worktree() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        commit_file(root, "logo.png", &png(8, 8), "Add logo");
+        run_git(root, &["mv", "logo.png", "moved.png"]);
+        fs::remove_file(root.join("moved.png")).unwrap();
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+            let mut files = get_changed_files_impl(backend).unwrap();
+            assert!(
+                matches!(files[0].status, ChangeKind::Renamed { .. }),
+                "[{}] {:?}",
+                backend.kind(),
+                files[0].status
+            );
+
+            add_similarity_impl(backend, &mut files)
+                .expect("A missing worktree file should be skipped");
+
+            assert_eq!(files[0].similarity, None, "[{}]", backend.kind());
+        }
+    }
+
+    #[test]
+    fn test_add_diff_similarity_compares_blobs_or_worktree() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        fs::write(root.join("kept.png"), gradient_png(false)).unwrap();
+        commit_file(root, "logo.png", &gradient_png(false), "First");
+        let first = rev_parse(root, "HEAD");
+        fs::rename(root.join("logo.png"), root.join("moved.png")).unwrap();
+        commit_file(root, "fresh.png", &png(8, 8), "Second");
+        // Uncommitted, only seen against the working tree
+        fs::write(root.join("kept.png"), gradient_png(true)).unwrap();
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+            let kind = backend.kind();
+
+            let mut files = get_changes_between_impl(backend, &first, "HEAD", false).unwrap();
+            add_diff_similarity_impl(backend, &mut files, false).unwrap();
+            let moved = find(&files, "moved.png").file.similarity.as_ref();
+            assert!(moved.is_some_and(|s| s.trivial), "[{}] {:?}", kind, moved);
+            assert_eq!(
+                find(&files, "fresh.png").file.similarity,
+                None,
+                "[{}]",
+                kind
+            );
+
+            let mut files = get_changed_files_against_impl(backend, &first).unwrap();
+            add_diff_similarity_impl(backend, &mut files, true).unwrap();
+            let kept = find(&files, "kept.png").file.similarity.as_ref();
+            assert!(kept.is_some_and(|s| !s.trivial), "[{}] {:?}", kind, kept);
+        }
+    }
+
+    // ============================================
+    // Tests for Git LFS pointers
+    // ============================================
+
+    const LFS_OBJECT: &[u8] = b"\x89PNG\r\n\x1a\nthe real hero";
+
+    #[test]
+    fn test_lfs_pointer_reads_the_fetched_object() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        let pointer = commit_lfs_pointer(root, LFS_OBJECT);
+        store_lfs_object(root, &pointer, LFS_OBJECT);
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+
+            assert_eq!(
+                get_file_bytes_impl(backend, "hero.png", "HEAD"),
+                Ok(LFS_OBJECT.to_vec()),
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(
+                get_file_at_index_impl(backend, "hero.png"),
+                Ok(STANDARD.encode(LFS_OBJECT)),
+                "[{}]",
+                backend.kind()
+            );
+
+            let metadata = get_blob_metadata_impl(backend, "hero.png", "HEAD").unwrap();
+            assert_eq!(metadata.size, LFS_OBJECT.len() as u64);
+            assert_eq!(metadata.mime, "image/png");
+            assert_eq!(
+                metadata.lfs,
+                LfsStatus {
+                    is_lfs: true,
+                    lfs_missing: false,
+                },
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_lfs_object_returns_the_pointer() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        let pointer = commit_lfs_pointer(root, LFS_OBJECT);
+        // A partial download does not count
+        store_lfs_object(root, &pointer, &LFS_OBJECT[..4]);
+
+        for backend in all_backends(root) {
+            let backend = backend.as_ref();
+
+            let metadata = get_blob_metadata_impl(backend, "hero.png", "HEAD").unwrap();
+
+            assert_eq!(
+                metadata.lfs,
+                LfsStatus {
+                    is_lfs: true,
+                    lfs_missing: true,
+                },
+                "[{}]",
+                backend.kind()
+            );
+            assert!(get_file_bytes_impl(backend, "hero.png", "HEAD")
+                .unwrap()
+                .starts_with(b"version https://git-lfs"));
+        }
+    }
+
+    #[test]
+    fn test_missing_lfs_object_goes_through_the_smudge_filter() {
+        let temp_repo = create_test_git_repo();
+        let root = temp_repo.path();
+        fs::write(root.join(".gitattributes"), "*.png filter=lfs\n").unwrap();
+        // Stands in for git-lfs downloading the object
+        run_git(
+            root,
+            &[
+                "config",
+                "filter.lfs.smudge",
+                "cat >/dev/null; printf fetched",
+            ],
+        );
+        commit_lfs_pointer(root, LFS_OBJECT);
+
+        for backend in all_backends(root) {
+            let metadata = get_blob_metadata_impl(backend.as_ref(), "hero.png", "HEAD").unwrap();
+            assert!(!metadata.lfs.lfs_missing, "[{}]", backend.kind());
+            assert_eq!(
+                get_file_bytes_impl(backend.as_ref(), "hero.png", "HEAD").unwrap(),
+                b"fetched",
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_restore_file_checks_out_the_lfs_object() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            let pointer = commit_lfs_pointer(root, LFS_OBJECT);
+            store_lfs_object(root, &pointer, LFS_OBJECT);
+        }) {
+            let backend = backend.as_ref();
+            let temp_trash = TempDir::new().unwrap();
+            let trash = Trash::new(temp_trash.path().to_path_buf());
+            fs::remove_file(temp_repo.path().join("hero.png")).unwrap();
+
+            restore_file_impl(backend, &trash, "hero.png", "HEAD", RestoreTarget::Worktree)
+                .unwrap();
+
+            assert_eq!(
+                fs::read(temp_repo.path().join("hero.png")).unwrap(),
+                LFS_OBJECT,
+                "[{}]",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_restore_file_refuses_a_missing_lfs_object() {
+        for (temp_repo, backend) in each_backend_with_repo(|root| {
+            commit_lfs_pointer(root, LFS_OBJECT);
+            commit_file(root, "hero.png", b"current", "Replace hero");
+        }) {
+            let backend = backend.as_ref();
+            let temp_trash = TempDir::new().unwrap();
+            let trash = Trash::new(temp_trash.path().to_path_buf());
+
+            let result = restore_file_impl(
+                backend,
+                &trash,
+                "hero.png",
+                "HEAD~1",
+                RestoreTarget::Worktree,
+            );
+
+            assert_eq!(
+                result.map(|_| ()),
+                Err(VisiGitError::LfsObjectMissing {
+                    path: "hero.png".to_string()
+                }),
+                "[{}]",
+                backend.kind()
+            );
+            assert_eq!(
+                fs::read(temp_repo.path().join("hero.png")).unwrap(),
+                b"current"
+            );
+            assert_eq!(fs::read_dir(temp_trash.path()).unwrap().count(), 0);
+        }
+    }
+
+    // ============================================
+    // Tests for get_file_at_commit_impl
+    // ============================================
+
+    #[test]
+    fn test_get_file_at_commit_returns_correct_content() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        // Create first commit with initial content
+        let initial_content = b"initial image content";
+        fs::write(path.join("test.png"), initial_content).expect("Failed to write file");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add files");
+        Command::new("git")
+            .args(["commit", "-m", "First commit"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to create first commit");
+
+        // Get the first commit hash
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to get commit hash");
+        let first_commit_hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        // Create second commit with modified content
+        let modified_content = b"modified image content";
+        fs::write(path.join("test.png"), modified_content).expect("Failed to write modified file");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add files");
+        Command::new("git")
+            .args(["commit", "-m", "Second commit"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to create second commit");
+
+        for backend in all_backends(path) {
+            // Get file content at first commit
+            let result = get_file_at_commit_impl(backend.as_ref(), "test.png", &first_commit_hash);
+
+            assert!(
+                result.is_ok(),
+                "[{}] Expected Ok result, got {:?}",
+                backend.kind(),
+                result
+            );
+            let base64_content = result.unwrap();
+            let decoded = STANDARD
+                .decode(&base64_content)
+                .expect("Failed to decode base64");
+            assert_eq!(
+                decoded,
+                initial_content,
+                "[{}] Content at first commit should match initial content",
+                backend.kind()
+            );
+        }
+    }
+    #[test]
+    fn test_get_file_at_commit_returns_revision_not_found_for_unknown_commit() {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        fs::write(path.join("test.png"), b"content").expect("Failed to write file");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add files");
+        Command::new("git")
+            .args(["commit", "-m", "First commit"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        for backend in all_backends(path) {
+            let result = get_file_at_commit_impl(backend.as_ref(), "test.png", "deadbeefdeadbeef");
+
+            assert_eq!(
+                result,
+                Err(VisiGitError::RevisionNotFound {
+                    rev: "deadbeefdeadbeef".to_string()
+                }),
+                "[{}] Expected RevisionNotFound",
+                backend.kind()
+            );
+        }
+    }
+
+    // ============================================
+    // Tests for argument validation
+    // ============================================
+
+    /// Helper to create a repository with `test.png` committed
+    fn create_committed_repo() -> TempDir {
+        let temp_repo = create_test_git_repo();
+        let path = temp_repo.path();
+
+        fs::write(path.join("test.png"), b"content").expect("Failed to write file");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .expect("Failed to add files");
+        Command::new("git")
+            .args(["commit", "-m", "First commit"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to commit");
+
+        temp_repo
+    }
+
+    #[test]
+    fn test_option_like_commit_is_rejected_before_reaching_git() {
+        let temp_repo = create_committed_repo();
+        let path = temp_repo.path();
+        let target = path.join("injected.txt");
+        let commit = format!("--output={}", target.display());
+
+        for backend in all_backends(path) {
+            let result = get_file_at_commit_impl(backend.as_ref(), "test.png", &commit);
+
+            assert_eq!(
+                result,
+                Err(VisiGitError::InvalidRevision {
+                    rev: commit.clone()
+                }),
+                "[{}] Expected InvalidRevision",
+                backend.kind()
+            );
+            assert!(!target.exists(), "[{}] git wrote the file", backend.kind());
+        }
+    }
+
+    #[test]
+    fn test_commit_with_object_path_is_rejected() {
+        let temp_repo = create_committed_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let result = get_file_at_commit_impl(backend.as_ref(), "other.png", "HEAD:test.png");
+
+            assert!(
+                matches!(result, Err(VisiGitError::InvalidRevision { .. })),
+                "[{}] Expected InvalidRevision, got {:?}",
+                backend.kind(),
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_parent_escaping_path_is_rejected() {
+        let temp_repo = create_committed_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            for file_path in ["../../etc/passwd", "assets/../../test.png"] {
+                let result = get_file_at_commit_impl(backend.as_ref(), file_path, "HEAD");
+
+                assert_eq!(
+                    result,
+                    Err(VisiGitError::PathEscapesRepo {
+                        path: file_path.to_string()
+                    }),
+                    "[{}] Expected PathEscapesRepo for {}",
+                    backend.kind(),
+                    file_path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_absolute_path_is_rejected() {
+        let temp_repo = create_committed_repo();
+        let absolute = temp_repo.path().join("test.png");
+        let absolute = absolute.to_str().unwrap();
+
+        for backend in all_backends(temp_repo.path()) {
+            let result = get_file_at_head_impl(backend.as_ref(), absolute);
+
+            assert_eq!(
+                result,
+                Err(VisiGitError::AbsolutePath {
+                    path: absolute.to_string()
+                }),
+                "[{}] Expected AbsolutePath",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_path_inside_repo_is_normalized() {
+        let temp_repo = create_committed_repo();
+
+        for backend in all_backends(temp_repo.path()) {
+            let result = get_file_bytes_impl(backend.as_ref(), "./assets/../test.png", "HEAD");
+
+            assert_eq!(
+                result,
+                Ok(b"content".to_vec()),
+                "[{}] Expected the file at the normalized path",
+                backend.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends_open_non_canonical_repo_path() {
+        let temp_repo = create_committed_repo();
+        let name = temp_repo.path().file_name().unwrap();
+        let indirect = temp_repo.path().join("..").join(name).join(".");
+
+        for backend in all_backends(&indirect) {
+            assert!(
+                get_file_at_head_impl(backend.as_ref(), "test.png").is_ok(),
+                "[{}] Expected the repo to open through {}",
+                backend.kind(),
+                indirect.display()
+            );
+        }
+    }
+}
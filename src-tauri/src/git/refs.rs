@@ -0,0 +1,212 @@
+//! Parser for `git for-each-ref --format=REF_FORMAT`.
+//!
+//! One line per ref, fields separated by the ASCII unit separator (ref
+//! names cannot contain control characters). For an annotated tag the
+//! plain fields describe the tag object, so the commit comes from the
+//! `%(*...)` fields, which git fills in one level down; for every other
+//! ref those are empty.
+
+use super::{RefInfo, RefKind};
+use crate::error::{Result, VisiGitError};
+
+/// `--format` for one ref: name, symref target, `*` if HEAD is this
+/// branch, then object type, id and committer date (Unix and ISO) for the
+/// ref and for what an annotated tag points at, then upstream and its
+/// ahead/behind summary
+pub const REF_FORMAT: &str = "%(refname)%1f%(symref)%1f%(HEAD)%1f\
+%(objecttype)%1f%(objectname)%1f%(committerdate:unix)%1f%(committerdate:iso)%1f\
+%(*objecttype)%1f%(*objectname)%1f%(*committerdate:unix)%1f%(*committerdate:iso)%1f\
+%(upstream)%1f%(upstream:track,nobracket)";
+
+/// Parse the stdout of `git for-each-ref --format=REF_FORMAT`, leaving out
+/// symbolic refs, refs outside branches, remotes and tags, and tags that
+/// do not point at a commit
+pub fn parse_refs(output: &[u8]) -> Result<Vec<RefInfo>> {
+    let output = String::from_utf8_lossy(output);
+    let mut refs = Vec::new();
+
+    for line in output.lines().filter(|line| !line.is_empty()) {
+        if let Some(info) = parse_ref_line(line)? {
+            refs.push(info);
+        }
+    }
+
+    Ok(refs)
+}
+
+fn parse_ref_line(line: &str) -> Result<Option<RefInfo>> {
+    let malformed = || VisiGitError::UnexpectedOutput {
+        message: format!("Malformed git for-each-ref line: {:?}", line),
+    };
+
+    let parts: Vec<&str> = line.split('\x1f').collect();
+    let [name, symref, head, object_type, object, unix, iso, peeled_type, peeled, peeled_unix, peeled_iso, upstream, track] =
+        parts.as_slice()
+    else {
+        return Err(malformed());
+    };
+
+    if !symref.is_empty() {
+        return Ok(None);
+    }
+    let Some((kind, short_name)) = RefKind::of(name) else {
+        return Ok(None);
+    };
+    let (target, unix, date) = match (*object_type, *peeled_type) {
+        ("commit", _) => (object, unix, iso),
+        ("tag", "commit") => (peeled, peeled_unix, peeled_iso),
+        _ => return Ok(None),
+    };
+
+    let upstream = Some(upstream.to_string()).filter(|upstream| !upstream.is_empty());
+    let (ahead, behind) = match upstream {
+        Some(_) => parse_track(track).ok_or_else(malformed)?,
+        None => (None, None),
+    };
+
+    Ok(Some(RefInfo {
+        name: name.to_string(),
+        short_name: short_name.to_string(),
+        kind,
+        target: target.to_string(),
+        upstream,
+        ahead,
+        behind,
+        date: date.to_string(),
+        timestamp: unix.parse().map_err(|_| malformed())?,
+        is_head: *head == "*",
+    }))
+}
+
+/// Ahead and behind counts from `%(upstream:track,nobracket)`, which is
+/// empty when in sync and `gone` when the upstream ref does not exist
+fn parse_track(track: &str) -> Option<(Option<usize>, Option<usize>)> {
+    if track == "gone" {
+        return Some((None, None));
+    }
+
+    let (mut ahead, mut behind) = (0, 0);
+    for part in track.split(", ").filter(|part| !part.is_empty()) {
+        match part.split_once(' ')? {
+            ("ahead", count) => ahead = count.parse().ok()?,
+            ("behind", count) => behind = count.parse().ok()?,
+            _ => return None,
+        }
+    }
+
+    Some((Some(ahead), Some(behind)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA_A: &str = "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391";
+    const SHA_B: &str = "5716ca5987cbf97d6bb54920bea6adde242d87e6";
+    const DATE: &str = "2024-01-01 12:00:00 +0000";
+
+    /// Line for a ref that points straight at commit `SHA_A`
+    fn commit_line(name: &str, symref: &str, upstream: &str, track: &str) -> String {
+        format!(
+            "{name}\x1f{symref}\x1f \x1fcommit\x1f{SHA_A}\x1f1704110400\x1f{DATE}\x1f\
+             \x1f\x1f\x1f\x1f{upstream}\x1f{track}\n"
+        )
+    }
+
+    /// Line for an annotated tag whose tag object points at a `peeled_type`
+    fn tag_line(name: &str, peeled_type: &str) -> String {
+        format!(
+            "{name}\x1f\x1f \x1ftag\x1f{SHA_B}\x1f\x1f\x1f\
+             {peeled_type}\x1f{SHA_A}\x1f1704110400\x1f{DATE}\x1f\x1f\n"
+        )
+    }
+
+    #[test]
+    fn test_parse_refs_reads_branches_and_peels_annotated_tags() {
+        let head = commit_line(
+            "refs/heads/feature/new-icons",
+            "",
+            "refs/remotes/origin/feature/new-icons",
+            "ahead 2, behind 1",
+        )
+        .replacen("\x1f \x1f", "\x1f*\x1f", 1);
+        let output = head + &tag_line("refs/tags/v1", "commit");
+
+        let refs = parse_refs(output.as_bytes()).unwrap();
+
+        assert_eq!(
+            refs,
+            vec![
+                RefInfo {
+                    name: "refs/heads/feature/new-icons".to_string(),
+                    short_name: "feature/new-icons".to_string(),
+                    kind: RefKind::Branch,
+                    target: SHA_A.to_string(),
+                    upstream: Some("refs/remotes/origin/feature/new-icons".to_string()),
+                    ahead: Some(2),
+                    behind: Some(1),
+                    date: DATE.to_string(),
+                    timestamp: 1_704_110_400,
+                    is_head: true,
+                },
+                RefInfo {
+                    name: "refs/tags/v1".to_string(),
+                    short_name: "v1".to_string(),
+                    kind: RefKind::Tag,
+                    target: SHA_A.to_string(),
+                    upstream: None,
+                    ahead: None,
+                    behind: None,
+                    date: DATE.to_string(),
+                    timestamp: 1_704_110_400,
+                    is_head: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_refs_skips_symrefs_other_refs_and_non_commit_tags() {
+        let output = [
+            commit_line(
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/main",
+                "",
+                "",
+            ),
+            commit_line("refs/notes/commits", "", "", ""),
+            tag_line("refs/tags/tree-tag", "tree"),
+            tag_line("refs/tags/tag-of-tag", "tag"),
+        ]
+        .concat();
+
+        assert_eq!(parse_refs(output.as_bytes()).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_parse_refs_keeps_upstream_that_is_gone() {
+        let output = commit_line("refs/heads/old", "", "refs/remotes/origin/old", "gone");
+
+        let refs = parse_refs(output.as_bytes()).unwrap();
+
+        assert_eq!(refs[0].upstream.as_deref(), Some("refs/remotes/origin/old"));
+        assert_eq!((refs[0].ahead, refs[0].behind), (None, None));
+    }
+
+    #[test]
+    fn test_parse_track_reads_each_upstream_state() {
+        assert_eq!(parse_track(""), Some((Some(0), Some(0))));
+        assert_eq!(parse_track("ahead 3"), Some((Some(3), Some(0))));
+        assert_eq!(parse_track("behind 4"), Some((Some(0), Some(4))));
+        assert_eq!(parse_track("gone"), Some((None, None)));
+        assert_eq!(parse_track("sideways 1"), None);
+    }
+
+    #[test]
+    fn test_parse_refs_rejects_malformed_output() {
+        assert!(matches!(
+            parse_refs(b"refs/heads/main\n"),
+            Err(VisiGitError::UnexpectedOutput { .. })
+        ));
+    }
+}
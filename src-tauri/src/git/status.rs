@@ -0,0 +1,343 @@
+//! Parser for `git status --porcelain=v2 -z`.
+//!
+//! Records are NUL-terminated and paths are never quoted, so spaces, quotes
+//! and non-ASCII file names come through verbatim. Rename and copy records
+//! are followed by an extra NUL-terminated field holding the original path.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, VisiGitError};
+
+/// Blob ids of an unmerged path's index stages (`:1:`, `:2:` and `:3:`),
+/// `None` for a side that has no version of the file
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConflictBlobs {
+    /// Stage 1, the version in the merge base
+    pub base: Option<String>,
+    /// Stage 2, the version on the branch being merged into
+    pub ours: Option<String>,
+    /// Stage 3, the version being merged in
+    pub theirs: Option<String>,
+}
+
+/// One path reported by `git status`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEntry {
+    /// Path relative to the repository root
+    pub path: String,
+    /// Path before a rename or copy
+    pub orig_path: Option<String>,
+    /// Index (staged) status code, `.` when unchanged
+    pub index: char,
+    /// Worktree (unstaged) status code, `.` when unchanged
+    pub worktree: char,
+    /// Stage blob ids, only for unmerged paths
+    pub conflict: Option<ConflictBlobs>,
+}
+
+impl StatusEntry {
+    /// Untracked files are reported as `??`
+    pub fn is_untracked(&self) -> bool {
+        self.index == '?'
+    }
+
+    /// Ignored files are reported as `!!`
+    pub fn is_ignored(&self) -> bool {
+        self.index == '!'
+    }
+
+    /// Unmerged paths during a merge, rebase or cherry-pick
+    pub fn is_unmerged(&self) -> bool {
+        matches!(
+            (self.index, self.worktree),
+            ('D', 'D')
+                | ('A', 'U')
+                | ('U', 'D')
+                | ('U', 'A')
+                | ('D', 'U')
+                | ('A', 'A')
+                | ('U', 'U')
+        )
+    }
+}
+
+/// Parse the raw stdout of `git status --porcelain=v2 -z`
+pub fn parse_porcelain_v2(output: &[u8]) -> Result<Vec<StatusEntry>> {
+    let mut fields = output
+        .split(|b| *b == 0)
+        .filter(|field| !field.is_empty())
+        .map(|field| String::from_utf8_lossy(field).into_owned());
+    let mut entries = Vec::new();
+
+    while let Some(record) = fields.next() {
+        let malformed = || VisiGitError::UnexpectedOutput {
+            message: format!("Malformed git status record: {:?}", record),
+        };
+
+        match record.as_bytes()[0] {
+            // Headers only appear with --branch/--show-stash
+            b'#' => continue,
+            // 1 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <path>
+            b'1' => {
+                let (index, worktree, path) = split_record(&record, 9).ok_or_else(malformed)?;
+                entries.push(StatusEntry {
+                    path,
+                    orig_path: None,
+                    index,
+                    worktree,
+                    conflict: None,
+                });
+            }
+            // 2 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <X><score> <path>\0<origPath>
+            b'2' => {
+                let (index, worktree, path) = split_record(&record, 10).ok_or_else(malformed)?;
+                let orig_path = fields.next().ok_or_else(malformed)?;
+                entries.push(StatusEntry {
+                    path,
+                    orig_path: Some(orig_path),
+                    index,
+                    worktree,
+                    conflict: None,
+                });
+            }
+            // u <XY> <sub> <m1> <m2> <m3> <mW> <h1> <h2> <h3> <path>
+            b'u' => {
+                let (index, worktree, path) = split_record(&record, 11).ok_or_else(malformed)?;
+                // A side without the file reports an all-zero id
+                let stage = |i: usize| {
+                    record
+                        .split(' ')
+                        .nth(i)
+                        .filter(|id| !id.bytes().all(|b| b == b'0'))
+                        .map(str::to_string)
+                };
+                entries.push(StatusEntry {
+                    path,
+                    orig_path: None,
+                    index,
+                    worktree,
+                    conflict: Some(ConflictBlobs {
+                        base: stage(7),
+                        ours: stage(8),
+                        theirs: stage(9),
+                    }),
+                });
+            }
+            // ? <path> and ! <path>
+            b'?' | b'!' => {
+                let code = record.as_bytes()[0] as char;
+                let path = record
+                    .get(2..)
+                    .filter(|p| !p.is_empty())
+                    .ok_or_else(malformed)?;
+                entries.push(StatusEntry {
+                    path: path.to_string(),
+                    orig_path: None,
+                    index: code,
+                    worktree: code,
+                    conflict: None,
+                });
+            }
+            _ => return Err(malformed()),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Split a space-separated record with `field_count` fields into its XY
+/// status and trailing path. The path is last so it may contain spaces.
+fn split_record(record: &str, field_count: usize) -> Option<(char, char, String)> {
+    let parts: Vec<&str> = record.splitn(field_count, ' ').collect();
+    if parts.len() != field_count || parts[field_count - 1].is_empty() {
+        return None;
+    }
+
+    let mut xy = parts[1].chars();
+    match (xy.next(), xy.next(), xy.next()) {
+        (Some(x), Some(y), None) => Some((x, y, parts[field_count - 1].to_string())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA_A: &str = "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391";
+    const SHA_B: &str = "5716ca5987cbf97d6bb54920bea6adde242d87e6";
+    const ZERO: &str = "0000000000000000000000000000000000000000";
+
+    fn ordinary(xy: &str, path: &str) -> String {
+        format!(
+            "1 {} N... 100644 100644 100644 {} {} {}\0",
+            xy, SHA_A, SHA_B, path
+        )
+    }
+
+    #[test]
+    fn test_parse_empty_output() {
+        assert_eq!(parse_porcelain_v2(b"").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_parse_ordinary_entries_keep_both_status_columns() {
+        let output = ordinary(".M", "icons/logo.png") + &ordinary("M.", "icons/staged.png");
+
+        let entries = parse_porcelain_v2(output.as_bytes()).unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                StatusEntry {
+                    path: "icons/logo.png".to_string(),
+                    orig_path: None,
+                    index: '.',
+                    worktree: 'M',
+                    conflict: None,
+                },
+                StatusEntry {
+                    path: "icons/staged.png".to_string(),
+                    orig_path: None,
+                    index: 'M',
+                    worktree: '.',
+                    conflict: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_paths_with_spaces_quotes_and_unicode() {
+        let output = ordinary(".M", "assets/héro image.png")
+            + &ordinary(".M", "assets/say \"cheese\".jpg")
+            + "? new folder/ünïcode 🎨.svg\0";
+
+        let entries = parse_porcelain_v2(output.as_bytes()).unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                "assets/héro image.png",
+                "assets/say \"cheese\".jpg",
+                "new folder/ünïcode 🎨.svg",
+            ]
+        );
+        assert!(entries[2].is_untracked());
+    }
+
+    #[test]
+    fn test_parse_rename_reads_original_path() {
+        let output = format!(
+            "2 R. N... 100644 100644 100644 {} {} R100 brand/logo final.svg\0img/logo final.svg\0",
+            SHA_A, SHA_A
+        ) + &ordinary(".M", "after.png");
+
+        let entries = parse_porcelain_v2(output.as_bytes()).unwrap();
+
+        assert_eq!(
+            entries.len(),
+            2,
+            "Original path must not be read as a record"
+        );
+        assert_eq!(entries[0].path, "brand/logo final.svg");
+        assert_eq!(entries[0].orig_path.as_deref(), Some("img/logo final.svg"));
+        assert_eq!((entries[0].index, entries[0].worktree), ('R', '.'));
+        assert_eq!(entries[1].path, "after.png");
+    }
+
+    #[test]
+    fn test_parse_copy_reads_original_path() {
+        let output = format!(
+            "2 C. N... 100644 100644 100644 {} {} C75 copy.png\0source.png\0",
+            SHA_A, SHA_A
+        );
+
+        let entries = parse_porcelain_v2(output.as_bytes()).unwrap();
+
+        assert_eq!(entries[0].path, "copy.png");
+        assert_eq!(entries[0].orig_path.as_deref(), Some("source.png"));
+        assert_eq!(entries[0].index, 'C');
+    }
+
+    #[test]
+    fn test_parse_unmerged_entry() {
+        let output = format!(
+            "u UU N... 100644 100644 100644 100644 {} {} {} art/hero.psd\0",
+            SHA_A, SHA_B, SHA_A
+        );
+
+        let entries = parse_porcelain_v2(output.as_bytes()).unwrap();
+
+        assert_eq!(entries[0].path, "art/hero.psd");
+        assert!(entries[0].is_unmerged());
+        assert_eq!(
+            entries[0].conflict,
+            Some(ConflictBlobs {
+                base: Some(SHA_A.to_string()),
+                ours: Some(SHA_B.to_string()),
+                theirs: Some(SHA_A.to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_unmerged_entry_without_base() {
+        let output = format!(
+            "u AA N... 000000 100644 100644 100644 {} {} {} both added.png\0",
+            ZERO, SHA_A, SHA_B
+        );
+
+        let entries = parse_porcelain_v2(output.as_bytes()).unwrap();
+
+        assert_eq!(entries[0].path, "both added.png");
+        assert_eq!(
+            entries[0].conflict,
+            Some(ConflictBlobs {
+                base: None,
+                ours: Some(SHA_A.to_string()),
+                theirs: Some(SHA_B.to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_added_and_deleted_entries() {
+        let output = format!(
+            "1 A. N... 000000 100644 100644 {} {} added.png\0\
+             1 .D N... 100644 100644 000000 {} {} removed.png\0",
+            ZERO, SHA_A, SHA_A, SHA_A
+        );
+
+        let entries = parse_porcelain_v2(output.as_bytes()).unwrap();
+
+        assert_eq!((entries[0].index, entries[0].worktree), ('A', '.'));
+        assert_eq!((entries[1].index, entries[1].worktree), ('.', 'D'));
+    }
+
+    #[test]
+    fn test_parse_skips_headers_and_reads_ignored() {
+        let output = "# branch.oid (initial)\0# branch.head main\0! build/out.png\0";
+
+        let entries = parse_porcelain_v2(output.as_bytes()).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].is_ignored());
+        assert_eq!(entries[0].path, "build/out.png");
+    }
+
+    #[test]
+    fn test_parse_rejects_truncated_records() {
+        assert!(parse_porcelain_v2(b"1 .M N... 100644\0").is_err());
+        assert!(parse_porcelain_v2(b"? \0").is_err());
+        assert!(parse_porcelain_v2(b"X what\0").is_err());
+
+        // Rename without the original path field
+        let truncated = format!(
+            "2 R. N... 100644 100644 100644 {} {} R100 new.png\0",
+            SHA_A, SHA_A
+        );
+        assert!(parse_porcelain_v2(truncated.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Validation for revisions and paths that come from the frontend.
+//!
+//! Everything here runs before an argument reaches git or the filesystem,
+//! so a revision can never be read as an option and a path can never name
+//! something outside the repository.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{Result, VisiGitError};
+
+/// Check that `rev` is a plain revision expression.
+///
+/// Rejects anything git could read as an option (a leading `-`), empty
+/// strings, control characters and whitespace, and `:` (which would turn
+/// `{rev}:{path}` into a different object lookup). Ref names cannot
+/// contain any of these, so real branches and tags always pass.
+pub fn revision(rev: &str) -> Result<&str> {
+    let invalid = rev.is_empty()
+        || rev.starts_with('-')
+        || rev.contains(':')
+        || rev.chars().any(|c| c.is_control() || c.is_whitespace());
+
+    if invalid {
+        return Err(VisiGitError::InvalidRevision {
+            rev: rev.to_string(),
+        });
+    }
+
+    Ok(rev)
+}
+
+/// Normalize a repo-relative path, with `/` separators.
+///
+/// `.` components are dropped and `..` is resolved lexically, so
+/// `assets/../logo.png` becomes `logo.png`. Absolute paths and paths that
+/// climb above the repository root are rejected.
+pub fn repo_path(path: &str) -> Result<String> {
+    if path.is_empty() || path.chars().any(char::is_control) {
+        return Err(VisiGitError::InvalidPath {
+            path: path.to_string(),
+        });
+    }
+
+    let mut parts: Vec<String> = Vec::new();
+
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if parts.pop().is_none() {
+                    return Err(VisiGitError::PathEscapesRepo {
+                        path: path.to_string(),
+                    });
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(VisiGitError::AbsolutePath {
+                    path: path.to_string(),
+                })
+            }
+        }
+    }
+
+    if parts.is_empty() {
+        return Err(VisiGitError::InvalidPath {
+            path: path.to_string(),
+        });
+    }
+
+    Ok(parts.join("/"))
+}
+
+/// Canonical form of a repository root, so symlinked and relative paths
+/// to the same repository agree
+pub fn repo_root(path: &Path) -> Result<PathBuf> {
+    if !path.exists() {
+        return Err(VisiGitError::RepoNotFound {
+            path: path.display().to_string(),
+        });
+    }
+
+    Ok(path.canonicalize()?)
+}
+
+/// Resolve `path` to a file in the working tree at `root`.
+///
+/// The result is canonicalized and must stay under the canonical root, so
+/// a symlink pointing out of the repository is rejected as well.
+pub fn worktree_file(root: &Path, path: &str) -> Result<PathBuf> {
+    let root = repo_root(root)?;
+    let file = root.join(repo_path(path)?).canonicalize()?;
+
+    if !file.starts_with(&root) {
+        return Err(VisiGitError::PathEscapesRepo {
+            path: path.to_string(),
+        });
+    }
+
+    Ok(file)
+}
+
+/// Check that `id` is a full hexadecimal object id
+pub fn object_id(id: &str) -> Result<&str> {
+    if !matches!(id.len(), 40 | 64) || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(VisiGitError::InvalidRevision {
+            rev: id.to_string(),
+        });
+    }
+
+    Ok(id)
+}
+
+/// Resolve `path` to a location in the working tree at `root` that is
+/// about to be written, and may not exist yet.
+///
+/// Paths inside `.git` are rejected. The file, or its nearest existing
+/// parent folder, has to stay under the canonical root once symlinks are
+/// resolved.
+pub fn worktree_destination(root: &Path, path: &str) -> Result<PathBuf> {
+    let root = repo_root(root)?;
+    let relative = repo_path(path)?;
+    if relative
+        .split('/')
+        .any(|part| part.eq_ignore_ascii_case(".git"))
+    {
+        return Err(VisiGitError::InvalidPath {
+            path: path.to_string(),
+        });
+    }
+
+    let destination = root.join(&relative);
+    let existing = destination
+        .ancestors()
+        .find(|ancestor| ancestor.symlink_metadata().is_ok())
+        .unwrap_or(&root);
+    if !existing.canonicalize()?.starts_with(&root) {
+        return Err(VisiGitError::PathEscapesRepo {
+            path: path.to_string(),
+        });
+    }
+
+    Ok(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_revision_accepts_revision_expressions() {
+        for rev in [
+            "HEAD",
+            "HEAD~2",
+            "main^{commit}",
+            "feature/logo",
+            "v1.0.0",
+            "a1b2c3d",
+            "@{u}",
+        ] {
+            assert_eq!(revision(rev), Ok(rev), "for {}", rev);
+        }
+    }
+
+    #[test]
+    fn test_revision_rejects_option_injection() {
+        for rev in ["--output=/tmp/x", "-n1", "--all", "-"] {
+            assert_eq!(
+                revision(rev),
+                Err(VisiGitError::InvalidRevision {
+                    rev: rev.to_string()
+                }),
+                "for {}",
+                rev
+            );
+        }
+    }
+
+    #[test]
+    fn test_revision_rejects_malformed_input() {
+        for rev in ["", "HEAD:logo.png", "HEAD\0", "HEAD\n--all", "main branch"] {
+            assert!(
+                matches!(revision(rev), Err(VisiGitError::InvalidRevision { .. })),
+                "for {:?}",
+                rev
+            );
+        }
+    }
+
+    #[test]
+    fn test_repo_path_normalizes_relative_paths() {
+        assert_eq!(repo_path("logo.png").unwrap(), "logo.png");
+        assert_eq!(repo_path("./assets/logo.png").unwrap(), "assets/logo.png");
+        assert_eq!(repo_path("assets/../logo.png").unwrap(), "logo.png");
+        assert_eq!(
+            repo_path("assets//icons/a.png").unwrap(),
+            "assets/icons/a.png"
+        );
+        assert_eq!(repo_path("--output=x.png").unwrap(), "--output=x.png");
+    }
+
+    #[test]
+    fn test_repo_path_rejects_parent_escapes() {
+        for path in ["../../etc/passwd", "..", "assets/../../secret.png"] {
+            assert_eq!(
+                repo_path(path),
+                Err(VisiGitError::PathEscapesRepo {
+                    path: path.to_string()
+                }),
+                "for {}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_repo_path_rejects_absolute_paths() {
+        for path in ["/etc/passwd", "/"] {
+            assert_eq!(
+                repo_path(path),
+                Err(VisiGitError::AbsolutePath {
+                    path: path.to_string()
+                }),
+                "for {}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_repo_path_rejects_empty_and_control_characters() {
+        for path in ["", ".", "logo\0.png", "logo.png\n"] {
+            assert!(
+                matches!(repo_path(path), Err(VisiGitError::InvalidPath { .. })),
+                "for {:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_repo_root_is_canonical() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("repo")).unwrap();
+        let indirect = temp_dir.path().join("repo/../repo/.");
+
+        assert_eq!(
+            repo_root(&indirect).unwrap(),
+            temp_dir.path().join("repo").canonicalize().unwrap()
+        );
+        assert!(matches!(
+            repo_root(&temp_dir.path().join("missing")),
+            Err(VisiGitError::RepoNotFound { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_worktree_file_rejects_symlinks_out_of_the_repo() {
+        let repo = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        fs::write(outside.path().join("secret.png"), b"secret").unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("secret.png"),
+            repo.path().join("link.png"),
+        )
+        .unwrap();
+        fs::write(repo.path().join("logo.png"), b"logo").unwrap();
+
+        assert!(worktree_file(repo.path(), "logo.png").is_ok());
+        assert_eq!(
+            worktree_file(repo.path(), "link.png"),
+            Err(VisiGitError::PathEscapesRepo {
+                path: "link.png".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_object_id_requires_full_hex_ids() {
+        let id = "b6fc4c620b67d95f953a5c1c1230aaab5db5a1b0";
+        assert_eq!(object_id(id), Ok(id));
+
+        for id in [
+            "b6fc4c6",
+            "HEAD",
+            "b6fc4c620b67d95f953a5c1c1230aaab5db5a1b\n",
+        ] {
+            assert!(
+                matches!(object_id(id), Err(VisiGitError::InvalidRevision { .. })),
+                "for {:?}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_worktree_destination_allows_missing_files() {
+        let repo = TempDir::new().unwrap();
+        let root = repo.path().canonicalize().unwrap();
+
+        assert_eq!(
+            worktree_destination(repo.path(), "icons/new/logo.png"),
+            Ok(root.join("icons/new/logo.png"))
+        );
+        for path in [".git/config", "assets/.GIT/hooks/pre-commit"] {
+            assert!(
+                matches!(
+                    worktree_destination(repo.path(), path),
+                    Err(VisiGitError::InvalidPath { .. })
+                ),
+                "for {}",
+                path
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_worktree_destination_rejects_symlinked_folders_out_of_the_repo() {
+        let repo = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), repo.path().join("assets")).unwrap();
+
+        assert_eq!(
+            worktree_destination(repo.path(), "assets/logo.png"),
+            Err(VisiGitError::PathEscapesRepo {
+                path: "assets/logo.png".to_string()
+            })
+        );
+    }
+}
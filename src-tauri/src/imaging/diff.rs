@@ -0,0 +1,470 @@
+//! Per-pixel comparison of two versions of an image.
+
+use std::io::Cursor;
+use std::num::NonZeroUsize;
+use std::thread;
+
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{DynamicImage, ImageDecoder, ImageReader, ImageResult, Limits, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::perceptual;
+
+/// Color of a changed pixel in the diff image
+pub(super) const CHANGED: [u8; 4] = [255, 0, 255, 255];
+
+/// Color of a pixel that only differs by anti-aliasing
+const ANTIALIASED: [u8; 4] = [255, 255, 0, 255];
+
+/// How pixels are compared, serialized as
+/// `{ "kind": "perceptual", "threshold": 0.1 }`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DiffMethod {
+    /// Mean absolute difference of the red, green and blue channels above
+    /// `threshold` (0-255); alpha is ignored
+    Average { threshold: f64 },
+    /// YIQ color distance above `threshold` (0-1), with anti-aliased
+    /// pixels counted apart from real changes
+    Perceptual { threshold: f64 },
+}
+
+/// Result of comparing two images pixel by pixel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PixelDiff {
+    /// Changed pixels in magenta and anti-aliasing in yellow, over a
+    /// dimmed grayscale copy of the current image. The size of the area
+    /// both images cover.
+    pub image: RgbaImage,
+    pub changed_pixels: u64,
+    /// Pixels that differ only by anti-aliasing, always 0 for
+    /// `DiffMethod::Average`
+    pub antialiased_pixels: u64,
+    pub total_pixels: u64,
+}
+
+/// What a pixel comparison found
+enum Change {
+    None,
+    Changed,
+    Antialiased,
+}
+
+/// Decode `content` to 8-bit RGBA, the first frame of an animation.
+///
+/// Fails for images that would need more memory than the default limit,
+/// whatever their header claims.
+pub fn decode_rgba(content: &[u8]) -> ImageResult<RgbaImage> {
+    let decoder = ImageReader::new(Cursor::new(content))
+        .with_guessed_format()?
+        .into_decoder()?;
+    Limits::default().reserve(decoder.total_bytes())?;
+    Ok(DynamicImage::from_decoder(decoder)?.into_rgba8())
+}
+
+/// Compare the top-left area both images cover with `method`. Rows are
+/// split between threads, one per available core.
+pub fn pixel_diff(previous: &RgbaImage, current: &RgbaImage, method: DiffMethod) -> PixelDiff {
+    let width = previous.width().min(current.width());
+    let height = previous.height().min(current.height());
+    let mut image = RgbaImage::new(width, height);
+    let total_pixels = u64::from(width) * u64::from(height);
+    if total_pixels == 0 {
+        return PixelDiff {
+            image,
+            changed_pixels: 0,
+            antialiased_pixels: 0,
+            total_pixels,
+        };
+    }
+
+    let row_bytes = width as usize * 4;
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(height as usize);
+    let rows_per_chunk = (height as usize).div_ceil(threads);
+
+    let (changed_pixels, antialiased_pixels) = thread::scope(|scope| {
+        let workers: Vec<_> = image
+            .chunks_mut(rows_per_chunk * row_bytes)
+            .enumerate()
+            .map(|(chunk, out)| {
+                let first_row = (chunk * rows_per_chunk) as u32;
+                scope.spawn(move || diff_rows(previous, current, first_row, out, method))
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("diff worker panicked"))
+            .fold((0, 0), |total, counts| {
+                (total.0 + counts.0, total.1 + counts.1)
+            })
+    });
+
+    PixelDiff {
+        image,
+        changed_pixels,
+        antialiased_pixels,
+        total_pixels,
+    }
+}
+
+/// Fill the rows of `out`, starting at `first_row`, and count the changed
+/// and anti-aliased pixels among them
+fn diff_rows(
+    previous: &RgbaImage,
+    current: &RgbaImage,
+    first_row: u32,
+    out: &mut [u8],
+    method: DiffMethod,
+) -> (u64, u64) {
+    let width = previous.width().min(current.width());
+    let height = previous.height().min(current.height());
+    let mut counts = (0, 0);
+
+    for (y, out_row) in (first_row..).zip(out.chunks_exact_mut(width as usize * 4)) {
+        for (x, out) in (0..width).zip(out_row.chunks_exact_mut(4)) {
+            let a = previous.get_pixel(x, y).0;
+            let b = current.get_pixel(x, y).0;
+            let change = match method {
+                DiffMethod::Average { threshold } => average_change(a, b, threshold),
+                DiffMethod::Perceptual { threshold } => {
+                    perceptual_change(previous, current, x, y, width, height, threshold)
+                }
+            };
+
+            match change {
+                Change::None => {
+                    let gray = dimmed_gray(b[0], b[1], b[2]);
+                    out.copy_from_slice(&[gray, gray, gray, 255]);
+                }
+                Change::Changed => {
+                    counts.0 += 1;
+                    out.copy_from_slice(&CHANGED);
+                }
+                Change::Antialiased => {
+                    counts.1 += 1;
+                    out.copy_from_slice(&ANTIALIASED);
+                }
+            }
+        }
+    }
+    counts
+}
+
+/// How far apart two pixels are in the units of `method`'s threshold, so
+/// a pixel counts as changed when this is above it. Anti-aliasing is not
+/// looked at.
+pub(super) fn pixel_delta(a: [u8; 4], b: [u8; 4], method: DiffMethod) -> f64 {
+    match method {
+        DiffMethod::Average { .. } => average_delta(a, b),
+        DiffMethod::Perceptual { .. } => {
+            perceptual::threshold_for(perceptual::color_delta(a, b, false))
+        }
+    }
+}
+
+fn average_delta(a: [u8; 4], b: [u8; 4]) -> f64 {
+    let delta =
+        a[0].abs_diff(b[0]) as u32 + a[1].abs_diff(b[1]) as u32 + a[2].abs_diff(b[2]) as u32;
+    f64::from(delta) / 3.0
+}
+
+fn average_change(a: [u8; 4], b: [u8; 4], threshold: f64) -> Change {
+    if average_delta(a, b) > threshold {
+        Change::Changed
+    } else {
+        Change::None
+    }
+}
+
+fn perceptual_change(
+    previous: &RgbaImage,
+    current: &RgbaImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    threshold: f64,
+) -> Change {
+    let delta =
+        perceptual::color_delta(previous.get_pixel(x, y).0, current.get_pixel(x, y).0, false);
+    if delta.abs() <= perceptual::max_delta(threshold) {
+        Change::None
+    } else if perceptual::antialiased(previous, current, x, y, width, height)
+        || perceptual::antialiased(current, previous, x, y, width, height)
+    {
+        Change::Antialiased
+    } else {
+        Change::Changed
+    }
+}
+
+/// Luma of an unchanged pixel at half brightness. The arithmetic matches
+/// the webview's `Math.round(luma) * 0.5` stored into a
+/// `Uint8ClampedArray`, which rounds halves to even.
+fn dimmed_gray(r: u8, g: u8, b: u8) -> u8 {
+    let luma = (0.299 * f64::from(r) + 0.587 * f64::from(g) + 0.114 * f64::from(b)).round();
+    (luma * 0.5).round_ties_even() as u8
+}
+
+/// Encode a diff image as PNG, trading size for speed since it is only
+/// shown once
+pub fn encode_png(image: &RgbaImage) -> ImageResult<Vec<u8>> {
+    let mut content = Vec::new();
+    let encoder =
+        PngEncoder::new_with_quality(&mut content, CompressionType::Fast, FilterType::Adaptive);
+    image.write_with_encoder(encoder)?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn image(width: u32, height: u32, pixels: &[[u8; 4]]) -> RgbaImage {
+        RgbaImage::from_raw(width, height, pixels.concat()).unwrap()
+    }
+
+    fn filled(width: u32, height: u32, fill: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba(fill))
+    }
+
+    fn average(threshold: f64) -> DiffMethod {
+        DiffMethod::Average { threshold }
+    }
+
+    fn perceptual(threshold: f64) -> DiffMethod {
+        DiffMethod::Perceptual { threshold }
+    }
+
+    #[test]
+    fn test_identical_images_have_no_changes() {
+        let a = filled(2, 2, [100, 100, 100, 255]);
+        let diff = pixel_diff(&a, &a.clone(), average(10.0));
+        assert_eq!(diff.changed_pixels, 0);
+        assert_eq!(diff.total_pixels, 4);
+    }
+
+    #[test]
+    fn test_every_pixel_of_opposite_images_changes() {
+        let diff = pixel_diff(
+            &filled(2, 2, [0, 0, 0, 255]),
+            &filled(2, 2, [255, 255, 255, 255]),
+            average(10.0),
+        );
+        assert_eq!(diff.changed_pixels, 4);
+        assert!(diff.image.pixels().all(|p| p.0 == CHANGED));
+    }
+
+    #[test]
+    fn test_threshold_ignores_small_differences() {
+        let a = filled(1, 1, [100, 100, 100, 255]);
+        let b = filled(1, 1, [105, 105, 105, 255]);
+        assert_eq!(pixel_diff(&a, &b, average(10.0)).changed_pixels, 0);
+        // A difference equal to the threshold does not count
+        assert_eq!(pixel_diff(&a, &b, average(5.0)).changed_pixels, 0);
+        assert_eq!(pixel_diff(&a, &b, average(2.0)).changed_pixels, 1);
+    }
+
+    #[test]
+    fn test_uses_the_area_both_images_cover() {
+        let diff = pixel_diff(
+            &filled(3, 5, [0, 0, 0, 255]),
+            &filled(5, 3, [255, 255, 255, 255]),
+            average(10.0),
+        );
+        assert_eq!(diff.image.dimensions(), (3, 3));
+        assert_eq!(diff.total_pixels, 9);
+        assert_eq!(diff.changed_pixels, 9);
+    }
+
+    #[test]
+    fn test_empty_image_has_no_pixels() {
+        let diff = pixel_diff(&filled(0, 4, [0; 4]), &filled(4, 4, [0; 4]), average(10.0));
+        assert_eq!(diff.total_pixels, 0);
+        assert_eq!(diff.image.dimensions(), (0, 4));
+    }
+
+    // Expected values below are the output of the former TypeScript
+    // `computePixelDiff` for the same input
+
+    #[test]
+    fn test_matches_the_webview_implementation() {
+        let previous = image(
+            4,
+            2,
+            &[
+                [1, 1, 1, 255],
+                [3, 3, 3, 255],
+                [5, 5, 5, 0],
+                [9, 9, 9, 255],
+                [100, 100, 100, 255],
+                [0, 0, 0, 255],
+                [200, 10, 40, 128],
+                [7, 7, 7, 7],
+            ],
+        );
+        let current = image(
+            3,
+            3,
+            &[
+                [1, 1, 1, 255],
+                [3, 3, 3, 255],
+                [5, 5, 5, 255],
+                [110, 110, 110, 255],
+                [10, 11, 9, 255],
+                [180, 30, 61, 255],
+                [50, 50, 50, 255],
+                [60, 60, 60, 255],
+                [70, 70, 70, 255],
+            ],
+        );
+
+        let diff = pixel_diff(&previous, &current, average(10.0));
+        assert_eq!(diff.changed_pixels, 1);
+        assert_eq!(diff.total_pixels, 6);
+        assert_eq!(
+            diff.image,
+            image(
+                3,
+                2,
+                &[
+                    [0, 0, 0, 255],
+                    [2, 2, 2, 255],
+                    [2, 2, 2, 255],
+                    [55, 55, 55, 255],
+                    [5, 5, 5, 255],
+                    CHANGED,
+                ],
+            )
+        );
+
+        let diff = pixel_diff(&previous, &current, average(0.0));
+        assert_eq!(diff.changed_pixels, 3);
+        assert_eq!(diff.image.get_pixel(0, 1).0, CHANGED);
+    }
+
+    #[test]
+    fn test_matches_the_webview_implementation_across_gray_levels() {
+        let previous = RgbaImage::from_fn(256, 1, |x, _| {
+            let v = x as u8;
+            Rgba([v, v, v, 255])
+        });
+        let current = RgbaImage::from_fn(256, 1, |x, _| {
+            Rgba([x as u8, (x * 7 % 256) as u8, (x * 13 % 256) as u8, 255])
+        });
+
+        let diff = pixel_diff(&previous, &current, average(40.0));
+
+        let reds: Vec<u8> = diff.image.pixels().map(|p| p[0]).collect();
+        let expected: Vec<u8> = [
+            &[0, 3, 6, 9, 12, 14, 18][..],
+            &[255; 33],
+            &[14, 16, 20, 22, 26, 28, 31, 34, 37, 40],
+            &[255; 29],
+            &[24, 27, 30, 33, 36, 38, 42, 44, 48, 50, 54, 56, 60, 62],
+            &[255; 29],
+            &[46, 50, 52, 55, 58, 61, 64, 67, 70, 73, 76, 78, 82],
+            &[255; 29],
+            &[66, 68, 72, 74, 78, 80, 84, 86, 90, 92, 95, 98, 101, 104],
+            &[255; 29],
+            &[88, 91, 94, 97, 100, 102, 106, 108, 112, 114],
+            &[255; 33],
+            &[110, 114, 116, 119, 122, 125],
+        ]
+        .concat();
+        assert_eq!(reds, expected);
+        assert_eq!(diff.changed_pixels, 182);
+    }
+
+    #[test]
+    fn test_splitting_rows_gives_the_same_result() {
+        let previous = RgbaImage::from_fn(7, 97, |x, y| Rgba([(x * y) as u8, 0, 0, 255]));
+        let current = RgbaImage::from_fn(9, 101, |x, y| Rgba([(x + y) as u8, 0, 0, 255]));
+
+        for method in [average(10.0), perceptual(0.1)] {
+            let diff = pixel_diff(&previous, &current, method);
+
+            let mut expected = RgbaImage::new(7, 97);
+            let counts = diff_rows(&previous, &current, 0, &mut expected, method);
+            assert_eq!((diff.changed_pixels, diff.antialiased_pixels), counts);
+            assert_eq!(diff.image, expected);
+        }
+    }
+
+    /// Black on the left, white on the right, with column 2 filled with
+    /// `edge`
+    fn edge(edge: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_fn(5, 5, |x, _| match x {
+            0 | 1 => Rgba([0, 0, 0, 255]),
+            2 => Rgba(edge),
+            _ => Rgba([255, 255, 255, 255]),
+        })
+    }
+
+    #[test]
+    fn test_perceptual_counts_antialiasing_apart() {
+        let sharp = edge([255, 255, 255, 255]);
+        let smooth = edge([128, 128, 128, 255]);
+
+        let diff = pixel_diff(&sharp, &smooth, perceptual(0.1));
+        assert_eq!((diff.changed_pixels, diff.antialiased_pixels), (0, 5));
+        assert_eq!(diff.image.get_pixel(2, 0).0, ANTIALIASED);
+
+        // The average method has no notion of anti-aliasing
+        let diff = pixel_diff(&sharp, &smooth, average(10.0));
+        assert_eq!((diff.changed_pixels, diff.antialiased_pixels), (5, 0));
+    }
+
+    #[test]
+    fn test_perceptual_counts_a_changed_area() {
+        let white = filled(5, 5, [255, 255, 255, 255]);
+        let mut patched = white.clone();
+        for (x, y) in (1..4).flat_map(|x| (1..4).map(move |y| (x, y))) {
+            patched.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        }
+
+        let diff = pixel_diff(&white, &patched, perceptual(0.1));
+
+        assert_eq!((diff.changed_pixels, diff.antialiased_pixels), (9, 0));
+        assert_eq!(diff.image.get_pixel(2, 2).0, CHANGED);
+    }
+
+    #[test]
+    fn test_perceptual_threshold_scales_the_distance() {
+        let black = filled(1, 1, [0, 0, 0, 255]);
+        let white = filled(1, 1, [255, 255, 255, 255]);
+        assert_eq!(
+            pixel_diff(&black, &white, perceptual(0.9)).changed_pixels,
+            1
+        );
+        assert_eq!(
+            pixel_diff(&black, &white, perceptual(1.0)).changed_pixels,
+            0
+        );
+
+        let gray = filled(1, 1, [100, 100, 100, 255]);
+        let lighter = filled(1, 1, [105, 105, 105, 255]);
+        assert_eq!(
+            pixel_diff(&gray, &lighter, perceptual(0.1)).changed_pixels,
+            0
+        );
+        assert_eq!(
+            pixel_diff(&gray, &lighter, perceptual(0.01)).changed_pixels,
+            1
+        );
+    }
+
+    #[test]
+    fn test_diff_png_round_trips() {
+        let diff = pixel_diff(
+            &filled(3, 2, [0, 0, 0, 255]),
+            &filled(3, 2, [40, 40, 40, 255]),
+            average(10.0),
+        );
+        let png = encode_png(&diff.image).unwrap();
+        assert_eq!(decode_rgba(&png).unwrap(), diff.image);
+    }
+}
@@ -0,0 +1,321 @@
+//! Header and pixel inspection of encoded images.
+
+use std::io::Cursor;
+
+use image::{
+    AnimationDecoder, ExtendedColorType, ImageDecoder, ImageFormat, ImageReader, ImageResult,
+    Limits,
+};
+use serde::{Deserialize, Serialize};
+
+/// Width and height in pixels
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Dimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Channels the pixels are stored with, before any conversion for display
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorKind {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+    /// Only an alpha channel
+    Alpha,
+    Cmyk,
+    /// Palette indices or another layout the decoder does not expand
+    Unknown,
+}
+
+/// What an image is made of, as read by its decoder
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImageInfo {
+    /// `png`, `jpeg`, `gif`, `webp`, `bmp` or `ico`, from the content
+    /// rather than the file name
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    pub color: ColorKind,
+    /// Bits per channel
+    pub bit_depth: u8,
+    pub has_alpha: bool,
+    /// 1 for still images
+    pub frame_count: u32,
+    /// Why the pixels could not be decoded, for files whose header is
+    /// readable but whose data is truncated or corrupt. `frame_count`
+    /// then only counts the frames before the error.
+    pub decode_error: Option<String>,
+}
+
+/// Read the header of `content` and decode every frame, to tell whether
+/// it is a valid image.
+///
+/// Fails when the format is not recognised or the header cannot be read;
+/// errors in the pixel data are reported in `decode_error` instead.
+pub fn inspect(content: &[u8]) -> ImageResult<ImageInfo> {
+    let format = image::guess_format(content)?;
+    let reader = Cursor::new(content);
+
+    match format {
+        ImageFormat::Gif => {
+            let mut decoder = image::codecs::gif::GifDecoder::new(reader)?;
+            decoder.set_limits(Limits::default())?;
+            let info = header(format, &decoder);
+            Ok(with_frames(info, decoder.into_frames()))
+        }
+        ImageFormat::Png => {
+            let decoder = image::codecs::png::PngDecoder::with_limits(reader, Limits::default())?;
+            let info = header(format, &decoder);
+            if decoder.is_apng()? {
+                Ok(with_frames(info, decoder.apng()?.into_frames()))
+            } else {
+                Ok(with_still(info, decoder))
+            }
+        }
+        ImageFormat::WebP => {
+            let mut decoder = image::codecs::webp::WebPDecoder::new(reader)?;
+            decoder.set_limits(Limits::default())?;
+            let info = header(format, &decoder);
+            if decoder.has_animation() {
+                Ok(with_frames(info, decoder.into_frames()))
+            } else {
+                Ok(with_still(info, decoder))
+            }
+        }
+        _ => {
+            // Applies the default limits itself
+            let decoder = ImageReader::with_format(reader, format).into_decoder()?;
+            let info = header(format, &decoder);
+            Ok(with_still(info, decoder))
+        }
+    }
+}
+
+/// Width and height from the header of `content`, without decoding the
+/// pixels. `None` if it is not an image this module can read.
+pub fn dimensions(content: &[u8]) -> Option<Dimensions> {
+    let (width, height) = ImageReader::new(Cursor::new(content))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()?;
+    Some(Dimensions { width, height })
+}
+
+/// Everything but the frame count, from the decoder's header
+fn header(format: ImageFormat, decoder: &impl ImageDecoder) -> ImageInfo {
+    let (width, height) = decoder.dimensions();
+    let color_type = decoder.original_color_type();
+    let color = color_kind(color_type);
+
+    ImageInfo {
+        format: format_name(format).to_string(),
+        width,
+        height,
+        color,
+        bit_depth: (color_type.bits_per_pixel() / u16::from(color_type.channel_count().max(1)))
+            as u8,
+        has_alpha: matches!(
+            color,
+            ColorKind::GrayAlpha | ColorKind::Rgba | ColorKind::Alpha
+        ),
+        frame_count: 0,
+        decode_error: None,
+    }
+}
+
+/// Decode the only frame of a still image
+fn with_still(mut info: ImageInfo, decoder: impl ImageDecoder) -> ImageInfo {
+    // The header can claim any size; refuse more than the default limit
+    // before allocating for it
+    let decoded = Limits::default()
+        .reserve(decoder.total_bytes())
+        .and_then(|()| {
+            let mut buffer = vec![0; decoder.total_bytes() as usize];
+            decoder.read_image(&mut buffer)
+        });
+    match decoded {
+        Ok(()) => info.frame_count = 1,
+        Err(e) => info.decode_error = Some(e.to_string()),
+    }
+    info
+}
+
+/// Decode the frames of an animation one by one, stopping at the first
+/// broken one
+fn with_frames(mut info: ImageInfo, frames: image::Frames) -> ImageInfo {
+    for frame in frames {
+        match frame {
+            Ok(_) => info.frame_count += 1,
+            Err(e) => {
+                info.decode_error = Some(e.to_string());
+                break;
+            }
+        }
+    }
+    info
+}
+
+fn color_kind(color_type: ExtendedColorType) -> ColorKind {
+    use ExtendedColorType::*;
+
+    match color_type {
+        L1 | L2 | L4 | L8 | L16 => ColorKind::Gray,
+        La1 | La2 | La4 | La8 | La16 => ColorKind::GrayAlpha,
+        Rgb1 | Rgb2 | Rgb4 | Rgb5x1 | Rgb8 | Rgb16 | Bgr8 | Rgb32F => ColorKind::Rgb,
+        Rgba1 | Rgba2 | Rgba4 | Rgba8 | Rgba16 | Bgra8 | Rgba32F => ColorKind::Rgba,
+        A8 => ColorKind::Alpha,
+        Cmyk8 | Cmyk16 => ColorKind::Cmyk,
+        _ => ColorKind::Unknown,
+    }
+}
+
+fn format_name(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Png => "png",
+        ImageFormat::Jpeg => "jpeg",
+        ImageFormat::Gif => "gif",
+        ImageFormat::WebP => "webp",
+        ImageFormat::Bmp => "bmp",
+        ImageFormat::Ico => "ico",
+        // Recognised by its magic bytes, but no decoder is built in, so
+        // inspect() has failed before this is reached
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::{DynamicImage, Frame, GrayImage, ImageBuffer, Luma, Rgba, RgbaImage};
+
+    fn encode(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut content = Cursor::new(Vec::new());
+        image.write_to(&mut content, format).unwrap();
+        content.into_inner()
+    }
+
+    fn rgba_png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 128]));
+        encode(&DynamicImage::ImageRgba8(image), ImageFormat::Png)
+    }
+
+    #[test]
+    fn test_inspect_png() {
+        assert_eq!(
+            inspect(&rgba_png(3, 2)).unwrap(),
+            ImageInfo {
+                format: "png".to_string(),
+                width: 3,
+                height: 2,
+                color: ColorKind::Rgba,
+                bit_depth: 8,
+                has_alpha: true,
+                frame_count: 1,
+                decode_error: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_inspect_reports_bit_depth_and_color() {
+        let gray16 = ImageBuffer::<Luma<u16>, _>::from_pixel(4, 4, Luma([40_000]));
+        let info = inspect(&encode(
+            &DynamicImage::ImageLuma16(gray16),
+            ImageFormat::Png,
+        ))
+        .unwrap();
+        assert_eq!(
+            (info.color, info.bit_depth, info.has_alpha),
+            (ColorKind::Gray, 16, false)
+        );
+
+        let gray = GrayImage::from_pixel(8, 8, Luma([7]));
+        let info = inspect(&encode(&DynamicImage::ImageLuma8(gray), ImageFormat::Jpeg)).unwrap();
+        assert_eq!(info.format, "jpeg");
+        assert_eq!((info.color, info.bit_depth), (ColorKind::Gray, 8));
+    }
+
+    #[test]
+    fn test_inspect_counts_animation_frames() {
+        let mut content = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut content);
+            for shade in [0, 128, 255] {
+                let image = RgbaImage::from_pixel(5, 4, Rgba([shade, shade, shade, 255]));
+                encoder.encode_frame(Frame::new(image)).unwrap();
+            }
+        }
+
+        let info = inspect(&content).unwrap();
+
+        assert_eq!(info.format, "gif");
+        assert_eq!((info.width, info.height), (5, 4));
+        assert_eq!(info.frame_count, 3);
+        assert_eq!(info.decode_error, None);
+    }
+
+    #[test]
+    fn test_inspect_truncated_image_keeps_its_header() {
+        let content = rgba_png(64, 64);
+
+        let info = inspect(&content[..content.len() / 2]).unwrap();
+
+        assert_eq!((info.width, info.height), (64, 64));
+        assert_eq!(info.frame_count, 0);
+        assert!(info.decode_error.is_some());
+    }
+
+    #[test]
+    fn test_inspect_does_not_allocate_for_a_huge_header() {
+        // A BMP header for a 30000x30000 canvas, with no pixels after it
+        let mut content = b"BM".to_vec();
+        content.extend_from_slice(&[0; 8]);
+        content.extend_from_slice(&54u32.to_le_bytes());
+        content.extend_from_slice(&40u32.to_le_bytes());
+        content.extend_from_slice(&30_000i32.to_le_bytes());
+        content.extend_from_slice(&30_000i32.to_le_bytes());
+        content.extend_from_slice(&1u16.to_le_bytes());
+        content.extend_from_slice(&24u16.to_le_bytes());
+        content.extend_from_slice(&[0; 24]);
+
+        let info = inspect(&content).unwrap();
+
+        assert_eq!((info.width, info.height), (30_000, 30_000));
+        assert!(info.decode_error.is_some());
+    }
+
+    #[test]
+    fn test_inspect_rejects_what_it_cannot_read() {
+        for content in [
+            b"not an image".as_slice(),
+            b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>",
+            // A PNG signature with no header after it
+            b"\x89PNG\r\n\x1a\n",
+        ] {
+            assert!(
+                inspect(content).is_err(),
+                "for {:?}",
+                String::from_utf8_lossy(content)
+            );
+        }
+    }
+
+    #[test]
+    fn test_dimensions_reads_only_the_header() {
+        let content = rgba_png(7, 9);
+
+        assert_eq!(
+            dimensions(&content[..64]),
+            Some(Dimensions {
+                width: 7,
+                height: 9
+            })
+        );
+        assert_eq!(dimensions(b"GIF89a"), None);
+    }
+}
@@ -0,0 +1,17 @@
+//! Decoding of image content in the backend.
+//!
+//! The webview decodes images for display; this module answers questions
+//! about them without a round trip through the frontend, with the
+//! pure-Rust `image` crate. PNG, JPEG, GIF, WebP, BMP and ICO can be
+//! decoded; SVG and PSD are left to the webview.
+
+mod diff;
+mod inspect;
+mod perceptual;
+mod regions;
+mod similarity;
+
+pub use diff::{decode_rgba, encode_png, pixel_diff, DiffMethod};
+pub use inspect::{dimensions, inspect, ColorKind, Dimensions, ImageInfo};
+pub use regions::{changed_regions, Region, RegionOptions};
+pub use similarity::{similarity, Similarity, SsimMap};
@@ -0,0 +1,210 @@
+//! Perceptual color distance and anti-aliasing detection, after
+//! [pixelmatch](https://github.com/mapbox/pixelmatch).
+//!
+//! Colors are compared in YIQ space, weighted by how sensitive the eye is
+//! to each axis, after blending translucent pixels over white. A pixel
+//! whose distance is above the threshold may still be anti-aliasing: a
+//! pixel between a darker and a brighter neighbor, where one of those
+//! neighbors sits in a flat area of both images. Redrawing an edge with a
+//! different rasterizer changes such pixels without changing the shape.
+
+use image::RgbaImage;
+
+/// Largest possible YIQ distance, between black and white
+const MAX_YIQ_DELTA: f64 = 35215.0;
+
+/// Smallest distance counted as a change for a `threshold` between 0 and 1
+pub fn max_delta(threshold: f64) -> f64 {
+    MAX_YIQ_DELTA * threshold * threshold
+}
+
+/// Threshold at which a distance of `delta` stops counting as a change,
+/// the inverse of `max_delta`
+pub fn threshold_for(delta: f64) -> f64 {
+    (delta.abs() / MAX_YIQ_DELTA).sqrt()
+}
+
+/// Squared YIQ distance between two pixels, negative when `b` is darker.
+/// With `y_only`, the signed difference in brightness alone.
+pub fn color_delta(a: [u8; 4], b: [u8; 4], y_only: bool) -> f64 {
+    if a == b {
+        return 0.0;
+    }
+    let (r1, g1, b1) = over_white(a);
+    let (r2, g2, b2) = over_white(b);
+
+    let y1 = rgb2y(r1, g1, b1);
+    let y2 = rgb2y(r2, g2, b2);
+    let y = y1 - y2;
+    if y_only {
+        return y;
+    }
+    let i = rgb2i(r1, g1, b1) - rgb2i(r2, g2, b2);
+    let q = rgb2q(r1, g1, b1) - rgb2q(r2, g2, b2);
+
+    let delta = 0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q;
+    if y1 > y2 {
+        -delta
+    } else {
+        delta
+    }
+}
+
+/// Whether the pixel at `(x, y)` of `image` looks like anti-aliasing,
+/// given the other version of the image. Only the top-left `width` by
+/// `height` area both images cover is looked at.
+pub fn antialiased(
+    image: &RgbaImage,
+    other: &RgbaImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> bool {
+    let center = image.get_pixel(x, y).0;
+    // Pixels on the border miss neighbors, which count as equal ones
+    let mut zeroes = u32::from(on_border(x, y, width, height));
+    // Most negative and most positive brightness difference, from the
+    // brightest and the darkest neighbor
+    let mut min = (0.0, None);
+    let mut max = (0.0, None);
+
+    for (nx, ny) in neighbors(x, y, width, height) {
+        let delta = color_delta(center, image.get_pixel(nx, ny).0, true);
+        if delta == 0.0 {
+            zeroes += 1;
+            // In a flat area rather than on an edge
+            if zeroes > 2 {
+                return false;
+            }
+        } else if delta < min.0 {
+            min = (delta, Some((nx, ny)));
+        } else if delta > max.0 {
+            max = (delta, Some((nx, ny)));
+        }
+    }
+
+    // An edge pixel lies between a darker and a brighter neighbor, one of
+    // which is part of a flat area in both images
+    let (Some(brighter), Some(darker)) = (min.1, max.1) else {
+        return false;
+    };
+    [brighter, darker].into_iter().any(|(nx, ny)| {
+        has_many_siblings(image, nx, ny, width, height)
+            && has_many_siblings(other, nx, ny, width, height)
+    })
+}
+
+/// Whether more than two neighbors of `(x, y)` have exactly its color
+fn has_many_siblings(image: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> bool {
+    let center = image.get_pixel(x, y);
+    let mut zeroes = u32::from(on_border(x, y, width, height));
+    for (nx, ny) in neighbors(x, y, width, height) {
+        if image.get_pixel(nx, ny) == center {
+            zeroes += 1;
+            if zeroes > 2 {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// The up to eight pixels around `(x, y)`, column by column
+fn neighbors(x: u32, y: u32, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    let xs = x.saturating_sub(1)..=(x + 1).min(width - 1);
+    xs.flat_map(move |nx| (y.saturating_sub(1)..=(y + 1).min(height - 1)).map(move |ny| (nx, ny)))
+        .filter(move |&point| point != (x, y))
+}
+
+fn on_border(x: u32, y: u32, width: u32, height: u32) -> bool {
+    x == 0 || y == 0 || x == width - 1 || y == height - 1
+}
+
+/// RGB of a pixel blended over a white background
+pub(super) fn over_white([r, g, b, a]: [u8; 4]) -> (f64, f64, f64) {
+    let alpha = f64::from(a) / 255.0;
+    let blend = |c: u8| 255.0 + (f64::from(c) - 255.0) * alpha;
+    (blend(r), blend(g), blend(b))
+}
+
+fn rgb2y(r: f64, g: f64, b: f64) -> f64 {
+    r * 0.29889531 + g * 0.58662247 + b * 0.11448223
+}
+
+fn rgb2i(r: f64, g: f64, b: f64) -> f64 {
+    r * 0.59597799 - g * 0.27417610 - b * 0.32180189
+}
+
+fn rgb2q(r: f64, g: f64, b: f64) -> f64 {
+    r * 0.21147017 - g * 0.52261711 + b * 0.31114694
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    #[test]
+    fn test_color_delta_is_signed_by_brightness() {
+        let delta = color_delta(BLACK, WHITE, false);
+        assert!((delta - 32857.0).abs() < 1.0, "{}", delta);
+        assert_eq!(color_delta(WHITE, BLACK, false), -delta);
+        assert_eq!(color_delta(WHITE, WHITE, false), 0.0);
+        assert!(color_delta(WHITE, BLACK, true) > 254.0);
+    }
+
+    #[test]
+    fn test_transparent_pixels_are_blended_over_white() {
+        assert!(color_delta([0, 0, 0, 0], WHITE, false).abs() < 1e-6);
+        assert!(color_delta([255, 0, 0, 0], [0, 0, 255, 0], false).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_max_delta_scales_with_the_square_of_the_threshold() {
+        assert_eq!(max_delta(1.0), MAX_YIQ_DELTA);
+        assert!((max_delta(0.1) - 352.15).abs() < 1e-9);
+        assert!((threshold_for(-max_delta(0.3)) - 0.3).abs() < 1e-12);
+    }
+
+    /// Black on the left, white on the right, with column 2 filled with
+    /// `edge` in every row
+    fn edge(edge: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_fn(5, 5, |x, _| match x {
+            0 | 1 => Rgba(BLACK),
+            2 => Rgba(edge),
+            _ => Rgba(WHITE),
+        })
+    }
+
+    #[test]
+    fn test_gray_between_flat_areas_is_antialiasing() {
+        let sharp = edge(WHITE);
+        let smooth = edge([128, 128, 128, 255]);
+
+        assert!(antialiased(&smooth, &sharp, 2, 2, 5, 5));
+        // On the sharp side the pixel is part of the white area
+        assert!(!antialiased(&sharp, &smooth, 2, 2, 5, 5));
+    }
+
+    #[test]
+    fn test_pixel_in_a_flat_area_is_not_antialiasing() {
+        let flat = RgbaImage::from_pixel(5, 5, Rgba(WHITE));
+        let mut spotted = flat.clone();
+        spotted.put_pixel(2, 2, Rgba(BLACK));
+
+        assert!(!antialiased(&spotted, &flat, 2, 2, 5, 5));
+        assert!(!antialiased(&flat, &spotted, 2, 2, 5, 5));
+    }
+
+    #[test]
+    fn test_neighbors_stay_inside_the_area() {
+        assert_eq!(neighbors(0, 0, 3, 3).count(), 3);
+        assert_eq!(neighbors(1, 1, 3, 3).count(), 8);
+        assert_eq!(neighbors(2, 1, 3, 3).count(), 5);
+        assert_eq!(neighbors(0, 0, 1, 1).count(), 0);
+    }
+}
@@ -0,0 +1,392 @@
+//! Clusters of changed pixels, so a small change in a large image can be
+//! found without scanning the whole diff.
+//!
+//! Changed pixels that touch, sideways or diagonally, form a region. Then
+//! regions whose bounding boxes come within the merge distance of each
+//! other are merged, until no two are that close, and regions with fewer
+//! pixels than the minimum are dropped. Anti-aliased pixels are not
+//! changes and belong to no region.
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use super::diff::{pixel_delta, DiffMethod, PixelDiff, CHANGED};
+
+/// How changed pixels are clustered into regions
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RegionOptions {
+    /// Regions with fewer changed pixels are left out
+    pub min_pixels: u64,
+    /// Regions whose bounding boxes are at most this many pixels apart
+    /// are merged; 0 merges only boxes that touch or overlap
+    pub merge_distance: u32,
+}
+
+impl Default for RegionOptions {
+    fn default() -> Self {
+        Self {
+            min_pixels: 4,
+            merge_distance: 8,
+        }
+    }
+}
+
+/// A cluster of changed pixels
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Region {
+    /// Bounding box of the changed pixels, in pixels of the diff image
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Changed pixels inside the region, not the area of its box
+    pub pixels: u64,
+    /// Mean difference of those pixels, in the units of the method's
+    /// threshold: RGB levels for `average`, 0-1 for `perceptual`
+    pub mean_delta: f64,
+}
+
+/// A region being built, with its box edges inclusive
+#[derive(Debug, Clone, Copy)]
+struct Cluster {
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+    pixels: u64,
+    delta_sum: f64,
+}
+
+impl Cluster {
+    fn new(x: u32, y: u32) -> Self {
+        Self {
+            left: x,
+            top: y,
+            right: x,
+            bottom: y,
+            pixels: 0,
+            delta_sum: 0.0,
+        }
+    }
+
+    fn add(&mut self, x: u32, y: u32, delta: f64) {
+        self.left = self.left.min(x);
+        self.top = self.top.min(y);
+        self.right = self.right.max(x);
+        self.bottom = self.bottom.max(y);
+        self.pixels += 1;
+        self.delta_sum += delta;
+    }
+
+    fn absorb(&mut self, other: &Cluster) {
+        self.left = self.left.min(other.left);
+        self.top = self.top.min(other.top);
+        self.right = self.right.max(other.right);
+        self.bottom = self.bottom.max(other.bottom);
+        self.pixels += other.pixels;
+        self.delta_sum += other.delta_sum;
+    }
+
+    /// Whether the boxes are at most `distance` pixels apart on both axes
+    fn near(&self, other: &Cluster, distance: u32) -> bool {
+        gap(self.left, self.right, other.left, other.right) <= distance
+            && gap(self.top, self.bottom, other.top, other.bottom) <= distance
+    }
+
+    fn region(&self) -> Region {
+        Region {
+            x: self.left,
+            y: self.top,
+            width: self.right - self.left + 1,
+            height: self.bottom - self.top + 1,
+            pixels: self.pixels,
+            mean_delta: self.delta_sum / self.pixels as f64,
+        }
+    }
+}
+
+/// Pixels strictly between two ranges, 0 if they touch or overlap
+fn gap(start: u32, end: u32, other_start: u32, other_end: u32) -> u32 {
+    if other_start > end {
+        other_start - end - 1
+    } else if start > other_end {
+        start - other_end - 1
+    } else {
+        0
+    }
+}
+
+/// Regions of the pixels `diff` marked as changed between `previous` and
+/// `current`, top to bottom and then left to right
+pub fn changed_regions(
+    previous: &RgbaImage,
+    current: &RgbaImage,
+    diff: &PixelDiff,
+    method: DiffMethod,
+    options: RegionOptions,
+) -> Vec<Region> {
+    let clusters = merge_nearby(
+        connected(previous, current, diff, method),
+        options.merge_distance,
+    );
+
+    let mut regions: Vec<Region> = clusters
+        .iter()
+        .filter(|cluster| cluster.pixels >= options.min_pixels)
+        .map(Cluster::region)
+        .collect();
+    regions.sort_by_key(|region| (region.y, region.x));
+    regions
+}
+
+/// Groups of changed pixels that touch, found by flood fill
+fn connected(
+    previous: &RgbaImage,
+    current: &RgbaImage,
+    diff: &PixelDiff,
+    method: DiffMethod,
+) -> Vec<Cluster> {
+    let image = &diff.image;
+    let (width, height) = image.dimensions();
+    let index = |x: u32, y: u32| y as usize * width as usize + x as usize;
+    let mut visited = vec![false; width as usize * height as usize];
+    let mut clusters = Vec::new();
+    let mut stack = Vec::new();
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel.0 != CHANGED || visited[index(x, y)] {
+            continue;
+        }
+
+        let mut cluster = Cluster::new(x, y);
+        visited[index(x, y)] = true;
+        stack.push((x, y));
+        while let Some((x, y)) = stack.pop() {
+            let delta = pixel_delta(
+                previous.get_pixel(x, y).0,
+                current.get_pixel(x, y).0,
+                method,
+            );
+            cluster.add(x, y, delta);
+
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    if !visited[index(nx, ny)] && image.get_pixel(nx, ny).0 == CHANGED {
+                        visited[index(nx, ny)] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+        clusters.push(cluster);
+    }
+    clusters
+}
+
+/// Merge clusters whose boxes are within `distance` of each other, until
+/// none are. Each pass sweeps the clusters from left to right, comparing
+/// each only with those whose right edge is still within reach; a merge
+/// grows a box, which can bring it near another, so passes repeat until
+/// one merges nothing.
+fn merge_nearby(mut clusters: Vec<Cluster>, distance: u32) -> Vec<Cluster> {
+    loop {
+        let count = clusters.len();
+        clusters.sort_by_key(|cluster| cluster.left);
+
+        let mut merged: Vec<Cluster> = Vec::with_capacity(count);
+        // Indices into `merged` that later clusters can still reach. A
+        // merged cluster's left edge never moves, so `merged` stays sorted.
+        let mut reachable: Vec<usize> = Vec::new();
+        for cluster in clusters {
+            reachable.retain(|&i| {
+                u64::from(merged[i].right) + u64::from(distance) + 1 >= u64::from(cluster.left)
+            });
+            match reachable
+                .iter()
+                .find(|&&i| merged[i].near(&cluster, distance))
+            {
+                Some(&i) => merged[i].absorb(&cluster),
+                None => {
+                    reachable.push(merged.len());
+                    merged.push(cluster);
+                }
+            }
+        }
+
+        if merged.len() == count {
+            return merged;
+        }
+        clusters = merged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imaging::pixel_diff;
+    use image::Rgba;
+
+    const AVERAGE: DiffMethod = DiffMethod::Average { threshold: 10.0 };
+
+    /// A white image of `width` by `height` with the given pixels painted
+    /// in `color`
+    fn painted(width: u32, height: u32, pixels: &[(u32, u32)], color: [u8; 4]) -> RgbaImage {
+        let mut image = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+        for &(x, y) in pixels {
+            image.put_pixel(x, y, Rgba(color));
+        }
+        image
+    }
+
+    /// Every pixel of the box from `(x, y)`, `width` by `height`
+    fn block(x: u32, y: u32, width: u32, height: u32) -> Vec<(u32, u32)> {
+        (y..y + height)
+            .flat_map(|py| (x..x + width).map(move |px| (px, py)))
+            .collect()
+    }
+
+    fn regions(
+        width: u32,
+        height: u32,
+        pixels: &[(u32, u32)],
+        min_pixels: u64,
+        merge_distance: u32,
+    ) -> Vec<Region> {
+        let previous = painted(width, height, &[], [0; 4]);
+        let current = painted(width, height, pixels, [0, 0, 0, 255]);
+        let diff = pixel_diff(&previous, &current, AVERAGE);
+        let options = RegionOptions {
+            min_pixels,
+            merge_distance,
+        };
+        changed_regions(&previous, &current, &diff, AVERAGE, options)
+    }
+
+    fn boxes(regions: &[Region]) -> Vec<(u32, u32, u32, u32, u64)> {
+        regions
+            .iter()
+            .map(|r| (r.x, r.y, r.width, r.height, r.pixels))
+            .collect()
+    }
+
+    #[test]
+    fn test_unchanged_image_has_no_regions() {
+        assert!(regions(16, 16, &[], 1, 0).is_empty());
+    }
+
+    #[test]
+    fn test_touching_pixels_form_one_region() {
+        // An L shape and a diagonal step
+        let mut pixels = block(1, 1, 1, 3);
+        pixels.extend([(2, 3), (3, 3), (10, 10), (11, 11), (12, 12)]);
+
+        let found = regions(16, 16, &pixels, 1, 0);
+
+        assert_eq!(boxes(&found), [(1, 1, 3, 3, 5), (10, 10, 3, 3, 3)]);
+        assert_eq!(found[0].mean_delta, 255.0);
+    }
+
+    #[test]
+    fn test_merge_distance_joins_nearby_regions() {
+        // Two blocks with a gap of 3 pixels between them
+        let mut pixels = block(0, 0, 2, 2);
+        pixels.extend(block(5, 0, 2, 2));
+
+        assert_eq!(regions(10, 4, &pixels, 1, 2).len(), 2);
+        assert_eq!(boxes(&regions(10, 4, &pixels, 1, 3)), [(0, 0, 7, 2, 8)]);
+    }
+
+    #[test]
+    fn test_merging_repeats_until_no_boxes_are_near() {
+        // The last two merge into a box that reaches the first, which
+        // was too far from either on its own
+        let pixels = [(0, 0), (2, 4), (3, 2)];
+
+        let found = regions(8, 8, &pixels, 1, 1);
+
+        assert_eq!(boxes(&found), [(0, 0, 4, 5, 3)]);
+    }
+
+    #[test]
+    fn test_min_pixels_drops_small_regions_after_merging() {
+        let mut pixels = block(0, 0, 3, 3);
+        pixels.push((5, 0));
+        pixels.push((15, 15));
+
+        let found = regions(16, 16, &pixels, 2, 2);
+
+        // The single pixel near the block joins it; the far one is dropped
+        assert_eq!(boxes(&found), [(0, 0, 6, 3, 10)]);
+    }
+
+    #[test]
+    fn test_regions_are_sorted_top_to_bottom_then_left_to_right() {
+        let pixels = [(12, 9), (1, 9), (7, 2)];
+
+        let found = regions(16, 16, &pixels, 1, 0);
+
+        assert_eq!(
+            found.iter().map(|r| (r.x, r.y)).collect::<Vec<_>>(),
+            [(7, 2), (1, 9), (12, 9)]
+        );
+    }
+
+    #[test]
+    fn test_mean_delta_uses_the_method_units() {
+        let previous = painted(5, 5, &[], [0; 4]);
+        let mut current = previous.clone();
+        current.put_pixel(0, 0, Rgba([155, 155, 155, 255]));
+        current.put_pixel(1, 0, Rgba([55, 55, 55, 255]));
+        let options = RegionOptions {
+            min_pixels: 1,
+            merge_distance: 0,
+        };
+
+        let diff = pixel_diff(&previous, &current, AVERAGE);
+        let found = changed_regions(&previous, &current, &diff, AVERAGE, options);
+        assert_eq!(found[0].mean_delta, 150.0);
+
+        // Solid, so none of it looks like anti-aliasing
+        let current = painted(5, 5, &block(1, 1, 3, 3), [0, 0, 0, 255]);
+        let perceptual = DiffMethod::Perceptual { threshold: 0.1 };
+        let diff = pixel_diff(&previous, &current, perceptual);
+        let found = changed_regions(&previous, &current, &diff, perceptual, options);
+        assert_eq!(found[0].pixels, 9);
+        // Black on white is 32857 of the 35215 largest YIQ distance
+        assert!(
+            (found[0].mean_delta - 0.966).abs() < 1e-3,
+            "{}",
+            found[0].mean_delta
+        );
+    }
+
+    #[test]
+    fn test_antialiased_pixels_belong_to_no_region() {
+        // Column 2 is gray between black and white in one version only
+        let edge = |gray: u8| {
+            RgbaImage::from_fn(5, 5, |x, _| match x {
+                0 | 1 => Rgba([0, 0, 0, 255]),
+                2 => Rgba([gray, gray, gray, 255]),
+                _ => Rgba([255, 255, 255, 255]),
+            })
+        };
+        let (sharp, smooth) = (edge(255), edge(128));
+        let perceptual = DiffMethod::Perceptual { threshold: 0.1 };
+
+        let diff = pixel_diff(&sharp, &smooth, perceptual);
+        let options = RegionOptions {
+            min_pixels: 1,
+            merge_distance: 0,
+        };
+
+        assert!(changed_regions(&sharp, &smooth, &diff, perceptual, options).is_empty());
+    }
+
+    #[test]
+    fn test_gap_between_ranges() {
+        assert_eq!(gap(0, 1, 5, 6), 3);
+        assert_eq!(gap(5, 6, 0, 1), 3);
+        assert_eq!(gap(0, 4, 5, 6), 0);
+        assert_eq!(gap(0, 9, 3, 4), 0);
+    }
+}
@@ -0,0 +1,320 @@
+//! How alike two versions of an image look, as a structural similarity
+//! (SSIM) score and a perceptual hash distance.
+//!
+//! Both work on brightness alone, with translucent pixels blended over
+//! white. SSIM compares the area both images cover in 8x8 windows, so it
+//! notices where an image changed; the hash scales each image down to
+//! 32x32 first, so it is blind to size and small details but tells a
+//! re-export of the same picture from a different one.
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::perceptual::over_white;
+
+/// Side of the windows SSIM is computed over
+const WINDOW: u32 = 8;
+
+/// Most tiles along either side of the SSIM map
+const MAX_TILES: u32 = 16;
+
+/// Stabilizing constants of the SSIM formula for 8-bit values
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// Side of the image the perceptual hash is computed from
+const HASH_SIZE: u32 = 32;
+
+/// Side of the block of low frequencies the hash bits come from
+const HASH_BITS: usize = 8;
+
+/// Smallest SSIM of a change that only re-exports the same picture
+pub const TRIVIAL_SSIM: f64 = 0.99;
+
+/// Largest hash distance of a change that only re-exports the same picture
+pub const TRIVIAL_HASH_DISTANCE: u32 = 2;
+
+/// How alike two versions of an image look
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Similarity {
+    /// Mean SSIM over the area both versions cover: 1 for the same
+    /// pixels, around 0 for unrelated ones
+    pub ssim: f64,
+    /// SSIM of each tile of that area
+    pub tiles: SsimMap,
+    /// Bits that differ between the 64-bit perceptual hashes, 0 to 64
+    pub hash_distance: u32,
+    /// Same size, SSIM of at least `TRIVIAL_SSIM` and a hash distance of
+    /// at most `TRIVIAL_HASH_DISTANCE`: the picture was saved again
+    /// rather than changed
+    pub trivial: bool,
+}
+
+/// SSIM of square tiles covering the compared area, row by row. Tiles on
+/// the right and bottom edges can be cut short.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SsimMap {
+    /// Side of a tile in pixels, a multiple of 8 chosen so there are at
+    /// most 16 tiles along either side
+    pub tile_size: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub scores: Vec<f64>,
+}
+
+/// Compare two versions of an image
+pub fn similarity(previous: &RgbaImage, current: &RgbaImage) -> Similarity {
+    let (ssim, tiles) = ssim(&luma(previous), &luma(current));
+    let hash_distance = (perceptual_hash(previous) ^ perceptual_hash(current)).count_ones();
+    let trivial = previous.dimensions() == current.dimensions()
+        && ssim >= TRIVIAL_SSIM
+        && hash_distance <= TRIVIAL_HASH_DISTANCE;
+
+    Similarity {
+        ssim,
+        tiles,
+        hash_distance,
+        trivial,
+    }
+}
+
+/// Brightness of every pixel, blended over white, with BT.601 weights
+fn luma(image: &RgbaImage) -> GrayImage {
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let (r, g, b) = over_white(image.get_pixel(x, y).0);
+        Luma([(0.299 * r + 0.587 * g + 0.114 * b).round() as u8])
+    })
+}
+
+/// Mean SSIM of the top-left area both images cover, and the map of its
+/// tiles. Each window weighs by its number of pixels.
+fn ssim(previous: &GrayImage, current: &GrayImage) -> (f64, SsimMap) {
+    let width = previous.width().min(current.width());
+    let height = previous.height().min(current.height());
+    let tile_size = width
+        .max(height)
+        .div_ceil(MAX_TILES)
+        .next_multiple_of(WINDOW)
+        .max(WINDOW);
+    let columns = width.div_ceil(tile_size);
+    let rows = height.div_ceil(tile_size);
+
+    // Sum of window scores times their pixels, and the pixels, per tile
+    let mut tiles = vec![(0.0, 0u64); (columns * rows) as usize];
+    for wy in (0..height).step_by(WINDOW as usize) {
+        for wx in (0..width).step_by(WINDOW as usize) {
+            let w = WINDOW.min(width - wx);
+            let h = WINDOW.min(height - wy);
+            let pixels = u64::from(w) * u64::from(h);
+            let score = window_ssim(previous, current, wx, wy, w, h);
+
+            let tile = &mut tiles[((wy / tile_size) * columns + wx / tile_size) as usize];
+            tile.0 += score * pixels as f64;
+            tile.1 += pixels;
+        }
+    }
+
+    let (sum, pixels) = tiles
+        .iter()
+        .fold((0.0, 0), |total, tile| (total.0 + tile.0, total.1 + tile.1));
+    // Nothing to compare is nothing that differs
+    let mean = |sum: f64, pixels: u64| {
+        if pixels == 0 {
+            1.0
+        } else {
+            sum / pixels as f64
+        }
+    };
+
+    let map = SsimMap {
+        tile_size,
+        columns,
+        rows,
+        scores: tiles
+            .iter()
+            .map(|&(sum, pixels)| mean(sum, pixels))
+            .collect(),
+    };
+    (mean(sum, pixels), map)
+}
+
+/// SSIM of one window, from the means, variances and covariance of its
+/// pixels
+fn window_ssim(a: &GrayImage, b: &GrayImage, x0: u32, y0: u32, w: u32, h: u32) -> f64 {
+    let n = f64::from(w * h);
+    let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for y in y0..y0 + h {
+        for x in x0..x0 + w {
+            let pa = f64::from(a.get_pixel(x, y)[0]);
+            let pb = f64::from(b.get_pixel(x, y)[0]);
+            sum_a += pa;
+            sum_b += pb;
+            sum_aa += pa * pa;
+            sum_bb += pb * pb;
+            sum_ab += pa * pb;
+        }
+    }
+
+    let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+    let var_a = sum_aa / n - mean_a * mean_a;
+    let var_b = sum_bb / n - mean_b * mean_b;
+    let covariance = sum_ab / n - mean_a * mean_b;
+
+    ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+        / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2))
+}
+
+/// 64-bit perceptual hash (pHash): one bit per low frequency of the
+/// image's brightness at 32x32, set when it is above the median
+fn perceptual_hash(image: &RgbaImage) -> u64 {
+    let small = imageops::resize(&luma(image), HASH_SIZE, HASH_SIZE, FilterType::Triangle);
+    let size = HASH_SIZE as usize;
+
+    // DCT-II of the rows, then of the columns, keeping only the lowest
+    // frequencies of each
+    let cosines: Vec<f64> = (0..HASH_BITS * size)
+        .map(|i| {
+            let (k, n) = (i / size, i % size);
+            (std::f64::consts::PI / size as f64 * (n as f64 + 0.5) * k as f64).cos()
+        })
+        .collect();
+    let dct = |values: &mut dyn Iterator<Item = f64>| -> [f64; HASH_BITS] {
+        let values: Vec<f64> = values.collect();
+        std::array::from_fn(|k| {
+            let basis = &cosines[k * size..][..size];
+            values.iter().zip(basis).map(|(v, c)| v * c).sum()
+        })
+    };
+    let rows: Vec<[f64; HASH_BITS]> = small
+        .rows()
+        .map(|row| dct(&mut row.map(|p| f64::from(p[0]))))
+        .collect();
+    let low: Vec<f64> = (0..HASH_BITS)
+        .flat_map(|u| dct(&mut rows.iter().map(|row| row[u])))
+        .collect();
+
+    let mut sorted = low.clone();
+    sorted.sort_by(f64::total_cmp);
+    let median = (sorted[31] + sorted[32]) / 2.0;
+
+    low.iter()
+        .enumerate()
+        .filter(|(_, &v)| v > median)
+        .fold(0, |hash, (bit, _)| hash | 1 << bit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// A diagonal gradient with a dark square, a picture with some detail
+    fn picture(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            if (width / 4..width / 2).contains(&x) && (height / 4..height / 2).contains(&y) {
+                Rgba([20, 30, 40, 255])
+            } else {
+                let v = (255 * (x + y) / (width + height)) as u8;
+                Rgba([v, 255 - v, 128, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_identical_images_are_trivial() {
+        let image = picture(64, 48);
+
+        let result = similarity(&image, &image.clone());
+
+        assert_eq!(result.ssim, 1.0);
+        assert_eq!(result.hash_distance, 0);
+        assert!(result.trivial);
+        assert!(result.tiles.scores.iter().all(|&score| score == 1.0));
+    }
+
+    #[test]
+    fn test_slight_noise_is_still_trivial() {
+        let image = picture(64, 48);
+        let mut noisy = image.clone();
+        for (x, y, pixel) in noisy.enumerate_pixels_mut() {
+            if (x + y) % 7 == 0 {
+                pixel[0] = pixel[0].saturating_add(2);
+            }
+        }
+
+        let result = similarity(&image, &noisy);
+
+        assert!(
+            result.ssim < 1.0 && result.ssim >= TRIVIAL_SSIM,
+            "{}",
+            result.ssim
+        );
+        assert!(result.trivial);
+    }
+
+    #[test]
+    fn test_changed_area_lowers_its_tile() {
+        let image = picture(128, 128);
+        let mut patched = image.clone();
+        for x in 100..120 {
+            for y in 100..120 {
+                patched.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+
+        let result = similarity(&image, &patched);
+
+        assert!(!result.trivial);
+        assert_eq!(result.tiles.tile_size, 8);
+        assert_eq!((result.tiles.columns, result.tiles.rows), (16, 16));
+        let tile = |column: usize, row: usize| result.tiles.scores[row * 16 + column];
+        assert!(tile(13, 13) < 0.5, "{}", tile(13, 13));
+        assert_eq!(tile(0, 0), 1.0);
+        assert!(result.ssim > tile(13, 13));
+    }
+
+    #[test]
+    fn test_tiles_cover_large_images_in_at_most_16_per_side() {
+        let result = similarity(&picture(1000, 300), &picture(1000, 300));
+        assert_eq!(result.tiles.tile_size, 64);
+        assert_eq!((result.tiles.columns, result.tiles.rows), (16, 5));
+        assert_eq!(result.tiles.scores.len(), 80);
+    }
+
+    #[test]
+    fn test_hash_ignores_size_but_not_content() {
+        let image = picture(64, 64);
+
+        let resized = similarity(&image, &picture(128, 128));
+        assert!(
+            resized.hash_distance <= TRIVIAL_HASH_DISTANCE,
+            "{}",
+            resized.hash_distance
+        );
+        // A different size is never trivial
+        assert!(!resized.trivial);
+
+        let flipped = similarity(&image, &imageops::flip_horizontal(&image));
+        assert!(flipped.hash_distance > 10, "{}", flipped.hash_distance);
+    }
+
+    #[test]
+    fn test_inverted_image_is_dissimilar() {
+        let image = picture(32, 32);
+        let mut inverted = image.clone();
+        imageops::invert(&mut inverted);
+
+        let result = similarity(&image, &inverted);
+
+        assert!(result.ssim < 0.5, "{}", result.ssim);
+        assert!(result.hash_distance > 32, "{}", result.hash_distance);
+    }
+
+    #[test]
+    fn test_transparent_pixels_look_white() {
+        let clear = RgbaImage::from_pixel(16, 16, Rgba([0, 0, 0, 0]));
+        let white = RgbaImage::from_pixel(16, 16, Rgba([255, 255, 255, 255]));
+        assert_eq!(similarity(&clear, &white).ssim, 1.0);
+    }
+}
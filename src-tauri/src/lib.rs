@@ -1,246 +1,382 @@
-use base64::{engine::general_purpose::STANDARD, Engine};
-use serde::{Deserialize, Serialize};
-use std::path::Path;
-use std::process::Command;
-use tauri::Manager;
-
-/// Represents a changed file in the git repository
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct ChangedFile {
-    pub path: String,
-    pub filename: String,
-    pub status: String, // "modified", "added", or "deleted"
-}
+mod diff_images;
+mod error;
+mod git;
+mod imaging;
+mod mime;
+mod protocol;
+#[cfg(test)]
+mod test_support;
+mod trash;
+
+pub use diff_images::DiffImages;
+pub use error::VisiGitError;
+pub use imaging::{
+    ColorKind, DiffMethod, Dimensions, ImageInfo, Region, RegionOptions, Similarity, SsimMap,
+};
+pub use trash::{Backup, Trash};
+
+pub use git::{
+    add_diff_similarity_impl, add_dimensions_impl, add_similarity_impl, compute_image_diff_impl,
+    create_commit_impl, discover_repo_impl, get_blob_metadata_impl, get_changed_files_against_impl,
+    get_changed_files_impl, get_changes_between_impl, get_commits_impl, get_file_at_commit_impl,
+    get_file_at_head_impl, get_file_at_index_impl, get_file_bytes_at_index_impl,
+    get_file_bytes_at_stage_impl, get_file_bytes_impl, get_file_history_impl, get_history_impl,
+    inspect_image_impl, list_refs_impl, resolve_conflict_impl, restore_file_impl, stage_files_impl,
+    undo_restore_impl, unstage_files_impl, validate_git_repo_impl, BackendCache, BackendKind,
+    BlobMetadata, ChangeKind, ChangedFile, CoAuthor, CommitInfo, ConflictBlobs, ConflictResolution,
+    ConflictSide, ConflictStage, DiffedFile, FileHistoryEntry, FileVersion, GitBackend,
+    HistoryFilter, HistoryQuery, Identity, ImageDiff, ImageDimensions, Page, RefInfo, RefKind,
+    RepoInfo, RepoOperation, RestoreTarget,
+};
+
+use tauri::ipc::Response;
+use tauri::{Manager, State};
 
-/// Represents information about a git commit
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct CommitInfo {
-    pub hash: String,
-    pub short_hash: String,
-    pub message: String,
-    pub author: String,
-    pub date: String,
-}
+// ============================================
+// Tauri Commands (thin wrappers around core logic)
+// ============================================
 
-/// Image file extensions we care about
-const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "ico"];
+#[tauri::command]
+fn validate_git_repo(path: &str) -> Result<bool, VisiGitError> {
+    validate_git_repo_impl(path)
+}
 
-/// Check if a file path has an image extension
-fn is_image_file(path: &str) -> bool {
-    let path_lower = path.to_lowercase();
-    IMAGE_EXTENSIONS.iter().any(|ext| path_lower.ends_with(&format!(".{}", ext)))
+/// Find the repository containing `path`, from any folder inside it
+#[tauri::command]
+fn discover_repo(path: &str) -> Result<RepoInfo, VisiGitError> {
+    discover_repo_impl(path)
 }
 
-/// Core logic: Validates if the given path is a git repository
-pub fn validate_git_repo_impl(path: &str) -> Result<bool, String> {
-    let path = Path::new(path);
+/// Id that stands for the repository in `visigit://` URLs
+#[tauri::command]
+fn get_repo_id(backends: State<'_, BackendCache>, repo_path: &str) -> Result<String, VisiGitError> {
+    backends.repo_id(repo_path)
+}
 
-    if !path.exists() {
-        return Err(format!("Path does not exist: {}", path.display()));
+/// Changed images in the index and working tree. With `with_dimensions`,
+/// each also gets its width and height at HEAD and in the working tree;
+/// with `with_similarity`, how alike it looks there. Runs on the async
+/// runtime, since scoring similarity decodes every changed image.
+#[tauri::command(async)]
+fn get_changed_files(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    with_dimensions: Option<bool>,
+    with_similarity: Option<bool>,
+) -> Result<Vec<ChangedFile>, VisiGitError> {
+    let backend = backends.get(repo_path)?;
+    let mut files = get_changed_files_impl(backend.as_ref())?;
+    if with_dimensions.unwrap_or(false) {
+        add_dimensions_impl(backend.as_ref(), &mut files)?;
     }
-
-    // Check if .git directory exists
-    let git_dir = path.join(".git");
-    Ok(git_dir.exists() && git_dir.is_dir())
+    if with_similarity.unwrap_or(false) {
+        add_similarity_impl(backend.as_ref(), &mut files)?;
+    }
+    Ok(files)
 }
 
-/// Core logic: Gets the list of changed image files in the repository
-pub fn get_changed_files_impl(repo_path: &str) -> Result<Vec<ChangedFile>, String> {
-    let path = Path::new(repo_path);
+/// Stage the working tree state of `paths` and return the changed images.
+/// Refuses to run during a merge or rebase unless `allow_in_progress`.
+#[tauri::command]
+fn stage_files(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    paths: Vec<String>,
+    allow_in_progress: bool,
+) -> Result<Vec<ChangedFile>, VisiGitError> {
+    stage_files_impl(backends.get(repo_path)?.as_ref(), &paths, allow_in_progress)
+}
 
-    if !path.exists() {
-        return Err(format!("Repository path does not exist: {}", repo_path));
-    }
+/// Put `paths` in the index back to HEAD and return the changed images.
+/// Refuses to run during a merge or rebase unless `allow_in_progress`.
+#[tauri::command]
+fn unstage_files(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    paths: Vec<String>,
+    allow_in_progress: bool,
+) -> Result<Vec<ChangedFile>, VisiGitError> {
+    unstage_files_impl(backends.get(repo_path)?.as_ref(), &paths, allow_in_progress)
+}
 
-    // Run git status --porcelain to get changed files
-    let output = Command::new("git")
-        .args(["status", "--porcelain"])
-        .current_dir(path)
-        .output()
-        .map_err(|e| format!("Failed to run git status: {}", e))?;
-
-    if !output.status.success() {
-        return Err(format!(
-            "git status failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
-    }
+/// Commit the staged changes, or amend HEAD, optionally as another
+/// author. Refuses to run during a merge or rebase unless
+/// `allow_in_progress`.
+#[tauri::command]
+fn create_commit(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    message: &str,
+    author: Option<Identity>,
+    amend: bool,
+    allow_in_progress: bool,
+) -> Result<CommitInfo, VisiGitError> {
+    create_commit_impl(
+        backends.get(repo_path)?.as_ref(),
+        message,
+        author.as_ref(),
+        amend,
+        allow_in_progress,
+    )
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut files = Vec::new();
-
-    for line in stdout.lines() {
-        if line.len() < 3 {
-            continue;
-        }
-
-        let status_code = &line[0..2];
-        let file_path = line[3..].trim();
-
-        // Skip non-image files
-        if !is_image_file(file_path) {
-            continue;
-        }
-
-        // Parse status
-        let status = match status_code.trim() {
-            "M" | " M" | "MM" => "modified",
-            "A" | " A" | "AM" => "added",
-            "D" | " D" => "deleted",
-            "??" => "added", // Untracked files are new
-            "R" | " R" => "modified", // Renamed counts as modified
-            _ => continue, // Skip other statuses
-        };
-
-        // Extract filename from path
-        let filename = Path::new(file_path)
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| file_path.to_string());
-
-        files.push(ChangedFile {
-            path: file_path.to_string(),
-            filename,
-            status: status.to_string(),
-        });
-    }
+/// Restore a file in the working tree or the index to its version at
+/// `source_rev`. The replaced version is backed up to the trash first.
+#[tauri::command]
+fn restore_file(
+    backends: State<'_, BackendCache>,
+    trash: State<'_, Trash>,
+    repo_path: &str,
+    file_path: &str,
+    source_rev: &str,
+    target: RestoreTarget,
+) -> Result<Backup, VisiGitError> {
+    restore_file_impl(
+        backends.get(repo_path)?.as_ref(),
+        &trash,
+        file_path,
+        source_rev,
+        target,
+    )
+}
 
-    Ok(files)
+/// Put back what a `restore_file` call replaced
+#[tauri::command]
+fn undo_restore(
+    backends: State<'_, BackendCache>,
+    trash: State<'_, Trash>,
+    repo_path: &str,
+    backup_id: &str,
+) -> Result<Vec<ChangedFile>, VisiGitError> {
+    undo_restore_impl(backends.get(repo_path)?.as_ref(), &trash, backup_id)
 }
 
-/// Core logic: Gets the base64-encoded content of a file at HEAD
-pub fn get_file_at_head_impl(repo_path: &str, file_path: &str) -> Result<String, String> {
-    let path = Path::new(repo_path);
+/// Resolve a conflicted file with the base, ours or theirs version, or the
+/// working tree file as it is, and stage the result
+#[tauri::command]
+fn resolve_conflict(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    file_path: &str,
+    resolution: ConflictResolution,
+) -> Result<Vec<ChangedFile>, VisiGitError> {
+    resolve_conflict_impl(backends.get(repo_path)?.as_ref(), file_path, resolution)
+}
 
-    if !path.exists() {
-        return Err(format!("Repository path does not exist: {}", repo_path));
+/// Changed images between `rev` (or the fork point, for `rev...`) and the
+/// working tree, scored for similarity with `with_similarity`
+#[tauri::command(async)]
+fn get_changed_files_against(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    rev: &str,
+    with_similarity: Option<bool>,
+) -> Result<Vec<DiffedFile>, VisiGitError> {
+    let backend = backends.get(repo_path)?;
+    let mut files = get_changed_files_against_impl(backend.as_ref(), rev)?;
+    if with_similarity.unwrap_or(false) {
+        add_diff_similarity_impl(backend.as_ref(), &mut files, true)?;
     }
+    Ok(files)
+}
 
-    // Run git show HEAD:file_path
-    let output = Command::new("git")
-        .args(["show", &format!("HEAD:{}", file_path)])
-        .current_dir(path)
-        .output()
-        .map_err(|e| format!("Failed to run git show: {}", e))?;
-
-    if !output.status.success() {
-        return Err(format!(
-            "File does not exist at HEAD: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+/// Changed images between two commits. `first_parent` follows only the
+/// first parent of merges on `to`'s side. `with_similarity` scores how
+/// alike each image looks on both sides.
+#[tauri::command(async)]
+fn get_changes_between(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    from: &str,
+    to: &str,
+    first_parent: bool,
+    with_similarity: Option<bool>,
+) -> Result<Vec<DiffedFile>, VisiGitError> {
+    let backend = backends.get(repo_path)?;
+    let mut files = get_changes_between_impl(backend.as_ref(), from, to, first_parent)?;
+    if with_similarity.unwrap_or(false) {
+        add_diff_similarity_impl(backend.as_ref(), &mut files, false)?;
     }
-
-    // Encode the binary content as base64
-    Ok(STANDARD.encode(&output.stdout))
+    Ok(files)
 }
 
-/// Core logic: Gets the list of commits in the repository
-pub fn get_commits_impl(repo_path: &str, limit: u32) -> Result<Vec<CommitInfo>, String> {
-    let path = Path::new(repo_path);
+#[tauri::command]
+fn get_file_at_head(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    file_path: &str,
+) -> Result<String, VisiGitError> {
+    get_file_at_head_impl(backends.get(repo_path)?.as_ref(), file_path)
+}
 
-    if !path.exists() {
-        return Err(format!("Repository path does not exist: {}", repo_path));
-    }
+/// Base64 content of the version of a file staged in the index
+#[tauri::command]
+fn get_file_at_index(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    file_path: &str,
+) -> Result<String, VisiGitError> {
+    get_file_at_index_impl(backends.get(repo_path)?.as_ref(), file_path)
+}
 
-    // Run git log with custom format: hash|short_hash|message|author|date
-    let output = Command::new("git")
-        .args([
-            "log",
-            &format!("-{}", limit),
-            "--format=%H|%h|%s|%an|%ai",
-        ])
-        .current_dir(path)
-        .output()
-        .map_err(|e| format!("Failed to run git log: {}", e))?;
-
-    if !output.status.success() {
-        return Err(format!(
-            "git log failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
-    }
+/// Recent commits reachable from `rev`, a commit, branch or tag name
+/// (HEAD when omitted)
+#[tauri::command]
+fn get_commits(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    limit: u32,
+    rev: Option<String>,
+) -> Result<Vec<CommitInfo>, VisiGitError> {
+    get_commits_impl(backends.get(repo_path)?.as_ref(), limit, rev.as_deref())
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut commits = Vec::new();
-
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.splitn(5, '|').collect();
-        if parts.len() == 5 {
-            commits.push(CommitInfo {
-                hash: parts[0].to_string(),
-                short_hash: parts[1].to_string(),
-                message: parts[2].to_string(),
-                author: parts[3].to_string(),
-                date: parts[4].to_string(),
-            });
-        }
-    }
+/// Local branches, remote-tracking branches and tags, with upstream
+/// ahead/behind counts for branches
+#[tauri::command]
+fn list_refs(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+) -> Result<Vec<RefInfo>, VisiGitError> {
+    list_refs_impl(backends.get(repo_path)?.as_ref())
+}
 
-    Ok(commits)
+/// One page of commit history matching `query`. Pass `next_cursor` back
+/// in the query to continue.
+#[tauri::command]
+fn get_history(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    query: HistoryQuery,
+) -> Result<Page<CommitInfo>, VisiGitError> {
+    get_history_impl(backends.get(repo_path)?.as_ref(), &query)
 }
 
-/// Core logic: Gets the base64-encoded content of a file at a specific commit
-pub fn get_file_at_commit_impl(
+/// Base64 content of a file at `commit_hash`, which may also be a branch
+/// or tag name
+#[tauri::command]
+fn get_file_at_commit(
+    backends: State<'_, BackendCache>,
     repo_path: &str,
     file_path: &str,
     commit_hash: &str,
-) -> Result<String, String> {
-    let path = Path::new(repo_path);
-
-    if !path.exists() {
-        return Err(format!("Repository path does not exist: {}", repo_path));
-    }
-
-    // Run git show {commit}:{file_path}
-    let output = Command::new("git")
-        .args(["show", &format!("{}:{}", commit_hash, file_path)])
-        .current_dir(path)
-        .output()
-        .map_err(|e| format!("Failed to run git show: {}", e))?;
-
-    if !output.status.success() {
-        return Err(format!(
-            "File does not exist at commit {}: {}",
-            commit_hash,
-            String::from_utf8_lossy(&output.stderr)
-        ));
-    }
+) -> Result<String, VisiGitError> {
+    get_file_at_commit_impl(backends.get(repo_path)?.as_ref(), file_path, commit_hash)
+}
 
-    // Encode the binary content as base64
-    Ok(STANDARD.encode(&output.stdout))
+/// One page of a file's history, following it back through renames.
+/// Pass `next_cursor` from the previous page to continue.
+#[tauri::command]
+fn get_file_history(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    file_path: &str,
+    limit: u32,
+    cursor: Option<String>,
+) -> Result<Page<FileHistoryEntry>, VisiGitError> {
+    get_file_history_impl(
+        backends.get(repo_path)?.as_ref(),
+        file_path,
+        limit,
+        cursor.as_deref(),
+    )
 }
 
-// ============================================
-// Tauri Commands (thin wrappers around core logic)
-// ============================================
+/// Raw bytes of a file at HEAD, sent as a binary IPC response
+#[tauri::command]
+fn get_file_bytes_at_head(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    file_path: &str,
+) -> Result<Response, VisiGitError> {
+    let bytes = get_file_bytes_impl(backends.get(repo_path)?.as_ref(), file_path, "HEAD")?;
+    Ok(Response::new(bytes))
+}
 
+/// Raw bytes of a file at a commit, branch or tag, sent as a binary IPC
+/// response
 #[tauri::command]
-fn validate_git_repo(path: &str) -> Result<bool, String> {
-    validate_git_repo_impl(path)
+fn get_file_bytes_at_commit(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    file_path: &str,
+    commit_hash: &str,
+) -> Result<Response, VisiGitError> {
+    let bytes = get_file_bytes_impl(backends.get(repo_path)?.as_ref(), file_path, commit_hash)?;
+    Ok(Response::new(bytes))
 }
 
+/// Raw bytes of the version of a file staged in the index, sent as a
+/// binary IPC response
 #[tauri::command]
-fn get_changed_files(repo_path: &str) -> Result<Vec<ChangedFile>, String> {
-    get_changed_files_impl(repo_path)
+fn get_file_bytes_at_index(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    file_path: &str,
+) -> Result<Response, VisiGitError> {
+    let bytes = get_file_bytes_at_index_impl(backends.get(repo_path)?.as_ref(), file_path)?;
+    Ok(Response::new(bytes))
 }
 
+/// Raw bytes of the base, ours or theirs version of a conflicted file,
+/// sent as a binary IPC response
 #[tauri::command]
-fn get_file_at_head(repo_path: &str, file_path: &str) -> Result<String, String> {
-    get_file_at_head_impl(repo_path, file_path)
+fn get_file_bytes_at_stage(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    file_path: &str,
+    stage: ConflictStage,
+) -> Result<Response, VisiGitError> {
+    let bytes = get_file_bytes_at_stage_impl(backends.get(repo_path)?.as_ref(), file_path, stage)?;
+    Ok(Response::new(bytes))
 }
 
 #[tauri::command]
-fn get_commits(repo_path: &str, limit: u32) -> Result<Vec<CommitInfo>, String> {
-    get_commits_impl(repo_path, limit)
+fn get_blob_metadata(
+    backends: State<'_, BackendCache>,
+    repo_path: &str,
+    file_path: &str,
+    rev: &str,
+) -> Result<BlobMetadata, VisiGitError> {
+    get_blob_metadata_impl(backends.get(repo_path)?.as_ref(), file_path, rev)
 }
 
+/// Decode a file at `rev`, or the working tree file when `rev` is omitted,
+/// and report its format, dimensions, color type, bit depth and frames
 #[tauri::command]
-fn get_file_at_commit(
+fn inspect_image(
+    backends: State<'_, BackendCache>,
     repo_path: &str,
     file_path: &str,
-    commit_hash: &str,
-) -> Result<String, String> {
-    get_file_at_commit_impl(repo_path, file_path, commit_hash)
+    rev: Option<String>,
+) -> Result<ImageInfo, VisiGitError> {
+    inspect_image_impl(backends.get(repo_path)?.as_ref(), file_path, rev.as_deref())
+}
+
+/// Compare two versions of an image pixel by pixel and find the regions
+/// that changed, clustered with `regions` or the defaults. The diff image
+/// is not in the result: it is loaded from `visigit://diff/<id>`. Runs on
+/// the async runtime rather than the main thread, since decoding and
+/// comparing large images takes a while.
+#[tauri::command(async)]
+fn compute_image_diff(
+    backends: State<'_, BackendCache>,
+    diffs: State<'_, DiffImages>,
+    repo_path: &str,
+    previous: FileVersion,
+    current: FileVersion,
+    method: DiffMethod,
+    regions: Option<RegionOptions>,
+) -> Result<ImageDiff, VisiGitError> {
+    let backend = backends.get(repo_path)?;
+    compute_image_diff_impl(
+        backend.as_ref(),
+        &diffs,
+        &previous,
+        &current,
+        method,
+        regions.unwrap_or_default(),
+    )
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -249,14 +385,57 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .manage(BackendCache::default())
+        .manage(DiffImages::default())
+        .register_asynchronous_uri_scheme_protocol(protocol::SCHEME, |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            // Reading a large blob should not hold up the webview
+            std::thread::spawn(move || {
+                let backends = app.state::<BackendCache>();
+                let diffs = app.state::<DiffImages>();
+                responder.respond(protocol::handle(&backends, &diffs, &request));
+            });
+        })
+        .on_window_event(|window, event| {
+            // Stop the git processes kept running for open repositories
+            if let tauri::WindowEvent::Destroyed = event {
+                window.state::<BackendCache>().shutdown();
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             validate_git_repo,
+            discover_repo,
+            get_repo_id,
             get_changed_files,
+            stage_files,
+            unstage_files,
+            create_commit,
+            restore_file,
+            undo_restore,
+            resolve_conflict,
+            get_changed_files_against,
+            get_changes_between,
             get_file_at_head,
+            get_file_at_index,
             get_commits,
-            get_file_at_commit
+            list_refs,
+            get_history,
+            get_file_at_commit,
+            get_file_history,
+            get_file_bytes_at_head,
+            get_file_bytes_at_commit,
+            get_file_bytes_at_index,
+            get_file_bytes_at_stage,
+            get_blob_metadata,
+            inspect_image,
+            compute_image_diff
         ])
         .setup(|app| {
+            // Backups of restored files live with the app's data, not in
+            // the repository
+            let trash_dir = app.path().app_data_dir()?.join("trash");
+            app.manage(Trash::new(trash_dir));
+
             #[cfg(debug_assertions)]
             {
                 let window = app.get_webview_window("main").unwrap();
@@ -267,377 +446,3 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use std::process::Command;
-    use tempfile::TempDir;
-
-    /// Helper to create a temporary git repository for testing
-    fn create_test_git_repo() -> TempDir {
-        let temp_dir = TempDir::new().expect("Failed to create temp dir");
-        let path = temp_dir.path();
-
-        // Initialize git repo
-        Command::new("git")
-            .args(["init"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to init git repo");
-
-        // Configure git user for commits
-        Command::new("git")
-            .args(["config", "user.email", "test@test.com"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to configure git email");
-
-        Command::new("git")
-            .args(["config", "user.name", "Test User"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to configure git user");
-
-        temp_dir
-    }
-
-    // ============================================
-    // Tests for validate_git_repo_impl
-    // ============================================
-
-    #[test]
-    fn test_validate_git_repo_returns_true_for_valid_repo() {
-        let temp_repo = create_test_git_repo();
-        let path = temp_repo.path().to_str().unwrap();
-
-        let result = validate_git_repo_impl(path);
-
-        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
-        assert!(result.unwrap(), "Expected true for valid git repo");
-    }
-
-    #[test]
-    fn test_validate_git_repo_returns_false_for_non_git_directory() {
-        let temp_dir = TempDir::new().expect("Failed to create temp dir");
-        let path = temp_dir.path().to_str().unwrap();
-
-        let result = validate_git_repo_impl(path);
-
-        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
-        assert!(!result.unwrap(), "Expected false for non-git directory");
-    }
-
-    #[test]
-    fn test_validate_git_repo_returns_error_for_nonexistent_path() {
-        let result = validate_git_repo_impl("/nonexistent/path/that/does/not/exist");
-
-        assert!(result.is_err(), "Expected Err result for nonexistent path");
-    }
-
-    // ============================================
-    // Tests for get_changed_files_impl
-    // ============================================
-
-    #[test]
-    fn test_get_changed_files_returns_empty_for_clean_repo() {
-        let temp_repo = create_test_git_repo();
-        let path = temp_repo.path().to_str().unwrap();
-
-        let result = get_changed_files_impl(path);
-
-        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
-        assert!(result.unwrap().is_empty(), "Expected empty list for clean repo");
-    }
-
-    #[test]
-    fn test_get_changed_files_detects_modified_png() {
-        let temp_repo = create_test_git_repo();
-        let path = temp_repo.path();
-        let path_str = path.to_str().unwrap();
-
-        // Create and commit an image file
-        let image_path = path.join("test.png");
-        fs::write(&image_path, b"fake png content").expect("Failed to write image");
-
-        Command::new("git")
-            .args(["add", "test.png"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to add file");
-
-        Command::new("git")
-            .args(["commit", "-m", "Initial commit"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to commit");
-
-        // Modify the image
-        fs::write(&image_path, b"modified png content").expect("Failed to modify image");
-
-        let result = get_changed_files_impl(path_str);
-
-        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
-        let files = result.unwrap();
-        assert_eq!(files.len(), 1, "Expected 1 changed file");
-        assert_eq!(files[0].path, "test.png");
-        assert_eq!(files[0].status, "modified");
-    }
-
-    #[test]
-    fn test_get_changed_files_detects_new_image() {
-        let temp_repo = create_test_git_repo();
-        let path = temp_repo.path();
-        let path_str = path.to_str().unwrap();
-
-        // Create initial commit (needed for git status to work properly)
-        fs::write(path.join("README.md"), "readme").expect("Failed to write readme");
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(path)
-            .output()
-            .expect("Failed to add");
-        Command::new("git")
-            .args(["commit", "-m", "Initial"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to commit");
-
-        // Create a new untracked image
-        fs::write(path.join("new.jpg"), b"new jpg content").expect("Failed to write new image");
-
-        let result = get_changed_files_impl(path_str);
-
-        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
-        let files = result.unwrap();
-        assert_eq!(files.len(), 1, "Expected 1 new file");
-        assert_eq!(files[0].path, "new.jpg");
-        assert_eq!(files[0].status, "added");
-    }
-
-    #[test]
-    fn test_get_changed_files_ignores_non_image_files() {
-        let temp_repo = create_test_git_repo();
-        let path = temp_repo.path();
-        let path_str = path.to_str().unwrap();
-
-        // Create initial commit
-        fs::write(path.join("README.md"), "readme").expect("Failed to write readme");
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(path)
-            .output()
-            .expect("Failed to add");
-        Command::new("git")
-            .args(["commit", "-m", "Initial"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to commit");
-
-        // Add a non-image file
-        fs::write(path.join("code.rs"), "fn main() {}").expect("Failed to write code file");
-
-        let result = get_changed_files_impl(path_str);
-
-        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
-        let files = result.unwrap();
-        assert!(files.is_empty(), "Expected no files (code.rs should be ignored)");
-    }
-
-    // ============================================
-    // Tests for get_file_at_head_impl
-    // ============================================
-
-    #[test]
-    fn test_get_file_at_head_returns_base64_content() {
-        let temp_repo = create_test_git_repo();
-        let path = temp_repo.path();
-        let path_str = path.to_str().unwrap();
-
-        // Create and commit an image file
-        let content = b"test image content";
-        fs::write(path.join("test.png"), content).expect("Failed to write image");
-
-        Command::new("git")
-            .args(["add", "test.png"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to add file");
-
-        Command::new("git")
-            .args(["commit", "-m", "Add image"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to commit");
-
-        let result = get_file_at_head_impl(path_str, "test.png");
-
-        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
-
-        // Verify the base64 decodes to original content
-        let base64_content = result.unwrap();
-        let decoded = STANDARD.decode(&base64_content).expect("Failed to decode base64");
-        assert_eq!(decoded, content, "Decoded content should match original");
-    }
-
-    #[test]
-    fn test_get_file_at_head_returns_error_for_new_file() {
-        let temp_repo = create_test_git_repo();
-        let path = temp_repo.path();
-        let path_str = path.to_str().unwrap();
-
-        // Create initial commit
-        fs::write(path.join("README.md"), "readme").expect("Failed to write readme");
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(path)
-            .output()
-            .expect("Failed to add");
-        Command::new("git")
-            .args(["commit", "-m", "Initial"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to commit");
-
-        // Create a new file that hasn't been committed
-        fs::write(path.join("new.png"), b"new content").expect("Failed to write new file");
-
-        let result = get_file_at_head_impl(path_str, "new.png");
-
-        // Should return error since file doesn't exist at HEAD
-        assert!(result.is_err(), "Expected error for file not in HEAD");
-    }
-
-    // ============================================
-    // Tests for is_image_file helper
-    // ============================================
-
-    #[test]
-    fn test_is_image_file_recognizes_common_formats() {
-        assert!(is_image_file("photo.png"));
-        assert!(is_image_file("photo.PNG"));
-        assert!(is_image_file("photo.jpg"));
-        assert!(is_image_file("photo.jpeg"));
-        assert!(is_image_file("icon.svg"));
-        assert!(is_image_file("animation.gif"));
-        assert!(is_image_file("modern.webp"));
-    }
-
-    #[test]
-    fn test_is_image_file_rejects_non_images() {
-        assert!(!is_image_file("code.rs"));
-        assert!(!is_image_file("style.css"));
-        assert!(!is_image_file("script.js"));
-        assert!(!is_image_file("README.md"));
-        assert!(!is_image_file("data.json"));
-    }
-
-    // ============================================
-    // Tests for get_commits_impl
-    // ============================================
-
-    #[test]
-    fn test_get_commits_returns_commit_history() {
-        let temp_repo = create_test_git_repo();
-        let path = temp_repo.path();
-        let path_str = path.to_str().unwrap();
-
-        // Create first commit
-        fs::write(path.join("file1.txt"), "content 1").expect("Failed to write file1");
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(path)
-            .output()
-            .expect("Failed to add files");
-        Command::new("git")
-            .args(["commit", "-m", "First commit"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to create first commit");
-
-        // Create second commit
-        fs::write(path.join("file2.txt"), "content 2").expect("Failed to write file2");
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(path)
-            .output()
-            .expect("Failed to add files");
-        Command::new("git")
-            .args(["commit", "-m", "Second commit"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to create second commit");
-
-        let result = get_commits_impl(path_str, 10);
-
-        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
-        let commits = result.unwrap();
-        assert_eq!(commits.len(), 2, "Expected 2 commits");
-
-        // Most recent commit should be first
-        assert_eq!(commits[0].message, "Second commit");
-        assert_eq!(commits[1].message, "First commit");
-
-        // Verify commit info structure
-        assert!(!commits[0].hash.is_empty(), "Hash should not be empty");
-        assert!(!commits[0].short_hash.is_empty(), "Short hash should not be empty");
-        assert!(!commits[0].author.is_empty(), "Author should not be empty");
-        assert!(!commits[0].date.is_empty(), "Date should not be empty");
-    }
-
-    // ============================================
-    // Tests for get_file_at_commit_impl
-    // ============================================
-
-    #[test]
-    fn test_get_file_at_commit_returns_correct_content() {
-        let temp_repo = create_test_git_repo();
-        let path = temp_repo.path();
-        let path_str = path.to_str().unwrap();
-
-        // Create first commit with initial content
-        let initial_content = b"initial image content";
-        fs::write(path.join("test.png"), initial_content).expect("Failed to write file");
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(path)
-            .output()
-            .expect("Failed to add files");
-        Command::new("git")
-            .args(["commit", "-m", "First commit"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to create first commit");
-
-        // Get the first commit hash
-        let output = Command::new("git")
-            .args(["rev-parse", "HEAD"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to get commit hash");
-        let first_commit_hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
-
-        // Create second commit with modified content
-        let modified_content = b"modified image content";
-        fs::write(path.join("test.png"), modified_content).expect("Failed to write modified file");
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(path)
-            .output()
-            .expect("Failed to add files");
-        Command::new("git")
-            .args(["commit", "-m", "Second commit"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to create second commit");
-
-        // Get file content at first commit
-        let result = get_file_at_commit_impl(path_str, "test.png", &first_commit_hash);
-
-        assert!(result.is_ok(), "Expected Ok result, got {:?}", result);
-        let base64_content = result.unwrap();
-        let decoded = STANDARD.decode(&base64_content).expect("Failed to decode base64");
-        assert_eq!(decoded, initial_content, "Content at first commit should match initial content");
-    }
-}
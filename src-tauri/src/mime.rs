@@ -0,0 +1,91 @@
+//! MIME type detection for blob content.
+
+use std::path::Path;
+
+/// Detect the MIME type of `content`, preferring magic bytes and falling
+/// back to the extension of `path`
+pub fn detect_mime(content: &[u8], path: &str) -> &'static str {
+    sniff(content).unwrap_or_else(|| from_extension(path))
+}
+
+/// Recognize common image formats from their leading bytes
+fn sniff(content: &[u8]) -> Option<&'static str> {
+    let mime = match content {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => "image/png",
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [b'B', b'M', ..] => "image/bmp",
+        [0x00, 0x00, 0x01, 0x00, ..] => "image/x-icon",
+        [b'8', b'B', b'P', b'S', ..] => "image/vnd.adobe.photoshop",
+        _ if looks_like_svg(content) => "image/svg+xml",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// SVG is text, so look for the root element near the start of the file
+fn looks_like_svg(content: &[u8]) -> bool {
+    let head = &content[..content.len().min(1024)];
+    String::from_utf8_lossy(head).contains("<svg")
+}
+
+/// MIME type from the file extension, matching the frontend's table
+fn from_extension(path: &str) -> &'static str {
+    let ext = Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "psd" => "image/vnd.adobe.photoshop",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_mime_from_magic_bytes() {
+        assert_eq!(detect_mime(b"\x89PNG\r\n\x1a\n....", "x.bin"), "image/png");
+        assert_eq!(detect_mime(b"\xFF\xD8\xFF\xE0", "x.bin"), "image/jpeg");
+        assert_eq!(detect_mime(b"GIF89a", "x.bin"), "image/gif");
+        assert_eq!(
+            detect_mime(b"RIFF\x00\x00\x00\x00WEBPVP8 ", "x.bin"),
+            "image/webp"
+        );
+        assert_eq!(
+            detect_mime(b"8BPS\x00\x01", "x.bin"),
+            "image/vnd.adobe.photoshop"
+        );
+        assert_eq!(
+            detect_mime(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"\">", "x.bin"),
+            "image/svg+xml"
+        );
+    }
+
+    #[test]
+    fn test_detect_mime_prefers_content_over_extension() {
+        // A PNG saved with the wrong extension
+        assert_eq!(detect_mime(b"\x89PNG\r\n\x1a\n", "photo.jpg"), "image/png");
+    }
+
+    #[test]
+    fn test_detect_mime_falls_back_to_extension() {
+        assert_eq!(detect_mime(b"not an image", "logo.PNG"), "image/png");
+        assert_eq!(detect_mime(b"", "icon.ico"), "image/x-icon");
+        assert_eq!(
+            detect_mime(b"plain text", "notes.txt"),
+            "application/octet-stream"
+        );
+    }
+}
@@ -0,0 +1,915 @@
+//! `visigit://` URI scheme protocol.
+//!
+//! Serves file content straight to `<img>` tags, so the webview can cache
+//! and lazy-load images instead of receiving them through `invoke`:
+//!
+//! - `visigit://blob/<repo-id>/<rev>/<path>` - the blob at a revision
+//! - `visigit://index/<repo-id>/<path>` - the version staged in the index
+//! - `visigit://stage/<repo-id>/<base|ours|theirs>/<path>` - one side of a
+//!   conflicted file
+//! - `visigit://worktree/<repo-id>/<path>` - the working copy
+//! - `visigit://diff/<id>` - an image diff PNG kept in `DiffImages`
+//!
+//! Each path segment is percent-decoded on its own, so a revision such as
+//! `feature/logo` is sent as `feature%2Flogo`. Windows and Android webviews
+//! send custom schemes as `http://visigit.localhost/...`, and
+//! `convertFileSrc` produces `visigit://localhost/...`; both are accepted
+//! with the kind (`blob` / `index` / `stage` / `worktree` / `diff`) as the
+//! first path segment.
+//!
+//! Git LFS pointers are replaced by their object. Responses for LFS files
+//! carry an `X-Lfs-Status` header, and `HEAD` requests can read it without
+//! the body.
+
+use std::fs;
+use std::path::Path;
+
+use percent_encoding::percent_decode_str;
+use tauri::http::{header, HeaderMap, Method, Request, Response, StatusCode, Uri};
+
+use crate::diff_images::DiffImages;
+use crate::error::VisiGitError;
+use crate::git::{smudge_blob, validate, BackendCache, Blob, ConflictStage, GitBackend, LfsStatus};
+use crate::mime::detect_mime;
+
+/// Scheme name registered in `run()`
+pub const SCHEME: &str = "visigit";
+
+/// Response header set for Git LFS files: `object` when the LFS object is
+/// served, `missing` when it has not been fetched and the pointer text is
+/// served instead
+pub const LFS_STATUS_HEADER: &str = "x-lfs-status";
+
+/// What a `visigit://` URL points at
+#[derive(Debug, PartialEq, Eq)]
+enum Resource {
+    Blob {
+        repo_id: String,
+        rev: String,
+        path: String,
+    },
+    Index {
+        repo_id: String,
+        path: String,
+    },
+    Stage {
+        repo_id: String,
+        stage: ConflictStage,
+        path: String,
+    },
+    Worktree {
+        repo_id: String,
+        path: String,
+    },
+    Diff {
+        id: String,
+    },
+}
+
+/// Content to serve, with the headers that depend on where it came from
+struct Served {
+    content: Vec<u8>,
+    mime: &'static str,
+    /// Git blob id, used as the ETag
+    blob_id: String,
+    /// Whether the URL always names the same content
+    immutable: bool,
+    lfs: LfsStatus,
+}
+
+/// Requested byte range, inclusive on both ends
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// No usable `Range` header: send the whole body
+    Full,
+    Partial {
+        start: u64,
+        end: u64,
+    },
+    /// The range lies outside the content
+    Unsatisfiable,
+}
+
+/// Answer a request made to the `visigit://` scheme
+pub fn handle(
+    backends: &BackendCache,
+    diffs: &DiffImages,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let Some(resource) = parse_uri(request.uri()) else {
+        return text_response(StatusCode::BAD_REQUEST, "Malformed visigit URL");
+    };
+
+    let mut response = match serve(backends, diffs, &resource) {
+        Ok(served) => respond(served, request.headers()),
+        Err(e) => text_response(status_for(&e), &e.to_string()),
+    };
+    if request.method() == Method::HEAD {
+        response.body_mut().clear();
+    }
+    response
+}
+
+/// Split a `visigit://` URL into the resource it names
+fn parse_uri(uri: &Uri) -> Option<Resource> {
+    let host = uri.host().unwrap_or_default();
+    let mut segments = uri
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            percent_decode_str(segment)
+                .decode_utf8()
+                .ok()
+                .map(|decoded| decoded.into_owned())
+        })
+        .collect::<Option<Vec<_>>>()?
+        .into_iter();
+
+    // visigit://blob/... puts the kind in the host; the localhost forms
+    // put it in the first path segment
+    let kind = match host {
+        "localhost" | "visigit.localhost" => segments.next()?,
+        _ => host.to_string(),
+    };
+    // A diff belongs to no repository
+    if kind == "diff" {
+        let id = segments.next()?;
+        return segments.next().is_none().then_some(Resource::Diff { id });
+    }
+    let repo_id = segments.next()?;
+
+    let resource = match kind.as_str() {
+        "blob" => {
+            let rev = segments.next()?;
+            Resource::Blob {
+                repo_id,
+                rev,
+                path: join_segments(segments)?,
+            }
+        }
+        "index" => Resource::Index {
+            repo_id,
+            path: join_segments(segments)?,
+        },
+        "stage" => {
+            let stage = match segments.next()?.as_str() {
+                "base" => ConflictStage::Base,
+                "ours" => ConflictStage::Ours,
+                "theirs" => ConflictStage::Theirs,
+                _ => return None,
+            };
+            Resource::Stage {
+                repo_id,
+                stage,
+                path: join_segments(segments)?,
+            }
+        }
+        "worktree" => Resource::Worktree {
+            repo_id,
+            path: join_segments(segments)?,
+        },
+        _ => return None,
+    };
+
+    Some(resource)
+}
+
+/// Rebuild a repo-relative path from the remaining URL segments
+fn join_segments(segments: impl Iterator<Item = String>) -> Option<String> {
+    let path = segments.collect::<Vec<_>>().join("/");
+    (!path.is_empty()).then_some(path)
+}
+
+/// Load the content a resource names
+fn serve(
+    backends: &BackendCache,
+    diffs: &DiffImages,
+    resource: &Resource,
+) -> Result<Served, VisiGitError> {
+    match resource {
+        Resource::Blob { repo_id, rev, path } => {
+            let backend = backends.get(&known_repo(backends, repo_id)?)?;
+            let blob = backend.read_blob(rev, path)?;
+
+            // A full SHA-1 or SHA-256 commit id can never point at
+            // different content
+            served(backend.as_ref(), blob, path, is_full_hash(rev))
+        }
+        Resource::Index { repo_id, path } => {
+            let backend = backends.get(&known_repo(backends, repo_id)?)?;
+            let blob = backend.read_index_blob(path)?;
+
+            // Staging changes what the URL names
+            served(backend.as_ref(), blob, path, false)
+        }
+        Resource::Stage {
+            repo_id,
+            stage,
+            path,
+        } => {
+            let backend = backends.get(&known_repo(backends, repo_id)?)?;
+            let blob = backend.read_stage_blob(path, *stage)?;
+
+            // Resolving or restarting the merge changes what the URL names
+            served(backend.as_ref(), blob, path, false)
+        }
+        Resource::Worktree { repo_id, path } => {
+            let repo_path = known_repo(backends, repo_id)?;
+            let backend = backends.get(&repo_path)?;
+            let file = validate::worktree_file(Path::new(&repo_path), path)?;
+            let content = fs::read(file)?;
+            let blob = Blob {
+                id: backend.hash_blob(&content)?,
+                content,
+            };
+
+            // Checked out with GIT_LFS_SKIP_SMUDGE, the file is a pointer
+            served(backend.as_ref(), blob, path, false)
+        }
+        Resource::Diff { id } => Ok(Served {
+            content: diffs
+                .get(id)
+                .ok_or_else(|| VisiGitError::DiffNotFound { id: id.clone() })?,
+            mime: "image/png",
+            blob_id: id.clone(),
+            // Ids are never reused
+            immutable: true,
+            lfs: LfsStatus::default(),
+        }),
+    }
+}
+
+/// Content to serve for `blob`, with LFS pointers replaced by their object
+fn served(
+    backend: &dyn GitBackend,
+    blob: Blob,
+    path: &str,
+    immutable: bool,
+) -> Result<Served, VisiGitError> {
+    let (blob, lfs) = smudge_blob(backend, blob, path)?;
+
+    Ok(Served {
+        mime: detect_mime(&blob.content, path),
+        content: blob.content,
+        blob_id: blob.id,
+        // The object can still be fetched, changing the content
+        immutable: immutable && !lfs.lfs_missing,
+        lfs,
+    })
+}
+
+fn known_repo(backends: &BackendCache, repo_id: &str) -> Result<String, VisiGitError> {
+    backends
+        .repo_path(repo_id)
+        .ok_or_else(|| VisiGitError::RepoNotFound {
+            path: repo_id.to_string(),
+        })
+}
+
+fn is_full_hash(rev: &str) -> bool {
+    matches!(rev.len(), 40 | 64) && rev.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Parse a single-range `Range: bytes=...` header against `len` bytes.
+///
+/// Multiple ranges and malformed headers fall back to the full body, which
+/// RFC 9110 allows.
+fn parse_range(value: Option<&str>, len: u64) -> ByteRange {
+    let Some(spec) = value.and_then(|value| value.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        // bytes=-N: the last N bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        // bytes=N-: from N to the end
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+            _ => return ByteRange::Full,
+        },
+    };
+
+    if len == 0 || start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+
+    ByteRange::Partial { start, end }
+}
+
+/// Build the response for loaded content, honouring `If-None-Match` and
+/// `Range`
+fn respond(served: Served, request_headers: &HeaderMap) -> Response<Vec<u8>> {
+    // A pointer's blob id stays the same once its object is fetched
+    let etag = if served.lfs.lfs_missing {
+        format!("\"{}-lfs-missing\"", served.blob_id)
+    } else {
+        format!("\"{}\"", served.blob_id)
+    };
+    let cache_control = if served.immutable {
+        "public, max-age=31536000, immutable"
+    } else {
+        // Refs and the working tree move, so revalidate against the ETag
+        "no-cache"
+    };
+    let builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::ACCEPT_RANGES, "bytes")
+        // Lets the diff view read pixels back from a canvas
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(header::ACCESS_CONTROL_EXPOSE_HEADERS, LFS_STATUS_HEADER);
+    let builder = match (served.lfs.is_lfs, served.lfs.lfs_missing) {
+        (false, _) => builder,
+        (true, false) => builder.header(LFS_STATUS_HEADER, "object"),
+        (true, true) => builder.header(LFS_STATUS_HEADER, "missing"),
+    };
+
+    let not_modified = request_headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        });
+    if not_modified {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Vec::new())
+            .expect("static response parts are valid");
+    }
+
+    let builder = builder.header(header::CONTENT_TYPE, served.mime);
+    let len = served.content.len() as u64;
+    let range = request_headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+
+    let response = match parse_range(range, len) {
+        ByteRange::Full => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, len)
+            .body(served.content),
+        ByteRange::Partial { start, end } => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, len),
+            )
+            .header(header::CONTENT_LENGTH, end - start + 1)
+            .body(served.content[start as usize..=end as usize].to_vec()),
+        ByteRange::Unsatisfiable => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(Vec::new()),
+    };
+
+    response.expect("static response parts are valid")
+}
+
+/// HTTP status for a failed lookup
+fn status_for(error: &VisiGitError) -> StatusCode {
+    match error {
+        VisiGitError::RepoNotFound { .. }
+        | VisiGitError::NotARepository { .. }
+        | VisiGitError::PathNotInRevision { .. }
+        | VisiGitError::PathNotInIndex { .. }
+        | VisiGitError::PathNotInStage { .. }
+        | VisiGitError::RevisionNotFound { .. }
+        | VisiGitError::DiffNotFound { .. } => StatusCode::NOT_FOUND,
+        VisiGitError::AbsolutePath { .. } | VisiGitError::PathEscapesRepo { .. } => {
+            StatusCode::FORBIDDEN
+        }
+        VisiGitError::InvalidRevision { .. } | VisiGitError::InvalidPath { .. } => {
+            StatusCode::BAD_REQUEST
+        }
+        VisiGitError::Io { kind, .. } if kind == "NotFound" => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn text_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(message.as_bytes().to_vec())
+        .expect("static response parts are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_lfs_pointer, store_lfs_object};
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n0123456789";
+
+    /// Helper to create a repository with `logo.png` committed, registered
+    /// in a fresh cache. Returns the temp dir, cache and repo id.
+    fn registered_repo() -> (TempDir, BackendCache, String) {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path();
+
+        for args in [
+            &["init"][..],
+            &["config", "user.email", "test@test.com"],
+            &["config", "user.name", "Test User"],
+        ] {
+            Command::new("git")
+                .args(args)
+                .current_dir(path)
+                .output()
+                .expect("Failed to set up git repo");
+        }
+
+        fs::write(path.join("logo.png"), PNG).unwrap();
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        Command::new("git")
+            .args(["commit", "-m", "Add logo"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+
+        let cache = BackendCache::default();
+        let id = cache.repo_id(path.to_str().unwrap()).unwrap();
+        (temp_dir, cache, id)
+    }
+
+    fn get(cache: &BackendCache, uri: &str, headers: &[(&str, &str)]) -> Response<Vec<u8>> {
+        let mut request = Request::builder().uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        handle(
+            cache,
+            &DiffImages::default(),
+            &request.body(Vec::new()).unwrap(),
+        )
+    }
+
+    fn header_value(response: &Response<Vec<u8>>, name: header::HeaderName) -> &str {
+        response.headers()[name].to_str().unwrap()
+    }
+
+    // ============================================
+    // Tests for URL parsing
+    // ============================================
+
+    #[test]
+    fn test_parse_uri_blob_index_stage_and_worktree() {
+        let uri: Uri = "visigit://blob/abc/HEAD/assets/logo.png".parse().unwrap();
+        assert_eq!(
+            parse_uri(&uri),
+            Some(Resource::Blob {
+                repo_id: "abc".to_string(),
+                rev: "HEAD".to_string(),
+                path: "assets/logo.png".to_string(),
+            })
+        );
+
+        let uri: Uri = "visigit://index/abc/assets/logo.png".parse().unwrap();
+        assert_eq!(
+            parse_uri(&uri),
+            Some(Resource::Index {
+                repo_id: "abc".to_string(),
+                path: "assets/logo.png".to_string(),
+            })
+        );
+
+        let uri: Uri = "visigit://stage/abc/theirs/assets/logo.png"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            parse_uri(&uri),
+            Some(Resource::Stage {
+                repo_id: "abc".to_string(),
+                stage: ConflictStage::Theirs,
+                path: "assets/logo.png".to_string(),
+            })
+        );
+
+        let uri: Uri = "visigit://worktree/abc/logo.png".parse().unwrap();
+        assert_eq!(
+            parse_uri(&uri),
+            Some(Resource::Worktree {
+                repo_id: "abc".to_string(),
+                path: "logo.png".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_uri_accepts_localhost_forms() {
+        let expected = Some(Resource::Worktree {
+            repo_id: "abc".to_string(),
+            path: "logo.png".to_string(),
+        });
+
+        for url in [
+            "visigit://localhost/worktree/abc/logo.png",
+            "http://visigit.localhost/worktree/abc/logo.png",
+        ] {
+            let uri: Uri = url.parse().unwrap();
+            assert_eq!(parse_uri(&uri), expected, "for {}", url);
+        }
+    }
+
+    #[test]
+    fn test_parse_uri_decodes_each_segment() {
+        let uri: Uri = "visigit://blob/abc/feature%2Flogo/my%20images/caf%C3%A9.png"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            parse_uri(&uri),
+            Some(Resource::Blob {
+                repo_id: "abc".to_string(),
+                rev: "feature/logo".to_string(),
+                path: "my images/café.png".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_uri_rejects_incomplete_urls() {
+        for url in [
+            "visigit://blob/abc/HEAD",
+            "visigit://worktree/abc",
+            "visigit://stage/abc/ours",
+            "visigit://stage/abc/mine/logo.png",
+            "visigit://other/abc/logo.png",
+            "visigit://localhost/",
+            "visigit://diff/",
+            "visigit://diff/abc/logo.png",
+        ] {
+            let uri: Uri = url.parse().unwrap();
+            assert_eq!(parse_uri(&uri), None, "for {}", url);
+        }
+    }
+
+    // ============================================
+    // Tests for Range parsing
+    // ============================================
+
+    #[test]
+    fn test_parse_range_forms() {
+        assert_eq!(parse_range(None, 10), ByteRange::Full);
+        assert_eq!(
+            parse_range(Some("bytes=2-5"), 10),
+            ByteRange::Partial { start: 2, end: 5 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=4-"), 10),
+            ByteRange::Partial { start: 4, end: 9 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-3"), 10),
+            ByteRange::Partial { start: 7, end: 9 }
+        );
+        // End past the content is clamped
+        assert_eq!(
+            parse_range(Some("bytes=8-100"), 10),
+            ByteRange::Partial { start: 8, end: 9 }
+        );
+    }
+
+    #[test]
+    fn test_parse_range_unsatisfiable_and_ignored() {
+        assert_eq!(parse_range(Some("bytes=10-"), 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=-0"), 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-"), 0), ByteRange::Unsatisfiable);
+
+        assert_eq!(parse_range(Some("bytes=5-2"), 10), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-1,4-5"), 10), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 10), ByteRange::Full);
+    }
+
+    // ============================================
+    // Tests for handle
+    // ============================================
+
+    #[test]
+    fn test_serves_blob_with_content_type_and_etag() {
+        let (temp_repo, cache, id) = registered_repo();
+        let blob_id = String::from_utf8(
+            Command::new("git")
+                .args(["rev-parse", "HEAD:logo.png"])
+                .current_dir(temp_repo.path())
+                .output()
+                .unwrap()
+                .stdout,
+        )
+        .unwrap();
+
+        let response = get(&cache, &format!("visigit://blob/{}/HEAD/logo.png", id), &[]);
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), PNG);
+        assert_eq!(header_value(&response, header::CONTENT_TYPE), "image/png");
+        assert_eq!(
+            header_value(&response, header::ETAG),
+            format!("\"{}\"", blob_id.trim())
+        );
+        assert_eq!(header_value(&response, header::CACHE_CONTROL), "no-cache");
+    }
+
+    #[test]
+    fn test_serves_worktree_file_with_blob_id_etag() {
+        let (temp_repo, cache, id) = registered_repo();
+        let modified = b"\x89PNG\r\n\x1a\nmodified";
+        fs::write(temp_repo.path().join("logo.png"), modified).unwrap();
+
+        let backend = cache.get(&temp_repo.path().to_string_lossy()).unwrap();
+
+        let response = get(&cache, &format!("visigit://worktree/{}/logo.png", id), &[]);
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), modified);
+        assert_eq!(
+            header_value(&response, header::ETAG),
+            format!("\"{}\"", backend.hash_blob(modified).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_serves_staged_version_from_index() {
+        let (temp_repo, cache, id) = registered_repo();
+        let staged = b"\x89PNG\r\n\x1a\nstaged";
+        fs::write(temp_repo.path().join("logo.png"), staged).unwrap();
+        Command::new("git")
+            .args(["add", "logo.png"])
+            .current_dir(temp_repo.path())
+            .output()
+            .unwrap();
+        fs::write(temp_repo.path().join("logo.png"), b"\x89PNG\r\n\x1a\nlater").unwrap();
+
+        let response = get(&cache, &format!("visigit://index/{}/logo.png", id), &[]);
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), staged);
+        assert_eq!(header_value(&response, header::CONTENT_TYPE), "image/png");
+        assert_eq!(header_value(&response, header::CACHE_CONTROL), "no-cache");
+    }
+
+    #[test]
+    fn test_full_commit_hash_is_immutable() {
+        let (temp_repo, cache, id) = registered_repo();
+        let hash = String::from_utf8(
+            Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .current_dir(temp_repo.path())
+                .output()
+                .unwrap()
+                .stdout,
+        )
+        .unwrap();
+
+        let response = get(
+            &cache,
+            &format!("visigit://blob/{}/{}/logo.png", id, hash.trim()),
+            &[],
+        );
+
+        assert_eq!(
+            header_value(&response, header::CACHE_CONTROL),
+            "public, max-age=31536000, immutable"
+        );
+    }
+
+    #[test]
+    fn test_full_hash_accepts_sha1_and_sha256_ids() {
+        assert!(is_full_hash(&"a".repeat(40)));
+        assert!(is_full_hash(&"0123456789abcdef".repeat(4)));
+        assert!(!is_full_hash("abc1234"));
+        assert!(!is_full_hash(&"a".repeat(50)));
+        assert!(!is_full_hash(&"g".repeat(64)));
+    }
+
+    #[test]
+    fn test_matching_if_none_match_returns_not_modified() {
+        let (_temp_repo, cache, id) = registered_repo();
+        let url = format!("visigit://blob/{}/HEAD/logo.png", id);
+        let etag = header_value(&get(&cache, &url, &[]), header::ETAG).to_string();
+
+        let response = get(&cache, &url, &[("If-None-Match", &etag)]);
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.body().is_empty());
+    }
+
+    #[test]
+    fn test_range_request_returns_partial_content() {
+        let (_temp_repo, cache, id) = registered_repo();
+        let url = format!("visigit://blob/{}/HEAD/logo.png", id);
+
+        let response = get(&cache, &url, &[("Range", "bytes=8-11")]);
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.body(), b"0123");
+        assert_eq!(
+            header_value(&response, header::CONTENT_RANGE),
+            format!("bytes 8-11/{}", PNG.len())
+        );
+        // The type comes from the whole blob, not the slice
+        assert_eq!(header_value(&response, header::CONTENT_TYPE), "image/png");
+
+        let response = get(&cache, &url, &[("Range", "bytes=1000-")]);
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    }
+
+    #[test]
+    fn test_missing_content_is_not_found() {
+        let (_temp_repo, cache, id) = registered_repo();
+
+        for url in [
+            format!("visigit://blob/{}/HEAD/missing.png", id),
+            format!("visigit://blob/{}/no-such-rev/logo.png", id),
+            format!("visigit://index/{}/missing.png", id),
+            // Not in conflict, so there are no stages
+            format!("visigit://stage/{}/ours/logo.png", id),
+            format!("visigit://worktree/{}/missing.png", id),
+            "visigit://blob/unknown/HEAD/logo.png".to_string(),
+        ] {
+            assert_eq!(
+                get(&cache, &url, &[]).status(),
+                StatusCode::NOT_FOUND,
+                "for {}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_paths_outside_the_repo_are_forbidden() {
+        let (_temp_repo, cache, id) = registered_repo();
+
+        for url in [
+            format!("visigit://worktree/{}/..%2Fsecret.png", id),
+            format!("visigit://worktree/{}/assets/..%2F..%2Fsecret.png", id),
+            format!("visigit://worktree/{}/%2Fetc%2Fpasswd", id),
+            format!("visigit://blob/{}/HEAD/..%2Fsecret.png", id),
+        ] {
+            assert_eq!(
+                get(&cache, &url, &[]).status(),
+                StatusCode::FORBIDDEN,
+                "for {}",
+                url
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_worktree_symlink_out_of_the_repo_is_forbidden() {
+        let (temp_repo, cache, id) = registered_repo();
+        let outside = TempDir::new().unwrap();
+        fs::write(outside.path().join("secret.png"), PNG).unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("secret.png"),
+            temp_repo.path().join("link.png"),
+        )
+        .unwrap();
+
+        let response = get(&cache, &format!("visigit://worktree/{}/link.png", id), &[]);
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_serves_lfs_object_instead_of_pointer() {
+        let (temp_repo, cache, id) = registered_repo();
+        let pointer = commit_lfs_pointer(temp_repo.path(), PNG);
+        store_lfs_object(temp_repo.path(), &pointer, PNG);
+
+        for url in [
+            format!("visigit://blob/{}/HEAD/hero.png", id),
+            format!("visigit://index/{}/hero.png", id),
+            format!("visigit://worktree/{}/hero.png", id),
+        ] {
+            let response = get(&cache, &url, &[]);
+
+            assert_eq!(response.status(), StatusCode::OK, "for {}", url);
+            assert_eq!(response.body(), PNG, "for {}", url);
+            assert_eq!(header_value(&response, header::CONTENT_TYPE), "image/png");
+            assert_eq!(
+                header_value(
+                    &response,
+                    header::HeaderName::from_static(LFS_STATUS_HEADER)
+                ),
+                "object"
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_lfs_object_is_flagged() {
+        let (temp_repo, cache, id) = registered_repo();
+        commit_lfs_pointer(temp_repo.path(), PNG);
+        let head = String::from_utf8(
+            Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .current_dir(temp_repo.path())
+                .output()
+                .unwrap()
+                .stdout,
+        )
+        .unwrap();
+
+        let response = get(
+            &cache,
+            &format!("visigit://blob/{}/{}/hero.png", id, head.trim()),
+            &[],
+        );
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.body().starts_with(b"version https://git-lfs"));
+        assert_eq!(
+            header_value(
+                &response,
+                header::HeaderName::from_static(LFS_STATUS_HEADER)
+            ),
+            "missing"
+        );
+        assert!(header_value(&response, header::ETAG).ends_with("-lfs-missing\""));
+        // Fetching the object later changes what the URL serves
+        assert_eq!(header_value(&response, header::CACHE_CONTROL), "no-cache");
+        assert_eq!(
+            get(&cache, &format!("visigit://blob/{}/HEAD/logo.png", id), &[])
+                .headers()
+                .get(LFS_STATUS_HEADER),
+            None
+        );
+    }
+
+    #[test]
+    fn test_head_request_has_headers_but_no_body() {
+        let (_temp_repo, cache, id) = registered_repo();
+        let request = Request::builder()
+            .method(Method::HEAD)
+            .uri(format!("visigit://blob/{}/HEAD/logo.png", id))
+            .body(Vec::new())
+            .unwrap();
+
+        let response = handle(&cache, &DiffImages::default(), &request);
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.body().is_empty());
+        assert_eq!(
+            header_value(&response, header::CONTENT_LENGTH),
+            PNG.len().to_string()
+        );
+    }
+
+    #[test]
+    fn test_serves_kept_diff_image() {
+        let cache = BackendCache::default();
+        let diffs = DiffImages::default();
+        let id = diffs.insert(PNG.to_vec());
+        let request = |url: String| Request::builder().uri(url).body(Vec::new()).unwrap();
+
+        let response = handle(&cache, &diffs, &request(format!("visigit://diff/{}", id)));
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), PNG);
+        assert_eq!(header_value(&response, header::CONTENT_TYPE), "image/png");
+        assert_eq!(header_value(&response, header::ETAG), format!("\"{}\"", id));
+        assert!(header_value(&response, header::CACHE_CONTROL).contains("immutable"));
+
+        let response = handle(
+            &cache,
+            &diffs,
+            &request("visigit://localhost/diff/gone".into()),
+        );
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_malformed_url_is_bad_request() {
+        let (_temp_repo, cache, _id) = registered_repo();
+
+        let response = get(&cache, "visigit://blob/", &[]);
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,44 @@
+//! Helpers shared by tests in more than one module.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::git::LfsPointer;
+
+/// Commit `hero.png` in the repository at `root` as an LFS pointer to
+/// `object`, returning the pointer. The object itself is not stored.
+pub fn commit_lfs_pointer(root: &Path, object: &[u8]) -> LfsPointer {
+    let pointer = LfsPointer {
+        oid: "cd".repeat(32),
+        size: object.len() as u64,
+    };
+    let text = format!(
+        "version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize {}\n",
+        pointer.oid, pointer.size
+    );
+    fs::write(root.join("hero.png"), text).expect("Failed to write pointer");
+
+    for args in [&["add", "-A"][..], &["commit", "-m", "Add hero"]] {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .output()
+            .expect("Failed to run git");
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    pointer
+}
+
+/// Store `content` where `git lfs fetch` would put `pointer`'s object
+pub fn store_lfs_object(root: &Path, pointer: &LfsPointer, content: &[u8]) {
+    let object_path = pointer.object_path(&root.join(".git").join("lfs"));
+    fs::create_dir_all(object_path.parent().unwrap()).unwrap();
+    fs::write(object_path, content).unwrap();
+}
@@ -0,0 +1,171 @@
+//! Backups of the versions a restore replaces, so it can be undone.
+//!
+//! Every backup is a folder in the app's trash directory named after its
+//! id. It holds `backup.json` and, when a working tree file was replaced,
+//! that file's bytes in `content`.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, VisiGitError};
+use crate::git::RestoreTarget;
+
+const METADATA_FILE: &str = "backup.json";
+const CONTENT_FILE: &str = "content";
+
+/// What a restore replaced
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Backup {
+    pub id: String,
+    /// Canonical working tree root of the repository
+    pub repo: String,
+    pub path: String,
+    pub target: RestoreTarget,
+    /// Revision the file was restored from
+    pub source_rev: String,
+    /// Blob id of the version that was replaced, `None` if there was none
+    pub previous_id: Option<String>,
+    /// Unix time of the restore
+    pub created_at: i64,
+}
+
+/// App-managed directory holding backups
+pub struct Trash {
+    dir: PathBuf,
+}
+
+impl Trash {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Fresh id for a backup: the current time plus a counter, in hex
+    pub fn new_id() -> String {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        format!("{:x}-{:x}", nanos, COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Save `backup`, with the bytes of the working tree file it replaced
+    /// if there was one
+    pub fn save(&self, backup: &Backup, content: Option<&[u8]>) -> Result<()> {
+        let folder = self.dir.join(&backup.id);
+        fs::create_dir_all(&folder)?;
+
+        if let Some(content) = content {
+            fs::write(folder.join(CONTENT_FILE), content)?;
+        }
+        // Written last, so a backup is never loaded without its content
+        let metadata = serde_json::to_vec_pretty(backup).map_err(|e| VisiGitError::Io {
+            kind: "InvalidData".to_string(),
+            message: e.to_string(),
+        })?;
+        fs::write(folder.join(METADATA_FILE), metadata)?;
+
+        Ok(())
+    }
+
+    /// The backup `id` saved for the repository at `repo`
+    pub fn load(&self, repo: &str, id: &str) -> Result<Backup> {
+        let not_found = || VisiGitError::BackupNotFound { id: id.to_string() };
+        // Ids only ever contain hex digits and a dash, so one can never
+        // name a folder outside the trash
+        if id.is_empty() || !id.bytes().all(|b| b.is_ascii_hexdigit() || b == b'-') {
+            return Err(not_found());
+        }
+
+        let metadata = match fs::read(self.dir.join(id).join(METADATA_FILE)) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(not_found()),
+            Err(e) => return Err(e.into()),
+        };
+        let backup: Backup = serde_json::from_slice(&metadata).map_err(|_| not_found())?;
+        if backup.id != id || backup.repo != repo {
+            return Err(not_found());
+        }
+
+        Ok(backup)
+    }
+
+    /// Bytes of the working tree file `backup` replaced
+    pub fn content(&self, backup: &Backup) -> Result<Vec<u8>> {
+        Ok(fs::read(self.dir.join(&backup.id).join(CONTENT_FILE))?)
+    }
+
+    /// Delete `backup` once it has been undone
+    pub fn remove(&self, backup: &Backup) -> Result<()> {
+        Ok(fs::remove_dir_all(self.dir.join(&backup.id))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn backup(repo: &str) -> Backup {
+        Backup {
+            id: Trash::new_id(),
+            repo: repo.to_string(),
+            path: "logo.png".to_string(),
+            target: RestoreTarget::Worktree,
+            source_rev: "HEAD~3".to_string(),
+            previous_id: Some("b6fc4c620b67d95f953a5c1c1230aaab5db5a1b0".to_string()),
+            created_at: 1_710_000_000,
+        }
+    }
+
+    #[test]
+    fn test_new_ids_are_unique() {
+        assert_ne!(Trash::new_id(), Trash::new_id());
+    }
+
+    #[test]
+    fn test_saved_backup_round_trips() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::new(temp_dir.path().join("trash"));
+        let saved = backup("/repo");
+
+        trash.save(&saved, Some(b"hello")).unwrap();
+
+        let loaded = trash.load("/repo", &saved.id).unwrap();
+        assert_eq!(loaded, saved);
+        assert_eq!(trash.content(&loaded).unwrap(), b"hello");
+
+        trash.remove(&loaded).unwrap();
+        assert_eq!(
+            trash.load("/repo", &saved.id),
+            Err(VisiGitError::BackupNotFound { id: saved.id })
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_other_repos_and_foreign_ids() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::new(temp_dir.path().join("trash"));
+        let saved = backup("/repo");
+        trash.save(&saved, None).unwrap();
+
+        for (repo, id) in [
+            ("/other", saved.id.as_str()),
+            ("/repo", "../trash"),
+            ("/repo", ""),
+        ] {
+            assert_eq!(
+                trash.load(repo, id),
+                Err(VisiGitError::BackupNotFound { id: id.to_string() }),
+                "for {:?}",
+                id
+            );
+        }
+    }
+}
@@ -4,24 +4,46 @@ import ImageComparer from './components/ImageComparer'
 import FolderPicker from './components/FolderPicker'
 import ErrorBoundary from './components/ErrorBoundary'
 import CommitSelector from './components/CommitSelector'
+import StageSelector from './components/StageSelector'
+import CommitPanel from './components/CommitPanel'
+import RestoreControls from './components/RestoreControls'
+import ConflictResolver from './components/ConflictResolver'
+import LfsNotice from './components/LfsNotice'
 
 function App() {
   const {
     repoPath,
     changedFiles,
     selectedFile,
+    compareStage,
     isLoading,
     error,
     imageData,
+    conflictImages,
+    lfsMissing,
     commits,
+    commitsCursor,
+    refs,
     baseCommit,
     compareCommit,
+    lastBackup,
+    sortByDifference,
     openRepo,
     refreshFiles,
     selectFile,
+    selectCompareStage,
     clearError,
+    stageFiles,
+    unstageFiles,
+    createCommit,
+    restoreFile,
+    undoRestore,
+    resolveConflict,
+    loadMoreCommits,
     selectBaseCommit,
     selectCompareCommit,
+    computeImageDiff,
+    setSortByDifference,
   } = useGitRepo()
 
   return (
@@ -96,39 +118,87 @@ function App() {
                 <div className="p-3 border-b border-gray-200 dark:border-gray-700 space-y-3">
                   <CommitSelector
                     commits={commits}
+                    refs={refs}
                     selectedCommit={baseCommit}
                     onSelect={selectBaseCommit}
                     label="Base (Before)"
                   />
                   <CommitSelector
                     commits={commits}
+                    refs={refs}
                     selectedCommit={compareCommit}
                     onSelect={selectCompareCommit}
                     label="Compare (After)"
                   />
+                  {commitsCursor && (
+                    <button
+                      onClick={loadMoreCommits}
+                      disabled={isLoading}
+                      className="w-full px-2 py-1 text-xs rounded border border-gray-300 dark:border-gray-600 hover:bg-gray-100 dark:hover:bg-gray-700 transition-colors"
+                    >
+                      Load older commits
+                    </button>
+                  )}
                 </div>
 
-                <div className="p-3 border-b border-gray-200 dark:border-gray-700">
+                <div className="p-3 border-b border-gray-200 dark:border-gray-700 space-y-3">
                   <h2 className="font-medium text-sm text-gray-600 dark:text-gray-300">
                     Changed Images
                     {changedFiles.length > 0 && (
                       <span className="ml-2 text-gray-400">({changedFiles.length})</span>
                     )}
                   </h2>
+                  <StageSelector compareStage={compareStage} onSelect={selectCompareStage} />
+                  <label className="flex items-center gap-2 text-sm text-gray-600 dark:text-gray-300">
+                    <input
+                      type="checkbox"
+                      checked={sortByDifference}
+                      onChange={(e) => setSortByDifference(e.target.checked)}
+                      disabled={isLoading}
+                    />
+                    Most changed first
+                  </label>
                 </div>
+                <CommitPanel
+                  files={changedFiles}
+                  selectedFile={selectedFile}
+                  onStage={stageFiles}
+                  onUnstage={unstageFiles}
+                  onCommit={createCommit}
+                  isLoading={isLoading}
+                />
+                <RestoreControls
+                  selectedFile={selectedFile}
+                  sourceRev={baseCommit}
+                  lastBackup={lastBackup}
+                  onRestore={restoreFile}
+                  onUndo={undoRestore}
+                  isLoading={isLoading}
+                />
                 <FileList
                   files={changedFiles}
                   selectedFile={selectedFile}
                   onSelect={selectFile}
                   isLoading={isLoading}
+                  sortByDifference={sortByDifference}
                 />
               </aside>
 
               {/* Image Comparer */}
-              <section className="flex-1 overflow-hidden bg-gray-50 dark:bg-gray-950">
+              <section className="flex-1 flex flex-col overflow-hidden bg-gray-50 dark:bg-gray-950">
+                {selectedFile && conflictImages && (
+                  <ConflictResolver
+                    path={selectedFile}
+                    images={conflictImages}
+                    onResolve={resolveConflict}
+                    isLoading={isLoading}
+                  />
+                )}
+                {selectedFile && lfsMissing && <LfsNotice path={selectedFile} />}
                 <ImageComparer
                   currentSrc={imageData.currentSrc}
                   previousSrc={imageData.previousSrc}
+                  onComputeDiff={computeImageDiff}
                 />
               </section>
             </>
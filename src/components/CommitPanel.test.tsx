@@ -0,0 +1,87 @@
+import { describe, it, expect, vi } from 'vitest'
+import { render, screen, fireEvent, waitFor } from '@testing-library/react'
+import CommitPanel from './CommitPanel'
+import type { ChangedFile } from '../types'
+
+describe('CommitPanel', () => {
+  const file = (path: string, staged: boolean, unstaged: boolean): ChangedFile => ({
+    path,
+    filename: path,
+    status: { kind: 'modified' },
+    staged,
+    unstaged,
+    index_status: staged ? { kind: 'modified' } : null,
+    worktree_status: unstaged ? { kind: 'modified' } : null,
+    conflict: null,
+    dimensions: null,
+    similarity: null,
+  })
+
+  const mockFiles = [file('logo.png', true, false), file('hero.png', false, true)]
+
+  const renderPanel = (props: Partial<Parameters<typeof CommitPanel>[0]> = {}) =>
+    render(
+      <CommitPanel
+        files={mockFiles}
+        selectedFile={null}
+        onStage={() => {}}
+        onUnstage={() => {}}
+        onCommit={async () => null}
+        isLoading={false}
+        {...props}
+      />
+    )
+
+  it('stages the selected file', () => {
+    const handleStage = vi.fn()
+    renderPanel({ selectedFile: 'hero.png', onStage: handleStage })
+
+    expect(screen.getByText('Unstage')).toBeDisabled()
+    fireEvent.click(screen.getByText('Stage'))
+
+    expect(handleStage).toHaveBeenCalledWith(['hero.png'], false)
+  })
+
+  it('unstages the selected file', () => {
+    const handleUnstage = vi.fn()
+    renderPanel({ selectedFile: 'logo.png', onUnstage: handleUnstage })
+
+    expect(screen.getByText('Stage')).toBeDisabled()
+    fireEvent.click(screen.getByText('Unstage'))
+
+    expect(handleUnstage).toHaveBeenCalledWith(['logo.png'], false)
+  })
+
+  it('needs a message before committing', () => {
+    renderPanel()
+
+    expect(screen.getByText('Commit 1 staged')).toBeDisabled()
+  })
+
+  it('commits with the message and clears it afterwards', async () => {
+    const handleCommit = vi.fn().mockResolvedValue({ hash: 'abc123' })
+    renderPanel({ onCommit: handleCommit })
+
+    const textarea = screen.getByPlaceholderText('Commit message')
+    fireEvent.change(textarea, { target: { value: 'Brighten logo' } })
+    fireEvent.click(screen.getByLabelText('Allow during merge or rebase'))
+    fireEvent.click(screen.getByText('Commit 1 staged'))
+
+    expect(handleCommit).toHaveBeenCalledWith('Brighten logo', {
+      amend: false,
+      allowInProgress: true,
+    })
+    await waitFor(() => expect(textarea).toHaveValue(''))
+  })
+
+  it('allows amending with nothing staged', () => {
+    renderPanel({ files: [file('hero.png', false, true)] })
+
+    fireEvent.change(screen.getByPlaceholderText('Commit message'), {
+      target: { value: 'Reword' },
+    })
+    fireEvent.click(screen.getByLabelText('Amend last commit'))
+
+    expect(screen.getByText('Amend')).toBeEnabled()
+  })
+})
@@ -0,0 +1,91 @@
+import { useState } from 'react'
+import type { ChangedFile } from '../types'
+import type { CommitOptions } from '../hooks/useGitRepo'
+
+interface CommitPanelProps {
+  files: ChangedFile[]
+  selectedFile: string | null
+  onStage: (paths: string[], allowInProgress: boolean) => void
+  onUnstage: (paths: string[], allowInProgress: boolean) => void
+  onCommit: (message: string, options: CommitOptions) => Promise<unknown>
+  isLoading: boolean
+}
+
+export default function CommitPanel({
+  files,
+  selectedFile,
+  onStage,
+  onUnstage,
+  onCommit,
+  isLoading,
+}: CommitPanelProps) {
+  const [message, setMessage] = useState('')
+  const [amend, setAmend] = useState(false)
+  // Lets the commands run while a merge or rebase is in progress
+  const [allowInProgress, setAllowInProgress] = useState(false)
+
+  const selected = files.find((f) => f.path === selectedFile)
+  const stagedCount = files.filter((f) => f.staged).length
+  const canCommit = !isLoading && message.trim() !== '' && (stagedCount > 0 || amend)
+
+  const commit = async () => {
+    const result = await onCommit(message, { amend, allowInProgress })
+    if (result) {
+      setMessage('')
+      setAmend(false)
+    }
+  }
+
+  const buttonClass =
+    'flex-1 px-2 py-1 text-xs rounded border border-gray-300 dark:border-gray-600 hover:bg-gray-100 dark:hover:bg-gray-700 transition-colors disabled:opacity-50'
+
+  return (
+    <div className="p-3 border-b border-gray-200 dark:border-gray-700 space-y-2">
+      <div className="flex gap-2">
+        <button
+          onClick={() => selected && onStage([selected.path], allowInProgress)}
+          disabled={isLoading || !selected?.unstaged}
+          className={buttonClass}
+        >
+          Stage
+        </button>
+        <button
+          onClick={() => selected && onUnstage([selected.path], allowInProgress)}
+          disabled={isLoading || !selected?.staged}
+          className={buttonClass}
+        >
+          Unstage
+        </button>
+      </div>
+
+      <textarea
+        value={message}
+        onChange={(e) => setMessage(e.target.value)}
+        placeholder="Commit message"
+        rows={3}
+        className="w-full px-2 py-1 text-sm rounded border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100"
+      />
+
+      <label className="flex items-center gap-2 text-xs text-gray-600 dark:text-gray-400">
+        <input type="checkbox" checked={amend} onChange={(e) => setAmend(e.target.checked)} />
+        Amend last commit
+      </label>
+      <label className="flex items-center gap-2 text-xs text-gray-600 dark:text-gray-400">
+        <input
+          type="checkbox"
+          checked={allowInProgress}
+          onChange={(e) => setAllowInProgress(e.target.checked)}
+        />
+        Allow during merge or rebase
+      </label>
+
+      <button
+        onClick={commit}
+        disabled={!canCommit}
+        className="w-full px-3 py-1 text-sm rounded bg-blue-600 hover:bg-blue-700 text-white transition-colors disabled:opacity-50"
+      >
+        {amend ? 'Amend' : `Commit ${stagedCount} staged`}
+      </button>
+    </div>
+  )
+}
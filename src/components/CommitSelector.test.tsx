@@ -1,30 +1,55 @@
 import { describe, it, expect, vi } from 'vitest'
 import { render, screen, fireEvent } from '@testing-library/react'
 import CommitSelector from './CommitSelector'
-import type { CommitInfo } from '../types'
+import type { CommitInfo, RefInfo } from '../types'
 
 describe('CommitSelector', () => {
+  const commit = (hash: string, message: string, date: string): CommitInfo => ({
+    hash,
+    short_hash: hash.slice(0, 7),
+    message,
+    body: '',
+    parents: [],
+    author: 'Test User',
+    author_email: 'test@example.com',
+    date,
+    timestamp: 0,
+    timezone_offset: -300,
+    committer: 'Test User',
+    committer_email: 'test@example.com',
+    co_authors: [],
+    refs: [],
+  })
+
   const mockCommits: CommitInfo[] = [
-    {
-      hash: 'abc123456789',
-      short_hash: 'abc1234',
-      message: 'First commit',
-      author: 'Test User',
-      date: '2024-01-15 10:30:00 -0500',
-    },
-    {
-      hash: 'def987654321',
-      short_hash: 'def9876',
-      message: 'Second commit',
-      author: 'Test User',
-      date: '2024-01-16 14:20:00 -0500',
-    },
+    commit('abc123456789', 'First commit', '2024-01-15 10:30:00 -0500'),
+    commit('def987654321', 'Second commit', '2024-01-16 14:20:00 -0500'),
+  ]
+
+  const ref = (name: string, short_name: string, kind: RefInfo['kind']): RefInfo => ({
+    name,
+    short_name,
+    kind,
+    target: 'abc123456789',
+    upstream: null,
+    ahead: null,
+    behind: null,
+    date: '2024-01-15 10:30:00 -0500',
+    timestamp: 0,
+    is_head: false,
+  })
+
+  const mockRefs: RefInfo[] = [
+    { ...ref('refs/heads/feature/new-icons', 'feature/new-icons', 'branch'), ahead: 2, behind: 1 },
+    ref('refs/remotes/origin/main', 'origin/main', 'remote_branch'),
+    ref('refs/tags/v1.0', 'v1.0', 'tag'),
   ]
 
   it('renders dropdown with commits', () => {
     render(
       <CommitSelector
         commits={mockCommits}
+        refs={[]}
         selectedCommit={null}
         onSelect={() => {}}
         label="Base"
@@ -44,6 +69,7 @@ describe('CommitSelector', () => {
     render(
       <CommitSelector
         commits={mockCommits}
+        refs={[]}
         selectedCommit={null}
         onSelect={() => {}}
         label="Compare"
@@ -58,6 +84,7 @@ describe('CommitSelector', () => {
     render(
       <CommitSelector
         commits={mockCommits}
+        refs={[]}
         selectedCommit={null}
         onSelect={handleSelect}
         label="Base"
@@ -69,4 +96,38 @@ describe('CommitSelector', () => {
 
     expect(handleSelect).toHaveBeenCalledWith('abc123456789')
   })
+
+  it('groups branches, remote branches and tags by kind', () => {
+    const { container } = render(
+      <CommitSelector
+        commits={mockCommits}
+        refs={mockRefs}
+        selectedCommit={null}
+        onSelect={() => {}}
+        label="Base"
+      />
+    )
+
+    const group = (label: string) => container.querySelector(`optgroup[label="${label}"]`)
+    expect(group('Branches')).toHaveTextContent('feature/new-icons (↑2 ↓1)')
+    expect(group('Remote Branches')).toHaveTextContent('origin/main')
+    expect(group('Tags')).toHaveTextContent('v1.0')
+  })
+
+  it('selects a ref by its full name', () => {
+    const handleSelect = vi.fn()
+    render(
+      <CommitSelector
+        commits={mockCommits}
+        refs={mockRefs}
+        selectedCommit={null}
+        onSelect={handleSelect}
+        label="Base"
+      />
+    )
+
+    fireEvent.change(screen.getByRole('combobox'), { target: { value: 'refs/tags/v1.0' } })
+
+    expect(handleSelect).toHaveBeenCalledWith('refs/tags/v1.0')
+  })
 })
@@ -1,7 +1,22 @@
-import type { CommitInfo } from '../types'
+import type { CommitInfo, RefInfo, RefKind } from '../types'
+
+const REF_GROUPS: { kind: RefKind; label: string }[] = [
+  { kind: 'branch', label: 'Branches' },
+  { kind: 'remote_branch', label: 'Remote Branches' },
+  { kind: 'tag', label: 'Tags' },
+]
+
+// " (↑2 ↓1)" for a branch that has diverged from its upstream
+function trackingSummary(ref: RefInfo): string {
+  const parts: string[] = []
+  if (ref.ahead) parts.push(`↑${ref.ahead}`)
+  if (ref.behind) parts.push(`↓${ref.behind}`)
+  return parts.length > 0 ? ` (${parts.join(' ')})` : ''
+}
 
 interface CommitSelectorProps {
   commits: CommitInfo[]
+  refs: RefInfo[]
   selectedCommit: string | null
   onSelect: (commitHash: string | null) => void
   label: string
@@ -9,6 +24,7 @@ interface CommitSelectorProps {
 
 export default function CommitSelector({
   commits,
+  refs,
   selectedCommit,
   onSelect,
   label,
@@ -29,6 +45,20 @@ export default function CommitSelector({
         className="px-2 py-1 text-sm rounded border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100"
       >
         <option value="">Working Directory</option>
+        {REF_GROUPS.map(({ kind, label: groupLabel }) => {
+          const group = refs.filter((ref) => ref.kind === kind)
+          if (group.length === 0) return null
+          return (
+            <optgroup key={kind} label={groupLabel}>
+              {group.map((ref) => (
+                <option key={ref.name} value={ref.name}>
+                  {ref.short_name}
+                  {trackingSummary(ref)}
+                </option>
+              ))}
+            </optgroup>
+          )
+        })}
         {commits.map((commit) => (
           <option key={commit.hash} value={commit.hash}>
             {commit.short_hash} - {commit.message}
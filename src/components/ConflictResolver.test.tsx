@@ -0,0 +1,48 @@
+import { describe, it, expect, vi } from 'vitest'
+import { render, screen, fireEvent } from '@testing-library/react'
+import ConflictResolver from './ConflictResolver'
+import type { ConflictImages } from '../types'
+
+describe('ConflictResolver', () => {
+  const images: ConflictImages = {
+    base: 'visigit://localhost/stage/abc/base/hero.png',
+    ours: 'visigit://localhost/stage/abc/ours/hero.png',
+    theirs: null,
+  }
+
+  it('shows each side of the conflict', () => {
+    render(
+      <ConflictResolver path="hero.png" images={images} onResolve={() => {}} isLoading={false} />
+    )
+
+    expect(screen.getByAltText('Base')).toHaveAttribute('src', images.base)
+    expect(screen.getByAltText('Ours')).toHaveAttribute('src', images.ours)
+    expect(screen.queryByAltText('Theirs')).not.toBeInTheDocument()
+    expect(screen.getByText('No file')).toBeInTheDocument()
+  })
+
+  it('resolves with the chosen side', () => {
+    const handleResolve = vi.fn()
+    render(
+      <ConflictResolver path="hero.png" images={images} onResolve={handleResolve} isLoading={false} />
+    )
+
+    fireEvent.click(screen.getByText('Use Ours'))
+    fireEvent.click(screen.getByText('Delete (Theirs)'))
+    fireEvent.click(screen.getByText('Mark working file as resolved'))
+
+    expect(handleResolve.mock.calls).toEqual([
+      ['hero.png', 'ours'],
+      ['hero.png', 'theirs'],
+      ['hero.png', 'worktree_file'],
+    ])
+  })
+
+  it('is disabled while loading', () => {
+    render(
+      <ConflictResolver path="hero.png" images={images} onResolve={() => {}} isLoading={true} />
+    )
+
+    expect(screen.getByText('Use Base')).toBeDisabled()
+  })
+})
@@ -0,0 +1,63 @@
+import type { ConflictImages, ConflictResolution, ConflictStage } from '../types'
+
+interface ConflictResolverProps {
+  path: string
+  images: ConflictImages
+  onResolve: (path: string, resolution: ConflictResolution) => void
+  isLoading: boolean
+}
+
+const SIDES: { stage: ConflictStage; label: string }[] = [
+  { stage: 'base', label: 'Base' },
+  { stage: 'ours', label: 'Ours' },
+  { stage: 'theirs', label: 'Theirs' },
+]
+
+export default function ConflictResolver({
+  path,
+  images,
+  onResolve,
+  isLoading,
+}: ConflictResolverProps) {
+  const buttonClass =
+    'w-full px-2 py-1 text-xs rounded border border-gray-300 dark:border-gray-600 hover:bg-gray-100 dark:hover:bg-gray-700 transition-colors disabled:opacity-50'
+
+  return (
+    <div className="p-3 border-b border-gray-200 dark:border-gray-700 bg-white dark:bg-gray-800 space-y-2">
+      <h2 className="font-medium text-sm text-gray-600 dark:text-gray-300">
+        Conflict in {path}
+      </h2>
+      <div className="grid grid-cols-3 gap-3">
+        {SIDES.map(({ stage, label }) => {
+          const src = images[stage]
+          return (
+            <div key={stage} className="flex flex-col gap-1">
+              <div className="h-32 flex items-center justify-center rounded border border-gray-200 dark:border-gray-700 bg-gray-50 dark:bg-gray-900">
+                {src ? (
+                  <img src={src} alt={label} className="max-h-full max-w-full object-contain" />
+                ) : (
+                  // A side that deleted the file, or never had it
+                  <span className="text-xs text-gray-400">No file</span>
+                )}
+              </div>
+              <button
+                onClick={() => onResolve(path, stage)}
+                disabled={isLoading}
+                className={buttonClass}
+              >
+                {src ? `Use ${label}` : `Delete (${label})`}
+              </button>
+            </div>
+          )
+        })}
+      </div>
+      <button
+        onClick={() => onResolve(path, 'worktree_file')}
+        disabled={isLoading}
+        className={buttonClass}
+      >
+        Mark working file as resolved
+      </button>
+    </div>
+  )
+}
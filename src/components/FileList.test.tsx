@@ -5,9 +5,9 @@ import type { ChangedFile } from '../types'
 
 describe('FileList', () => {
   const mockFiles: ChangedFile[] = [
-    { path: 'images/logo.png', filename: 'logo.png', status: 'modified' },
-    { path: 'assets/icon.svg', filename: 'icon.svg', status: 'added' },
-    { path: 'old/banner.jpg', filename: 'banner.jpg', status: 'deleted' },
+    { path: 'images/logo.png', filename: 'logo.png', status: { kind: 'modified' }, staged: false, unstaged: true, index_status: null, worktree_status: { kind: 'modified' }, conflict: null, dimensions: null, similarity: null },
+    { path: 'assets/icon.svg', filename: 'icon.svg', status: { kind: 'added' }, staged: true, unstaged: false, index_status: { kind: 'added' }, worktree_status: null, conflict: null, dimensions: null, similarity: null },
+    { path: 'old/banner.jpg', filename: 'banner.jpg', status: { kind: 'deleted' }, staged: false, unstaged: true, index_status: null, worktree_status: { kind: 'deleted' }, conflict: null, dimensions: null, similarity: null },
   ]
 
   it('renders a list of files', () => {
@@ -27,6 +27,18 @@ describe('FileList', () => {
     expect(screen.getByText('D')).toBeInTheDocument()
   })
 
+  it('shows renamed and conflicted files', () => {
+    const files: ChangedFile[] = [
+      { path: 'brand/logo.svg', filename: 'logo.svg', status: { kind: 'renamed', from: 'img/logo.svg' }, staged: true, unstaged: false, index_status: { kind: 'renamed', from: 'img/logo.svg' }, worktree_status: null, conflict: null, dimensions: null, similarity: null },
+      { path: 'art/hero.png', filename: 'hero.png', status: { kind: 'unmerged', ours: 'modified', theirs: 'modified' }, staged: false, unstaged: true, index_status: null, worktree_status: { kind: 'unmerged', ours: 'modified', theirs: 'modified' }, conflict: { base: 'a1', ours: 'b2', theirs: 'c3' }, dimensions: null, similarity: null },
+    ]
+    render(<FileList files={files} selectedFile={null} onSelect={() => {}} />)
+
+    expect(screen.getByText('R')).toHaveAttribute('title', 'renamed from img/logo.svg')
+    expect(screen.getByText('U')).toBeInTheDocument()
+    expect(screen.getByText('hero.png')).toBeInTheDocument()
+  })
+
   it('calls onSelect when a file is clicked', () => {
     const handleSelect = vi.fn()
     render(<FileList files={mockFiles} selectedFile={null} onSelect={handleSelect} />)
@@ -49,6 +61,31 @@ describe('FileList', () => {
     expect(selectedItem).toHaveClass('bg-blue-100')
   })
 
+  const scored = (path: string, ssim: number, trivial = false): ChangedFile => ({
+    ...mockFiles[0],
+    path,
+    filename: path,
+    similarity: { ssim, tiles: { tile_size: 8, columns: 1, rows: 1, scores: [ssim] }, hash_distance: trivial ? 0 : 20, trivial },
+  })
+
+  it('sorts the most changed images first when asked', () => {
+    const files = [scored('resaved.png', 0.999, true), mockFiles[1], scored('redrawn.png', 0.2), scored('tweaked.png', 0.8)]
+
+    const { rerender } = render(<FileList files={files} selectedFile={null} onSelect={() => {}} />)
+    const names = () => screen.getAllByRole('button').map((button) => button.textContent)
+    expect(names()[0]).toContain('resaved.png')
+
+    rerender(<FileList files={files} selectedFile={null} onSelect={() => {}} sortByDifference />)
+    expect(names()).toEqual(['Mredrawn.png', 'Mtweaked.png', 'Mresaved.pngTrivial', 'Aicon.svg'])
+  })
+
+  it('flags trivial re-exports with their scores', () => {
+    render(<FileList files={[scored('resaved.png', 0.999, true), scored('redrawn.png', 0.2)]} selectedFile={null} onSelect={() => {}} />)
+
+    expect(screen.getAllByText('Trivial')).toHaveLength(1)
+    expect(screen.getByText('resaved.png')).toHaveAttribute('title', 'SSIM 0.999, hash distance 0')
+  })
+
   it('shows empty state when no files', () => {
     render(<FileList files={[]} selectedFile={null} onSelect={() => {}} />)
 
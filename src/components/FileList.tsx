@@ -1,26 +1,65 @@
-import type { ChangedFile } from '../types'
+import type { ChangedFile, ChangeKind } from '../types'
 
 interface FileListProps {
   files: ChangedFile[]
   selectedFile: string | null
   onSelect: (path: string) => void
   isLoading?: boolean
+  sortByDifference?: boolean // Least similar first, by `similarity.ssim`; unscored files last
 }
 
-function getStatusIndicator(status: string): { letter: string; className: string } {
-  switch (status) {
+function getStatusIndicator(status: ChangeKind): { letter: string; className: string } {
+  switch (status.kind) {
     case 'modified':
+    case 'type_changed':
       return { letter: 'M', className: 'bg-yellow-500 text-yellow-900' }
     case 'added':
+    case 'untracked':
       return { letter: 'A', className: 'bg-green-500 text-green-900' }
     case 'deleted':
       return { letter: 'D', className: 'bg-red-500 text-red-900' }
+    case 'renamed':
+      return { letter: 'R', className: 'bg-blue-500 text-blue-900' }
+    case 'copied':
+      return { letter: 'C', className: 'bg-blue-500 text-blue-900' }
+    case 'unmerged':
+      return { letter: 'U', className: 'bg-orange-500 text-orange-900' }
     default:
       return { letter: '?', className: 'bg-gray-500 text-gray-900' }
   }
 }
 
-export default function FileList({ files, selectedFile, onSelect, isLoading }: FileListProps) {
+function getStatusTitle(file: ChangedFile): string {
+  if (file.status.kind === 'unmerged') {
+    return `Conflict (ours: ${file.status.ours}, theirs: ${file.status.theirs})`
+  }
+  if (file.status.kind === 'renamed' || file.status.kind === 'copied') {
+    return `${file.status.kind} from ${file.status.from}`
+  }
+  if (file.staged && file.unstaged) return 'Staged, with unstaged edits'
+  if (file.staged) return 'Staged'
+  return 'Not staged'
+}
+
+function getSimilarityTitle(file: ChangedFile): string | undefined {
+  if (!file.similarity) return undefined
+  const { ssim, hash_distance } = file.similarity
+  return `SSIM ${ssim.toFixed(3)}, hash distance ${hash_distance}`
+}
+
+// Most visually changed first; files without a score keep their order at the end
+function byDifference(files: ChangedFile[]): ChangedFile[] {
+  const score = (file: ChangedFile) => file.similarity?.ssim ?? Infinity
+  return [...files].sort((a, b) => score(a) - score(b))
+}
+
+export default function FileList({
+  files,
+  selectedFile,
+  onSelect,
+  isLoading,
+  sortByDifference,
+}: FileListProps) {
   if (isLoading) {
     return (
       <div className="p-4 text-gray-500 dark:text-gray-400">
@@ -39,7 +78,7 @@ export default function FileList({ files, selectedFile, onSelect, isLoading }: F
 
   return (
     <div className="flex flex-col">
-      {files.map((file) => {
+      {(sortByDifference ? byDifference(files) : files).map((file) => {
         const { letter, className } = getStatusIndicator(file.status)
         const isSelected = selectedFile === file.path
 
@@ -54,6 +93,7 @@ export default function FileList({ files, selectedFile, onSelect, isLoading }: F
             `}
           >
             <span
+              title={getStatusTitle(file)}
               className={`
                 inline-flex items-center justify-center w-5 h-5
                 text-xs font-bold rounded ${className}
@@ -61,9 +101,20 @@ export default function FileList({ files, selectedFile, onSelect, isLoading }: F
             >
               {letter}
             </span>
-            <span className="truncate text-sm text-gray-900 dark:text-gray-100">
+            <span
+              title={getSimilarityTitle(file)}
+              className="truncate text-sm text-gray-900 dark:text-gray-100"
+            >
               {file.filename}
             </span>
+            {file.similarity?.trivial && (
+              <span
+                title="Looks the same as before: probably only saved again"
+                className="ml-auto shrink-0 px-1.5 text-xs rounded bg-gray-200 text-gray-600 dark:bg-gray-700 dark:text-gray-300"
+              >
+                Trivial
+              </span>
+            )}
           </button>
         )
       })}
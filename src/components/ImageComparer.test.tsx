@@ -1,6 +1,7 @@
-import { describe, it, expect } from 'vitest'
+import { describe, it, expect, vi } from 'vitest'
 import { render, screen, fireEvent } from '@testing-library/react'
 import ImageComparer from './ImageComparer'
+import type { ShownImageDiff } from '../types'
 
 describe('ImageComparer', () => {
   const currentSrc = 'data:image/png;base64,currentImageData'
@@ -118,10 +119,123 @@ describe('ImageComparer', () => {
       expect(slider).toHaveAttribute('aria-label', 'Sensitivity')
     })
 
-    it('shows diff canvas in Diff mode', () => {
-      render(<ImageComparer currentSrc={currentSrc} previousSrc={previousSrc} />)
+    const diff: ShownImageDiff = {
+      width: 4,
+      height: 2,
+      changed_pixels: 2,
+      antialiased_pixels: 3,
+      total_pixels: 8,
+      id: 'd1',
+      url: 'visigit://localhost/diff/d1',
+      regions: [],
+    }
+    const defaultRegions = { min_pixels: 4, merge_distance: 8 }
+
+    it('shows the diff image computed by the backend in Diff mode', async () => {
+      const onComputeDiff = vi.fn().mockResolvedValue(diff)
+      render(
+        <ImageComparer currentSrc={currentSrc} previousSrc={previousSrc} onComputeDiff={onComputeDiff} />
+      )
       fireEvent.click(screen.getByRole('radio', { name: /diff/i }))
-      expect(screen.getByTestId('diff-canvas')).toBeInTheDocument()
+
+      const image = await screen.findByTestId('diff-image')
+      expect(image).toHaveAttribute('src', 'visigit://localhost/diff/d1')
+      expect(screen.getByText(/changed: 2\/8 pixels \(25\.0%\)/i)).toBeInTheDocument()
+      expect(screen.getByText(/anti-aliasing: 3 pixels/i)).toBeInTheDocument()
+      expect(onComputeDiff).toHaveBeenCalledWith({ kind: 'perceptual', threshold: 0.1 }, defaultRegions)
+    })
+
+    it('recomputes the diff when the sensitivity changes', async () => {
+      const onComputeDiff = vi.fn().mockResolvedValue(diff)
+      render(
+        <ImageComparer currentSrc={currentSrc} previousSrc={previousSrc} onComputeDiff={onComputeDiff} />
+      )
+      fireEvent.click(screen.getByRole('radio', { name: /diff/i }))
+      await screen.findByTestId('diff-image')
+
+      fireEvent.change(screen.getByRole('slider', { name: /sensitivity/i }), { target: { value: '25' } })
+
+      expect(onComputeDiff).toHaveBeenLastCalledWith({ kind: 'perceptual', threshold: 0.25 }, defaultRegions)
+    })
+
+    it('switches to the average method when Perceptual is unchecked', async () => {
+      const onComputeDiff = vi.fn().mockResolvedValue({ ...diff, antialiased_pixels: 0 })
+      render(
+        <ImageComparer currentSrc={currentSrc} previousSrc={previousSrc} onComputeDiff={onComputeDiff} />
+      )
+      fireEvent.click(screen.getByRole('radio', { name: /diff/i }))
+      await screen.findByTestId('diff-image')
+
+      fireEvent.click(screen.getByRole('checkbox', { name: /perceptual/i }))
+
+      expect(onComputeDiff).toHaveBeenLastCalledWith({ kind: 'average', threshold: 10 }, defaultRegions)
+      expect(screen.queryByText(/anti-aliasing/i)).not.toBeInTheDocument()
+    })
+
+    const withRegions: ShownImageDiff = {
+      ...diff,
+      width: 100,
+      height: 50,
+      regions: [
+        { x: 10, y: 5, width: 20, height: 10, pixels: 150, mean_delta: 0.4 },
+        { x: 60, y: 30, width: 10, height: 10, pixels: 80, mean_delta: 0.2 },
+      ],
+    }
+
+    it('draws a box around each changed region', async () => {
+      const onComputeDiff = vi.fn().mockResolvedValue(withRegions)
+      render(
+        <ImageComparer currentSrc={currentSrc} previousSrc={previousSrc} onComputeDiff={onComputeDiff} />
+      )
+      fireEvent.click(screen.getByRole('radio', { name: /diff/i }))
+
+      const boxes = await screen.findAllByTestId('diff-region')
+      expect(boxes).toHaveLength(2)
+      expect(boxes[0]).toHaveStyle({ left: '10%', top: '10%', width: '20%', height: '20%' })
+      expect(boxes[1]).toHaveAttribute('title', '80 pixels changed')
+      expect(screen.getByText('2 changed regions')).toBeInTheDocument()
+    })
+
+    it('jumps between changed regions, wrapping around', async () => {
+      const onComputeDiff = vi.fn().mockResolvedValue(withRegions)
+      render(
+        <ImageComparer currentSrc={currentSrc} previousSrc={previousSrc} onComputeDiff={onComputeDiff} />
+      )
+      fireEvent.click(screen.getByRole('radio', { name: /diff/i }))
+      await screen.findAllByTestId('diff-region')
+
+      fireEvent.click(screen.getByRole('button', { name: /next change/i }))
+      expect(screen.getByText('Change 1 of 2')).toBeInTheDocument()
+      // The region's center (20, 10) moves to the middle of the 100x50 image
+      expect(screen.getByTestId('diff-image').closest('[style*="translate"]')).toHaveStyle({
+        transform: 'translate(30px, 15px) scale(1)',
+      })
+
+      fireEvent.click(screen.getByRole('button', { name: /next change/i }))
+      expect(screen.getByText('Change 2 of 2')).toBeInTheDocument()
+      fireEvent.click(screen.getByRole('button', { name: /next change/i }))
+      expect(screen.getByText('Change 1 of 2')).toBeInTheDocument()
+      fireEvent.click(screen.getByRole('button', { name: /previous change/i }))
+      expect(screen.getByText('Change 2 of 2')).toBeInTheDocument()
+    })
+
+    it('recomputes the regions when the clustering settings change', async () => {
+      const onComputeDiff = vi.fn().mockResolvedValue(diff)
+      render(
+        <ImageComparer currentSrc={currentSrc} previousSrc={previousSrc} onComputeDiff={onComputeDiff} />
+      )
+      fireEvent.click(screen.getByRole('radio', { name: /diff/i }))
+      await screen.findByTestId('diff-image')
+      expect(screen.getByText('No changed regions')).toBeInTheDocument()
+      expect(screen.getByRole('button', { name: /next change/i })).toBeDisabled()
+
+      fireEvent.change(screen.getByRole('spinbutton', { name: /minimum region size/i }), { target: { value: '20' } })
+      fireEvent.change(screen.getByRole('spinbutton', { name: /merge distance/i }), { target: { value: '0' } })
+
+      expect(onComputeDiff).toHaveBeenLastCalledWith(
+        { kind: 'perceptual', threshold: 0.1 },
+        { min_pixels: 20, merge_distance: 0 }
+      )
     })
 
     it('sensitivity slider has default value of 10', () => {
@@ -176,10 +290,14 @@ describe('ImageComparer', () => {
       expect(slider).toBeInTheDocument()
     })
 
-    it('hides diff canvas in Onion Skin mode', () => {
-      render(<ImageComparer currentSrc={currentSrc} previousSrc={previousSrc} />)
+    it('does not compute a diff in Onion Skin mode', () => {
+      const onComputeDiff = vi.fn()
+      render(
+        <ImageComparer currentSrc={currentSrc} previousSrc={previousSrc} onComputeDiff={onComputeDiff} />
+      )
       // Default is onion skin mode
-      expect(screen.queryByTestId('diff-canvas')).not.toBeInTheDocument()
+      expect(onComputeDiff).not.toHaveBeenCalled()
+      expect(screen.queryByTestId('diff-image')).not.toBeInTheDocument()
     })
   })
 
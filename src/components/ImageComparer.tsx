@@ -1,19 +1,23 @@
 import { useState, useRef, useEffect, useCallback } from 'react'
-import { computePixelDiff } from '../utils/pixelDiff'
+import type { DiffMethod, RegionOptions, ShownImageDiff } from '../types'
 
 type ViewMode = 'onion' | 'side-by-side' | 'diff'
 
 interface ImageComparerProps {
   currentSrc: string | null
   previousSrc: string | null
+  onComputeDiff?: (method: DiffMethod, regions: RegionOptions) => Promise<ShownImageDiff | null> // Pixel diff for Diff mode
 }
 
-export default function ImageComparer({ currentSrc, previousSrc }: ImageComparerProps) {
+export default function ImageComparer({ currentSrc, previousSrc, onComputeDiff }: ImageComparerProps) {
   const [opacity, setOpacity] = useState(100)
   const [viewMode, setViewMode] = useState<ViewMode>('onion')
   const [sensitivity, setSensitivity] = useState(10)
-  const canvasRef = useRef<HTMLCanvasElement>(null)
-  const [diffStats, setDiffStats] = useState<{ changed: number; total: number } | null>(null)
+  const [perceptual, setPerceptual] = useState(true) // YIQ distance with anti-aliasing detection
+  const [diff, setDiff] = useState<ShownImageDiff | null>(null)
+  const [minPixels, setMinPixels] = useState(4) // Smallest changed region worth a box
+  const [mergeDistance, setMergeDistance] = useState(8) // Regions closer than this become one
+  const [regionIndex, setRegionIndex] = useState<number | null>(null) // Change jumped to, if any
 
   // Zoom and pan state
   const [zoom, setZoom] = useState(100)
@@ -64,63 +68,51 @@ export default function ImageComparer({ currentSrc, previousSrc }: ImageComparer
     setIsDragging(false)
   }, [])
 
-  const computeDiff = useCallback(() => {
-    if (viewMode !== 'diff' || !currentSrc || !previousSrc || !canvasRef.current) {
+  // The slider's 0-50 reads as RGB levels for the average method and as
+  // hundredths of the 0-1 YIQ threshold for the perceptual one, so the
+  // default of 10 is each method's usual default
+  const threshold = perceptual ? sensitivity / 100 : sensitivity
+
+  // The backend decodes and compares both versions off the main thread;
+  // only the answer for the latest settings is kept
+  useEffect(() => {
+    if (viewMode !== 'diff' || !currentSrc || !previousSrc || !onComputeDiff) {
       return
     }
 
-    const canvas = canvasRef.current
-    const ctx = canvas.getContext('2d')
-    if (!ctx) return
-
-    const img1 = new Image()
-    const img2 = new Image()
-
-    let loaded = 0
-    const onLoad = () => {
-      loaded++
-      if (loaded < 2) return
-
-      // Set canvas size to match images
-      const width = Math.max(img1.width, img2.width)
-      const height = Math.max(img1.height, img2.height)
-      canvas.width = width
-      canvas.height = height
-
-      // Create temporary canvases to get image data
-      const tempCanvas1 = document.createElement('canvas')
-      tempCanvas1.width = img1.width
-      tempCanvas1.height = img1.height
-      const tempCtx1 = tempCanvas1.getContext('2d')
-      if (!tempCtx1) return
-      tempCtx1.drawImage(img1, 0, 0)
-      const imgData1 = tempCtx1.getImageData(0, 0, img1.width, img1.height)
-
-      const tempCanvas2 = document.createElement('canvas')
-      tempCanvas2.width = img2.width
-      tempCanvas2.height = img2.height
-      const tempCtx2 = tempCanvas2.getContext('2d')
-      if (!tempCtx2) return
-      tempCtx2.drawImage(img2, 0, 0)
-      const imgData2 = tempCtx2.getImageData(0, 0, img2.width, img2.height)
-
-      // Compute diff
-      const result = computePixelDiff(imgData1, imgData2, sensitivity)
+    let cancelled = false
+    const method: DiffMethod = { kind: perceptual ? 'perceptual' : 'average', threshold }
+    const regions: RegionOptions = { min_pixels: minPixels, merge_distance: mergeDistance }
+    onComputeDiff(method, regions).then((result) => {
+      if (cancelled) return
+      setDiff(result)
+      setRegionIndex(null)
+    })
 
-      // Draw diff to canvas
-      ctx.putImageData(result.diffImageData, 0, 0)
-      setDiffStats({ changed: result.changedPixelCount, total: result.totalPixelCount })
+    return () => {
+      cancelled = true
     }
-
-    img1.onload = onLoad
-    img2.onload = onLoad
-    img1.src = previousSrc
-    img2.src = currentSrc
-  }, [viewMode, currentSrc, previousSrc, sensitivity])
-
-  useEffect(() => {
-    computeDiff()
-  }, [computeDiff])
+  }, [viewMode, currentSrc, previousSrc, perceptual, threshold, minPixels, mergeDistance, onComputeDiff])
+
+  // Step through the changed regions in reading order, wrapping around,
+  // and pan so the region lands in the middle of the view
+  const regionCount = diff?.regions.length ?? 0
+  const jumpToRegion = useCallback(
+    (step: 1 | -1) => {
+      if (!diff || regionCount === 0) return
+      // From nothing selected, the first step lands on the first or last region
+      const from = regionIndex ?? (step === 1 ? -1 : regionCount)
+      const index = (from + step + regionCount) % regionCount
+      const region = diff.regions[index]
+      const scale = zoom / 100
+      setRegionIndex(index)
+      setPan({
+        x: (diff.width / 2 - (region.x + region.width / 2)) * scale,
+        y: (diff.height / 2 - (region.y + region.height / 2)) * scale,
+      })
+    },
+    [diff, regionCount, regionIndex, zoom]
+  )
 
   // No images to compare
   if (!currentSrc && !previousSrc) {
@@ -395,12 +387,34 @@ export default function ImageComparer({ currentSrc, previousSrc }: ImageComparer
               transformOrigin: 'center center',
             }}
           >
-            <canvas
-              ref={canvasRef}
-              data-testid="diff-canvas"
-              className="max-w-none"
-              style={{ pointerEvents: 'none' }}
-            />
+            {diff && (
+              <div className="relative">
+                <img
+                  src={diff.url}
+                  alt="Changed pixels"
+                  data-testid="diff-image"
+                  className="max-w-none"
+                  style={{ pointerEvents: 'none' }}
+                />
+                {diff.regions.map((region, index) => (
+                  <div
+                    key={`${region.x},${region.y}`}
+                    data-testid="diff-region"
+                    title={`${region.pixels} pixels changed`}
+                    className={`absolute border-2 ${
+                      index === regionIndex ? 'border-blue-500' : 'border-cyan-400/70'
+                    }`}
+                    style={{
+                      left: `${(region.x / diff.width) * 100}%`,
+                      top: `${(region.y / diff.height) * 100}%`,
+                      width: `${(region.width / diff.width) * 100}%`,
+                      height: `${(region.height / diff.height) * 100}%`,
+                      pointerEvents: 'none',
+                    }}
+                  />
+                ))}
+              </div>
+            )}
           </div>
         </div>
       )}
@@ -448,9 +462,68 @@ export default function ImageComparer({ currentSrc, previousSrc }: ImageComparer
             <span className="text-sm text-gray-600 dark:text-gray-300 w-16 text-right">
               Tolerant
             </span>
+            <label className="flex items-center gap-1 text-sm text-gray-600 dark:text-gray-300 cursor-pointer">
+              <input
+                type="checkbox"
+                checked={perceptual}
+                onChange={(e) => setPerceptual(e.target.checked)}
+                aria-label="Perceptual"
+              />
+              Perceptual
+            </label>
           </div>
           <div className="text-center mt-1 text-xs text-gray-500 dark:text-gray-400">
-            Threshold: {sensitivity}{diffStats && ` | Changed: ${diffStats.changed}/${diffStats.total} pixels (${((diffStats.changed / diffStats.total) * 100).toFixed(1)}%)`}
+            Threshold: {perceptual ? threshold.toFixed(2) : threshold}{diff && diff.total_pixels > 0 && ` | Changed: ${diff.changed_pixels}/${diff.total_pixels} pixels (${((diff.changed_pixels / diff.total_pixels) * 100).toFixed(1)}%)`}{diff && perceptual && ` | Anti-aliasing: ${diff.antialiased_pixels} pixels`}
+          </div>
+          <div className="flex items-center justify-center gap-3 mt-2 text-xs text-gray-600 dark:text-gray-300">
+            <label className="flex items-center gap-1">
+              Min region
+              <input
+                type="number"
+                min="1"
+                value={minPixels}
+                onChange={(e) => setMinPixels(Math.max(1, Number(e.target.value)))}
+                aria-label="Minimum region size"
+                className="w-14 px-1 rounded bg-white dark:bg-gray-700"
+              />
+              px
+            </label>
+            <label className="flex items-center gap-1">
+              Merge within
+              <input
+                type="number"
+                min="0"
+                value={mergeDistance}
+                onChange={(e) => setMergeDistance(Math.max(0, Number(e.target.value)))}
+                aria-label="Merge distance"
+                className="w-14 px-1 rounded bg-white dark:bg-gray-700"
+              />
+              px
+            </label>
+            <div className="w-px h-4 bg-gray-300 dark:bg-gray-600" />
+            <button
+              onClick={() => jumpToRegion(-1)}
+              disabled={regionCount === 0}
+              aria-label="Previous change"
+              className="px-2 py-0.5 bg-gray-200 dark:bg-gray-700 hover:bg-gray-300 dark:hover:bg-gray-600 rounded disabled:opacity-50"
+            >
+              ‹
+            </button>
+            <span>
+              {regionCount === 0
+                ? 'No changed regions'
+                : regionIndex === null
+                  ? `${regionCount} changed region${regionCount === 1 ? '' : 's'}`
+                  : `Change ${regionIndex + 1} of ${regionCount}`}
+            </span>
+            <button
+              onClick={() => jumpToRegion(1)}
+              disabled={regionCount === 0}
+              aria-label="Next change"
+              className="px-2 py-0.5 bg-gray-200 dark:bg-gray-700 hover:bg-gray-300 dark:hover:bg-gray-600 rounded disabled:opacity-50"
+            >
+              ›
+            </button>
           </div>
         </div>
       )}
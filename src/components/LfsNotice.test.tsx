@@ -0,0 +1,12 @@
+import { describe, it, expect } from 'vitest'
+import { render, screen } from '@testing-library/react'
+import LfsNotice from './LfsNotice'
+
+describe('LfsNotice', () => {
+  it('explains how to fetch the missing object', () => {
+    render(<LfsNotice path="art/hero.png" />)
+
+    expect(screen.getByText(/art\/hero\.png is stored in Git LFS/)).toBeInTheDocument()
+    expect(screen.getByText('git lfs pull')).toBeInTheDocument()
+  })
+})
@@ -0,0 +1,13 @@
+interface LfsNoticeProps {
+  path: string
+}
+
+export default function LfsNotice({ path }: LfsNoticeProps) {
+  return (
+    <div className="p-3 border-b border-amber-200 dark:border-amber-800 bg-amber-50 dark:bg-amber-900/30 text-sm text-amber-800 dark:text-amber-200">
+      {path} is stored in Git LFS and has not been downloaded yet, so only its pointer file is
+      available. Run <code className="font-mono">git lfs pull</code> in the repository to fetch
+      it.
+    </div>
+  )
+}
@@ -0,0 +1,69 @@
+import { describe, it, expect, vi } from 'vitest'
+import { render, screen, fireEvent } from '@testing-library/react'
+import RestoreControls from './RestoreControls'
+import type { Backup } from '../types'
+
+describe('RestoreControls', () => {
+  const backup: Backup = {
+    id: '17b2c3d4e5f60000-0',
+    repo: '/repo',
+    path: 'logo.png',
+    target: 'worktree',
+    source_rev: 'abc123456789',
+    previous_id: null,
+    created_at: 0,
+  }
+
+  it('needs a selected file and a base commit', () => {
+    render(
+      <RestoreControls
+        selectedFile="logo.png"
+        sourceRev={null}
+        lastBackup={null}
+        onRestore={() => {}}
+        onUndo={() => {}}
+        isLoading={false}
+      />
+    )
+
+    expect(screen.getByText('Restore from Base')).toBeDisabled()
+    expect(screen.queryByText(/undo restore/i)).not.toBeInTheDocument()
+  })
+
+  it('restores the selected file into the chosen target', () => {
+    const handleRestore = vi.fn()
+    render(
+      <RestoreControls
+        selectedFile="logo.png"
+        sourceRev="abc123456789"
+        lastBackup={null}
+        onRestore={handleRestore}
+        onUndo={() => {}}
+        isLoading={false}
+      />
+    )
+
+    fireEvent.change(screen.getByLabelText('Restore into'), { target: { value: 'index' } })
+    fireEvent.click(screen.getByText('Restore from Base'))
+
+    expect(handleRestore).toHaveBeenCalledWith('logo.png', 'abc123456789', 'index')
+  })
+
+  it('offers to undo the last restore', () => {
+    const handleUndo = vi.fn()
+    render(
+      <RestoreControls
+        selectedFile={null}
+        sourceRev={null}
+        lastBackup={backup}
+        onRestore={() => {}}
+        onUndo={handleUndo}
+        isLoading={false}
+      />
+    )
+
+    fireEvent.click(screen.getByText('Undo restore of logo.png'))
+
+    expect(handleUndo).toHaveBeenCalled()
+  })
+})
@@ -0,0 +1,60 @@
+import { useState } from 'react'
+import type { Backup, RestoreTarget } from '../types'
+
+interface RestoreControlsProps {
+  selectedFile: string | null
+  sourceRev: string | null // The Base (Before) commit; null is the working directory
+  lastBackup: Backup | null
+  onRestore: (path: string, sourceRev: string, target: RestoreTarget) => void
+  onUndo: () => void
+  isLoading: boolean
+}
+
+export default function RestoreControls({
+  selectedFile,
+  sourceRev,
+  lastBackup,
+  onRestore,
+  onUndo,
+  isLoading,
+}: RestoreControlsProps) {
+  const [target, setTarget] = useState<RestoreTarget>('worktree')
+
+  const buttonClass =
+    'w-full px-2 py-1 text-xs rounded border border-gray-300 dark:border-gray-600 hover:bg-gray-100 dark:hover:bg-gray-700 transition-colors disabled:opacity-50'
+
+  return (
+    <div className="p-3 border-b border-gray-200 dark:border-gray-700 space-y-2">
+      <div className="flex flex-col gap-1">
+        <label
+          htmlFor="restore-target"
+          className="text-xs font-medium text-gray-600 dark:text-gray-400"
+        >
+          Restore into
+        </label>
+        <select
+          id="restore-target"
+          value={target}
+          onChange={(e) => setTarget(e.target.value as RestoreTarget)}
+          className="px-2 py-1 text-sm rounded border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100"
+        >
+          <option value="worktree">Working file</option>
+          <option value="index">Staged version</option>
+        </select>
+      </div>
+      <button
+        onClick={() => selectedFile && sourceRev && onRestore(selectedFile, sourceRev, target)}
+        disabled={isLoading || !selectedFile || !sourceRev}
+        title={sourceRev ? undefined : 'Pick a Base (Before) commit to restore from'}
+        className={buttonClass}
+      >
+        Restore from Base
+      </button>
+      {lastBackup && (
+        <button onClick={onUndo} disabled={isLoading} className={buttonClass}>
+          Undo restore of {lastBackup.path}
+        </button>
+      )}
+    </div>
+  )
+}
@@ -0,0 +1,33 @@
+import type { CompareStage } from '../types'
+
+const STAGES: { stage: CompareStage; label: string }[] = [
+  { stage: 'all', label: 'All changes (HEAD → Working Directory)' },
+  { stage: 'staged', label: 'Staged (HEAD → Index)' },
+  { stage: 'unstaged', label: 'Unstaged (Index → Working Directory)' },
+]
+
+interface StageSelectorProps {
+  compareStage: CompareStage
+  onSelect: (stage: CompareStage) => void
+}
+
+export default function StageSelector({ compareStage, onSelect }: StageSelectorProps) {
+  return (
+    <div className="flex flex-col gap-1">
+      <label className="text-xs font-medium text-gray-600 dark:text-gray-400">
+        Compare
+      </label>
+      <select
+        value={compareStage}
+        onChange={(e) => onSelect(e.target.value as CompareStage)}
+        className="px-2 py-1 text-sm rounded border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100"
+      >
+        {STAGES.map(({ stage, label }) => (
+          <option key={stage} value={stage}>
+            {label}
+          </option>
+        ))}
+      </select>
+    </div>
+  )
+}
@@ -1,46 +1,129 @@
-import { useState, useCallback } from 'react'
-import { invoke } from '@tauri-apps/api/core'
+import { useState, useCallback, useEffect } from 'react'
+import { convertFileSrc, invoke } from '@tauri-apps/api/core'
 import { open } from '@tauri-apps/plugin-dialog'
-import { readFile } from '@tauri-apps/plugin-fs'
-import type { ChangedFile, ImageData, CommitInfo } from '../types'
+import type {
+  Backup,
+  ChangedFile,
+  ChangeKind,
+  CompareStage,
+  ConflictImages,
+  ConflictResolution,
+  DiffMethod,
+  FileVersion,
+  ImageData,
+  ImageDiff,
+  CommitInfo,
+  HistoryQuery,
+  Identity,
+  Page,
+  RefInfo,
+  RegionOptions,
+  RepoInfo,
+  RestoreTarget,
+  ShownImageDiff,
+  VisiGitError,
+} from '../types'
+import { blobUrl, diffUrl, indexUrl, isLfsMissing, stageUrl, worktreeUrl } from '../utils/visigitUrl'
 
 interface UseGitRepoState {
   repoPath: string | null
+  repoId: string | null // Stands for the repo in visigit:// URLs
   isValidRepo: boolean
   changedFiles: ChangedFile[]
   selectedFile: string | null
+  compareStage: CompareStage // Which versions of the selected file to compare
   isLoading: boolean
   error: string | null
   imageData: ImageData
+  conflictImages: ConflictImages | null // Sides of the selected file, if it is conflicted
+  lfsMissing: boolean // A compared version is an LFS pointer whose object was not fetched
   commits: CommitInfo[]
+  commitsCursor: string | null // Where the next page of history starts, null when done
+  refs: RefInfo[] // Branches, remote branches and tags to compare by name
   baseCommit: string | null
   compareCommit: string | null
+  lastBackup: Backup | null // The most recent restore, until it is undone
+  sortByDifference: boolean // List the most visually changed images first, scoring their similarity
+}
+
+// Nothing selected to compare
+const NO_IMAGES: ImageData = {
+  currentSrc: null,
+  previousSrc: null,
+  currentVersion: null,
+  previousVersion: null,
 }
 
 interface UseGitRepoReturn extends UseGitRepoState {
   openRepo: () => Promise<void>
   refreshFiles: () => Promise<void>
   selectFile: (path: string) => Promise<void>
+  selectCompareStage: (stage: CompareStage) => void
   clearError: () => void
+  stageFiles: (paths: string[], allowInProgress?: boolean) => Promise<void>
+  unstageFiles: (paths: string[], allowInProgress?: boolean) => Promise<void>
+  createCommit: (message: string, options?: CommitOptions) => Promise<CommitInfo | null>
+  restoreFile: (path: string, sourceRev: string, target: RestoreTarget) => Promise<void>
+  undoRestore: () => Promise<void>
+  resolveConflict: (path: string, resolution: ConflictResolution) => Promise<void>
   loadCommits: () => Promise<void>
+  loadMoreCommits: () => Promise<void>
   selectBaseCommit: (hash: string | null) => void
   selectCompareCommit: (hash: string | null) => void
+  computeImageDiff: (method: DiffMethod, regions?: RegionOptions) => Promise<ShownImageDiff | null>
+  setSortByDifference: (on: boolean) => Promise<void>
+}
+
+export interface CommitOptions {
+  author?: Identity // Commit as someone else; the committer stays the configured identity
+  amend?: boolean // Replace HEAD instead of adding a commit on top
+  allowInProgress?: boolean // Commit even while a merge or rebase is in progress
 }
 
 export function useGitRepo(): UseGitRepoReturn {
   const [state, setState] = useState<UseGitRepoState>({
     repoPath: null,
+    repoId: null,
     isValidRepo: false,
     changedFiles: [],
     selectedFile: null,
+    compareStage: 'all',
     isLoading: false,
     error: null,
-    imageData: { currentSrc: null, previousSrc: null },
+    imageData: NO_IMAGES,
+    conflictImages: null,
+    lfsMissing: false,
     commits: [],
+    commitsCursor: null,
+    refs: [],
     baseCommit: null,
     compareCommit: null,
+    lastBackup: null,
+    sortByDifference: false,
   })
 
+  // The protocol serves the pointer of an LFS file whose object is not in
+  // the local store; ask for the headers of both versions to find out
+  const { currentSrc, previousSrc } = state.imageData
+  useEffect(() => {
+    let cancelled = false
+    const sources = [currentSrc, previousSrc].filter((src): src is string => src !== null)
+
+    Promise.all(
+      sources.map((src) =>
+        fetch(src, { method: 'HEAD' })
+          .then((response) => isLfsMissing(response.headers))
+          .catch(() => false)
+      )
+    ).then((missing) => {
+      if (!cancelled) setState((prev) => ({ ...prev, lfsMissing: missing.includes(true) }))
+    })
+
+    return () => {
+      cancelled = true
+    }
+  }, [currentSrc, previousSrc])
+
   const clearError = useCallback(() => {
     setState((prev) => ({ ...prev, error: null }))
   }, [])
@@ -61,12 +144,13 @@ export function useGitRepo(): UseGitRepoReturn {
         return
       }
 
-      const repoPath = selected as string
-
-      // Validate it's a git repo
-      const isValid = await invoke<boolean>('validate_git_repo', { path: repoPath })
-
-      if (!isValid) {
+      // Find the repository the folder belongs to; any subfolder,
+      // linked worktree or submodule resolves to its working tree root
+      let info: RepoInfo
+      try {
+        info = await invoke<RepoInfo>('discover_repo', { path: selected as string })
+      } catch (err) {
+        if ((err as VisiGitError).code !== 'not_a_repository') throw err
         setState((prev) => ({
           ...prev,
           isLoading: false,
@@ -78,29 +162,52 @@ export function useGitRepo(): UseGitRepoReturn {
         return
       }
 
+      if (!info.toplevel) {
+        setState((prev) => ({
+          ...prev,
+          isLoading: false,
+          error: 'Bare repositories have no working tree to compare',
+        }))
+        return
+      }
+
+      const repoPath = info.toplevel
+
+      // Register the repo with the visigit:// protocol
+      const repoId = await invoke<string>('get_repo_id', { repoPath })
+
       // Get changed files
-      const files = await invoke<ChangedFile[]>('get_changed_files', { repoPath })
+      const files = await fetchChangedFiles(repoPath, false)
+
+      // Get the first page of commit history
+      const history = await fetchHistory(repoPath, {})
 
-      // Get commit history
-      const commits = await invoke<CommitInfo[]>('get_commits', { repoPath, limit: 50 })
+      // Branches and tags can be picked by name
+      const refs = await invoke<RefInfo[]>('list_refs', { repoPath })
 
       setState((prev) => ({
         ...prev,
         repoPath,
+        repoId,
         isValidRepo: true,
         changedFiles: files,
-        commits,
+        commits: history.items,
+        commitsCursor: history.next_cursor,
+        refs,
         selectedFile: null,
         isLoading: false,
-        imageData: { currentSrc: null, previousSrc: null },
+        imageData: NO_IMAGES,
+        conflictImages: null,
         baseCommit: null,
         compareCommit: null,
+        lastBackup: null,
+        sortByDifference: false,
       }))
     } catch (err) {
       setState((prev) => ({
         ...prev,
         isLoading: false,
-        error: `Failed to open repository: ${err}`,
+        error: `Failed to open repository: ${errorMessage(err)}`,
       }))
     }
   }, [])
@@ -111,9 +218,7 @@ export function useGitRepo(): UseGitRepoReturn {
     try {
       setState((prev) => ({ ...prev, isLoading: true, error: null }))
 
-      const files = await invoke<ChangedFile[]>('get_changed_files', {
-        repoPath: state.repoPath,
-      })
+      const files = await fetchChangedFiles(state.repoPath, state.sortByDifference)
 
       setState((prev) => ({
         ...prev,
@@ -124,67 +229,228 @@ export function useGitRepo(): UseGitRepoReturn {
       setState((prev) => ({
         ...prev,
         isLoading: false,
-        error: `Failed to refresh files: ${err}`,
+        error: `Failed to refresh files: ${errorMessage(err)}`,
       }))
     }
-  }, [state.repoPath])
+  }, [state.repoPath, state.sortByDifference])
 
   const selectFile = useCallback(
     async (filePath: string) => {
+      const repoId = state.repoId
+      if (!state.repoPath || !repoId) return
+
+      const file = state.changedFiles.find((f) => f.path === filePath)
+      if (!file) return
+
+      setState((prev) => ({
+        ...prev,
+        selectedFile: filePath,
+        imageData: imageSources(file, prev.compareStage, repoId),
+        conflictImages: conflictSources(file, repoId),
+      }))
+    },
+    [state.repoPath, state.repoId, state.changedFiles]
+  )
+
+  // Stage or unstage images; the backend answers with the updated file list
+  const updateIndex = useCallback(
+    async (command: 'stage_files' | 'unstage_files', paths: string[], allowInProgress: boolean) => {
       if (!state.repoPath) return
 
       try {
-        setState((prev) => ({ ...prev, selectedFile: filePath, isLoading: true }))
+        setState((prev) => ({ ...prev, isLoading: true, error: null }))
 
-        const file = state.changedFiles.find((f) => f.path === filePath)
-        if (!file) {
-          setState((prev) => ({ ...prev, isLoading: false }))
-          return
-        }
+        const files = await invoke<ChangedFile[]>(command, {
+          repoPath: state.repoPath,
+          paths,
+          allowInProgress,
+        })
 
-        let currentSrc: string | null = null
-        let previousSrc: string | null = null
-
-        // Get current version (from disk) for non-deleted files
-        if (file.status !== 'deleted') {
-          try {
-            const fullPath = `${state.repoPath}/${filePath}`
-            const fileData = await readFile(fullPath)
-            const mimeType = getMimeType(filePath)
-            currentSrc = `data:${mimeType};base64,${arrayBufferToBase64(fileData)}`
-          } catch {
-            // File might not exist on disk
-          }
-        }
+        // Staging changes neither HEAD nor the working tree, so the
+        // similarity scores still hold
+        setState((prev) => ({
+          ...prev,
+          changedFiles: keepSimilarity(files, prev.changedFiles),
+          isLoading: false,
+        }))
+      } catch (err) {
+        setState((prev) => ({
+          ...prev,
+          isLoading: false,
+          error: `Failed to update staged files: ${errorMessage(err)}`,
+        }))
+      }
+    },
+    [state.repoPath]
+  )
+
+  const stageFiles = useCallback(
+    (paths: string[], allowInProgress = false) =>
+      updateIndex('stage_files', paths, allowInProgress),
+    [updateIndex]
+  )
+
+  const unstageFiles = useCallback(
+    (paths: string[], allowInProgress = false) =>
+      updateIndex('unstage_files', paths, allowInProgress),
+    [updateIndex]
+  )
+
+  // Commit what is staged, then reload the files, history and refs the
+  // new commit changes. Resolves to null if the commit was refused.
+  const createCommit = useCallback(
+    async (message: string, options: CommitOptions = {}) => {
+      if (!state.repoPath) return null
+      const repoPath = state.repoPath
 
-        // Get previous version (from HEAD) for non-added files
-        if (file.status !== 'added') {
-          try {
-            const base64Data = await invoke<string>('get_file_at_head', {
-              repoPath: state.repoPath,
-              filePath,
-            })
-            const mimeType = getMimeType(filePath)
-            previousSrc = `data:${mimeType};base64,${base64Data}`
-          } catch {
-            // File might not exist at HEAD
-          }
+      try {
+        setState((prev) => ({ ...prev, isLoading: true, error: null }))
+
+        const commit = await invoke<CommitInfo>('create_commit', {
+          repoPath,
+          message,
+          author: options.author ?? null,
+          amend: options.amend ?? false,
+          allowInProgress: options.allowInProgress ?? false,
+        })
+
+        const files = await fetchChangedFiles(repoPath, state.sortByDifference)
+        const history = await fetchHistory(repoPath, {})
+        const refs = await invoke<RefInfo[]>('list_refs', { repoPath })
+
+        setState((prev) => ({
+          ...prev,
+          changedFiles: files,
+          commits: history.items,
+          commitsCursor: history.next_cursor,
+          refs,
+          isLoading: false,
+        }))
+        return commit
+      } catch (err) {
+        setState((prev) => ({
+          ...prev,
+          isLoading: false,
+          error: `Failed to commit: ${errorMessage(err)}`,
+        }))
+        return null
+      }
+    },
+    [state.repoPath, state.sortByDifference]
+  )
+
+  // Swap in the new file list after the working tree or index changed
+  // under the selected file, reloading its images
+  const applyChangedFiles = useCallback(
+    (files: ChangedFile[], lastBackup: Backup | null) => {
+      const repoId = state.repoId
+      setState((prev) => {
+        const file = files.find((f) => f.path === prev.selectedFile)
+        return {
+          ...prev,
+          changedFiles: files,
+          lastBackup,
+          isLoading: false,
+          imageData: file && repoId ? imageSources(file, prev.compareStage, repoId) : prev.imageData,
+          conflictImages: file && repoId ? conflictSources(file, repoId) : null,
         }
+      })
+    },
+    [state.repoId]
+  )
+
+  // Write the version of a file at `sourceRev` back to the working tree or
+  // the index. The backend backs up what it replaces, so it can be undone.
+  const restoreFile = useCallback(
+    async (path: string, sourceRev: string, target: RestoreTarget) => {
+      if (!state.repoPath) return
+      const repoPath = state.repoPath
+
+      try {
+        setState((prev) => ({ ...prev, isLoading: true, error: null }))
+
+        const backup = await invoke<Backup>('restore_file', {
+          repoPath,
+          filePath: path,
+          sourceRev,
+          target,
+        })
+        const files = await fetchChangedFiles(repoPath, state.sortByDifference)
 
+        applyChangedFiles(files, backup)
+      } catch (err) {
         setState((prev) => ({
           ...prev,
-          imageData: { currentSrc, previousSrc },
           isLoading: false,
+          error: `Failed to restore file: ${errorMessage(err)}`,
         }))
+      }
+    },
+    [state.repoPath, state.sortByDifference, applyChangedFiles]
+  )
+
+  const undoRestore = useCallback(async () => {
+    if (!state.repoPath || !state.lastBackup) return
+
+    try {
+      setState((prev) => ({ ...prev, isLoading: true, error: null }))
+
+      const files = await invoke<ChangedFile[]>('undo_restore', {
+        repoPath: state.repoPath,
+        backupId: state.lastBackup.id,
+      })
+
+      applyChangedFiles(files, null)
+    } catch (err) {
+      setState((prev) => ({
+        ...prev,
+        isLoading: false,
+        error: `Failed to undo restore: ${errorMessage(err)}`,
+      }))
+    }
+  }, [state.repoPath, state.lastBackup, applyChangedFiles])
+
+  // Keep one side of a conflicted file, or the working file as it is, and
+  // stage it
+  const resolveConflict = useCallback(
+    async (path: string, resolution: ConflictResolution) => {
+      if (!state.repoPath) return
+
+      try {
+        setState((prev) => ({ ...prev, isLoading: true, error: null }))
+
+        const files = await invoke<ChangedFile[]>('resolve_conflict', {
+          repoPath: state.repoPath,
+          filePath: path,
+          resolution,
+        })
+
+        applyChangedFiles(files, state.lastBackup)
       } catch (err) {
         setState((prev) => ({
           ...prev,
           isLoading: false,
-          error: `Failed to load image: ${err}`,
+          error: `Failed to resolve conflict: ${errorMessage(err)}`,
         }))
       }
     },
-    [state.repoPath, state.changedFiles]
+    [state.repoPath, state.lastBackup, applyChangedFiles]
+  )
+
+  // Switch between HEAD -> working tree, HEAD -> index and index -> working
+  // tree for the selected file
+  const selectCompareStage = useCallback(
+    (stage: CompareStage) => {
+      const repoId = state.repoId
+      const file = state.changedFiles.find((f) => f.path === state.selectedFile)
+
+      setState((prev) => ({
+        ...prev,
+        compareStage: stage,
+        imageData: file && repoId ? imageSources(file, stage, repoId) : prev.imageData,
+      }))
+    },
+    [state.repoId, state.changedFiles, state.selectedFile]
   )
 
   const loadCommits = useCallback(async () => {
@@ -193,25 +459,47 @@ export function useGitRepo(): UseGitRepoReturn {
     try {
       setState((prev) => ({ ...prev, isLoading: true, error: null }))
 
-      const commits = await invoke<CommitInfo[]>('get_commits', {
-        repoPath: state.repoPath,
-        limit: 50,
-      })
+      const history = await fetchHistory(state.repoPath, {})
 
       setState((prev) => ({
         ...prev,
-        commits,
+        commits: history.items,
+        commitsCursor: history.next_cursor,
         isLoading: false,
       }))
     } catch (err) {
       setState((prev) => ({
         ...prev,
         isLoading: false,
-        error: `Failed to load commits: ${err}`,
+        error: `Failed to load commits: ${errorMessage(err)}`,
       }))
     }
   }, [state.repoPath])
 
+  // Append the next page of history to the commit list
+  const loadMoreCommits = useCallback(async () => {
+    if (!state.repoPath || !state.commitsCursor) return
+
+    try {
+      setState((prev) => ({ ...prev, isLoading: true, error: null }))
+
+      const history = await fetchHistory(state.repoPath, { cursor: state.commitsCursor })
+
+      setState((prev) => ({
+        ...prev,
+        commits: [...prev.commits, ...history.items],
+        commitsCursor: history.next_cursor,
+        isLoading: false,
+      }))
+    } catch (err) {
+      setState((prev) => ({
+        ...prev,
+        isLoading: false,
+        error: `Failed to load commits: ${errorMessage(err)}`,
+      }))
+    }
+  }, [state.repoPath, state.commitsCursor])
+
   const selectBaseCommit = useCallback((hash: string | null) => {
     setState((prev) => ({ ...prev, baseCommit: hash }))
   }, [])
@@ -220,47 +508,183 @@ export function useGitRepo(): UseGitRepoReturn {
     setState((prev) => ({ ...prev, compareCommit: hash }))
   }, [])
 
+  // Compare the two versions on screen pixel by pixel in the backend, with
+  // the changed regions clustered by `regions` or the backend's defaults.
+  // Resolves to null when there is no pair to compare or the diff failed.
+  const { currentVersion, previousVersion } = state.imageData
+  const computeImageDiff = useCallback(
+    async (method: DiffMethod, regions?: RegionOptions) => {
+      if (!state.repoPath || !currentVersion || !previousVersion) return null
+
+      try {
+        const diff = await invoke<ImageDiff>('compute_image_diff', {
+          repoPath: state.repoPath,
+          previous: previousVersion,
+          current: currentVersion,
+          method,
+          regions: regions ?? null,
+        })
+        return { ...diff, url: diffUrl(convertFileSrc('', 'visigit'), diff.id) }
+      } catch (err) {
+        setState((prev) => ({
+          ...prev,
+          error: `Failed to compare images: ${errorMessage(err)}`,
+        }))
+        return null
+      }
+    },
+    [state.repoPath, currentVersion, previousVersion]
+  )
+
+  // Sorting by difference needs every image scored, so turning it on
+  // reloads the files with their similarity
+  const setSortByDifference = useCallback(
+    async (on: boolean) => {
+      if (!on || !state.repoPath) {
+        setState((prev) => ({ ...prev, sortByDifference: on }))
+        return
+      }
+
+      try {
+        setState((prev) => ({ ...prev, isLoading: true, error: null }))
+
+        const files = await fetchChangedFiles(state.repoPath, true)
+
+        setState((prev) => ({
+          ...prev,
+          changedFiles: files,
+          sortByDifference: true,
+          isLoading: false,
+        }))
+      } catch (err) {
+        setState((prev) => ({
+          ...prev,
+          isLoading: false,
+          error: `Failed to score images: ${errorMessage(err)}`,
+        }))
+      }
+    },
+    [state.repoPath]
+  )
+
   return {
     ...state,
     openRepo,
     refreshFiles,
     selectFile,
+    selectCompareStage,
     clearError,
+    stageFiles,
+    unstageFiles,
+    createCommit,
+    restoreFile,
+    undoRestore,
+    resolveConflict,
     loadCommits,
+    loadMoreCommits,
     selectBaseCommit,
     selectCompareCommit,
+    computeImageDiff,
+    setSortByDifference,
   }
 }
 
-// Helper to convert ArrayBuffer to base64
-function arrayBufferToBase64(buffer: Uint8Array): string {
-  let binary = ''
-  for (let i = 0; i < buffer.length; i++) {
-    binary += String.fromCharCode(buffer[i])
+// Helper to pick the before/after versions of a changed file, with their
+// image URLs. Every version is streamed by the visigit:// protocol, so the
+// webview loads and caches them like any other image.
+function imageSources(file: ChangedFile, stage: CompareStage, repoId: string): ImageData {
+  const base = convertFileSrc('', 'visigit')
+  const version = Date.now()
+
+  // The version at HEAD, for files that existed there. Renamed and copied
+  // files live under their original path at HEAD.
+  const head = (change: ChangeKind): FileVersion | null => {
+    if (change.kind === 'added' || change.kind === 'untracked') return null
+    const headPath =
+      change.kind === 'renamed' || change.kind === 'copied' ? change.from : file.path
+    return { kind: 'revision', rev: 'HEAD', path: headPath }
   }
-  return btoa(binary)
+  const index: FileVersion = { kind: 'index', path: file.path }
+  const worktree: FileVersion = { kind: 'worktree', path: file.path }
+
+  const url = (v: FileVersion | null): string | null => {
+    if (!v) return null
+    switch (v.kind) {
+      case 'revision':
+        return blobUrl(base, repoId, v.rev, v.path)
+      case 'index':
+        return indexUrl(base, repoId, v.path, version)
+      case 'stage':
+        return stageUrl(base, repoId, v.stage, v.path, version)
+      case 'worktree':
+        return worktreeUrl(base, repoId, v.path, version)
+    }
+  }
+  const pair = (previousVersion: FileVersion | null, currentVersion: FileVersion | null) => ({
+    previousSrc: url(previousVersion),
+    currentSrc: url(currentVersion),
+    previousVersion,
+    currentVersion,
+  })
+
+  switch (stage) {
+    case 'staged': {
+      // With nothing staged, the index still matches HEAD
+      const change = file.index_status ?? { kind: 'modified' }
+      return pair(head(change), change.kind === 'deleted' ? null : index)
+    }
+    case 'unstaged': {
+      const change = file.worktree_status ?? { kind: 'modified' }
+      // Untracked and conflicted files have no staged version
+      const notInIndex =
+        change.kind === 'untracked' ||
+        change.kind === 'unmerged' ||
+        file.index_status?.kind === 'deleted'
+      return pair(notInIndex ? null : index, change.kind === 'deleted' ? null : worktree)
+    }
+    case 'all':
+      return pair(head(file.status), file.status.kind !== 'deleted' ? worktree : null)
+  }
+}
+
+// Helper to pick the URLs of each side of a conflicted file, null if it is
+// not in conflict
+function conflictSources(file: ChangedFile, repoId: string): ConflictImages | null {
+  if (!file.conflict) return null
+  const base = convertFileSrc('', 'visigit')
+  const version = Date.now()
+  const src = (id: string | null, stage: keyof ConflictImages) =>
+    id ? stageUrl(base, repoId, stage, file.path, version) : null
+
+  return {
+    base: src(file.conflict.base, 'base'),
+    ours: src(file.conflict.ours, 'ours'),
+    theirs: src(file.conflict.theirs, 'theirs'),
+  }
+}
+
+// Helper to list the changed images, scored for similarity when asked
+function fetchChangedFiles(repoPath: string, withSimilarity: boolean): Promise<ChangedFile[]> {
+  return invoke<ChangedFile[]>('get_changed_files', { repoPath, withSimilarity })
+}
+
+// Helper to carry the similarity scores of `previous` over to the same
+// paths in `files`
+function keepSimilarity(files: ChangedFile[], previous: ChangedFile[]): ChangedFile[] {
+  const scores = new Map(previous.map((file) => [file.path, file.similarity]))
+  return files.map((file) => ({ ...file, similarity: scores.get(file.path) ?? file.similarity }))
+}
+
+// Helper to fetch one page of commit history
+function fetchHistory(repoPath: string, query: HistoryQuery): Promise<Page<CommitInfo>> {
+  return invoke<Page<CommitInfo>>('get_history', { repoPath, query })
 }
 
-// Helper to get MIME type from file extension
-function getMimeType(filePath: string): string {
-  const ext = filePath.toLowerCase().split('.').pop()
-  switch (ext) {
-    case 'png':
-      return 'image/png'
-    case 'jpg':
-    case 'jpeg':
-      return 'image/jpeg'
-    case 'gif':
-      return 'image/gif'
-    case 'svg':
-      return 'image/svg+xml'
-    case 'webp':
-      return 'image/webp'
-    case 'bmp':
-      return 'image/bmp'
-    case 'ico':
-      return 'image/x-icon'
-    default:
-      return 'application/octet-stream'
+// Helper to get a readable message from a rejected invoke() call.
+// Backend commands reject with a VisiGitError; anything else is stringified.
+function errorMessage(err: unknown): string {
+  if (err && typeof err === 'object' && 'message' in err) {
+    return (err as VisiGitError).message
   }
+  return String(err)
 }
@@ -7,21 +7,288 @@ export interface AppState {
   error: string | null;
 }
 
+// Which side of a merge conflict did what to the file
+export type ConflictSide = 'added' | 'deleted' | 'modified';
+
+// How a file changed, tagged by `kind` (mirrors the Rust `ChangeKind` enum)
+export type ChangeKind =
+  | { kind: 'added' }
+  | { kind: 'modified' }
+  | { kind: 'deleted' }
+  | { kind: 'renamed'; from: string }
+  | { kind: 'copied'; from: string }
+  | { kind: 'type_changed' }
+  | { kind: 'unmerged'; ours: ConflictSide; theirs: ConflictSide }
+  | { kind: 'untracked' }
+  | { kind: 'ignored' };
+
 export interface ChangedFile {
   path: string;
-  status: string; // "modified", "added", or "deleted"
+  status: ChangeKind;
   filename: string;
+  staged: boolean; // The index differs from HEAD
+  unstaged: boolean; // The working tree differs from the index
+  index_status: ChangeKind | null; // HEAD -> index, null when nothing is staged
+  worktree_status: ChangeKind | null; // Index -> working tree; conflicts and untracked files only appear here
+  conflict: ConflictBlobs | null; // Stage blob ids, only for conflicted files
+  dimensions: ImageDimensions | null; // Only when `get_changed_files` is called with `withDimensions`
+  similarity: Similarity | null; // Only when called with `withSimilarity` and both sides are readable images
+}
+
+export interface Dimensions {
+  width: number;
+  height: number;
+}
+
+// Size of a changed image at HEAD and in the working tree, null where it is missing or unreadable
+export interface ImageDimensions {
+  before: Dimensions | null;
+  after: Dimensions | null;
+}
+
+// How alike a changed image looks before and after the change
+export interface Similarity {
+  ssim: number; // Mean structural similarity of the area both cover: 1 for the same pixels, around 0 for unrelated ones
+  tiles: SsimMap;
+  hash_distance: number; // Bits that differ between the 64-bit perceptual hashes, 0-64
+  trivial: boolean; // Same size, ssim >= 0.99 and hash_distance <= 2: saved again rather than changed
+}
+
+// SSIM of square tiles of the compared area, row by row
+export interface SsimMap {
+  tile_size: number; // Pixels on a side, a multiple of 8; tiles on the right and bottom edges can be cut short
+  columns: number;
+  rows: number;
+  scores: number[];
+}
+
+// Channels an image's pixels are stored with
+export type ColorKind = 'gray' | 'gray_alpha' | 'rgb' | 'rgba' | 'alpha' | 'cmyk' | 'unknown';
+
+// What the `inspect_image` command found when decoding a file
+export interface ImageInfo {
+  format: 'png' | 'jpeg' | 'gif' | 'webp' | 'bmp' | 'ico';
+  width: number;
+  height: number;
+  color: ColorKind;
+  bit_depth: number; // Bits per channel
+  has_alpha: boolean;
+  frame_count: number; // 1 for still images
+  decode_error: string | null; // Set when the header is readable but the pixel data is not
+}
+
+// Blob ids of a conflicted file's index stages, null for a side without the file
+export interface ConflictBlobs {
+  base: string | null; // Stage 1, the merge base
+  ours: string | null; // Stage 2, the branch being merged into
+  theirs: string | null; // Stage 3, the branch being merged in
+}
+
+// One side of a conflicted file, as fetched by `get_file_bytes_at_stage`
+export type ConflictStage = 'base' | 'ours' | 'theirs';
+
+// URLs of the base, ours and theirs versions of a conflicted file, null for a side without it
+export type ConflictImages = Record<ConflictStage, string | null>;
+
+// Which version `resolve_conflict` keeps for a conflicted file
+export type ConflictResolution = ConflictStage | 'worktree_file';
+
+// Which pair of versions the comparer shows for a changed file
+export type CompareStage =
+  | 'all' // HEAD -> working tree
+  | 'staged' // HEAD -> index: what is about to be committed
+  | 'unstaged'; // Index -> working tree: edits made since staging
+
+// A changed file from a comparison, with the blob id on each side
+// (mirrors the Rust `DiffedFile`, whose `ChangedFile` fields are flattened)
+export interface DiffedFile extends ChangedFile {
+  old_blob_id: string | null;
+  new_blob_id: string | null; // For the working tree, the id its content would get
+}
+
+// Where a repository lives, as found by the `discover_repo` command
+export interface RepoInfo {
+  toplevel: string | null; // Working tree root, null for bare repositories
+  git_dir: string;
+  common_dir: string;
+  is_bare: boolean;
+  is_worktree: boolean; // A linked worktree (`git worktree add`)
+  is_submodule: boolean;
+  branch: string | null; // null when HEAD is detached
 }
 
 export interface ImageData {
   currentSrc: string | null;
   previousSrc: string | null;
+  currentVersion: FileVersion | null; // The same versions, for commands like `compute_image_diff`
+  previousVersion: FileVersion | null;
+}
+
+// One version of a file, as named to backend commands
+export type FileVersion =
+  | { kind: 'revision'; rev: string; path: string }
+  | { kind: 'index'; path: string }
+  | { kind: 'stage'; stage: ConflictStage; path: string }
+  | { kind: 'worktree'; path: string }
+  | { kind: 'blob'; id: string; path: string }; // By full object id, like a `DiffedFile`'s blob ids
+
+// How `compute_image_diff` compares pixels
+export type DiffMethod =
+  | { kind: 'average'; threshold: number } // Mean absolute RGB difference, 0-255
+  | { kind: 'perceptual'; threshold: number }; // YIQ distance like pixelmatch, 0-1, anti-aliasing counted apart
+
+// Result of the `compute_image_diff` command
+export interface ImageDiff {
+  width: number; // The area both versions cover
+  height: number;
+  changed_pixels: number;
+  antialiased_pixels: number; // Only differ by anti-aliasing; always 0 for the average method
+  total_pixels: number;
+  id: string; // Served as a PNG at `visigit://diff/<id>`: changes in magenta, anti-aliasing in yellow, over a dimmed copy of the current version
+  regions: Region[]; // Clusters of changed pixels, top to bottom and then left to right
+}
+
+// An `ImageDiff` with the URL of its diff image
+export interface ShownImageDiff extends ImageDiff {
+  url: string;
+}
+
+// A cluster of changed pixels in an `ImageDiff`
+export interface Region {
+  x: number; // Bounding box in pixels of the diff image
+  y: number;
+  width: number;
+  height: number;
+  pixels: number; // Changed pixels in the region, not the area of its box
+  mean_delta: number; // In the units of the method's threshold: RGB levels for average, 0-1 for perceptual
+}
+
+// How `compute_image_diff` clusters changed pixels; the backend defaults to 4 and 8
+export interface RegionOptions {
+  min_pixels: number; // Smaller regions are left out
+  merge_distance: number; // Regions whose boxes are at most this many pixels apart are merged
 }
 
 export interface CommitInfo {
   hash: string;
   short_hash: string;
-  message: string;
+  message: string; // Subject: the first paragraph, on one line
+  body: string; // Rest of the message, empty if there is none
+  parents: string[]; // First parent first; empty for a root commit
   author: string;
-  date: string;
+  author_email: string;
+  date: string; // Author date in ISO format
+  timestamp: number; // Author date as Unix time
+  timezone_offset: number; // Author's UTC offset in minutes
+  committer: string;
+  committer_email: string;
+  co_authors: CoAuthor[];
+  refs: string[]; // Full ref names pointing here, plus "HEAD"
+}
+
+// Someone credited in a `Co-authored-by:` trailer
+export interface CoAuthor {
+  name: string;
+  email: string; // Empty if the trailer has no <email>
+}
+
+export type RefKind = 'branch' | 'remote_branch' | 'tag'
+
+// A branch, remote-tracking branch or tag, from `list_refs`
+export interface RefInfo {
+  name: string; // Full name, e.g. "refs/heads/main"
+  short_name: string; // e.g. "main", "origin/main" or "v1.0"
+  kind: RefKind;
+  target: string; // Commit id, through an annotated tag
+  upstream: string | null; // Full name of a branch's upstream
+  ahead: number | null; // null without an upstream or when it is gone
+  behind: number | null;
+  date: string; // Committer date of the target
+  timestamp: number;
+  is_head: boolean;
+}
+
+// One version of a file in its history, from `get_file_history`
+export interface FileHistoryEntry {
+  commit: CommitInfo;
+  path: string; // Path at this commit, which changes across renames
+  blob_id: string | null; // null if the commit deleted the file
+  status: ChangeKind; // Relative to the commit's first parent
+}
+
+// A page of results; pass `next_cursor` back to get the next one
+export interface Page<T> {
+  items: T[];
+  next_cursor: string | null; // null on the last page
+}
+
+// Filters and paging for `get_history`; every field is optional
+// (mirrors the Rust `HistoryQuery`, whose `HistoryFilter` fields are flattened)
+export interface HistoryQuery {
+  start?: string; // Revision to walk back from, HEAD by default
+  all_branches?: boolean; // Also walk every local and remote-tracking branch
+  author?: string; // Case-insensitive substring of `Name <email>`
+  since?: number; // Unix time, inclusive
+  until?: number; // Unix time, inclusive
+  grep?: string; // Case-insensitive substring of the full message
+  paths?: string[]; // Files or folders the commit has to change
+  images_only?: boolean; // Only commits that change an image
+  cursor?: string | null; // `next_cursor` of the previous page
+  limit?: number; // Page size, 50 by default
+}
+
+// Name and email to commit as, instead of the configured identity
+export interface Identity {
+  name: string;
+  email: string;
+}
+
+// A merge, rebase or similar operation the repository is in the middle of
+export type RepoOperation = 'merge' | 'rebase' | 'cherry_pick' | 'revert' | 'apply_mailbox';
+
+// Where `restore_file` writes the restored version
+export type RestoreTarget = 'worktree' | 'index';
+
+// What a `restore_file` call replaced; pass `id` to `undo_restore`
+export interface Backup {
+  id: string;
+  repo: string; // Working tree root of the repository
+  path: string;
+  target: RestoreTarget;
+  source_rev: string; // Revision the file was restored from
+  previous_id: string | null; // Blob id of the replaced version, null if there was none
+  created_at: number; // Unix time
+}
+
+// Error returned by every backend command (mirrors the Rust `VisiGitError`)
+export interface VisiGitError {
+  code:
+    | 'repo_not_found'
+    | 'not_a_repository'
+    | 'path_not_in_revision'
+    | 'path_not_in_index'
+    | 'path_not_in_stage'
+    | 'not_conflicted'
+    | 'revision_not_found'
+    | 'no_merge_base'
+    | 'invalid_revision'
+    | 'invalid_cursor'
+    | 'invalid_identity'
+    | 'empty_commit_message'
+    | 'nothing_to_commit'
+    | 'unresolved_conflicts'
+    | 'operation_in_progress'
+    | 'backup_not_found'
+    | 'diff_not_found'
+    | 'absolute_path'
+    | 'path_escapes_repo'
+    | 'invalid_path'
+    | 'invalid_image'
+    | 'lfs_object_missing'
+    | 'git_not_installed'
+    | 'git_failed'
+    | 'unexpected_output'
+    | 'io';
+  message: string;
+  details: Record<string, unknown> | null;
 }
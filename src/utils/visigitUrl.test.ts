@@ -0,0 +1,63 @@
+import { describe, it, expect } from 'vitest'
+import { blobUrl, diffUrl, indexUrl, isLfsMissing, LFS_STATUS_HEADER, stageUrl, worktreeUrl } from './visigitUrl'
+
+describe('blobUrl', () => {
+  it('builds a blob URL under the scheme root', () => {
+    expect(blobUrl('visigit://localhost/', 'abc', 'HEAD', 'assets/logo.png')).toBe(
+      'visigit://localhost/blob/abc/HEAD/assets/logo.png'
+    )
+  })
+
+  it('keeps slashes in the revision inside one segment', () => {
+    expect(blobUrl('http://visigit.localhost', 'abc', 'feature/logo', 'my images/café.png')).toBe(
+      'http://visigit.localhost/blob/abc/feature%2Flogo/my%20images/caf%C3%A9.png'
+    )
+  })
+})
+
+describe('indexUrl', () => {
+  it('builds an index URL with an optional version', () => {
+    expect(indexUrl('visigit://localhost/', 'abc', 'assets/logo.png')).toBe(
+      'visigit://localhost/index/abc/assets/logo.png'
+    )
+    expect(indexUrl('http://visigit.localhost', 'abc', 'a b.png', 7)).toBe(
+      'http://visigit.localhost/index/abc/a%20b.png?v=7'
+    )
+  })
+})
+
+describe('stageUrl', () => {
+  it('puts the conflict side before the path', () => {
+    expect(stageUrl('visigit://localhost/', 'abc', 'theirs', 'art/hero image.png', 2)).toBe(
+      'visigit://localhost/stage/abc/theirs/art/hero%20image.png?v=2'
+    )
+  })
+})
+
+describe('worktreeUrl', () => {
+  it('builds a worktree URL', () => {
+    expect(worktreeUrl('visigit://localhost/', 'abc', 'logo.png')).toBe(
+      'visigit://localhost/worktree/abc/logo.png'
+    )
+  })
+
+  it('appends a cache-busting version', () => {
+    expect(worktreeUrl('visigit://localhost/', 'abc', 'a#b.png', 3)).toBe(
+      'visigit://localhost/worktree/abc/a%23b.png?v=3'
+    )
+  })
+})
+
+describe('diffUrl', () => {
+  it('builds a diff image URL', () => {
+    expect(diffUrl('http://visigit.localhost', 'a1b2')).toBe('http://visigit.localhost/diff/a1b2')
+  })
+})
+
+describe('isLfsMissing', () => {
+  it('reads the LFS status header', () => {
+    expect(isLfsMissing(new Headers({ [LFS_STATUS_HEADER]: 'missing' }))).toBe(true)
+    expect(isLfsMissing(new Headers({ [LFS_STATUS_HEADER]: 'object' }))).toBe(false)
+    expect(isLfsMissing(new Headers())).toBe(false)
+  })
+})
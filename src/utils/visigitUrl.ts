@@ -0,0 +1,63 @@
+// Builders for `visigit://` URLs, served by the Rust URI scheme protocol.
+//
+// `base` is the scheme root for the current platform, as returned by
+// `convertFileSrc('', 'visigit')` (`visigit://localhost/` on macOS/Linux,
+// `http://visigit.localhost/` on Windows). Every segment is encoded on its
+// own so revisions like `feature/logo` survive as a single segment.
+
+import type { ConflictStage } from '../types'
+
+function encodePath(path: string): string {
+  return path.split('/').map(encodeURIComponent).join('/')
+}
+
+function withSlash(base: string): string {
+  return base.endsWith('/') ? base : `${base}/`
+}
+
+// URL of `path` as committed at `rev`
+export function blobUrl(base: string, repoId: string, rev: string, path: string): string {
+  return `${withSlash(base)}blob/${encodeURIComponent(repoId)}/${encodeURIComponent(rev)}/${encodePath(path)}`
+}
+
+// URL of `path` as staged in the index. Staging changes the content under
+// the same URL, so pass a `version` to bust the cache like `worktreeUrl`.
+export function indexUrl(base: string, repoId: string, path: string, version?: number): string {
+  const url = `${withSlash(base)}index/${encodeURIComponent(repoId)}/${encodePath(path)}`
+  return version === undefined ? url : `${url}?v=${version}`
+}
+
+// URL of one side of a conflicted `path`. Resolving or redoing the merge
+// changes the content, so pass a `version` like `indexUrl`.
+export function stageUrl(
+  base: string,
+  repoId: string,
+  stage: ConflictStage,
+  path: string,
+  version?: number
+): string {
+  const url = `${withSlash(base)}stage/${encodeURIComponent(repoId)}/${stage}/${encodePath(path)}`
+  return version === undefined ? url : `${url}?v=${version}`
+}
+
+// URL of the working copy of `path`. `version` busts the webview's memory
+// cache, since the file can change on disk under the same URL.
+export function worktreeUrl(base: string, repoId: string, path: string, version?: number): string {
+  const url = `${withSlash(base)}worktree/${encodeURIComponent(repoId)}/${encodePath(path)}`
+  return version === undefined ? url : `${url}?v=${version}`
+}
+
+// URL of the diff image kept by `compute_image_diff` under `id`. Ids are
+// never reused, so no cache buster is needed.
+export function diffUrl(base: string, id: string): string {
+  return `${withSlash(base)}diff/${encodeURIComponent(id)}`
+}
+
+// Response header set on files stored in Git LFS: `object` when the real
+// object was served, `missing` when only the pointer is available locally
+export const LFS_STATUS_HEADER = 'x-lfs-status'
+
+// Whether a response is an LFS pointer whose object has not been fetched
+export function isLfsMissing(headers: Headers): boolean {
+  return headers.get(LFS_STATUS_HEADER) === 'missing'
+}